@@ -0,0 +1,47 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use formatify::{join_list, Formatify, PlaceholderFormatter, Template};
+use std::collections::HashMap;
+
+/// Templates rendered once per record, e.g. lines of a report.
+const TEMPLATES: [(&str, &str); 3] = [
+    (
+        "values",
+        "Hi %(name), you are %(age) years old and live in %(city).",
+    ),
+    (
+        "formats",
+        "%<(12,trunc)%(name)|%>(4)%(age)|%<(10)%(city)|%>(8)%(missing:-n/a)%n",
+    ),
+    (
+        "blocks",
+        "%(name)%(?age) (%(age))%(/age)%(for:tag in tags) #%(tag)%(end)",
+    ),
+];
+
+fn key_value() -> HashMap<&'static str, String> {
+    HashMap::from([
+        ("name", "Alice Wonderland".to_string()),
+        ("age", "31".to_string()),
+        ("city", "Berlin".to_string()),
+        ("tags", join_list(["admin", "ops"])),
+    ])
+}
+
+fn render(c: &mut Criterion) {
+    let formatter = Formatify::new();
+    let key_value = key_value();
+    for (name, inp) in TEMPLATES {
+        let mut group = c.benchmark_group(name);
+        group.bench_function("replace_placeholders", |b| {
+            b.iter(|| formatter.replace_placeholders(black_box(&key_value), black_box(inp)))
+        });
+        let template = Template::parse(&formatter, inp);
+        group.bench_function("template", |b| {
+            b.iter(|| template.render(black_box(&key_value)))
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, render);
+criterion_main!(benches);
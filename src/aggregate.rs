@@ -0,0 +1,82 @@
+/// Aggregate function over the items of a list value, e.g. `%(sum:amounts)` in the footer of a
+/// table whose rows are rendered by a loop block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate {
+    Sum,
+    Avg,
+    Min,
+    Max,
+    Count,
+}
+
+impl Aggregate {
+    pub const ALL: [Aggregate; 5] = [
+        Aggregate::Sum,
+        Aggregate::Avg,
+        Aggregate::Min,
+        Aggregate::Max,
+        Aggregate::Count,
+    ];
+
+    /// Returns the name used in templates, e.g. `sum`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Aggregate::Sum => "sum",
+            Aggregate::Avg => "avg",
+            Aggregate::Min => "min",
+            Aggregate::Max => "max",
+            Aggregate::Count => "count",
+        }
+    }
+
+    /// Computes the aggregate of the items. `count` counts all items, the other functions skip
+    /// items which aren't numbers. `None` if `avg`, `min` or `max` find no number.
+    ///
+    /// `sum` keeps the most decimal places of the items, `avg` rounds to two more and trims
+    /// trailing zeros. `min` and `max` return the item as written.
+    pub fn compute(self, items: &[String]) -> Option<String> {
+        let numbers: Vec<(&str, f64)> = items
+            .iter()
+            .map(|item| item.trim())
+            .filter_map(|item| Some((item, item.parse::<f64>().ok()?)))
+            .filter(|(_, number)| number.is_finite())
+            .collect();
+        let decimals = numbers
+            .iter()
+            .map(|(item, _)| decimal_places(item))
+            .max()
+            .unwrap_or(0);
+        let sum: f64 = numbers.iter().map(|(_, number)| number).sum();
+        match self {
+            Aggregate::Sum => Some(format!("{sum:.decimals$}")),
+            Aggregate::Avg => {
+                if numbers.is_empty() {
+                    return None;
+                }
+                let avg = sum / numbers.len() as f64;
+                let avg = format!("{:.*}", decimals + 2, avg);
+                Some(avg.trim_end_matches('0').trim_end_matches('.').to_string())
+            }
+            Aggregate::Min => numbers
+                .iter()
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(item, _)| item.to_string()),
+            Aggregate::Max => numbers
+                .iter()
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(item, _)| item.to_string()),
+            Aggregate::Count => Some(items.len().to_string()),
+        }
+    }
+}
+
+/// Returns the number of decimal places of a number like `12.50`. Numbers with an exponent
+/// have none.
+fn decimal_places(number: &str) -> usize {
+    if number.contains(['e', 'E']) {
+        return 0;
+    }
+    number
+        .split_once('.')
+        .map_or(0, |(_, fraction)| fraction.len())
+}
@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// Values merged into every render with the lowest precedence.
+pub type AmbientValues = RwLock<Arc<HashMap<String, String>>>;
+
+/// Guard which overrides an ambient value until it is dropped. Returned by
+/// `Formatify::override_ambient`.
+///
+/// Dropping the guard restores the value which was set before the override, so nested guards
+/// must be dropped in reverse order of their creation.
+#[must_use = "the override is reverted as soon as the guard is dropped"]
+pub struct AmbientGuard<'a> {
+    ambient: &'a AmbientValues,
+    key: String,
+    previous: Option<String>,
+}
+
+impl<'a> AmbientGuard<'a> {
+    /// Sets the ambient value of `key` and remembers the previous value.
+    pub(crate) fn new(ambient: &'a AmbientValues, key: &str, value: &str) -> Self {
+        let previous = insert(ambient, key, Some(value.to_string()));
+        Self {
+            ambient,
+            key: key.to_string(),
+            previous,
+        }
+    }
+}
+
+impl Drop for AmbientGuard<'_> {
+    fn drop(&mut self) {
+        insert(self.ambient, &self.key, self.previous.take());
+    }
+}
+
+/// Sets or removes the ambient value of `key` and returns the previous value.
+fn insert(ambient: &AmbientValues, key: &str, value: Option<String>) -> Option<String> {
+    let mut values = ambient.write().unwrap_or_else(PoisonError::into_inner);
+    let values = Arc::make_mut(&mut values);
+    match value {
+        Some(value) => values.insert(key.to_string(), value),
+        None => values.remove(key),
+    }
+}
+
+/// Returns a snapshot of the ambient values which isn't affected by later changes.
+pub fn snapshot(ambient: &AmbientValues) -> Arc<HashMap<String, String>> {
+    ambient
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
@@ -0,0 +1,32 @@
+/// Source of a resolved value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueSource {
+    /// The key-value map passed to the render.
+    KeyValue,
+    /// The ambient values of the formatter, see `Formatify::set_ambient`.
+    Ambient,
+    /// A built-in like `now` or `uuid`.
+    Builtin,
+    /// A loop variable of a `%(for:...)` block.
+    Binding,
+    /// A resolver computing the value, named by its namespace: the sigil of an extension like
+    /// `qr`, `#` for the length placeholder or e.g. `%d` for a custom char placeholder.
+    Resolver(String),
+    /// The default value of the placeholder, e.g. `%(key:-default)`.
+    Default,
+}
+
+/// A value resolved by a render, see `PlaceholderFormatter::replace_placeholders_with_audit`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// Key which provided the value. The key of the placeholder if the default value is used.
+    pub key: String,
+    /// Where the value came from.
+    pub source: ValueSource,
+    /// The value before it was transformed.
+    pub value: String,
+    /// The value was changed by a numeric format, a plural or select format or modifiers.
+    pub transformed: bool,
+    /// The value was truncated to fit its field.
+    pub truncated: bool,
+}
@@ -0,0 +1,26 @@
+/// State of an open block like a conditional section.
+pub struct BlockState {
+    /// Name used to match the closing tag of the block.
+    pub name: String,
+    /// True if the content of the current branch is rendered.
+    pub active: bool,
+    /// True if the surrounding content is rendered.
+    pub parent_active: bool,
+    /// True if one of the branches of the block was already rendered.
+    pub taken: bool,
+    /// Iteration state if the block is a loop.
+    pub repeat: Option<LoopState>,
+    /// Value compared by the cases if the block is a switch. `None` for other blocks
+    /// and for switches over missing keys.
+    pub subject: Option<String>,
+}
+
+/// Iteration state of a loop block like `%(for:item in items)`.
+pub struct LoopState {
+    /// The elements of the list value the loop iterates over.
+    pub items: Vec<String>,
+    /// Index of the current element.
+    pub index: usize,
+    /// Input position of the first char of the loop body.
+    pub body_start: usize,
+}
@@ -0,0 +1,43 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Clock source of the time-based built-ins `%(now)` and `|reldate`.
+pub type ClockFn = Arc<dyn Fn() -> DateTime<Utc> + Send + Sync>;
+
+/// Returns the clock which reads the system time.
+pub fn system_clock() -> ClockFn {
+    Arc::new(|| {
+        let elapsed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        DateTime::from_timestamp(elapsed.as_secs() as i64, elapsed.subsec_nanos())
+            .unwrap_or_default()
+    })
+}
+
+/// Renders the time of the clock as RFC 3339 timestamp in UTC, e.g. `2024-03-09T13:05:00Z`.
+pub fn now(clock: &ClockFn) -> String {
+    clock().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Renders the time between `date` and `now` like git's relative dates, e.g. `3 hours ago` or
+/// `in 2 days`.
+pub fn relative(date: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let secs = (now - date).num_seconds();
+    let elapsed = secs.unsigned_abs();
+    let (count, unit) = match elapsed {
+        0..90 => (elapsed, "second"),
+        90..5_400 => ((elapsed + 30) / 60, "minute"),
+        5_400..129_600 => ((elapsed + 1_800) / 3_600, "hour"),
+        129_600..1_209_600 => ((elapsed + 43_200) / 86_400, "day"),
+        1_209_600..6_048_000 => ((elapsed + 302_400) / 604_800, "week"),
+        6_048_000..31_536_000 => ((elapsed + 1_296_000) / 2_592_000, "month"),
+        _ => ((elapsed + 15_768_000) / 31_536_000, "year"),
+    };
+    let plural = if count == 1 { "" } else { "s" };
+    match secs {
+        0.. => format!("{count} {unit}{plural} ago"),
+        _ => format!("in {count} {unit}{plural}"),
+    }
+}
@@ -0,0 +1,390 @@
+/// A single color as understood by the `%C(...)` placeholder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    /// The terminal default color.
+    Default,
+    /// One of the eight basic colors (0 = black ... 7 = white).
+    Basic(u8),
+    /// One of the eight bright colors (0 = bright black ... 7 = bright white).
+    Bright(u8),
+    /// A color of the 256 color palette.
+    Palette(u8),
+    /// A 24-bit RGB color.
+    Rgb(u8, u8, u8),
+}
+
+/// Output format of color placeholders.
+///
+/// All targets are rendered from the same parsed `ColorSpec`, so a template serves terminals
+/// and chat systems alike.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorTarget {
+    /// ANSI escape sequences for terminals.
+    #[default]
+    Ansi,
+    /// mIRC control codes for IRC clients. Colors are mapped to the nearest of the 16 mIRC
+    /// colors; `dim` and `blink` are not supported.
+    Irc,
+    /// BBCode tags like `[b]` and `[color=red]`. Background colors, `dim`, `blink` and
+    /// `reverse` are not supported. A color placeholder closes the tags of the previous one.
+    BbCode,
+}
+
+/// Text attributes which can be combined with colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attribute {
+    Bold,
+    Dim,
+    Italic,
+    Underline,
+    Blink,
+    Reverse,
+    Strike,
+}
+
+/// Parsed representation of a color placeholder like `%C(bold blue)`.
+///
+/// The syntax follows git's pretty-format: the first color is the foreground,
+/// the second color is the background. Attributes may be given in any order.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ColorSpec {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub attrs: Vec<Attribute>,
+    pub reset: bool,
+}
+
+const COLOR_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+fn parse_color(word: &str) -> Option<Option<Color>> {
+    match word {
+        "normal" => return Some(None),
+        "default" => return Some(Some(Color::Default)),
+        _ => {}
+    }
+
+    if let Some(idx) = COLOR_NAMES.iter().position(|&name| name == word) {
+        return Some(Some(Color::Basic(idx as u8)));
+    }
+
+    if let Some(bright) = word.strip_prefix("bright") {
+        let idx = COLOR_NAMES.iter().position(|&name| name == bright)?;
+        return Some(Some(Color::Bright(idx as u8)));
+    }
+
+    if let Some(hex) = word.strip_prefix('#') {
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
+        let g = u8::from_str_radix(&hex[2..4], 16).ok()?;
+        let b = u8::from_str_radix(&hex[4..6], 16).ok()?;
+        return Some(Some(Color::Rgb(r, g, b)));
+    }
+
+    if word.chars().all(|ch| ch.is_ascii_digit()) {
+        return word.parse::<u8>().ok().map(|n| Some(Color::Palette(n)));
+    }
+
+    None
+}
+
+fn parse_attribute(word: &str) -> Option<Attribute> {
+    match word {
+        "bold" => Some(Attribute::Bold),
+        "dim" => Some(Attribute::Dim),
+        "italic" => Some(Attribute::Italic),
+        "ul" => Some(Attribute::Underline),
+        "blink" => Some(Attribute::Blink),
+        "reverse" => Some(Attribute::Reverse),
+        "strike" => Some(Attribute::Strike),
+        _ => None,
+    }
+}
+
+impl ColorSpec {
+    /// Parses the content of a `%C(...)` placeholder.
+    ///
+    /// Returns `None` if the specification contains unknown words or more than two colors.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let mut out = ColorSpec::default();
+        let mut color_count = 0;
+
+        for word in spec.split_whitespace() {
+            if word == "reset" {
+                out.reset = true;
+                continue;
+            }
+
+            if let Some(attr) = parse_attribute(word) {
+                out.attrs.push(attr);
+                continue;
+            }
+
+            let color = parse_color(word)?;
+            match color_count {
+                0 => out.fg = color,
+                1 => out.bg = color,
+                _ => return None,
+            }
+            color_count += 1;
+        }
+
+        if out.reset && (color_count > 0 || !out.attrs.is_empty()) {
+            return None; // reset can't be combined
+        }
+
+        Some(out)
+    }
+
+    /// Returns the ANSI escape sequence for this specification.
+    pub fn to_ansi(&self) -> String {
+        if self.reset {
+            return "\x1b[m".to_string();
+        }
+
+        let mut codes: Vec<String> = Vec::new();
+        for attr in self.attrs.iter() {
+            let code = match attr {
+                Attribute::Bold => 1,
+                Attribute::Dim => 2,
+                Attribute::Italic => 3,
+                Attribute::Underline => 4,
+                Attribute::Blink => 5,
+                Attribute::Reverse => 7,
+                Attribute::Strike => 9,
+            };
+            codes.push(code.to_string());
+        }
+
+        if let Some(fg) = self.fg {
+            codes.push(Self::color_code(fg, 30, 90, 38));
+        }
+
+        if let Some(bg) = self.bg {
+            codes.push(Self::color_code(bg, 40, 100, 48));
+        }
+
+        if codes.is_empty() {
+            return String::new();
+        }
+        format!("\x1b[{}m", codes.join(";"))
+    }
+
+    /// Returns the CSS classes for this specification, e.g. `fmt-bold fmt-fg-red` for
+    /// `%C(bold red)`. Used by the HTML target instead of escape sequences.
+    #[cfg(feature = "html")]
+    pub fn to_classes(&self) -> Vec<String> {
+        let mut classes: Vec<String> = Vec::new();
+        for attr in self.attrs.iter() {
+            let name = match attr {
+                Attribute::Bold => "bold",
+                Attribute::Dim => "dim",
+                Attribute::Italic => "italic",
+                Attribute::Underline => "ul",
+                Attribute::Blink => "blink",
+                Attribute::Reverse => "reverse",
+                Attribute::Strike => "strike",
+            };
+            classes.push(format!("fmt-{}", name));
+        }
+
+        for (color, layer) in [(self.fg, "fg"), (self.bg, "bg")] {
+            if let Some(name) = color.and_then(Self::color_class) {
+                classes.push(format!("fmt-{}-{}", layer, name));
+            }
+        }
+        classes
+    }
+
+    #[cfg(feature = "html")]
+    fn color_class(color: Color) -> Option<String> {
+        match color {
+            Color::Default => None,
+            Color::Basic(idx) => Some(COLOR_NAMES[idx as usize].to_string()),
+            Color::Bright(idx) => Some(format!("bright{}", COLOR_NAMES[idx as usize])),
+            Color::Palette(n) => Some(n.to_string()),
+            Color::Rgb(r, g, b) => Some(format!("{:02x}{:02x}{:02x}", r, g, b)),
+        }
+    }
+
+    /// Returns the mIRC control codes for this specification.
+    pub fn to_irc(&self) -> String {
+        if self.reset {
+            return "\x0f".to_string();
+        }
+
+        let mut out = String::new();
+        for attr in self.attrs.iter() {
+            let code = match attr {
+                Attribute::Bold => '\x02',
+                Attribute::Italic => '\x1d',
+                Attribute::Underline => '\x1f',
+                Attribute::Reverse => '\x16',
+                Attribute::Strike => '\x1e',
+                Attribute::Dim | Attribute::Blink => continue,
+            };
+            out.push(code);
+        }
+
+        match (self.fg, self.bg) {
+            (None, None) => {}
+            (fg, None) => out.push_str(&format!("\x03{:02}", Self::irc_color(fg))),
+            (fg, Some(bg)) => out.push_str(&format!(
+                "\x03{:02},{:02}",
+                Self::irc_color(fg),
+                Self::irc_color(Some(bg))
+            )),
+        }
+        out
+    }
+
+    /// Returns the opening BBCode tags for this specification.
+    pub fn to_bbcode_open(&self) -> String {
+        self.bbcode_tags()
+            .iter()
+            .map(|(tag, arg)| match arg {
+                Some(arg) => format!("[{}={}]", tag, arg),
+                None => format!("[{}]", tag),
+            })
+            .collect()
+    }
+
+    /// Returns the BBCode tags closing the tags of `to_bbcode_open` in reverse order.
+    pub fn to_bbcode_close(&self) -> String {
+        self.bbcode_tags()
+            .iter()
+            .rev()
+            .map(|(tag, _)| format!("[/{}]", tag))
+            .collect()
+    }
+
+    fn bbcode_tags(&self) -> Vec<(&'static str, Option<String>)> {
+        if self.reset {
+            return Vec::new();
+        }
+
+        let mut tags: Vec<(&'static str, Option<String>)> = Vec::new();
+        for attr in self.attrs.iter() {
+            let tag = match attr {
+                Attribute::Bold => "b",
+                Attribute::Italic => "i",
+                Attribute::Underline => "u",
+                Attribute::Strike => "s",
+                Attribute::Dim | Attribute::Blink | Attribute::Reverse => continue,
+            };
+            tags.push((tag, None));
+        }
+
+        let color = match self.fg {
+            None | Some(Color::Default) => None,
+            Some(Color::Basic(idx)) => Some(COLOR_NAMES[idx as usize].to_string()),
+            Some(color) => {
+                Self::rgb(color).map(|(r, g, b)| format!("#{:02x}{:02x}{:02x}", r, g, b))
+            }
+        };
+        if let Some(color) = color {
+            tags.push(("color", Some(color)));
+        }
+        tags
+    }
+
+    /// Returns the RGB value of a color based on the xterm default palette. `None` for the
+    /// terminal default color.
+    fn rgb(color: Color) -> Option<(u8, u8, u8)> {
+        const BASIC: [(u8, u8, u8); 16] = [
+            (0x00, 0x00, 0x00),
+            (0xcd, 0x00, 0x00),
+            (0x00, 0xcd, 0x00),
+            (0xcd, 0xcd, 0x00),
+            (0x00, 0x00, 0xee),
+            (0xcd, 0x00, 0xcd),
+            (0x00, 0xcd, 0xcd),
+            (0xe5, 0xe5, 0xe5),
+            (0x7f, 0x7f, 0x7f),
+            (0xff, 0x00, 0x00),
+            (0x00, 0xff, 0x00),
+            (0xff, 0xff, 0x00),
+            (0x5c, 0x5c, 0xff),
+            (0xff, 0x00, 0xff),
+            (0x00, 0xff, 0xff),
+            (0xff, 0xff, 0xff),
+        ];
+        const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+        match color {
+            Color::Default => None,
+            Color::Basic(idx) => Some(BASIC[idx as usize]),
+            Color::Bright(idx) => Some(BASIC[8 + idx as usize]),
+            Color::Palette(n @ 0..=15) => Some(BASIC[n as usize]),
+            Color::Palette(n @ 16..=231) => {
+                let idx = (n - 16) as usize;
+                Some((LEVELS[idx / 36], LEVELS[(idx / 6) % 6], LEVELS[idx % 6]))
+            }
+            Color::Palette(n) => {
+                let grey = 8 + 10 * (n - 232);
+                Some((grey, grey, grey))
+            }
+            Color::Rgb(r, g, b) => Some((r, g, b)),
+        }
+    }
+
+    /// Returns the mIRC color code of a color. Colors outside of the basic and bright colors
+    /// are mapped to the nearest mIRC color.
+    fn irc_color(color: Option<Color>) -> u8 {
+        const BASIC: [u8; 8] = [1, 5, 3, 7, 2, 6, 10, 15];
+        const BRIGHT: [u8; 8] = [14, 4, 9, 8, 12, 13, 11, 0];
+        const PALETTE: [(u8, u8, u8); 16] = [
+            (0xff, 0xff, 0xff),
+            (0x00, 0x00, 0x00),
+            (0x00, 0x00, 0x7f),
+            (0x00, 0x93, 0x00),
+            (0xff, 0x00, 0x00),
+            (0x7f, 0x00, 0x00),
+            (0x9c, 0x00, 0x9c),
+            (0xfc, 0x7f, 0x00),
+            (0xff, 0xff, 0x00),
+            (0x00, 0xfc, 0x00),
+            (0x00, 0x93, 0x93),
+            (0x00, 0xff, 0xff),
+            (0x00, 0x00, 0xfc),
+            (0xff, 0x00, 0xff),
+            (0x7f, 0x7f, 0x7f),
+            (0xd2, 0xd2, 0xd2),
+        ];
+
+        let color = match color {
+            None | Some(Color::Default) => return 99,
+            Some(Color::Basic(idx)) | Some(Color::Palette(idx @ 0..=7)) => {
+                return BASIC[idx as usize]
+            }
+            Some(Color::Bright(idx)) => return BRIGHT[idx as usize],
+            Some(Color::Palette(n @ 8..=15)) => return BRIGHT[n as usize - 8],
+            Some(color) => color,
+        };
+        let Some((r, g, b)) = Self::rgb(color) else {
+            return 99;
+        };
+        let distance = |(pr, pg, pb): (u8, u8, u8)| {
+            let dr = r as i32 - pr as i32;
+            let dg = g as i32 - pg as i32;
+            let db = b as i32 - pb as i32;
+            dr * dr + dg * dg + db * db
+        };
+        (0..PALETTE.len())
+            .min_by_key(|&idx| distance(PALETTE[idx]))
+            .unwrap_or(0) as u8
+    }
+
+    fn color_code(color: Color, basic: u8, bright: u8, extended: u8) -> String {
+        match color {
+            Color::Default => (basic + 9).to_string(),
+            Color::Basic(idx) => (basic + idx).to_string(),
+            Color::Bright(idx) => (bright + idx).to_string(),
+            Color::Palette(n) => format!("{};5;{}", extended, n),
+            Color::Rgb(r, g, b) => format!("{};2;{};{};{}", extended, r, g, b),
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use super::template::Template;
+use super::Formatify;
+
+use std::collections::HashMap;
+
+/// Fixed column widths of a row template, e.g. to tabulate an unbounded stream of rows.
+///
+/// `replace_placeholders_joined` measures all rows before rendering the first one. A
+/// `ColumnPlan` fixes the widths up-front, either explicitly or from a sampled prefix of the
+/// rows, so each row is rendered on its own and can be written immediately. Values are padded
+/// or truncated to exactly the width of their column, so all rows line up.
+///
+/// # Examples
+/// ```
+/// # use formatify::{ColumnPlan, Formatify};
+/// # use std::collections::HashMap;
+/// let formatter = Formatify::new();
+/// let row = |name: &str, size: &str| {
+///     HashMap::from([("name", name.to_string()), ("size", size.to_string())])
+/// };
+/// let rows = vec![row("Name", "Size"), row("a.txt", "12"), row("archive.tar.gz", "4096")];
+///
+/// // fix the widths from the header and the first row
+/// let plan = ColumnPlan::sample(&formatter, "%(name) | %>(1)%(size)", &rows[..2]);
+/// assert_eq!(plan.widths(), [5, 4]);
+///
+/// let lines: Vec<_> = rows.iter().map(|row| plan.render_row(row)).collect();
+/// assert_eq!(lines, ["Name  | Size", "a.txt |   12", "arch… | 4096"]);
+/// ```
+pub struct ColumnPlan<'f> {
+    template: Template<'f>,
+    widths: Vec<usize>,
+}
+
+impl<'f> ColumnPlan<'f> {
+    /// Creates a plan with the given widths of the value placeholders of `template` in order
+    /// of appearance. Value placeholders without a width keep their natural width.
+    pub fn new(formatter: &'f Formatify, template: &str, widths: Vec<usize>) -> Self {
+        Self {
+            template: Template::parse(formatter, template),
+            widths,
+        }
+    }
+
+    /// Creates a plan with the widths of the widest values of the sampled rows, e.g. the
+    /// header and a prefix of the row stream.
+    pub fn sample(
+        formatter: &'f Formatify,
+        template: &str,
+        rows: &[HashMap<&str, String>],
+    ) -> Self {
+        let template = Template::parse(formatter, template);
+        let mut widths = Vec::<usize>::new();
+        for row in rows {
+            let lengths = template.measure(row);
+            for (idx, &len) in lengths.iter().skip(1).enumerate() {
+                match widths.get_mut(idx) {
+                    Some(width) => *width = (*width).max(len),
+                    None => widths.push(len),
+                }
+            }
+        }
+        Self { template, widths }
+    }
+
+    /// Returns the widths of the value placeholders.
+    pub fn widths(&self) -> &[usize] {
+        &self.widths
+    }
+
+    /// Renders a row with the planned widths.
+    pub fn render_row(&self, key_value: &HashMap<&str, String>) -> String {
+        self.template.render_fixed(key_value, &self.widths)
+    }
+}
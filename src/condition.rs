@@ -0,0 +1,290 @@
+use std::cmp::Ordering;
+
+/// Comparison operators supported in conditions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// One side of a comparison.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operand {
+    /// The value of a key of the key-value map.
+    Key(String),
+    /// A quoted string or number literal.
+    Literal(String),
+}
+
+/// A condition of a conditional block like `%(?key)`, `%(?count>0)` or `%(?a && !b)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    /// True if the key is present and its value is non-empty.
+    Truthy(String),
+    /// Compares two operands. Numeric comparison is used if both sides parse as numbers.
+    Compare(Operand, CompareOp, Operand),
+    /// Logical negation.
+    Not(Box<Condition>),
+    /// Logical and with short-circuit evaluation.
+    And(Box<Condition>, Box<Condition>),
+    /// Logical or with short-circuit evaluation.
+    Or(Box<Condition>, Box<Condition>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Literal(String),
+    Op(CompareOp),
+    Not,
+    And,
+    Or,
+    LParen,
+    RParen,
+}
+
+fn is_ident_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+fn tokenize(inp: &str) -> Option<Vec<Token>> {
+    let chars: Vec<char> = inp.chars().collect();
+    let mut tokens = Vec::new();
+    let mut idx = 0;
+
+    while idx < chars.len() {
+        let ch = chars[idx];
+        let next = chars.get(idx + 1).copied();
+        match ch {
+            ' ' => idx += 1,
+            '"' => {
+                let end = chars[idx + 1..].iter().position(|&ch| ch == '"')?;
+                let literal: String = chars[idx + 1..idx + 1 + end].iter().collect();
+                tokens.push(Token::Literal(literal));
+                idx += end + 2;
+            }
+            '=' if next == Some('=') => {
+                tokens.push(Token::Op(CompareOp::Eq));
+                idx += 2;
+            }
+            '!' if next == Some('=') => {
+                tokens.push(Token::Op(CompareOp::Ne));
+                idx += 2;
+            }
+            '!' => {
+                tokens.push(Token::Not);
+                idx += 1;
+            }
+            '&' if next == Some('&') => {
+                tokens.push(Token::And);
+                idx += 2;
+            }
+            '|' if next == Some('|') => {
+                tokens.push(Token::Or);
+                idx += 2;
+            }
+            '(' => {
+                tokens.push(Token::LParen);
+                idx += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                idx += 1;
+            }
+            '<' | '>' => {
+                let op = match (ch, next == Some('=')) {
+                    ('<', false) => CompareOp::Lt,
+                    ('<', true) => CompareOp::Le,
+                    ('>', false) => CompareOp::Gt,
+                    _ => CompareOp::Ge,
+                };
+                tokens.push(Token::Op(op));
+                idx += if next == Some('=') { 2 } else { 1 };
+            }
+            _ if is_ident_char(ch) || ch == '-' || ch == '.' => {
+                let start = idx;
+                idx += 1;
+                while idx < chars.len() && (is_ident_char(chars[idx]) || chars[idx] == '.') {
+                    idx += 1;
+                }
+                let word: String = chars[start..idx].iter().collect();
+                if word.parse::<f64>().is_ok() {
+                    tokens.push(Token::Literal(word));
+                } else if word
+                    .split('.')
+                    .all(|name| !name.is_empty() && name.chars().all(is_ident_char))
+                {
+                    tokens.push(Token::Ident(word));
+                } else {
+                    return None;
+                }
+            }
+            _ => return None,
+        }
+    }
+    Some(tokens)
+}
+
+fn resolve(operand: &Operand, lookup: &dyn Fn(&str) -> Option<String>) -> Option<String> {
+    match operand {
+        Operand::Key(key) => lookup(key),
+        Operand::Literal(literal) => Some(literal.clone()),
+    }
+}
+
+fn compare(lhs: &str, rhs: &str) -> Option<Ordering> {
+    match (lhs.trim().parse::<f64>(), rhs.trim().parse::<f64>()) {
+        (Ok(lhs), Ok(rhs)) => lhs.partial_cmp(&rhs),
+        _ => Some(lhs.cmp(rhs)),
+    }
+}
+
+/// Recursive descent parser for conditions. Precedence from low to high:
+/// `||`, `&&`, `!`, comparisons.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Option<Condition> {
+        let mut lhs = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            let rhs = self.parse_and()?;
+            lhs = Condition::Or(Box::new(lhs), Box::new(rhs));
+        }
+        Some(lhs)
+    }
+
+    fn parse_and(&mut self) -> Option<Condition> {
+        let mut lhs = self.parse_unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            let rhs = self.parse_unary()?;
+            lhs = Condition::And(Box::new(lhs), Box::new(rhs));
+        }
+        Some(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Option<Condition> {
+        match self.peek()? {
+            Token::Not => {
+                self.next();
+                Some(Condition::Not(Box::new(self.parse_unary()?)))
+            }
+            Token::LParen => {
+                self.next();
+                let inner = self.parse_or()?;
+                if self.next()? != Token::RParen {
+                    return None;
+                }
+                Some(inner)
+            }
+            _ => self.parse_comparison(),
+        }
+    }
+
+    fn parse_operand(&mut self) -> Option<Operand> {
+        match self.next()? {
+            Token::Ident(key) => Some(Operand::Key(key)),
+            Token::Literal(literal) => Some(Operand::Literal(literal)),
+            _ => None,
+        }
+    }
+
+    fn parse_comparison(&mut self) -> Option<Condition> {
+        let lhs = self.parse_operand()?;
+        let Some(&Token::Op(op)) = self.peek() else {
+            return match lhs {
+                Operand::Key(key) => Some(Condition::Truthy(key)),
+                Operand::Literal(_) => None,
+            };
+        };
+        self.next();
+        let rhs = self.parse_operand()?;
+        Some(Condition::Compare(lhs, op, rhs))
+    }
+}
+
+impl Condition {
+    /// Parses the expression of a conditional block, e.g. `key`, `count>0`, `status=="open"`
+    /// or `a && (b || !c)`.
+    pub fn parse(inp: &str) -> Option<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(inp)?,
+            pos: 0,
+        };
+        let condition = parser.parse_or()?;
+        if parser.pos != parser.tokens.len() {
+            return None;
+        }
+        Some(condition)
+    }
+
+    /// Evaluates the condition using `lookup` to resolve the values of keys.
+    /// Comparisons involving a missing key are false.
+    pub fn evaluate(&self, lookup: &dyn Fn(&str) -> Option<String>) -> bool {
+        match self {
+            Condition::Truthy(key) => lookup(key).is_some_and(|value| !value.is_empty()),
+            Condition::Compare(lhs, op, rhs) => {
+                let (Some(lhs), Some(rhs)) = (resolve(lhs, lookup), resolve(rhs, lookup)) else {
+                    return false;
+                };
+                let Some(ordering) = compare(&lhs, &rhs) else {
+                    return false;
+                };
+                match op {
+                    CompareOp::Eq => ordering == Ordering::Equal,
+                    CompareOp::Ne => ordering != Ordering::Equal,
+                    CompareOp::Lt => ordering == Ordering::Less,
+                    CompareOp::Le => ordering != Ordering::Greater,
+                    CompareOp::Gt => ordering == Ordering::Greater,
+                    CompareOp::Ge => ordering != Ordering::Less,
+                }
+            }
+            Condition::Not(inner) => !inner.evaluate(lookup),
+            Condition::And(lhs, rhs) => lhs.evaluate(lookup) && rhs.evaluate(lookup),
+            Condition::Or(lhs, rhs) => lhs.evaluate(lookup) || rhs.evaluate(lookup),
+        }
+    }
+
+    /// Returns all keys referenced by the condition in order of appearance.
+    pub fn keys(&self) -> Vec<&str> {
+        let mut keys = Vec::new();
+        self.collect_keys(&mut keys);
+        keys
+    }
+
+    fn collect_keys<'a>(&'a self, keys: &mut Vec<&'a str>) {
+        match self {
+            Condition::Truthy(key) => keys.push(key.as_str()),
+            Condition::Compare(lhs, _, rhs) => {
+                for operand in [lhs, rhs] {
+                    if let Operand::Key(key) = operand {
+                        keys.push(key.as_str());
+                    }
+                }
+            }
+            Condition::Not(inner) => inner.collect_keys(keys),
+            Condition::And(lhs, rhs) | Condition::Or(lhs, rhs) => {
+                lhs.collect_keys(keys);
+                rhs.collect_keys(keys);
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+/// Returns the number of columns a char occupies in a monospace font: wide East Asian chars and
+/// emoji take two columns, combining marks and zero-width chars none.
+pub fn char_width(ch: char) -> usize {
+    match ch as u32 {
+        0x0300..=0x036f
+        | 0x1ab0..=0x1aff
+        | 0x1dc0..=0x1dff
+        | 0x200b..=0x200f
+        | 0x20d0..=0x20ff
+        | 0xfe00..=0xfe0f
+        | 0xfe20..=0xfe2f => 0,
+        0x1100..=0x115f
+        | 0x2e80..=0x303e
+        | 0x3041..=0x33ff
+        | 0x3400..=0x4dbf
+        | 0x4e00..=0x9fff
+        | 0xa000..=0xa4cf
+        | 0xac00..=0xd7a3
+        | 0xf900..=0xfaff
+        | 0xfe30..=0xfe4f
+        | 0xff00..=0xff60
+        | 0xffe0..=0xffe6
+        | 0x1f300..=0x1f64f
+        | 0x1f900..=0x1f9ff
+        | 0x20000..=0x2fffd
+        | 0x30000..=0x3fffd => 2,
+        _ => 1,
+    }
+}
+
+/// Returns the number of columns of the widest line of `text` in a monospace font. ANSI escape
+/// sequences like `\x1b[31m` take no columns.
+pub fn max_line_width(text: &str) -> usize {
+    let mut widest = 0;
+    for line in text.split('\n') {
+        let mut width = 0;
+        let mut chars = line.chars();
+        while let Some(ch) = chars.next() {
+            if ch == '\x1b' {
+                // skip the sequence up to its final byte
+                if chars.next() == Some('[') {
+                    for ch in chars.by_ref() {
+                        if ('\x40'..='\x7e').contains(&ch) {
+                            break;
+                        }
+                    }
+                }
+                continue;
+            }
+            width += char_width(ch);
+        }
+        widest = widest.max(width);
+    }
+    widest
+}
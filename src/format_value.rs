@@ -0,0 +1,127 @@
+use super::invalid_utf8_policy::InvalidUtf8Policy;
+use super::list_value::join_list;
+use super::value_tree::ValueTree;
+
+use std::collections::BTreeMap;
+
+/// Typed value for `replace_placeholders_values`.
+///
+/// In contrast to the `String` values of the key-value map, typed values keep their type
+/// until they are rendered.
+///
+/// # Examples
+/// ```
+/// # use formatify::{FormatValue, Formatify, PlaceholderFormatter};
+/// # use std::collections::HashMap;
+/// let mut values : HashMap<&str, FormatValue> = HashMap::new();
+/// values.insert("count", 3.into());
+/// values.insert("ok", true.into());
+/// values.insert("tags", FormatValue::List(vec!["a".into(), "b".into()]));
+/// let formatter = Formatify::new();
+/// let out = formatter.replace_placeholders_values(&values, "%(count) %(ok) %(tags[1])");
+/// assert_eq!(out, "3 true b");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormatValue {
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    /// Raw bytes, e.g. read from files or network payloads. Bytes which aren't valid UTF-8 are
+    /// rendered according to `FormatifyBuilder::invalid_utf8`.
+    Bytes(Vec<u8>),
+    /// Items of a list, usable with loop blocks and list indices.
+    List(Vec<FormatValue>),
+    /// Named child values, addressed with dotted paths like `%(user.name)`.
+    Map(BTreeMap<String, FormatValue>),
+}
+
+impl FormatValue {
+    /// Converts the value into a value tree. Scalars and lists become leafs. Bytes which aren't
+    /// valid UTF-8 are decoded lossily.
+    pub fn to_value_tree(&self) -> ValueTree {
+        // lossy decoding never rejects a value
+        self.to_value_tree_with(InvalidUtf8Policy::Lossy)
+            .unwrap_or_else(ValueTree::branch)
+    }
+
+    /// Converts the value into a value tree, decoding bytes according to `policy`. `None` if the
+    /// value is rejected. Rejected children of a map are left out, a list with a rejected item
+    /// is rejected as a whole.
+    pub fn to_value_tree_with(&self, policy: InvalidUtf8Policy) -> Option<ValueTree> {
+        let tree = match self {
+            FormatValue::Str(value) => ValueTree::Leaf(value.clone()),
+            FormatValue::Int(value) => ValueTree::Leaf(value.to_string()),
+            FormatValue::Float(value) => ValueTree::Leaf(value.to_string()),
+            FormatValue::Bool(value) => ValueTree::Leaf(value.to_string()),
+            FormatValue::Bytes(bytes) => ValueTree::Leaf(policy.decode(bytes)?),
+            FormatValue::List(items) => {
+                let items = items
+                    .iter()
+                    .map(|item| match item.to_value_tree_with(policy)? {
+                        ValueTree::Leaf(leaf) => Some(leaf),
+                        ValueTree::Branch(_) => Some(String::new()),
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                ValueTree::Leaf(join_list(items))
+            }
+            FormatValue::Map(children) => ValueTree::Branch(
+                children
+                    .iter()
+                    .filter_map(|(name, child)| {
+                        Some((name.clone(), child.to_value_tree_with(policy)?))
+                    })
+                    .collect(),
+            ),
+        };
+        Some(tree)
+    }
+}
+
+impl From<&str> for FormatValue {
+    fn from(value: &str) -> Self {
+        FormatValue::Str(value.to_string())
+    }
+}
+
+impl From<String> for FormatValue {
+    fn from(value: String) -> Self {
+        FormatValue::Str(value)
+    }
+}
+
+impl From<i64> for FormatValue {
+    fn from(value: i64) -> Self {
+        FormatValue::Int(value)
+    }
+}
+
+impl From<i32> for FormatValue {
+    fn from(value: i32) -> Self {
+        FormatValue::Int(value.into())
+    }
+}
+
+impl From<f64> for FormatValue {
+    fn from(value: f64) -> Self {
+        FormatValue::Float(value)
+    }
+}
+
+impl From<bool> for FormatValue {
+    fn from(value: bool) -> Self {
+        FormatValue::Bool(value)
+    }
+}
+
+impl From<Vec<u8>> for FormatValue {
+    fn from(value: Vec<u8>) -> Self {
+        FormatValue::Bytes(value)
+    }
+}
+
+impl From<&[u8]> for FormatValue {
+    fn from(value: &[u8]) -> Self {
+        FormatValue::Bytes(value.to_vec())
+    }
+}
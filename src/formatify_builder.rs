@@ -0,0 +1,253 @@
+use super::ambient::AmbientValues;
+#[cfg(feature = "chrono")]
+use super::clock::{self, ClockFn};
+#[cfg(feature = "colors")]
+use super::color_spec::ColorTarget;
+use super::invalid_utf8_policy::InvalidUtf8Policy;
+use super::missing_key_policy::MissingKeyPolicy;
+use super::number_format::NumberSymbols;
+use super::random::{self, RandomFn};
+use super::Formatify;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Builder used to configure a `Formatify` instance.
+///
+/// # Examples
+/// ```
+/// # use formatify::{Formatify, PlaceholderFormatter};
+/// let formatter = Formatify::builder().target_width(80).right_to_left(true).build();
+/// ```
+#[derive(Default)]
+pub struct FormatifyBuilder {
+    right_to_left: bool,
+    target_width: Option<usize>,
+    max_expansion_depth: Option<usize>,
+    #[cfg(feature = "colors")]
+    color_target: ColorTarget,
+    number_symbols: NumberSymbols,
+    #[cfg(feature = "chrono")]
+    clock: Option<ClockFn>,
+    random: Option<RandomFn>,
+    invalid_utf8: InvalidUtf8Policy,
+    missing_key: MissingKeyPolicy,
+    canonical: bool,
+}
+
+impl FormatifyBuilder {
+    /// Creates a builder with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mirrors the layout of each output line for right-to-left locales.
+    ///
+    /// The value placeholders of a line are emitted in reverse order and left and right
+    /// alignment are swapped. Literal text between the placeholders stays in place, so the
+    /// same table template serves both reading directions.
+    pub fn right_to_left(mut self, enabled: bool) -> Self {
+        self.right_to_left = enabled;
+        self
+    }
+
+    /// Sets the target line width used by the fill placeholders `%<(*)` and `%>(*)`.
+    ///
+    /// A `*` width resolves to the space between the current output column and `width`.
+    pub fn target_width(mut self, width: usize) -> Self {
+        self.target_width = Some(width);
+        self
+    }
+
+    /// Enables the recursive expansion of placeholders contained in values.
+    ///
+    /// Values are expanded up to `max_depth` levels, deeper values are inserted unchanged. A
+    /// placeholder referencing a value which is currently being expanded (a cycle) remains
+    /// unchanged in the output.
+    pub fn recursive_expansion(mut self, max_depth: usize) -> Self {
+        self.max_expansion_depth = Some(max_depth);
+        self
+    }
+
+    /// Sets the output format of color placeholders like `%C(bold red)`.
+    ///
+    /// The default target emits ANSI escape sequences. `ColorTarget::Irc` and
+    /// `ColorTarget::BbCode` render the same color specifications for chat systems.
+    #[cfg(feature = "colors")]
+    pub fn color_target(mut self, target: ColorTarget) -> Self {
+        self.color_target = target;
+        self
+    }
+
+    /// Uses the decimal and grouping separators of a locale like `de-DE` for the numeric format
+    /// `%(key,num=,.2)`.
+    ///
+    /// A locale which is unknown with its region falls back to its language, e.g. `de` for
+    /// `de-DE`. Unknown languages are ignored, keeping the default separators `.` and `,`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter};
+    /// # use std::collections::HashMap;
+    /// let mut key_value : HashMap<&str, String> = HashMap::new();
+    /// key_value.insert("amount", "1234567.891".into());
+    /// let formatter = Formatify::builder().locale("de-DE").build();
+    /// assert_eq!(formatter.replace_placeholders(&key_value, "%(amount,num=,.2)"), "1.234.567,89");
+    /// ```
+    #[cfg(feature = "num-format")]
+    pub fn locale(mut self, name: &str) -> Self {
+        if let Some(symbols) = NumberSymbols::from_locale(name) {
+            self.number_symbols = symbols;
+        }
+        self
+    }
+
+    /// Sets the clock source of the time-based built-ins `%(now)` and `|reldate`, e.g. a fixed
+    /// time for deterministic output in tests. The default clock reads the system time.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter};
+    /// # use std::collections::HashMap;
+    /// let now = chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+    /// let formatter = Formatify::builder().clock(move || now).build();
+    ///
+    /// let mut key_value : HashMap<&str, String> = HashMap::new();
+    /// key_value.insert("committed", "1699992800".into());
+    /// assert_eq!(formatter.replace_placeholders(&key_value, "%(now)"), "2023-11-14T22:13:20Z");
+    /// assert_eq!(formatter.replace_placeholders(&key_value, "%(committed|reldate)"), "2 hours ago");
+    /// ```
+    #[cfg(feature = "chrono")]
+    pub fn clock<F>(mut self, clock: F) -> Self
+    where
+        F: Fn() -> chrono::DateTime<chrono::Utc> + Send + Sync + 'static,
+    {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// Seeds the random source of the built-ins `%(uuid)` and `%(rand)`, so rendered output can
+    /// be reproduced bit-for-bit. Without a seed, each formatter uses a different random seed.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter};
+    /// # use std::collections::HashMap;
+    /// let key_value : HashMap<&str, String> = HashMap::new();
+    /// let first = Formatify::builder().seed(42).build();
+    /// let second = Formatify::builder().seed(42).build();
+    /// assert_eq!(
+    ///     first.replace_placeholders(&key_value, "%(uuid) %(rand)"),
+    ///     second.replace_placeholders(&key_value, "%(uuid) %(rand)")
+    /// );
+    /// ```
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.random = Some(random::seeded(seed));
+        self
+    }
+
+    /// Sets the random source of the built-ins `%(uuid)` and `%(rand)`. Each call of `random`
+    /// must return the next random number.
+    pub fn random_source<F>(mut self, random: F) -> Self
+    where
+        F: Fn() -> u64 + Send + Sync + 'static,
+    {
+        self.random = Some(Arc::new(random));
+        self
+    }
+
+    /// Sets how `FormatValue::Bytes` values which aren't valid UTF-8 are rendered by
+    /// `replace_placeholders_values`. Defaults to `InvalidUtf8Policy::Lossy`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{FormatValue, Formatify, InvalidUtf8Policy, PlaceholderFormatter};
+    /// # use std::collections::HashMap;
+    /// let mut values : HashMap<&str, FormatValue> = HashMap::new();
+    /// values.insert("name", b"caf\xe9".to_vec().into());
+    /// let formatter = Formatify::builder().invalid_utf8(InvalidUtf8Policy::HexEscape).build();
+    /// assert_eq!(formatter.replace_placeholders_values(&values, "%(name)"), "caf\\xe9");
+    /// ```
+    pub fn invalid_utf8(mut self, policy: InvalidUtf8Policy) -> Self {
+        self.invalid_utf8 = policy;
+        self
+    }
+
+    /// Sets how value placeholders whose key is missing are rendered. Defaults to
+    /// `MissingKeyPolicy::Keep`, which renders the placeholder unchanged.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, MissingKeyPolicy, PlaceholderFormatter};
+    /// # use std::collections::HashMap;
+    /// let mut key_value : HashMap<&str, String> = HashMap::new();
+    /// key_value.insert("name", "Alice".into());
+    /// let formatter = Formatify::builder().missing_key(MissingKeyPolicy::Empty).build();
+    /// assert_eq!(formatter.replace_placeholders(&key_value, "[%(name)%(title)]"), "[Alice]");
+    ///
+    /// let formatter = Formatify::builder()
+    ///     .missing_key(MissingKeyPolicy::custom(|key| format!("<{}?>", key)))
+    ///     .build();
+    /// assert_eq!(formatter.replace_placeholders(&key_value, "[%(title)]"), "[<title?>]");
+    /// ```
+    pub fn missing_key(mut self, policy: MissingKeyPolicy) -> Self {
+        self.missing_key = policy;
+        self
+    }
+
+    /// Enables the canonical rendering mode, whose output is guaranteed to stay byte-for-byte
+    /// the same across crate versions, e.g. for documents which are signed or diffed.
+    ///
+    /// A canonical formatter
+    /// - uses the separators `.` and `,` for the numeric format and ignores `locale`,
+    /// - truncates values with the marker `…` (U+2026), even if the default marker changes,
+    /// - leaves the environment-dependent built-ins `%(now)`, `%(uuid)` and `%(rand)` unresolved
+    ///   and keeps `|reldate` placeholders unchanged.
+    ///
+    /// Value sources are ordered maps or are looked up by key, so the output never depends on
+    /// hash map iteration order. Registered modifiers, placeholders and extensions are user code
+    /// and not covered by the guarantee.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter};
+    /// # use std::collections::HashMap;
+    /// let mut key_value : HashMap<&str, String> = HashMap::new();
+    /// key_value.insert("amount", "1234.5".into());
+    /// let formatter = Formatify::builder().canonical().build();
+    /// assert_eq!(formatter.replace_placeholders(&key_value, "%(amount,num=,.2)|%(uuid)"), "1,234.50|%(uuid)");
+    /// ```
+    pub fn canonical(mut self) -> Self {
+        self.canonical = true;
+        self
+    }
+
+    /// Creates the configured `Formatify` instance.
+    pub fn build(self) -> Formatify {
+        let number_symbols = match self.canonical {
+            true => NumberSymbols::default(),
+            false => self.number_symbols,
+        };
+        Formatify {
+            right_to_left: self.right_to_left,
+            target_width: self.target_width,
+            max_expansion_depth: self.max_expansion_depth,
+            #[cfg(feature = "colors")]
+            color_target: self.color_target,
+            number_symbols,
+            #[cfg(feature = "modifiers")]
+            modifiers: HashMap::new(),
+            char_placeholders: HashMap::new(),
+            default_specs: HashMap::new(),
+            presets: HashMap::new(),
+            ambient: AmbientValues::default(),
+            #[cfg(feature = "chrono")]
+            clock: self.clock.unwrap_or_else(clock::system_clock),
+            random: self.random.unwrap_or_else(random::system_random),
+            extensions: Vec::new(),
+            invalid_utf8: self.invalid_utf8,
+            missing_key: self.missing_key,
+            canonical: self.canonical,
+        }
+    }
+}
@@ -0,0 +1,74 @@
+#[cfg(feature = "html")]
+use super::value_span::ValueSpan;
+
+/// Appends the char to `out`, escaping characters with a special meaning in HTML.
+pub fn push_escaped(out: &mut String, ch: char) {
+    match ch {
+        '&' => out.push_str("&amp;"),
+        '<' => out.push_str("&lt;"),
+        '>' => out.push_str("&gt;"),
+        '"' => out.push_str("&quot;"),
+        '\'' => out.push_str("&#39;"),
+        _ => out.push(ch),
+    }
+}
+
+/// Escapes characters with a special meaning in HTML.
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        push_escaped(&mut out, ch);
+    }
+    out
+}
+
+/// Renders the escaped output chars and wraps each span in a `<span>` element carrying the key
+/// name and the style classes. Spans must be ordered by output position. Empty spans are
+/// rendered before a span starting at the same position; spans overlapping a previous one are
+/// rendered empty, so the elements are always properly nested.
+#[cfg(feature = "html")]
+pub fn render_html(chars: impl IntoIterator<Item = char>, spans: &[ValueSpan]) -> String {
+    let mut out = String::new();
+    let mut spans = spans.iter().peekable();
+    let mut open: Option<usize> = None;
+    for (idx, ch) in chars.into_iter().enumerate() {
+        if open == Some(idx) {
+            out.push_str("</span>");
+            open = None;
+        }
+        let mut opening = None;
+        while let Some(span) = spans.next_if(|span| span.output.start <= idx) {
+            if span.output.end <= idx || open.is_some() || opening.is_some() {
+                out.push_str(&open_tag(span));
+                out.push_str("</span>");
+                continue;
+            }
+            opening = Some(span);
+        }
+        if let Some(span) = opening {
+            out.push_str(&open_tag(span));
+            open = Some(span.output.end);
+        }
+        push_escaped(&mut out, ch);
+    }
+    if open.is_some() {
+        out.push_str("</span>");
+    }
+    for span in spans {
+        out.push_str(&open_tag(span));
+        out.push_str("</span>");
+    }
+    out
+}
+
+#[cfg(feature = "html")]
+fn open_tag(span: &ValueSpan) -> String {
+    let mut classes = vec!["fmt-value".to_string()];
+    classes.extend(span.classes.iter().cloned());
+    let mut tag = format!("<span class=\"{}\"", escape(&classes.join(" ")));
+    if let Some(key) = &span.key {
+        tag.push_str(&format!(" data-key=\"{}\"", escape(key)));
+    }
+    tag.push('>');
+    tag
+}
@@ -0,0 +1,36 @@
+/// Defines how byte values which aren't valid UTF-8 are rendered, see `FormatValue::Bytes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InvalidUtf8Policy {
+    /// Invalid sequences are replaced by the replacement char `�` (U+FFFD).
+    #[default]
+    Lossy,
+    /// Invalid bytes are rendered as hex escapes like `\xff`. Valid sequences are kept.
+    HexEscape,
+    /// The value is rejected and treated as missing, so the placeholder is rendered unchanged
+    /// or its default value is used.
+    Error,
+}
+
+impl InvalidUtf8Policy {
+    /// Decodes the bytes of a value. Valid UTF-8 is decoded unchanged by all policies. `None`
+    /// if the value is rejected.
+    pub fn decode(self, bytes: &[u8]) -> Option<String> {
+        if let Ok(value) = std::str::from_utf8(bytes) {
+            return Some(value.to_string());
+        }
+        match self {
+            InvalidUtf8Policy::Lossy => Some(String::from_utf8_lossy(bytes).into_owned()),
+            InvalidUtf8Policy::HexEscape => {
+                let mut value = String::new();
+                for chunk in bytes.utf8_chunks() {
+                    value.push_str(chunk.valid());
+                    for byte in chunk.invalid() {
+                        value.push_str(&format!("\\x{:02x}", byte));
+                    }
+                }
+                Some(value)
+            }
+            InvalidUtf8Policy::Error => None,
+        }
+    }
+}
@@ -0,0 +1,14 @@
+/// Decision of an access policy about the value of a key, see
+/// `PlaceholderFormatter::replace_placeholders_with_policy`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyAccess {
+    /// The value is rendered unchanged.
+    Allow,
+    /// The key is treated as missing, so fallback keys, default values and else branches apply.
+    Deny,
+    /// The value is replaced, e.g. by `***`.
+    Mask(String),
+}
+
+/// Access policy of a render. Receives the key and its value and decides how it is rendered.
+pub type KeyPolicy = dyn Fn(&str, &str) -> KeyAccess;
@@ -0,0 +1,90 @@
+use std::collections::{BTreeMap, HashMap};
+
+/// Source of values which are queried on demand, see
+/// `PlaceholderFormatter::replace_placeholders_provider`.
+///
+/// Unlike a key-value map, a provider doesn't have to materialize all of its keys, e.g. for a
+/// configuration dump with thousands of keys. Only the keys referenced by a template are
+/// queried.
+///
+/// # Examples
+/// ```
+/// # use formatify::{Formatify, KeyProvider, PlaceholderFormatter, ScopedProvider};
+/// let env = |key: &str| (key == "user").then(|| "alice".to_string());
+/// let config = |key: &str| (key == "port").then(|| "8080".to_string());
+/// let providers: [&dyn KeyProvider; 2] = [
+///     &ScopedProvider::new("env", env),
+///     &ScopedProvider::new("config", config),
+/// ];
+///
+/// let formatter = Formatify::new();
+/// let out = formatter.replace_placeholders_provider(&providers, "%(env.user)@%(config.port)");
+/// assert_eq!(out, "alice@8080");
+/// ```
+pub trait KeyProvider {
+    /// Returns the value of `key`. `None` if the key is missing.
+    fn get(&self, key: &str) -> Option<String>;
+}
+
+impl<F> KeyProvider for F
+where
+    F: Fn(&str) -> Option<String>,
+{
+    fn get(&self, key: &str) -> Option<String> {
+        self(key)
+    }
+}
+
+impl KeyProvider for HashMap<&str, String> {
+    fn get(&self, key: &str) -> Option<String> {
+        HashMap::get(self, key).cloned()
+    }
+}
+
+impl KeyProvider for HashMap<String, String> {
+    fn get(&self, key: &str) -> Option<String> {
+        HashMap::get(self, key).cloned()
+    }
+}
+
+impl KeyProvider for BTreeMap<String, String> {
+    fn get(&self, key: &str) -> Option<String> {
+        BTreeMap::get(self, key).cloned()
+    }
+}
+
+/// Providers queried in order. The first provider which has the key provides its value.
+impl KeyProvider for [&dyn KeyProvider] {
+    fn get(&self, key: &str) -> Option<String> {
+        self.iter().find_map(|provider| provider.get(key))
+    }
+}
+
+impl<const N: usize> KeyProvider for [&dyn KeyProvider; N] {
+    fn get(&self, key: &str) -> Option<String> {
+        KeyProvider::get(self.as_slice(), key)
+    }
+}
+
+/// Provider which serves the keys below a prefix, e.g. `env.user` for the prefix `env`. The
+/// wrapped provider is queried without the prefix, e.g. for `user`.
+pub struct ScopedProvider<P> {
+    prefix: String,
+    provider: P,
+}
+
+impl<P: KeyProvider> ScopedProvider<P> {
+    /// Mounts `provider` below `prefix`. The prefix and the key are separated by a dot.
+    pub fn new(prefix: &str, provider: P) -> Self {
+        Self {
+            prefix: format!("{}.", prefix),
+            provider,
+        }
+    }
+}
+
+impl<P: KeyProvider> KeyProvider for ScopedProvider<P> {
+    fn get(&self, key: &str) -> Option<String> {
+        self.provider.get(key.strip_prefix(&self.prefix)?)
+    }
+}
@@ -16,10 +16,100 @@
 //! 1. **Single-Character Placeholders**:
 //!    - **New Line (`%n`)**: Inserts a newline character where `%n` is placed.
 //!    - **Percentage (`%%`)**: Escapes and inserts a literal percent sign.
+//!    - **Tab (`%t`)**: Inserts a tab character.
+//!    - **Carriage Return (`%r`)**: Inserts a carriage return character.
+//!    - **Escape (`%e`)**: Inserts the ESC character, e.g. for terminal control sequences.
+//!    - **Non-Breaking Space (`%s`)**: Inserts a non-breaking space (U+00A0).
+//!    - **Hard Space (`%_`)**: Inserts a space which is never replaced by a line break when lines are wrapped with `%w`.
+//!    - **NUL (`%0`)**: Inserts the NUL character, e.g. for null-delimited output like `git log -z`.
+//!    - **Hex Code (`%xNN`)**: Inserts the ASCII character with the two-digit hex code `NN`, e.g. `%x1b` for ESC.
+//!    - **Custom**: Further single-character placeholders like `%d` can be registered with `Formatify::register_char_placeholder`.
+//!      The chars of the built-in placeholders are reserved.
 //!
 //! 2. **Variable Substitution**:
 //!    - **Syntax**: `%(key)`
 //!    - **Description**: Replaces this placeholder with the value associated with `key` in the `key_value` HashMap.
+//!    - **Dotted Paths**: `%(user.name)` resolves nested values of a `ValueTree` passed to `replace_placeholders_tree`.
+//!      In a flat `key_value` HashMap the dotted path is an ordinary key.
+//!    - **List Index**: `%(key[0])` renders the first item of a list value (see `join_list`), `%(key[-1])` the last one.
+//!      An index out of range is treated like a missing key.
+//!    - **Fallback Keys**: `%(key1|key2|key3)` uses the value of the first key which is part of the `key_value` HashMap.
+//!    - **Default Value**: `%(key:-default)` renders `default` if `key` is missing or its value is empty (bash-style).
+//!      Fallback keys and a default value can be combined, e.g. `%(nick|name:-anonymous)`.
+//!    - **Ambient Values**: Keys missing in the `key_value` HashMap are looked up in the values set with
+//!      `Formatify::set_ambient`, which can be overridden temporarily with `Formatify::override_ambient`.
+//!    - **Missing Keys**: A placeholder whose key can't be resolved is rendered unchanged. `FormatifyBuilder::missing_key`
+//!      renders an empty string, a fixed value or the value of a closure called with the key instead.
+//!    - **Modifiers**: `%(key|upper|trim)` applies a chain of transformations to the value before it is aligned.
+//!      Supported modifiers are `upper`, `lower`, `trim`, `ltrim`, `rtrim`, `capitalize`, the case modifiers, the radix
+//!      modifiers and the modifiers registered with `Formatify::register_modifier`.
+//!      `trim_start` and `trim_end` are aliases of `ltrim` and `rtrim`. `trim:collapse` trims the value and replaces
+//!      internal whitespace runs by a single space, e.g. for values from external sources in fixed-width layouts.
+//!      The case modifiers `title`, `snake`, `camel` and `kebab` convert e.g. `user ID` to `User Id`, `user_id`,
+//!      `userId` and `user-id`. Words are separated by non-alphanumeric chars and case changes like in `userId` or
+//!      `HTTPServer`. The radix modifiers `hex`, `oct` and `bin` render an
+//!      integer value in another radix; `0x`, `0o` and `0b` add the prefix as well. A trailing number zero-pads the
+//!      digits, e.g. `%(flags|0x8)` renders `0x000001ff`. Values which aren't integers are rendered unchanged.
+//!      `plural:s` or `plural:file,files` picks a word form based on the numeric value, e.g. `%(count) file%(count|plural:s)`
+//!      renders `1 file` or `2 files`.
+//!      `repeat:n` repeats the value `n` times, e.g. `%(star|repeat:5)`. `n` can be the key of the count, e.g.
+//!      `%(star|repeat:rating)`.
+//!      `replace:from,to` replaces all occurrences of the literal `from` by `to`, e.g. `%(path|replace:/,\\)`. `from`
+//!      can't contain `,` or start with `-`, and `)` and `|` are only allowed within balanced parentheses. With the
+//!      `regex` feature, `replace_re:pattern,to` replaces all matches of a regular expression, e.g.
+//!      `%(name|replace_re:(\w+) (\w+),$2 $1)`, where `to` refers to capture groups like `$1`.
+//!      `bytes` renders a byte count with binary units like `1.4 MiB`, `bytes_si` with SI units like `23 kB`. Values
+//!      below 10 get one decimal place, others none; a trailing number sets the decimal places, e.g. `bytes_si2`. Modifiers follow the key and its fallback keys, e.g.
+//!      `%(nick|name|upper:-anonymous)`. The default value is not modified, but it is used if the modified value is
+//!      empty. Requires the `modifiers` feature; without it all segments are fallback keys.
+//!      `html` escapes `<`, `>`, `&`, `"` and `'`, so values from untrusted sources are safe in HTML snippets, e.g.
+//!      `<b>%(name|html)</b>`. Alignment counts the chars of the escaped value.
+//!      `json` escapes and quotes the value as a JSON string, e.g. `{"name": %(name|json)}`. `shell` quotes it with
+//!      POSIX single quotes, e.g. `git log --author=%(author|shell)`, so it is a single word of a shell command.
+//!      `urlencode` percent-encodes all bytes except `A-Z`, `a-z`, `0-9`, `-`, `.`, `_` and `~`, e.g.
+//!      `https://host/search?q=%(term|urlencode)`. `urlencode:path` keeps `/` for paths, `urlencode:query` encodes
+//!      spaces as `+` like HTML forms.
+//!      `base64` encodes the value with Base64, e.g. `Authorization: Basic %(credentials|base64)`. `base64url` uses the
+//!      URL-safe alphabet without padding.
+//!    - **Date Format**: `%(timestamp|date:%Y-%m-%d %H:%M)` parses an RFC 3339 or unix epoch value and reformats it
+//!      with a strftime pattern, like git's `--date=format:`. Unix epoch values are rendered in UTC. Values which aren't
+//!      timestamps are rendered unchanged. Requires the `chrono` feature.
+//!    - **Relative Dates**: `%(timestamp|reldate)` renders the time relative to now like git's `--date=relative`, e.g.
+//!      `3 hours ago` or `in 2 days`. `%(now)` renders the current time in RFC 3339 if the key isn't set otherwise, so
+//!      `%(now|date:%Y)` renders the current year. The clock can be replaced with `FormatifyBuilder::clock` for
+//!      deterministic output. Requires the `chrono` feature.
+//!    - **Numeric Format**: `%(amount,num=.2)` rounds a numeric value to two decimal places, `%(count,num=,)` groups
+//!      its integer digits with thousands separators. Both can be combined, e.g. `%(total,num=,.2)`. The format follows
+//!      the fallback keys and modifiers and is applied before the modifiers. Non-numeric values are rendered unchanged.
+//!      With the `num-format` feature, `FormatifyBuilder::locale` selects locale-specific separators, e.g. `1.234,50`.
+//!    - **Plural and Select**: A subset of ICU MessageFormat selects text by the value, e.g.
+//!      `%(count,plural,=0{keine Dateien}one{# Datei}other{# Dateien})` or `%(gender,select,female{sie}other{er})`.
+//!      Plural selectors are exact matches like `=0`, which take precedence, `one` for the value 1 and `other`; `#` is
+//!      replaced by the value, formatted by a preceding numeric format. Select matches the value literally. The `other`
+//!      branch is required and used if no other branch matches. Branch texts can't contain braces.
+//!    - **Random Values**: `%(uuid)` renders a random UUID (version 4) and `%(rand)` a random number, unless the keys
+//!      are set otherwise. `FormatifyBuilder::seed` makes them reproducible, e.g. in tests.
+//!    - **Recursive Expansion**: Values may contain placeholders themselves. They are expanded if enabled with
+//!      `FormatifyBuilder::recursive_expansion`, which limits the depth and detects cycles.
+//!    - **Default Format Specs**: `Formatify::default_spec` sets a format per key, e.g. `<8,trunc` for `sha`, which is
+//!      applied whenever the template doesn't precede the value placeholder with a format placeholder.
+//!    - **Whitespace Prefixes**: Like git's pretty formats, `%+(key)` inserts a line feed and `% (key)` a space before the
+//!      value if it is non-empty. `%-(key)` removes the line feeds directly preceding the value if it is empty, e.g. for
+//!      optional trailer sections like `%(subject)%n%n%-(trailers)`. Missing keys leave the output unchanged. `%-(`
+//!      followed by a letter or `_` starts a value placeholder, anything else a horizontal rule, so keys starting with
+//!      a digit can't be used with `-`: `%-(4)` is a rule of width 4 even if the key `4` exists and `%-(4th)` is invalid.
+//!    - **Length**: `%(#key)` renders the length of the value of `key`: its number of chars, or its display width for
+//!      monospace targets like `replace_placeholders_code_block`. Format placeholders apply, e.g. `%>(3)%(#title)`.
+//!    - **Aggregates**: `%(sum:key)`, `%(avg:key)`, `%(min:key)`, `%(max:key)` and `%(count:key)` aggregate the items of
+//!      the list value `key`, e.g. in the footer of a table whose rows are rendered by a loop block. `count` counts all
+//!      items, the others skip items which aren't numbers. `sum` keeps the most decimal places of the items, `avg`
+//!      rounds to two more and trims trailing zeros, `min` and `max` render the item as written. Without numbers the
+//!      aggregate is empty. Modifiers, numeric formats and default values apply, e.g. `%(avg:latency,num=.1:-n/a)`.
+//!    - **Rendered Width**: `%(width_of:key)` renders the width the value placeholder `%(key)` occupied earlier in the
+//!      same render, including the padding of its format. It can also be used as width of format and horizontal rule
+//!      placeholders, e.g. `%(title)%n%-(%(width_of:title),'=')` underlines a title of any length. A placeholder which
+//!      wasn't rendered yet leaves the width placeholder unchanged, respectively makes the format invalid.
+//!    - **Extensions**: Third-party placeholder families like `%(qr:...)` can be added with `Formatify::register_extension`.
 //!
 //! 3. **Format Placeholders**:
 //!    - **Left Alignment**:
@@ -40,6 +130,107 @@
 //!    - **Right Alignment with left Truncation**:
 //!        - **Syntax**: `%>(width,ltrunc)`
 //!        - **Description**: Similar to right alignment, but left truncates the text to fit within the specified `width`. The placeholder itself is not displayed.
+//!    - **Full Justification**:
+//!        - **Syntax**: `%<(width,justify)`
+//!        - **Description**: Justifies the words of the subsequent placeholder to exactly `width` characters by widening
+//!          the gaps between them, e.g. for report-style text blocks. Longer values are broken into lines of `width`
+//!          characters, which start at the column of the placeholder; the last line is left-aligned. Words longer than
+//!          `width` are truncated with `…`, so every line is exactly `width` characters wide.
+//!    - **Absolute Column Positioning**:
+//!        - **Syntax**: `%<|(column)`, `%>|(column)`, optionally with `trunc` or `ltrunc`
+//!        - **Description**: Like the alignments above, but `column` is measured from the start of the current line instead of
+//!          the start of the placeholder. The subsequent placeholder is padded so that the output reaches `column`, regardless
+//!          of what came before.
+//!    - **Presets**:
+//!        - **Syntax**: `%<(preset:name)`, `%>(preset:name)`
+//!        - **Description**: Uses the width and truncation of a preset registered with `Formatify::register_preset`, e.g.
+//!          `8,trunc`, so width policies can be changed in one place. Unknown presets are treated as invalid.
+//!    - **Column Span**:
+//!        - **Syntax**: `%<(span=n)`, `%>(span=n)`
+//!        - **Description**: Aligns the subsequent value over the combined width of `n` columns of a table rendered with
+//!          `replace_placeholders_joined`, including the separators between them, e.g. for titles or grouped headers.
+//!          A wider value widens the last spanned column. Otherwise the value is rendered without format. Requires the
+//!          `tables` feature.
+//!    - **Block Indent**:
+//!        - **Syntax**: `%I(n)`, `%I("prefix")`
+//!        - **Description**: Prefixes each line of the subsequent multi-line value with `n` spaces or `prefix`, e.g.
+//!          `%I("> ")%(body)` quotes a message body. The prefix precedes the field of an alignment. Empty values
+//!          aren't prefixed.
+//!    - **Fill Remaining Width**:
+//!        - **Syntax**: `%<(*)`, `%>(*)`, optionally with `trunc` or `ltrunc`
+//!        - **Description**: Uses all space between the current column and the target width configured with
+//!          [`FormatifyBuilder::target_width`] as field width, e.g. to push a status segment to the right edge of the line.
+//!          Text following the subsequent placeholder on the same line is not taken into account. Without a configured
+//!          target width the placeholder is treated as invalid.
+//!
+//! 4. **Horizontal Rule Placeholders**:
+//!    - **Syntax**: `%-(width)`, `%-(width,'c')`, `%-(*)`, `%-(*,'c')`
+//!    - **Description**: Emits the fill character `c` (default `-`) `width` times. With `*` the rule fills the line up to the
+//!      target width configured with [`FormatifyBuilder::target_width`]. Useful for separators and boxes in reports.
+//!      The width is never read as key, see the `-` prefix of value placeholders.
+//!
+//! 5. **Tab-Stop Placeholders**:
+//!    - **Syntax**: `%T(n)`
+//!    - **Description**: Inserts spaces until the output reaches the next multiple of `n` columns (classic tab stops).
+//!      At least one space is inserted. Aligns loosely structured lines without knowing exact field widths.
+//!
+//! 6. **Line Wrapping**:
+//!    - **Syntax**: `%w(width)`, `%w(width,indent1)`, `%w(width,indent1,indent2)`
+//!    - **Description**: Like git's `%w()`, wraps the subsequent output at `width` columns, indenting the first line by
+//!      `indent1` and the continuation lines by `indent2` (both default to 0). Lines are broken at the last space which
+//!      keeps them within `width`; longer words aren't broken. A width of 0 only indents the lines, `%w(0)` switches
+//!      wrapping off, e.g. `%w(72,4,4)%(body)%w(0)`.
+//!
+//! 7. **Alignment Anchors**:
+//!    - **Syntax**: `%|(name)`
+//!    - **Description**: Pads the output with spaces to the column of the rightmost anchor of the same name in the
+//!      whole template, so anchors on different lines line up, e.g. `%(k1)%|(sep): %(v1)%n%(k2)%|(sep): %(v2)` aligns
+//!      both `:`. Anchors of different names may share a line. Anchors in inactive blocks are ignored.
+//!    - **Localized Labels**: The columns are measured for each render, so a single template aligns labels of any
+//!      length, e.g. translations passed as values: `%(l_name)%|(l): %(name)%n%(l_date)%|(l): %(date)`.
+//!
+//! 8. **Conditional Blocks**:
+//!    - **Syntax**: `%(?key)…%(/key)` and `%(?key)…%(:else)…%(/key)`
+//!    - **Description**: Renders the content between the tags only if `key` is present in the `key_value` HashMap and its
+//!      value is non-empty. The optional `%(:else)` branch is rendered otherwise. Blocks can be nested; the closing tag
+//!      must name the key of the innermost open block. `extract_placeholder_keys` lists the keys of all branches.
+//!      Requires the `blocks` feature, like loop and switch blocks.
+//!    - **Alternative Syntax**: `%(if:key)…%(else)…%(end)`
+//!      - Equivalent to the syntax above, accepting the same conditions. `%(end)` closes the innermost open block.
+//!      - `if:`, `for:`, `switch:` and `case:` are reserved and can't be used as keys. `default`, `else` and `end` are
+//!        reserved within blocks only, e.g. `%(end)` outside of blocks is the value of the key `end`.
+//!    - **Comparisons**: `%(?count>0)…%(/count)`, `%(?status=="open")…%(/status)`
+//!      - The operators `==`, `!=`, `<`, `<=`, `>` and `>=` compare a key with a number, a quoted string or another key.
+//!      - If both sides parse as numbers, they are compared numerically, otherwise as strings.
+//!      - Comparisons involving a missing key are false. The closing tag names the first key of the expression.
+//!    - **Logical Operators**: `%(?a && !b)…%(/a)`, `%(if:(a || b) && c > 1)…%(end)`
+//!      - `!` binds strongest, followed by `&&` and `||`. Brackets group sub-expressions.
+//!      - Evaluation short-circuits.
+//!
+//! 9. **Loop Blocks**:
+//!    - **Syntax**: `%(for:item in items)…%(end)`, optionally with an `%(else)` branch for empty lists
+//!    - **Description**: Renders the content once for each element of the list value `items`, binding the element to
+//!      `item`. List values are encoded by joining the elements with [`LIST_SEPARATOR`], see [`join_list`]. Loops can
+//!      be nested and may also be closed with `%(/items)`. Loop variables are not listed by `extract_placeholder_keys`.
+//!      Requires the `blocks` feature.
+//!
+//! 10. **Switch Blocks**:
+//!    - **Syntax**: `%(switch:key)%(case:value1)…%(case:value2)…%(default)…%(/switch)`
+//!    - **Description**: Renders the first case whose label equals the value of `key`, or the optional `%(default)`
+//!      branch if no case matches. Content before the first case is ignored. The block can also be closed with `%(end)`.
+//!      Requires the `blocks` feature.
+//!
+//! 11. **Color Placeholders**:
+//!    - **Syntax**: `%C(color)`, e.g. `%C(red)`, `%C(bold blue)`, `%C(white red)`, `%C(reset)`
+//!    - **Description**: Emits the ANSI escape sequence for the given color specification, mirroring git's pretty-format.
+//!      The first color is the foreground, the second the background. Supported colors are `normal`, `default`, `black`, `red`,
+//!      `green`, `yellow`, `blue`, `magenta`, `cyan`, `white`, their `bright` variants (e.g. `brightred`), 256 color numbers and
+//!      `#rrggbb`. Supported attributes are `bold`, `dim`, `italic`, `ul`, `blink`, `reverse` and `strike`.
+//!      The shorthands `%Cred`, `%Cgreen`, `%Cblue` and `%Creset` are supported as well.
+//!      Color placeholders are zero-width for `measure_lengths` and ignored by `extract_placeholder_keys`.
+//!      Instead of ANSI sequences, IRC control codes or BBCode tags can be emitted for chat systems, see
+//!      `FormatifyBuilder::color_target`.
+//!      Requires the `colors` feature.
 //!
 //!
 //! Note: In the context of format placeholders, `width` refers to the total number of characters allocated for the value being formatted. For example, `%<(10)` aligns the value within a 10-character wide field.
@@ -60,11 +251,88 @@
 //!
 //! Public methods utilizing these placeholders include:
 //! - `replace_placeholders`: Replaces placeholders in a string with values from a HashMap.
+//! - `replace_placeholders_vertical`: Renders the placeholders of a template as vertical `name: value` block.
+//! - `replace_placeholders_fixed_width`: Replaces placeholders and guarantees an exact output width, e.g. for fixed-length records.
+//! - `replace_placeholders_strict`: Replaces placeholders and rejects input which would be passed through unchanged, e.g. for code generation.
+//! - `replace_placeholders_sandboxed`: Replaces placeholders within hard quotas, e.g. for templates submitted by end users (requires the `sandbox` feature).
+//! - `replace_placeholders_tree`: Replaces placeholders with values of a hierarchical `ValueTree` using dotted paths.
+//! - `replace_placeholders_json`: Replaces placeholders with values of a JSON document (requires the `json` feature).
+//! - `replace_placeholders_provider`: Replaces placeholders with values queried on demand from a `KeyProvider`, e.g. for huge value sources.
+//! - `replace_placeholders_values`: Replaces placeholders with typed `FormatValue` values. Raw bytes are rendered according to `FormatifyBuilder::invalid_utf8`.
+//! - `replace_placeholders_joined`: Renders several templates as lines whose value placeholders share the same widths (requires the `tables` feature).
+//! - `replace_placeholders_code_block`: Like `replace_placeholders_joined`, but measures monospace display widths and wraps the output in a fenced code block for chat targets (requires the `tables` feature).
+//! - `replace_placeholders_fitting`: Renders the first of several templates, e.g. full, compact and minimal, whose output fits into the available width.
+//! - `replace_placeholders_negotiated`: Reports the natural field widths to a callback which returns the widths to render.
+//! - `replace_placeholders_with_policy`: Replaces placeholders while an access policy vetoes or masks values, e.g. for viewers without permission.
+//! - `replace_placeholders_with_source_map`: Replaces placeholders and maps each output range back to the template or value which produced it.
+//! - `replace_placeholders_with_truncations`: Replaces placeholders and records the complete values of truncated values.
+//! - `replace_placeholders_with_audit`: Replaces placeholders and lists each resolved value with its source, e.g. as compliance record of generated documents.
+//! - `replace_placeholders_checked`: Replaces placeholders and lists the missing keys with their counts, e.g. to log data-quality problems of batch jobs.
+//! - `replace_placeholders_html`: Renders escaped HTML with each substituted value wrapped in a `<span>` carrying its key name (requires the `html` feature).
+//! - `replace_placeholders_markdown`: Renders Slack mrkdwn or Discord markdown, e.g. for chat notifications (requires the `markdown` feature).
+//! - `equivalent`: Renders two templates with sample maps and reports the first sample whose outputs differ.
+//! - `validate`: Checks a template without rendering it and returns all problems with their kind, byte range and message.
+//! - `render_and_diff`: Renders a template with changed values and diffs the whole output against the previous output, e.g. to repaint only changed TUI cells.
 //! - `measure_lengths`: Calculates the length of strings and placeholders.
+//! - `measure_and_render`: Combines `replace_placeholders` and `measure_lengths` in a single pass.
 //! - `extract_placeholder_keys`: Extracts and lists all valid placeholder keys from a string.
+//! - `extract_unique_placeholder_keys`: Extracts each placeholder key of a string once.
+//! - `count_placeholder_keys`: Counts the references of each placeholder key of a string.
+//! - `extract_placeholders`: Lists the value placeholders of a string with their format and byte range.
+//! - `unused_keys`: Lists the keys of a key-value map which aren't referenced by a template.
+//! - `parse`: Parses a template into literal, placeholder, directive and error segments, e.g. for linters and editors.
+//! - `annotate`: Lists the placeholders and invalid placeholders of a template with byte ranges, e.g. for editor extensions.
 //!
 //! For more details on these methods and their usage, refer to the respective method documentation in this module.
 //!
+//! ## Compiled Templates
+//!
+//! `Template::parse` parses a template once with the configuration of a `Formatify` instance. Its `render`, `measure`
+//! and `keys` methods correspond to `replace_placeholders`, `measure_lengths` and `extract_placeholder_keys`, but skip
+//! parsing the value placeholders, e.g. when rendering thousands of records with the same template.
+//! `render_with` renders with values queried from a `KeyProvider` like `replace_placeholders_provider`.
+//! `render_nul_delimited` renders a batch of records joined by NUL chars for `xargs -0` style consumers.
+//!
+//! A `ColumnPlan` fixes the column widths of a row template up-front, explicitly or from a sampled prefix of the rows,
+//! so unbounded row streams can be tabulated row by row without measuring all rows first (requires the `tables`
+//! feature).
+//!
+//! ## Cargo Features
+//!
+//! The default build contains the placeholder engine with the plain text render variants of `PlaceholderFormatter`,
+//! e.g. checked and audited renders. Tables, output targets, sandboxing, subsystems which extend the template syntax
+//! and subsystems which need dependencies are opt-in:
+//! - `modifiers`: The modifier pipeline `%(key|upper|trim)` and `Formatify::register_modifier`.
+//! - `colors`: The color placeholders `%C(...)` for ANSI, IRC and BBCode targets.
+//! - `blocks`: The conditional, loop and switch blocks `%(if:key)…%(end)`, `%(for:item in items)…%(end)` and
+//!   `%(switch:key)…%(end)`, and the loop quotas of `sandbox`.
+//! - `tables`: `replace_placeholders_joined`, `replace_placeholders_code_block`, `ColumnPlan` and column spans like
+//!   `%<(span=2)`.
+//! - `sandbox`: `replace_placeholders_sandboxed` with `Quotas` for untrusted templates.
+//! - `html`: `replace_placeholders_html`, which renders escaped HTML with semantic spans.
+//! - `markdown`: `replace_placeholders_markdown` for Slack mrkdwn and Discord markdown. Implies `html`.
+//! - `json`: `replace_placeholders_json` to use a `serde_json::Value` as value source.
+//! - `tracing`: Emits `tracing` events for invalid placeholders and missing keys (warn) and truncated values (debug)
+//!   within a `render` span carrying the `template_id`, a stable hash of the template.
+//! - `metrics`: Emits the counters `formatify_renders_total` and `formatify_missing_keys_total` and the histogram
+//!   `formatify_output_bytes` via the `metrics` facade. Each rendered template counts as one render, e.g. each line
+//!   of `replace_placeholders_joined`; recursively expanded values don't count.
+//! - `chrono`: The date modifiers `%(key|date:%Y-%m-%d)` and `%(key|reldate)` and `%(now)`. Implies `modifiers`.
+//! - `regex`: The regex replacement modifier `%(key|replace_re:pattern,to)`. Implies `modifiers`.
+//! - `ratatui`: `TemplateRegion`, which renders a template into a region of a ratatui `Buffer` (and thereby any
+//!   ratatui backend like crossterm) and repaints only the cells which changed since the previous render. Color
+//!   placeholders style the cells if the `colors` feature is enabled.
+//! - `num-format`: `FormatifyBuilder::locale` for locale-specific separators of the numeric format `%(key,num=,.2)`.
+//! - `full`: Enables all of the above.
+//! - `test-util`: `MockPlaceholderFormatter`, a formatter with scripted outputs and call recording for unit tests of
+//!   code depending on `dyn PlaceholderFormatter`.
+//!
+//! ## Canonical Output
+//!
+//! A formatter built with `FormatifyBuilder::canonical` renders output which is guaranteed to stay byte-for-byte the
+//! same across crate versions: fixed numeric separators regardless of the locale, a pinned truncation marker and no
+//! clock- or random-based built-ins. Use it for generated documents which are signed or diffed.
+//!
 //! ## Integration and Compatibility
 //!
 //! Formatify is designed to be easily integrated into existing Rust projects and works seamlessly with standard data
@@ -75,27 +343,187 @@
 //! Contributions to Formatify are welcome. For bug reports, feature requests, or general feedback, please open an issue
 //! on the repository's issue tracker.
 
+mod aggregate;
+mod ambient;
+mod audit;
+#[cfg(feature = "blocks")]
+mod block_state;
+#[cfg(feature = "chrono")]
+mod clock;
+#[cfg(feature = "colors")]
+mod color_spec;
+#[cfg(feature = "tables")]
+mod column_plan;
+#[cfg(feature = "blocks")]
+mod condition;
+mod display_width;
+mod format_value;
+mod formatify_builder;
+#[cfg(any(feature = "html", feature = "modifiers"))]
+mod html;
+mod invalid_utf8_policy;
+mod key_access;
+mod key_provider;
+mod line_wrap;
+mod list_value;
+#[cfg(feature = "markdown")]
+mod markdown;
+mod message_format;
+mod missing_key_policy;
+#[cfg(feature = "test-util")]
+mod mock_formatter;
+#[cfg(feature = "modifiers")]
+mod modifier;
+mod multi_byte_policy;
+mod number_format;
+mod output_edit;
 mod output_format;
 mod parsing_context;
 mod parsing_task;
+mod parsing_task_audit;
+mod parsing_task_block_gate;
+#[cfg(feature = "tables")]
+mod parsing_task_cell_layout;
+mod parsing_task_check_fixed_width;
+mod parsing_task_check_literals;
+mod parsing_task_compile;
 mod parsing_task_extract_placeholder_keys;
+#[cfg(feature = "html")]
+mod parsing_task_html;
+#[cfg(feature = "markdown")]
+mod parsing_task_markdown;
+mod parsing_task_measure_anchors;
+mod parsing_task_measure_and_render;
 mod parsing_task_measure_lengths;
+mod parsing_task_missing_keys;
 mod parsing_task_replace_placeholders;
+mod parsing_task_segments;
+mod parsing_task_source_map;
+#[cfg(feature = "ratatui")]
+mod parsing_task_styled;
+mod parsing_task_truncations;
 mod peek_char_iterator;
+mod placeholder_extension;
 mod placeholder_formatter;
+mod quota;
+mod random;
+mod render_error;
+#[cfg(feature = "metrics")]
+mod render_metrics;
+mod segment;
+mod source_map;
+mod str_placeholder;
+mod template;
+mod template_error;
+#[cfg(feature = "tracing")]
+mod template_id;
+mod template_mismatch;
+mod token;
+mod truncation;
+#[cfg(feature = "ratatui")]
+mod tui;
+#[cfg(feature = "html")]
+mod value_span;
+mod value_tree;
+mod whitespace_prefix;
 
+use self::aggregate::Aggregate;
+pub use self::ambient::AmbientGuard;
+use self::ambient::AmbientValues;
+pub use self::audit::{AuditEntry, ValueSource};
+#[cfg(feature = "blocks")]
+use self::block_state::{BlockState, LoopState};
+#[cfg(feature = "chrono")]
+use self::clock::ClockFn;
+#[cfg(feature = "colors")]
+use self::color_spec::ColorSpec;
+#[cfg(feature = "colors")]
+pub use self::color_spec::ColorTarget;
+#[cfg(feature = "tables")]
+pub use self::column_plan::ColumnPlan;
+#[cfg(feature = "blocks")]
+use self::condition::Condition;
+pub use self::format_value::FormatValue;
+pub use self::formatify_builder::FormatifyBuilder;
+pub use self::invalid_utf8_policy::InvalidUtf8Policy;
+pub use self::key_access::{KeyAccess, KeyPolicy};
+pub use self::key_provider::{KeyProvider, ScopedProvider};
+use self::line_wrap::LineWrap;
+use self::list_value::split_list;
+pub use self::list_value::{join_list, LIST_SEPARATOR};
+#[cfg(feature = "markdown")]
+pub use self::markdown::MarkdownFlavor;
+use self::message_format::MessageFormat;
+pub use self::missing_key_policy::{MissingKeyFn, MissingKeyPolicy};
+#[cfg(feature = "test-util")]
+pub use self::mock_formatter::{MockCall, MockPlaceholderFormatter};
+#[cfg(feature = "modifiers")]
+use self::modifier::{Modifier, ModifierFn};
+pub use self::multi_byte_policy::MultiBytePolicy;
+use self::number_format::{NumberFormat, NumberSymbols};
+use self::output_edit::diff_outputs;
+pub use self::output_edit::OutputEdit;
 use self::output_format::OutputFormat;
 use self::parsing_context::ParsingContext;
 use self::parsing_task::ParsingTask;
+use self::parsing_task_audit::ParsingTaskAudit;
+use self::parsing_task_block_gate::ParsingTaskBlockGate;
+#[cfg(feature = "tables")]
+use self::parsing_task_cell_layout::{CellLayout, ParsingTaskCellLayout};
+use self::parsing_task_check_fixed_width::ParsingTaskCheckFixedWidth;
+use self::parsing_task_check_literals::ParsingTaskCheckLiterals;
 use self::parsing_task_extract_placeholder_keys::ParsingTaskExtractPlaceholderKeys;
+#[cfg(feature = "html")]
+use self::parsing_task_html::ParsingTaskHtml;
+#[cfg(feature = "markdown")]
+use self::parsing_task_markdown::ParsingTaskMarkdown;
+use self::parsing_task_measure_anchors::ParsingTaskMeasureAnchors;
+use self::parsing_task_measure_and_render::ParsingTaskMeasureAndRender;
 use self::parsing_task_measure_lengths::ParsingTaskMeasureLengths;
+use self::parsing_task_missing_keys::ParsingTaskMissingKeys;
 use self::parsing_task_replace_placeholders::ParsingTaskReplacePlaceholders;
-pub use self::placeholder_formatter::PlaceholderFormatter;
+use self::parsing_task_segments::ParsingTaskSegments;
+use self::parsing_task_source_map::ParsingTaskSourceMap;
+#[cfg(feature = "ratatui")]
+use self::parsing_task_styled::ParsingTaskStyled;
+use self::parsing_task_truncations::ParsingTaskTruncations;
+pub use self::placeholder_extension::PlaceholderExtension;
+#[cfg(feature = "tables")]
+use self::placeholder_formatter::fence_code_block;
+pub use self::placeholder_formatter::{ArcFormatter, PlaceholderFormatter};
+#[cfg(not(feature = "sandbox"))]
+use self::quota::Capability;
+#[cfg(feature = "sandbox")]
+use self::quota::QuotaState;
+use self::quota::QUOTA_CHECK_INTERVAL;
+#[cfg(feature = "sandbox")]
+pub use self::quota::{Capability, Quota, Quotas};
+use self::random::RandomFn;
+pub use self::render_error::RenderError;
+pub use self::segment::{Annotation, PlaceholderInfo, Segment};
+pub use self::source_map::{SourceMapEntry, SourceOrigin};
+use self::str_placeholder::StrPlaceholder;
+use self::template::Instruction;
+pub use self::template::Template;
+pub use self::template_error::{TemplateError, TemplateErrorKind};
+#[cfg(feature = "tracing")]
+use self::template_id::template_id;
+pub use self::template_mismatch::TemplateMismatch;
+pub use self::token::{tokenize, Token, TokenKind};
+pub use self::truncation::Truncation;
+#[cfg(feature = "ratatui")]
+pub use self::tui::TemplateRegion;
+pub use self::value_tree::ValueTree;
+use self::whitespace_prefix::WhitespacePrefix;
 use std::collections::HashMap;
+#[cfg(feature = "sandbox")]
+use std::rc::Rc;
+use std::sync::{Arc, PoisonError};
 
 /// `consume_expected_chars` checks and consumes the next char in the iterator if it matches the provided pattern(s).
 /// - `$context`: The parsing context containing the `PeekCharIterator`.
 /// - `$($a:pat)+`: Pattern(s) to match against the next char.
+///
 /// If the next char matches, it's consumed and returned as `Some(char)`. Otherwise, returns `None`.
 macro_rules! consume_expected_chars{
     ($context:ident, $($a:pat)+) => {
@@ -149,6 +577,13 @@ macro_rules! gather {
     }};
 }
 
+/// First chars of the directives within brackets like `%(if:key)`, `%(end)` or `%(#key)`.
+const DIRECTIVE_STARTS: &str = "?ifscde:/#w";
+
+/// Chars following `%` in built-in placeholders like `%n` or `%<(8)`. They can't be registered
+/// as custom single-char placeholders.
+const RESERVED_CHARS: &str = "(<>C-+ TwI|ntres_0x%";
+
 macro_rules! gather_str_placeholder {
     ($context:ident) => {
         gather!(
@@ -165,6 +600,7 @@ macro_rules! gather_str_placeholder {
                 | 'ü'
                 | 'ß'
                 | '?'
+                | '.'
         )
     };
 }
@@ -202,6 +638,8 @@ macro_rules! skip_until_neg_char_match {
 /// use std::collections::HashMap;
 /// ```
 ///
+/// Use [`Formatify::builder`] to configure optional behavior like right-to-left layouts.
+///
 /// ## Features
 ///
 /// 1. **Placeholder Replacement**: Replace placeholders in strings with values from a `HashMap`.
@@ -242,288 +680,8421 @@ macro_rules! skip_until_neg_char_match {
 /// let placeholder_keys = formatter.extract_placeholder_keys("Hello, %(name)! Today is %(day).");
 /// assert_eq!(placeholder_keys, vec!["name", "day"]);
 /// ```
-pub struct Formatify;
+pub struct Formatify {
+    right_to_left: bool,
+    target_width: Option<usize>,
+    max_expansion_depth: Option<usize>,
+    #[cfg(feature = "colors")]
+    color_target: ColorTarget,
+    number_symbols: NumberSymbols,
+    #[cfg(feature = "modifiers")]
+    modifiers: HashMap<String, ModifierFn>,
+    char_placeholders: HashMap<char, CharPlaceholderFn>,
+    default_specs: HashMap<String, OutputFormat>,
+    presets: HashMap<String, OutputFormat>,
+    ambient: AmbientValues,
+    #[cfg(feature = "chrono")]
+    clock: ClockFn,
+    random: RandomFn,
+    extensions: Vec<Arc<dyn PlaceholderExtension>>,
+    invalid_utf8: InvalidUtf8Policy,
+    missing_key: MissingKeyPolicy,
+    canonical: bool,
+}
+
+/// Handler of a user-registered single-character placeholder.
+type CharPlaceholderFn = Arc<dyn Fn(&HashMap<&str, String>) -> String + Send + Sync>;
 
 impl Formatify {
     pub fn new() -> Self {
-        Self
+        FormatifyBuilder::new().build()
+    }
+
+    /// Returns a builder to configure a `Formatify` instance.
+    pub fn builder() -> FormatifyBuilder {
+        FormatifyBuilder::new()
+    }
+
+    /// Registers a named modifier which can be used in value placeholders like `%(title|slug)`.
+    ///
+    /// A registered modifier takes precedence over a built-in modifier of the same name.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter};
+    /// # use std::collections::HashMap;
+    /// let mut key_value : HashMap<&str, String> = HashMap::new();
+    /// key_value.insert("title", "Hello World".into());
+    /// let mut formatter = Formatify::new();
+    /// formatter.register_modifier("slug", |s| s.to_lowercase().replace(' ', "-"));
+    /// assert_eq!(formatter.replace_placeholders(&key_value, "%(title|slug)"), "hello-world");
+    /// ```
+    #[cfg(feature = "modifiers")]
+    pub fn register_modifier<F>(&mut self, name: &str, modifier: F)
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        self.modifiers.insert(name.to_string(), Arc::new(modifier));
+    }
+
+    /// Registers a handler for the single-character placeholder `%<ch>`, e.g. `%d` for a date.
+    ///
+    /// The handler receives the key-value map and returns the value of the placeholder. The value
+    /// is rendered like a value placeholder, so a preceding format placeholder applies to it.
+    /// A registered handler replaces an existing one of the same char.
+    ///
+    /// The chars of built-in placeholders are reserved and can't be registered: `(`, `<`, `>`,
+    /// `C`, `-`, `+`, ` `, `T`, `w`, `I`, `|`, `n`, `t`, `r`, `e`, `s`, `_`, `0`, `x` and `%`.
+    /// `C` is reserved even without the `colors` feature, so templates mean the same in every
+    /// build.
+    ///
+    /// # Errors
+    /// Returns `RenderError::ReservedChar` if `ch` is reserved.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter};
+    /// # use std::collections::HashMap;
+    /// let mut key_value : HashMap<&str, String> = HashMap::new();
+    /// key_value.insert("hash", "3f2a9c1d7e".into());
+    /// let mut formatter = Formatify::new();
+    /// formatter.register_char_placeholder('h', |kv| kv["hash"].chars().take(7).collect()).unwrap();
+    /// assert_eq!(formatter.replace_placeholders(&key_value, "[%h]"), "[3f2a9c1]");
+    /// assert!(formatter.register_char_placeholder('n', |_| "never".into()).is_err());
+    /// ```
+    pub fn register_char_placeholder<F>(&mut self, ch: char, handler: F) -> Result<(), RenderError>
+    where
+        F: Fn(&HashMap<&str, String>) -> String + Send + Sync + 'static,
+    {
+        if RESERVED_CHARS.contains(ch) {
+            return Err(RenderError::ReservedChar { ch });
+        }
+        self.char_placeholders.insert(ch, Arc::new(handler));
+        Ok(())
+    }
+
+    /// Sets the format spec applied to the value of `key` whenever the template doesn't specify a
+    /// format for it, so data owners can enforce a consistent presentation across templates.
+    ///
+    /// The spec uses the syntax of a format placeholder without `%` and brackets, e.g. `<8`,
+    /// `>12` or `<8,trunc`. It is looked up by the first key of a value placeholder.
+    ///
+    /// # Errors
+    /// Returns `RenderError::MalformedPlaceholder` if the spec can't be parsed.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter};
+    /// # use std::collections::HashMap;
+    /// let mut key_value : HashMap<&str, String> = HashMap::new();
+    /// key_value.insert("sha", "3f2a9c1d7e".into());
+    /// let mut formatter = Formatify::new();
+    /// formatter.default_spec("sha", "<8,trunc").unwrap();
+    /// assert_eq!(formatter.replace_placeholders(&key_value, "[%(sha)]"), "[3f2a9c1…]");
+    /// assert_eq!(formatter.replace_placeholders(&key_value, "[%<(4,trunc)%(sha)]"), "[3f2…]");
+    /// ```
+    pub fn default_spec(&mut self, key: &str, spec: &str) -> Result<(), RenderError> {
+        let Some(format) = OutputFormat::parse_spec(spec) else {
+            return Err(RenderError::MalformedPlaceholder {
+                position: 0,
+                found: spec.to_string(),
+            });
+        };
+        self.default_specs.insert(key.to_string(), format);
+        Ok(())
+    }
+
+    /// Sets a value which is merged into every render, e.g. an application version which never
+    /// changes per call. Ambient values have the lowest precedence: a key of the key-value map
+    /// passed to a render shadows the ambient value of the same key.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter};
+    /// # use std::collections::HashMap;
+    /// let mut key_value : HashMap<&str, String> = HashMap::new();
+    /// key_value.insert("name", "Alice".into());
+    /// let mut formatter = Formatify::new();
+    /// formatter.set_ambient("app_version", "1.4.2");
+    /// assert_eq!(formatter.replace_placeholders(&key_value, "%(name) @ v%(app_version)"), "Alice @ v1.4.2");
+    /// ```
+    pub fn set_ambient(&mut self, key: &str, value: &str) {
+        let values = self
+            .ambient
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        Arc::make_mut(values).insert(key.to_string(), value.to_string());
+    }
+
+    /// Removes an ambient value. Returns the removed value, `None` if the key wasn't set.
+    pub fn remove_ambient(&mut self, key: &str) -> Option<String> {
+        let values = self
+            .ambient
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        Arc::make_mut(values).remove(key)
+    }
+
+    /// Overrides an ambient value until the returned guard is dropped, e.g. for the duration of
+    /// a request. The override is visible to all renders of this formatter, including renders
+    /// on other threads.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter};
+    /// # use std::collections::HashMap;
+    /// let key_value : HashMap<&str, String> = HashMap::new();
+    /// let mut formatter = Formatify::new();
+    /// formatter.set_ambient("env", "prod");
+    /// {
+    ///     let _guard = formatter.override_ambient("env", "staging");
+    ///     assert_eq!(formatter.replace_placeholders(&key_value, "[%(env)]"), "[staging]");
+    /// }
+    /// assert_eq!(formatter.replace_placeholders(&key_value, "[%(env)]"), "[prod]");
+    /// ```
+    pub fn override_ambient(&self, key: &str, value: &str) -> AmbientGuard<'_> {
+        AmbientGuard::new(&self.ambient, key, value)
+    }
+
+    /// Registers a named width preset which templates reference with `%<(preset:name)` or
+    /// `%>(preset:name)`, so width policy changes don't require editing every template.
+    ///
+    /// The spec is the content of the brackets of a format placeholder, e.g. `8`, `8,trunc` or
+    /// `8,ltrunc`. A registered preset replaces an existing one of the same name.
+    ///
+    /// # Errors
+    /// Returns `RenderError::MalformedPlaceholder` if the spec can't be parsed.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter};
+    /// # use std::collections::HashMap;
+    /// let mut key_value : HashMap<&str, String> = HashMap::new();
+    /// key_value.insert("sha", "3f2a9c1d7e".into());
+    /// let mut formatter = Formatify::new();
+    /// formatter.register_preset("idcol", "8,trunc").unwrap();
+    /// assert_eq!(formatter.replace_placeholders(&key_value, "[%<(preset:idcol)%(sha)]"), "[3f2a9c1…]");
+    /// ```
+    pub fn register_preset(&mut self, name: &str, spec: &str) -> Result<(), RenderError> {
+        let Some(format) = OutputFormat::parse_spec(&format!("<{}", spec)) else {
+            return Err(RenderError::MalformedPlaceholder {
+                position: 0,
+                found: spec.to_string(),
+            });
+        };
+        self.presets.insert(name.to_string(), format);
+        Ok(())
+    }
+
+    /// Registers an extension which adds a placeholder family like `%(qr:...)`.
+    ///
+    /// Built-in placeholders like `%(if:...)` take precedence over extensions. If several
+    /// extensions use the same sigil, the first registered one is used.
+    pub fn register_extension<E>(&mut self, extension: E)
+    where
+        E: PlaceholderExtension + 'static,
+    {
+        self.extensions.push(Arc::new(extension));
+    }
+
+    /// Renders the template again with the changed values of `delta` and returns the edits which
+    /// update `previous`, the output rendered from `key_value`, to the new output. Each edit
+    /// replaces a char range of `previous`, so a TUI can repaint only the changed cells instead of
+    /// whole lines.
+    ///
+    /// Only the repaint is differential, the render is not: each call costs a full render of the
+    /// template, which is diffed against `previous`. In return the edits are exact even if a value
+    /// moves other parts of the output, e.g. through alignment or blocks. Lines whose length is
+    /// unchanged yield an edit for each changed run of chars. Applying the edits from last to
+    /// first turns `previous` into the new output.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, OutputEdit, PlaceholderFormatter};
+    /// # use std::collections::HashMap;
+    /// let mut key_value : HashMap<&str, String> = HashMap::new();
+    /// key_value.insert("cpu", "12".into());
+    /// key_value.insert("mem", "40".into());
+    /// let formatter = Formatify::new();
+    /// let previous = formatter.replace_placeholders(&key_value, "cpu %>(3)%(cpu)% mem %>(3)%(mem)%");
+    ///
+    /// let mut delta : HashMap<&str, String> = HashMap::new();
+    /// delta.insert("mem", "41".into());
+    /// let edits = formatter.render_and_diff(&previous, &key_value, &delta, "cpu %>(3)%(cpu)% mem %>(3)%(mem)%");
+    /// assert_eq!(edits, vec![OutputEdit { range: 15..16, text: "1".into() }]);
+    /// ```
+    pub fn render_and_diff(
+        &self,
+        previous: &str,
+        key_value: &HashMap<&str, String>,
+        delta: &HashMap<&str, String>,
+        inp: &str,
+    ) -> Vec<OutputEdit> {
+        let mut updated = key_value.clone();
+        updated.extend(delta.iter().map(|(key, value)| (*key, value.clone())));
+        let current = self.replace_placeholders(&updated, inp);
+        diff_outputs(previous, &current)
+    }
+
+    /// Renders two templates with each of the sample maps and checks that their outputs match,
+    /// e.g. to prove that a template refactored to new syntax features behaves like before.
+    ///
+    /// # Errors
+    /// Returns a `TemplateMismatch` with both outputs of the first sample whose outputs differ
+    /// and the edits between them.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, OutputEdit};
+    /// # use std::collections::HashMap;
+    /// let formatter = Formatify::new();
+    /// let samples: Vec<HashMap<&str, String>> = vec![
+    ///     HashMap::from([("nick", "al".into()), ("name", "Alice".into())]),
+    ///     HashMap::from([("name", "Bob".into())]),
+    ///     HashMap::new(),
+    /// ];
+    /// let old = "%(nick|name:-anon)%x21";
+    /// assert!(formatter.equivalent(old, "%(nick|name:-anon)!", &samples).is_ok());
+    ///
+    /// let mismatch = formatter.equivalent(old, "%(name|nick:-anon)!", &samples).unwrap_err();
+    /// assert_eq!(mismatch.sample, 0);
+    /// assert_eq!((mismatch.left.as_str(), mismatch.right.as_str()), ("al!", "Alice!"));
+    /// assert_eq!(mismatch.diff, vec![OutputEdit { range: 0..2, text: "Alice".into() }]);
+    /// ```
+    pub fn equivalent(
+        &self,
+        a: &str,
+        b: &str,
+        samples: &[HashMap<&str, String>],
+    ) -> Result<(), TemplateMismatch> {
+        for (sample, key_value) in samples.iter().enumerate() {
+            let left = self.replace_placeholders(key_value, a);
+            let right = self.replace_placeholders(key_value, b);
+            if left != right {
+                let diff = diff_outputs(&left, &right);
+                return Err(TemplateMismatch {
+                    sample,
+                    left,
+                    right,
+                    diff,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks a template without rendering it, e.g. to report problems of user supplied
+    /// templates in an editor. Unlike `replace_placeholders_strict`, all branches of blocks are
+    /// checked and missing keys aren't reported.
+    ///
+    /// # Errors
+    /// Returns all problems of the template in order of their position. Each `TemplateError`
+    /// carries its kind, the byte range of the offending sequence and a human-readable message.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, TemplateErrorKind};
+    /// let formatter = Formatify::new();
+    /// assert!(formatter.validate("Hi %<(8,trunc)%(name)").is_ok());
+    ///
+    /// let errors = formatter.validate("%<(x)%(name) %>(8,foo)%(id) %(rest").unwrap_err();
+    /// let kinds: Vec<_> = errors.iter().map(|error| (error.kind, error.span.clone())).collect();
+    /// assert_eq!(
+    ///     kinds,
+    ///     vec![
+    ///         (TemplateErrorKind::BadWidth, 0..3),
+    ///         (TemplateErrorKind::UnknownArgument, 13..22),
+    ///         (TemplateErrorKind::Unterminated, 28..34),
+    ///     ]
+    /// );
+    /// assert_eq!(errors[0].message, "invalid width in '%<('");
+    /// ```
+    pub fn validate(&self, inp: &str) -> Result<(), Vec<TemplateError>> {
+        let mut errors: Vec<TemplateError> = self
+            .annotate(inp)
+            .into_iter()
+            .filter(|annotation| matches!(annotation.segment, Segment::Error { .. }))
+            .map(|annotation| TemplateError::malformed(inp, annotation.bytes))
+            .collect();
+        let key_value = HashMap::new();
+        for violation in self.parse_generic::<ParsingTaskCheckLiterals>(&key_value, inp) {
+            if let RenderError::UnclosedBlock { name } = violation {
+                errors.push(TemplateError::unclosed_block(inp, &name));
+            }
+        }
+        if errors.is_empty() {
+            return Ok(());
+        }
+        Err(errors)
+    }
+
+    /// Returns the registered or built-in modifier with the given name.
+    #[cfg(feature = "modifiers")]
+    fn modifier(&self, name: &str) -> Option<Modifier> {
+        match self.modifiers.get(name) {
+            Some(modifier) => Some(Modifier::Custom(modifier.clone())),
+            #[cfg(feature = "chrono")]
+            None if name == "reldate" => Some(Modifier::RelDate(self.clock.clone())),
+            None => Modifier::parse(name),
+        }
     }
 
     fn parse_decimal_number<I>(&self, context: &mut ParsingContext<'_, I>) -> Option<u32> {
         let mut decimal_vec = Vec::<char>::new();
 
-        let Some(first_digit) = consume_digits_without_0!(context) else {
-            return None;
-        };
+        let first_digit = consume_digits_without_0!(context)?;
 
         decimal_vec.push(first_digit);
         loop {
             let res_digit = consume_digits!(context);
 
             let Some(digit) = res_digit else {
+                // a width which overflows is invalid
                 let decimal_str: String = decimal_vec.into_iter().collect();
-                let decimal = decimal_str.parse::<u32>().unwrap();
-                return Some(decimal);
+                return decimal_str.parse::<u32>().ok();
             };
 
             decimal_vec.push(digit);
         }
     }
 
-    fn process_str_placeholder<T: ParsingTask>(&self, context: &mut ParsingContext<'_, T::Item>) {
-        let opt_literal = gather_str_placeholder!(context);
+    /// Parses a list index like `0]` or `-1]` including the closing bracket.
+    fn parse_list_index<I>(&self, context: &mut ParsingContext<'_, I>) -> Option<isize> {
+        let negative = consume_expected_chars!(context, '-').is_some();
+        let digits: String = gather!(context, '0'..='9')?.into_iter().collect();
+        consume_expected_chars!(context, ']')?;
+        let index = digits.parse::<isize>().ok()?;
+        Some(if negative { -index } else { index })
+    }
 
-        let Some(literal) = opt_literal else {
+    /// Parses the width of a format placeholder. The width is either a decimal number
+    /// or `*`, which denotes the remaining width up to the configured target width.
+    fn parse_format_width<I>(
+        &self,
+        context: &mut ParsingContext<'_, I>,
+        absolute: bool,
+    ) -> Option<u32> {
+        if consume_expected_chars!(context, '*').is_some() {
+            let target_width = context.target_width?;
+            return Some(target_width.saturating_sub(context.column) as u32);
+        }
+
+        let decimal = match self.consume_str(context, "%(width_of:") {
+            true => self.parse_width_reference(context)?,
+            false => self.parse_decimal_number(context)?,
+        };
+        if absolute {
+            // width is relative to the start of the line
+            return Some(decimal.saturating_sub(context.column as u32));
+        }
+        Some(decimal)
+    }
+
+    /// Parses the key of a width reference like `title)` following `%(width_of:` and returns the
+    /// width of the value placeholder rendered with this key. `None` if it wasn't rendered yet.
+    fn parse_width_reference<I>(&self, context: &mut ParsingContext<'_, I>) -> Option<u32> {
+        let key: String = gather_str_placeholder!(context)?.into_iter().collect();
+        consume_expected_chars!(context, ')')?;
+        context
+            .rendered_widths
+            .as_ref()?
+            .get(&key)
+            .map(|&width| width as u32)
+    }
+
+    /// Sets the format for the next placeholder. Formats inside inactive block branches are ignored.
+    fn set_format<I>(&self, context: &mut ParsingContext<'_, I>, format: OutputFormat) {
+        if context.is_active() {
+            context.format = format;
+        }
+    }
+
+    /// Consumes `pattern` if the remaining input starts with it.
+    fn consume_str<I>(&self, context: &mut ParsingContext<'_, I>, pattern: &str) -> bool {
+        if !context.iter.starts_with(pattern) {
+            return false;
+        }
+        for _ in pattern.chars() {
+            context.iter.next();
+        }
+        true
+    }
+
+    /// Gathers the name of a block tag like `%(?key)` and consumes the closing bracket.
+    fn gather_block_name<I>(&self, context: &mut ParsingContext<'_, I>) -> Option<String> {
+        let literal = gather_str_placeholder!(context)?;
+        consume_expected_chars!(context, ')')?;
+        Some(literal.into_iter().collect())
+    }
+
+    /// Gathers literal text, e.g. the label of a `%(case:label)` tag, up to the closing bracket.
+    fn gather_until_bracket<I>(&self, context: &mut ParsingContext<'_, I>) -> Option<String> {
+        let mut label = String::new();
+        loop {
+            match context.iter.next()? {
+                ')' => return Some(label),
+                ch => label.push(ch),
+            }
+        }
+    }
+
+    /// Returns true if a default value `:-default` follows.
+    #[cfg(feature = "modifiers")]
+    fn is_default<I>(&self, context: &ParsingContext<'_, I>) -> bool {
+        context.iter.starts_with(":-")
+    }
+
+    /// Gathers the argument of a modifier like `plural:file,files` up to the next modifier, the
+    /// default value or the closing bracket. Balanced parentheses are part of the argument, e.g.
+    /// the groups of `replace_re:(\w+)-(\w+),$2`.
+    #[cfg(feature = "modifiers")]
+    fn gather_modifier_argument<I>(&self, context: &mut ParsingContext<'_, I>) -> Option<String> {
+        let mut argument = String::new();
+        let mut depth = 0usize;
+        loop {
+            if depth == 0 && self.is_default(context) {
+                return Some(argument);
+            }
+            match context.iter.peek()? {
+                ')' | '|' if depth == 0 => return Some(argument),
+                ch => {
+                    match ch {
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        _ => {}
+                    }
+                    argument.push(ch);
+                    context.iter.next();
+                }
+            }
+        }
+    }
+
+    /// Returns the argument of a modifier. The count of `repeat` is looked up if it is a key
+    /// like in `repeat:rating` instead of a number. `None` if the key is missing.
+    #[cfg(feature = "modifiers")]
+    fn resolve_repeat_count<I>(
+        context: &ParsingContext<'_, I>,
+        segment: &str,
+        argument: String,
+    ) -> Option<String> {
+        if segment != "repeat" || argument.chars().all(|ch| ch.is_ascii_digit()) {
+            return Some(argument);
+        }
+        context.lookup(&argument)
+    }
+
+    /// Gathers the branches of a plural or select format like `one{# file}other{# files}` as
+    /// pairs of selector and text. Whitespace between the branches is ignored.
+    fn gather_message_branches<I>(
+        &self,
+        context: &mut ParsingContext<'_, I>,
+    ) -> Option<Vec<(String, String)>> {
+        let mut branches = Vec::new();
+        loop {
+            while context.iter.peek()?.is_whitespace() {
+                context.iter.next();
+            }
+            if context.iter.peek()? == ')' || context.iter.starts_with(":-") {
+                return Some(branches);
+            }
+            let selector = gather!(context, 'a'..='z' | '=' | '-' | '.' | '0'..='9')
+                .filter(|selector| !selector.is_empty())?;
+            while context.iter.peek()?.is_whitespace() {
+                context.iter.next();
+            }
+            consume_expected_chars!(context, '{')?;
+            let mut text = String::new();
+            loop {
+                match context.iter.next()? {
+                    '}' => break,
+                    '{' => return None,
+                    ch => text.push(ch),
+                }
+            }
+            branches.push((selector.into_iter().collect(), text));
+        }
+    }
+
+    /// Gathers the expression of a conditional block up to the closing bracket. Nested
+    /// brackets and brackets inside quoted strings don't terminate the expression.
+    #[cfg(feature = "blocks")]
+    fn gather_condition<I>(&self, context: &mut ParsingContext<'_, I>) -> Option<String> {
+        let mut expression = String::new();
+        let mut quoted = false;
+        let mut depth = 0;
+        loop {
+            let ch = context.iter.next()?;
+            match ch {
+                ')' if !quoted && depth == 0 => return Some(expression),
+                ')' if !quoted => depth -= 1,
+                '(' if !quoted => depth += 1,
+                '"' => quoted = !quoted,
+                _ => {}
+            }
+            expression.push(ch);
+        }
+    }
+
+    #[cfg(feature = "blocks")]
+    fn process_block_open<T: ParsingTask>(&self, context: &mut ParsingContext<'_, T::Item>) {
+        let Some(expression) = self.gather_condition(context) else {
+            T::error(context);
+            return;
+        };
+        let Some(condition) = Condition::parse(&expression) else {
             T::error(context);
             return;
         };
-        context.iter.next(); // consume ")"
 
-        T::process_str_placeholder(context, literal.into_iter().collect());
+        let keys = condition.keys();
+        for key in keys.iter() {
+            T::process_key_reference(context, key);
+        }
+        let name = keys.first().map_or(String::new(), |key| key.to_string());
 
-        // Reset format for next Placeholder
-        context.format = OutputFormat::None;
+        let parent_active = context.is_active();
+        let condition_met = condition.evaluate(&|key| context.lookup(key));
+        let active = parent_active && (T::VISIT_ALL_BRANCHES || condition_met);
+        context.blocks.push(BlockState {
+            name,
+            active,
+            parent_active,
+            taken: active,
+            repeat: None,
+            subject: None,
+        });
     }
 
-    fn process_format_left_placeholder<T: ParsingTask>(
-        &self,
-        context: &mut ParsingContext<'_, T::Item>,
-    ) {
-        if consume_expected_chars!(context, '(').is_none() {
+    #[cfg(feature = "blocks")]
+    fn process_loop_open<T: ParsingTask>(&self, context: &mut ParsingContext<'_, T::Item>) {
+        let var = gather_str_placeholder!(context).unwrap_or_default();
+        skip_until_neg_char_match!(context, ' '); // consume whitespaces
+        if var.is_empty() || !self.consume_str(context, "in ") {
             T::error(context);
             return;
         }
         skip_until_neg_char_match!(context, ' '); // consume whitespaces
-
-        let Some(decimal) = self.parse_decimal_number(context) else {
+        let list_key = gather_str_placeholder!(context).unwrap_or_default();
+        skip_until_neg_char_match!(context, ' '); // consume whitespaces
+        if list_key.is_empty() || consume_expected_chars!(context, ')').is_none() {
             T::error(context);
             return;
-        };
+        }
 
-        skip_until_neg_char_match!(context, ' '); // consume whitespaces
+        let var: String = var.into_iter().collect();
+        let list_key: String = list_key.into_iter().collect();
+        if !context.allows(Capability::Loops) {
+            return;
+        }
+        T::process_key_reference(context, &list_key);
 
-        // Check if optional arguments are available
-        if consume_expected_chars!(context, ',').is_some() {
-            skip_until_neg_char_match!(context, ' '); // consume whitespaces
-            let Some(literal) = gather_str_placeholder!(context) else {
-                T::error(context);
-                return;
-            };
-            skip_until_neg_char_match!(context, ' '); // consume whitespaces
-            context.iter.next(); // consume )
-            let arg: String = literal.into_iter().collect();
+        let mut items = context
+            .lookup(&list_key)
+            .map(|value| split_list(&value))
+            .unwrap_or_default();
+        if T::VISIT_ALL_BRANCHES {
+            items = vec![String::new()]; // visit the body exactly once
+        }
 
-            match arg.trim() {
-                "trunc" => {
-                    context.format = OutputFormat::LeftAlignTrunc(decimal);
-                    return;
-                }
-                "ltrunc" => {
-                    context.format = OutputFormat::LeftAlignLTrunc(decimal);
-                    return;
-                }
-                _ => {}
+        let parent_active = context.is_active();
+        let active = parent_active && !items.is_empty();
+        let mut repeat = None;
+        if active {
+            #[cfg(feature = "sandbox")]
+            if let Some(quota) = context.quota.as_ref() {
+                quota.consume_loop_iteration();
             }
+            context.bindings.push((var, items[0].clone()));
+            repeat = Some(LoopState {
+                items,
+                index: 0,
+                body_start: context.iter.position(),
+            });
+        }
+        context.blocks.push(BlockState {
+            name: list_key,
+            active,
+            parent_active,
+            taken: active,
+            repeat,
+            subject: None,
+        });
+    }
 
+    #[cfg(feature = "blocks")]
+    fn process_switch_open<T: ParsingTask>(&self, context: &mut ParsingContext<'_, T::Item>) {
+        let Some(key) = self.gather_block_name(context) else {
             T::error(context);
-        } else {
-            if consume_expected_chars!(context, ')').is_none() {
-                T::error(context);
-                return;
-            }
+            return;
+        };
+        T::process_key_reference(context, &key);
 
-            context.format = OutputFormat::LeftAlign(decimal);
-        }
+        // content before the first case is never rendered
+        context.blocks.push(BlockState {
+            name: "switch".to_string(),
+            active: false,
+            parent_active: context.is_active(),
+            taken: false,
+            repeat: None,
+            subject: context.lookup(&key),
+        });
     }
 
-    fn process_format_right_placeholder<T: ParsingTask>(
+    /// Processes `%(case:label)` and `%(default)` (`label` is `None`) of the innermost switch block.
+    #[cfg(feature = "blocks")]
+    fn process_switch_case<T: ParsingTask>(
         &self,
         context: &mut ParsingContext<'_, T::Item>,
+        label: Option<String>,
     ) {
-        if consume_expected_chars!(context, '(').is_none() {
+        let Some(block) = context.blocks.last_mut() else {
+            T::error(context);
+            return;
+        };
+        if block.name != "switch" || block.repeat.is_some() {
             T::error(context);
             return;
         }
-        skip_until_neg_char_match!(context, ' '); // consume whitespaces
 
-        let Some(decimal) = self.parse_decimal_number(context) else {
+        let matches = match label {
+            Some(label) => block.subject.as_deref() == Some(label.as_str()),
+            None => true,
+        };
+        block.active = block.parent_active && ((!block.taken && matches) || T::VISIT_ALL_BRANCHES);
+        block.taken |= block.active;
+    }
+
+    #[cfg(feature = "blocks")]
+    fn process_block_else<T: ParsingTask>(&self, context: &mut ParsingContext<'_, T::Item>) {
+        let Some(block) = context.blocks.last_mut() else {
             T::error(context);
             return;
         };
+        block.active = block.parent_active && (!block.taken || T::VISIT_ALL_BRANCHES);
+        block.taken |= block.active;
+    }
 
-        skip_until_neg_char_match!(context, ' '); // consume whitespaces
-
-        // Check if optional arguments are available
-        if consume_expected_chars!(context, ',').is_some() {
-            skip_until_neg_char_match!(context, ' '); // consume whitespaces
-            let Some(literal) = gather_str_placeholder!(context) else {
-                T::error(context);
-                return;
-            };
-            skip_until_neg_char_match!(context, ' '); // consume whitespaces
-            context.iter.next(); // consume )
-            let arg: String = literal.into_iter().collect();
+    /// Closes the innermost block. If `name` is given, it must match the name of the block.
+    #[cfg(feature = "blocks")]
+    fn process_block_close<T: ParsingTask>(
+        &self,
+        context: &mut ParsingContext<'_, T::Item>,
+        name: Option<&str>,
+    ) {
+        let Some(block) = context.blocks.last() else {
+            T::error(context);
+            return;
+        };
+        if name.is_some_and(|name| name != block.name) {
+            T::error(context);
+            return;
+        }
 
-            match arg.trim() {
-                "trunc" => {
-                    context.format = OutputFormat::RightAlignTrunc(decimal);
-                    return;
+        let block = context.blocks.last_mut().unwrap();
+        if let Some(repeat) = block.repeat.as_mut() {
+            if repeat.index + 1 < repeat.items.len() {
+                // start next iteration
+                #[cfg(feature = "sandbox")]
+                if let Some(quota) = context.quota.as_ref() {
+                    quota.consume_loop_iteration();
                 }
-                "ltrunc" => {
-                    context.format = OutputFormat::RightAlignLTrunc(decimal);
-                    return;
-                }
-                _ => {}
-            }
-
-            T::error(context);
-        } else {
-            if consume_expected_chars!(context, ')').is_none() {
-                T::error(context);
+                repeat.index += 1;
+                context.bindings.last_mut().unwrap().1 = repeat.items[repeat.index].clone();
+                block.active = true;
+                context.iter.set_position(repeat.body_start);
                 return;
             }
-
-            context.format = OutputFormat::RightAlign(decimal);
+            context.bindings.pop();
         }
+        context.blocks.pop();
     }
 
-    fn process_placeholder<T: ParsingTask>(&self, context: &mut ParsingContext<'_, T::Item>) {
-        let Some(ch) = context.iter.next() else {
+    /// Processes placeholders starting with a bracket: block tags and value placeholders.
+    fn process_bracket_placeholder<T: ParsingTask>(
+        &self,
+        context: &mut ParsingContext<'_, T::Item>,
+    ) {
+        // most bracket placeholders are plain keys, which skip the checks of the directives
+        let directive = context
+            .iter
+            .peek()
+            .is_some_and(|ch| DIRECTIVE_STARTS.contains(ch));
+        if directive && self.process_bracket_directive::<T>(context) {
             return;
-        };
+        }
+        if let Some(extension) = self.extensions.iter().find(|extension| {
+            context.iter.starts_with(extension.sigil())
+                && self.consume_str(context, &format!("{}:", extension.sigil()))
+        }) {
+            self.process_extension_placeholder::<T>(context, extension.as_ref());
+        } else if let Some(aggregate) = Aggregate::ALL.into_iter().find(|aggregate| {
+            // `%(count:-0)` is the key `count` with a default value
+            context.iter.starts_with(aggregate.name())
+                && !context.iter.starts_with(&format!("{}:-", aggregate.name()))
+                && self.consume_str(context, &format!("{}:", aggregate.name()))
+        }) {
+            self.process_aggregate_placeholder::<T>(context, aggregate);
+        } else {
+            self.process_str_placeholder::<T>(context, None);
+        }
+    }
 
-        match ch {
-            '(' => {
-                self.process_str_placeholder::<T>(context);
-            }
-            '<' => {
-                self.process_format_left_placeholder::<T>(context);
-            }
-            '>' => {
-                self.process_format_right_placeholder::<T>(context);
-            }
-            'n' => {
-                T::process_char_placeholder(context, '\n');
-            }
-            '%' => {
-                T::process_char_placeholder(context, '%');
+    /// Processes a bracket placeholder which is a directive like `%(if:key)` or `%(#key)`.
+    /// Returns false if the placeholder isn't a directive.
+    fn process_bracket_directive<T: ParsingTask>(
+        &self,
+        context: &mut ParsingContext<'_, T::Item>,
+    ) -> bool {
+        #[cfg(feature = "blocks")]
+        if self.process_block_tag::<T>(context) {
+            return true;
+        }
+        if self.consume_str(context, "#") {
+            self.process_length_placeholder::<T>(context);
+        } else if self.consume_str(context, "width_of:") {
+            self.process_width_placeholder::<T>(context);
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// Processes a block tag like `%(if:key)`, `%(for:item in items)` or `%(end)`. Returns false
+    /// if the placeholder isn't a block tag.
+    #[cfg(feature = "blocks")]
+    fn process_block_tag<T: ParsingTask>(&self, context: &mut ParsingContext<'_, T::Item>) -> bool {
+        if self.consume_str(context, "?") || self.consume_str(context, "if:") {
+            self.process_block_open::<T>(context);
+        } else if self.consume_str(context, "for:") {
+            self.process_loop_open::<T>(context);
+        } else if self.consume_str(context, "switch:") {
+            self.process_switch_open::<T>(context);
+        } else if self.consume_str(context, "case:") {
+            match self.gather_until_bracket(context) {
+                Some(label) => self.process_switch_case::<T>(context, Some(label)),
+                None => T::error(context),
             }
-            _ => {
-                T::error(context);
+        } else if self.consume_str(context, ":else)") {
+            self.process_block_else::<T>(context);
+        } else if self.consume_str(context, "/") {
+            match self.gather_block_name(context) {
+                Some(name) => self.process_block_close::<T>(context, Some(&name)),
+                None => T::error(context),
             }
+        } else if context.blocks.is_empty() {
+            // outside of blocks `%(default)`, `%(else)` and `%(end)` are value placeholders
+            return false;
+        } else if self.consume_str(context, "default)") {
+            self.process_switch_case::<T>(context, None);
+        } else if self.consume_str(context, "else)") {
+            self.process_block_else::<T>(context);
+        } else if self.consume_str(context, "end)") {
+            self.process_block_close::<T>(context, None);
+        } else {
+            return false;
         }
+        true
     }
 
-    fn parse_generic<T: ParsingTask>(
+    fn process_extension_placeholder<T: ParsingTask>(
         &self,
-        key_value: &HashMap<&str, String>,
-        inp: &str,
-    ) -> T::Output {
-        let mut context = T::init(inp, key_value);
-        loop {
-            let Some(ch) = context.iter.peek() else {
-                break;
-            };
-
-            match ch {
-                '%' => {
-                    context.iter.mark(); // mark position of placeholder start
-                    context.iter.next();
-                    self.process_placeholder::<T>(&mut context);
+        context: &mut ParsingContext<'_, T::Item>,
+        extension: &dyn PlaceholderExtension,
+    ) {
+        let Some(arg) = self.gather_until_bracket(context) else {
+            T::error(context);
+            return;
+        };
+        if !extension.parse(&arg) {
+            T::error(context);
+            return;
+        }
+        if !context.allows(Capability::Extensions) {
+            return;
+        }
+
+        let value = extension.render(&arg, &context.visible_key_value());
+        let key = format!("{}:{}", extension.sigil(), arg);
+        self.process_computed_value::<T>(context, extension.sigil(), key, value);
+    }
+
+    /// Processes a length placeholder like `%(#title)`, which renders the length of the value of
+    /// a key: its number of chars, or its display width in monospace mode. A missing key leaves
+    /// the placeholder unchanged.
+    fn process_length_placeholder<T: ParsingTask>(
+        &self,
+        context: &mut ParsingContext<'_, T::Item>,
+    ) {
+        let Some(key) = gather_str_placeholder!(context).filter(|key| !key.is_empty()) else {
+            T::error(context);
+            return;
+        };
+        if consume_expected_chars!(context, ')').is_none() {
+            T::error(context);
+            return;
+        }
+        let key: String = key.into_iter().collect();
+        T::process_key_reference(context, &key);
+
+        let Some(value) = context.lookup(&key) else {
+            T::error(context);
+            return;
+        };
+        let length = context.width(&value).to_string();
+        self.process_computed_value::<T>(context, "#", format!("#{key}"), length);
+    }
+
+    /// Processes a width placeholder like `%(width_of:title)`, which renders the width the value
+    /// placeholder with the key occupied earlier in the same render, including its padding. A
+    /// placeholder which wasn't rendered yet leaves the width placeholder unchanged.
+    fn process_width_placeholder<T: ParsingTask>(&self, context: &mut ParsingContext<'_, T::Item>) {
+        let Some(key) = gather_str_placeholder!(context).filter(|key| !key.is_empty()) else {
+            T::error(context);
+            return;
+        };
+        if consume_expected_chars!(context, ')').is_none() {
+            T::error(context);
+            return;
+        }
+        let key: String = key.into_iter().collect();
+
+        let Some(width) = context
+            .rendered_widths
+            .as_ref()
+            .and_then(|widths| widths.get(&key))
+        else {
+            T::error(context);
+            return;
+        };
+        let width = width.to_string();
+        self.process_computed_value::<T>(context, "width_of", format!("width_of:{key}"), width);
+    }
+
+    /// Processes an aggregate placeholder like `%(sum:amounts)`, which renders an aggregate of
+    /// the items of a list value. The placeholder may have fallback keys, modifiers, a numeric
+    /// format and a default value like a value placeholder. A missing key leaves it unchanged.
+    fn process_aggregate_placeholder<T: ParsingTask>(
+        &self,
+        context: &mut ParsingContext<'_, T::Item>,
+        aggregate: Aggregate,
+    ) {
+        let Some(mut placeholder) = self.parse_str_placeholder::<T>(context) else {
+            return;
+        };
+        for key in placeholder.keys() {
+            T::process_key_reference(context, key);
+        }
+
+        let value = placeholder
+            .find_value(|key| context.lookup(key))
+            .map(|(key, value)| (key.clone(), value));
+        let Some((key, value)) = value else {
+            T::error(context);
+            return;
+        };
+        let value = aggregate.compute(&split_list(&value)).unwrap_or_default();
+        placeholder.key = format!("{}:{key}", aggregate.name());
+        placeholder.index = None;
+        placeholder.fallbacks.clear();
+        self.process_computed_placeholder::<T>(context, aggregate.name(), placeholder, value);
+    }
+
+    /// Renders a value which is not part of the key-value map like a value placeholder.
+    fn process_computed_value<T: ParsingTask>(
+        &self,
+        context: &mut ParsingContext<'_, T::Item>,
+        namespace: &str,
+        key: String,
+        value: String,
+    ) {
+        self.process_computed_placeholder::<T>(context, namespace, StrPlaceholder::new(key), value);
+    }
+
+    /// Renders a computed value with the modifiers, formats and default value of `placeholder`,
+    /// whose key names the value.
+    fn process_computed_placeholder<T: ParsingTask>(
+        &self,
+        context: &mut ParsingContext<'_, T::Item>,
+        namespace: &str,
+        placeholder: StrPlaceholder,
+        value: String,
+    ) {
+        // The value is bound like a loop variable so it is rendered as a value placeholder
+        context.bindings.push((placeholder.key.clone(), value));
+        context.binding_source = Some(ValueSource::Resolver(namespace.to_string()));
+        T::process_str_placeholder(context, placeholder);
+        context.binding_source = None;
+        context.bindings.pop();
+
+        // Reset format for next Placeholder
+        context.format = OutputFormat::None;
+        context.block_indent = None;
+        #[cfg(feature = "tables")]
+        {
+            context.cell_span = 1;
+        }
+    }
+
+    /// Processes a value placeholder with a git-style whitespace prefix like `%+(key)`.
+    fn process_prefixed_placeholder<T: ParsingTask>(
+        &self,
+        context: &mut ParsingContext<'_, T::Item>,
+        prefix: WhitespacePrefix,
+    ) {
+        if consume_expected_chars!(context, '(').is_none() {
+            T::error(context);
+            return;
+        }
+        self.process_str_placeholder::<T>(context, Some(prefix));
+    }
+
+    /// Returns true if a bracket with a key follows, e.g. `(key)` in contrast to `(10)`.
+    fn starts_with_bracketed_key<I>(&self, context: &mut ParsingContext<'_, I>) -> bool {
+        let position = context.iter.position();
+        let is_key = context.iter.next() == Some('(')
+            && context
+                .iter
+                .peek()
+                .is_some_and(|ch| ch.is_alphabetic() || ch == '_');
+        context.iter.set_position(position);
+        is_key
+    }
+
+    /// Inserts or removes whitespace according to the prefix of a value placeholder. Values
+    /// which are missing leave the output unchanged.
+    fn apply_whitespace_prefix<T: ParsingTask>(
+        &self,
+        context: &mut ParsingContext<'_, T::Item>,
+        prefix: WhitespacePrefix,
+        placeholder: &StrPlaceholder,
+    ) {
+        let Some(value) = context.resolve(placeholder) else {
+            return;
+        };
+        match prefix {
+            WhitespacePrefix::LineFeed if !value.is_empty() => {
+                T::process_char_placeholder(context, '\n')
+            }
+            WhitespacePrefix::Space if !value.is_empty() => {
+                T::process_char_placeholder(context, ' ')
+            }
+            WhitespacePrefix::RemoveLineFeeds if value.is_empty() => {
+                T::remove_trailing_line_feeds(context)
+            }
+            _ => {}
+        }
+    }
+
+    fn process_str_placeholder<T: ParsingTask>(
+        &self,
+        context: &mut ParsingContext<'_, T::Item>,
+        prefix: Option<WhitespacePrefix>,
+    ) {
+        let placeholder = match Self::compiled_placeholder(context) {
+            Some(placeholder) => placeholder,
+            None => match self.parse_str_placeholder::<T>(context) {
+                Some(placeholder) => placeholder,
+                None => return,
+            },
+        };
+
+        // skips hashing the key for the common case without default specs
+        let default_spec = match self.default_specs.is_empty() {
+            true => None,
+            false => self.default_specs.get(&placeholder.key),
+        };
+        if let (OutputFormat::None, Some(&format)) = (context.format, default_spec) {
+            self.set_format(context, format);
+        }
+
+        if let Some(prefix) = prefix {
+            self.apply_whitespace_prefix::<T>(context, prefix, &placeholder);
+        }
+        self.render_value::<T>(context, placeholder);
+    }
+
+    /// Renders the value of a parsed value placeholder with the format in effect and resets the
+    /// format for the next placeholder.
+    fn render_value<T: ParsingTask>(
+        &self,
+        context: &mut ParsingContext<'_, T::Item>,
+        placeholder: StrPlaceholder,
+    ) {
+        let column = context.column;
+        let key = context
+            .rendered_widths
+            .is_some()
+            .then(|| placeholder.key.clone());
+        match self.expand_value(context, &placeholder) {
+            Some((key, value)) => {
+                // the expanded value keeps the source of the key
+                let source = context.source(&key);
+                context.bindings.push((key, value));
+                context.binding_source = source;
+                T::process_str_placeholder(context, placeholder);
+                context.binding_source = None;
+                context.bindings.pop();
+            }
+            None => T::process_str_placeholder(context, placeholder),
+        }
+        if let (true, Some(key), Some(widths)) =
+            (context.is_active(), key, context.rendered_widths.as_mut())
+        {
+            widths.insert(key, context.column.saturating_sub(column));
+        }
+
+        // Reset format for next Placeholder
+        context.format = OutputFormat::None;
+        context.block_indent = None;
+        #[cfg(feature = "tables")]
+        {
+            context.cell_span = 1;
+        }
+    }
+
+    /// Returns the value placeholder at the marked position if it was parsed in advance by a
+    /// `Template`, skipping its source.
+    fn compiled_placeholder<I>(context: &mut ParsingContext<'_, I>) -> Option<StrPlaceholder> {
+        let (placeholder, end) = context.compiled?.get(&context.iter.marked_position()?)?;
+        context.iter.set_position(*end);
+        Some(placeholder.clone())
+    }
+
+    /// Parses a value placeholder like `key|upper:-default)` following `%(`. `None` if the
+    /// placeholder is invalid or isn't rendered.
+    fn parse_str_placeholder<T: ParsingTask>(
+        &self,
+        context: &mut ParsingContext<'_, T::Item>,
+    ) -> Option<StrPlaceholder> {
+        let opt_literal = gather_str_placeholder!(context);
+
+        let Some(literal) = opt_literal else {
+            T::error(context);
+            return None;
+        };
+        let mut placeholder = StrPlaceholder::new(literal.into_iter().collect());
+
+        if consume_expected_chars!(context, '[').is_some() {
+            let Some(index) = self.parse_list_index(context) else {
+                T::error(context);
+                return None;
+            };
+            placeholder.index = Some(index);
+        }
+
+        // Segments following the key are fallback keys until the first modifier
+        while consume_expected_chars!(context, '|').is_some() {
+            let Some(segment) = gather_str_placeholder!(context).filter(|seg| !seg.is_empty())
+            else {
+                T::error(context);
+                return None;
+            };
+            let segment: String = segment.into_iter().collect();
+            #[cfg(feature = "modifiers")]
+            if context.iter.peek() == Some(':') && !self.is_default(context) {
+                context.iter.next(); // consume ":"
+                let Some(modifier) = self
+                    .gather_modifier_argument(context)
+                    .and_then(|argument| Self::resolve_repeat_count(context, &segment, argument))
+                    .and_then(|argument| Modifier::with_argument(&segment, &argument))
+                else {
+                    T::error(context);
+                    return None;
+                };
+                placeholder.modifiers.push(modifier);
+                continue;
+            }
+            #[cfg(feature = "chrono")]
+            if segment == "reldate" && !self.modifiers.contains_key("reldate") {
+                if self.canonical {
+                    T::error(context);
+                    return None;
                 }
-                _ => {
-                    context.iter.next();
-                    T::process_char(&mut context, ch);
+                if !context.allows(Capability::DynamicBuiltins) {
+                    return None;
+                }
+            }
+            #[cfg(feature = "modifiers")]
+            match self.modifier(&segment) {
+                Some(modifier) => placeholder.modifiers.push(modifier),
+                None if placeholder.modifiers.is_empty() => placeholder.fallbacks.push(segment),
+                None => {
+                    T::error(context);
+                    return None;
                 }
             }
+            #[cfg(not(feature = "modifiers"))]
+            placeholder.fallbacks.push(segment);
         }
-        T::done(context)
+
+        if self.consume_str(context, ",num=") {
+            // the thousands flag leads, so a following `,plural,` isn't part of the spec
+            let thousands = consume_expected_chars!(context, ',');
+            let precision = gather!(context, '.' | '0'..='9').unwrap_or_default();
+            let spec: String = thousands.into_iter().chain(precision).collect();
+            let Some(number) = NumberFormat::parse(&spec) else {
+                T::error(context);
+                return None;
+            };
+            placeholder.number = Some(number);
+        }
+
+        for (kind, tag) in [("plural", ",plural,"), ("select", ",select,")] {
+            if !self.consume_str(context, tag) {
+                continue;
+            }
+            let Some(message) = self
+                .gather_message_branches(context)
+                .and_then(|branches| MessageFormat::new(kind, branches))
+            else {
+                T::error(context);
+                return None;
+            };
+            placeholder.message = Some(message);
+            break;
+        }
+
+        if self.consume_str(context, ":-") {
+            let Some(default) = self.gather_until_bracket(context) else {
+                T::error(context);
+                return None;
+            };
+            placeholder.default = Some(default);
+        } else {
+            context.iter.next(); // consume ")"
+        }
+        Some(placeholder)
     }
-}
 
-impl PlaceholderFormatter for Formatify {
-    fn replace_placeholders(&self, key_value: &HashMap<&str, String>, inp: &str) -> String {
-        self.parse_generic::<ParsingTaskReplacePlaceholders>(key_value, inp)
+    /// Expands the placeholders contained in the value of a value placeholder if recursive
+    /// expansion is enabled. Returns the key which provides the value and the expanded value.
+    fn expand_value<I>(
+        &self,
+        context: &mut ParsingContext<'_, I>,
+        placeholder: &StrPlaceholder,
+    ) -> Option<(String, String)> {
+        let max_depth = self.max_expansion_depth?;
+        if !context.is_active() || context.expansions.len() >= max_depth {
+            return None;
+        }
+        let key = context.resolved_key(placeholder)?.to_string();
+        if context.expansions.contains(&key) {
+            // cycle: the value is currently being expanded
+            return Some((key, format!("%({})", placeholder.key)));
+        }
+        let value = context.lookup(&key)?;
+        if !value.contains('%') {
+            return None;
+        }
+        if !context.allows(Capability::Expansion) {
+            return None;
+        }
+        #[cfg(feature = "sandbox")]
+        if let Some(quota) = context.quota.as_ref() {
+            if !quota.enter_expansion(context.expansions.len() + 1) {
+                return None;
+            }
+        }
+
+        let mut nested =
+            self.init_context::<ParsingTaskReplacePlaceholders>(context.key_value, &value);
+        nested.right_to_left = false;
+        nested.bindings = context.bindings.clone();
+        nested.expansions = context.expansions.clone();
+        nested.expansions.push(key.clone());
+        #[cfg(feature = "sandbox")]
+        {
+            nested.quota = context.quota.clone();
+        }
+        nested.policy = context.policy;
+        let expanded = self.parse_context::<ParsingTaskReplacePlaceholders>(nested);
+        Some((key, expanded))
+    }
+
+    /// Sets the format of a preset like `%<(preset:idcol)`. A right alignment placeholder
+    /// mirrors the alignment of the preset.
+    fn process_preset<T: ParsingTask>(
+        &self,
+        context: &mut ParsingContext<'_, T::Item>,
+        absolute: bool,
+        right: bool,
+    ) {
+        let preset = self
+            .gather_block_name(context)
+            .and_then(|name| self.presets.get(&name).copied());
+        match preset {
+            Some(format) if !absolute => {
+                let format = if right { format.mirrored() } else { format };
+                self.set_format(context, format);
+            }
+            _ => T::error(context),
+        }
+    }
+
+    /// Processes a column span like `%<(span=2)`, which aligns the subsequent value placeholder
+    /// over the combined width of several columns of a joined table. Outside of joined tables
+    /// the value is rendered without format.
+    #[cfg(feature = "tables")]
+    fn process_span<T: ParsingTask>(
+        &self,
+        context: &mut ParsingContext<'_, T::Item>,
+        absolute: bool,
+        right: bool,
+    ) {
+        let Some(span) = self.parse_decimal_number(context).filter(|&span| span > 0) else {
+            T::error(context);
+            return;
+        };
+        if absolute || consume_expected_chars!(context, ')').is_none() {
+            T::error(context);
+            return;
+        }
+        if !context.is_active() {
+            return;
+        }
+        context.format = match right {
+            true => OutputFormat::RightAlign(0),
+            false => OutputFormat::LeftAlign(0),
+        };
+        context.cell_span = span as usize;
+    }
+
+    fn process_format_left_placeholder<T: ParsingTask>(
+        &self,
+        context: &mut ParsingContext<'_, T::Item>,
+    ) {
+        let absolute = consume_expected_chars!(context, '|').is_some();
+        if consume_expected_chars!(context, '(').is_none() {
+            T::error(context);
+            return;
+        }
+        if self.consume_str(context, "preset:") {
+            self.process_preset::<T>(context, absolute, false);
+            return;
+        }
+        #[cfg(feature = "tables")]
+        if self.consume_str(context, "span=") {
+            self.process_span::<T>(context, absolute, false);
+            return;
+        }
+        skip_until_neg_char_match!(context, ' '); // consume whitespaces
+
+        let Some(decimal) = self.parse_format_width(context, absolute) else {
+            T::error(context);
+            return;
+        };
+
+        skip_until_neg_char_match!(context, ' '); // consume whitespaces
+
+        // Check if optional arguments are available
+        if consume_expected_chars!(context, ',').is_some() {
+            skip_until_neg_char_match!(context, ' '); // consume whitespaces
+            let Some(literal) = gather_str_placeholder!(context) else {
+                T::error(context);
+                return;
+            };
+            skip_until_neg_char_match!(context, ' '); // consume whitespaces
+            context.iter.next(); // consume )
+            let arg: String = literal.into_iter().collect();
+
+            match arg.trim() {
+                "trunc" => {
+                    self.set_format(context, OutputFormat::LeftAlignTrunc(decimal));
+                    return;
+                }
+                "ltrunc" => {
+                    self.set_format(context, OutputFormat::LeftAlignLTrunc(decimal));
+                    return;
+                }
+                "justify" => {
+                    self.set_format(context, OutputFormat::Justify(decimal));
+                    return;
+                }
+                _ => {}
+            }
+
+            T::error(context);
+        } else {
+            if consume_expected_chars!(context, ')').is_none() {
+                T::error(context);
+                return;
+            }
+
+            self.set_format(context, OutputFormat::LeftAlign(decimal));
+        }
+    }
+
+    fn process_format_right_placeholder<T: ParsingTask>(
+        &self,
+        context: &mut ParsingContext<'_, T::Item>,
+    ) {
+        let absolute = consume_expected_chars!(context, '|').is_some();
+        if consume_expected_chars!(context, '(').is_none() {
+            T::error(context);
+            return;
+        }
+        if self.consume_str(context, "preset:") {
+            self.process_preset::<T>(context, absolute, true);
+            return;
+        }
+        #[cfg(feature = "tables")]
+        if self.consume_str(context, "span=") {
+            self.process_span::<T>(context, absolute, true);
+            return;
+        }
+        skip_until_neg_char_match!(context, ' '); // consume whitespaces
+
+        let Some(decimal) = self.parse_format_width(context, absolute) else {
+            T::error(context);
+            return;
+        };
+
+        skip_until_neg_char_match!(context, ' '); // consume whitespaces
+
+        // Check if optional arguments are available
+        if consume_expected_chars!(context, ',').is_some() {
+            skip_until_neg_char_match!(context, ' '); // consume whitespaces
+            let Some(literal) = gather_str_placeholder!(context) else {
+                T::error(context);
+                return;
+            };
+            skip_until_neg_char_match!(context, ' '); // consume whitespaces
+            context.iter.next(); // consume )
+            let arg: String = literal.into_iter().collect();
+
+            match arg.trim() {
+                "trunc" => {
+                    self.set_format(context, OutputFormat::RightAlignTrunc(decimal));
+                    return;
+                }
+                "ltrunc" => {
+                    self.set_format(context, OutputFormat::RightAlignLTrunc(decimal));
+                    return;
+                }
+                _ => {}
+            }
+
+            T::error(context);
+        } else {
+            if consume_expected_chars!(context, ')').is_none() {
+                T::error(context);
+                return;
+            }
+
+            self.set_format(context, OutputFormat::RightAlign(decimal));
+        }
+    }
+
+    #[cfg(feature = "colors")]
+    fn process_color_placeholder<T: ParsingTask>(&self, context: &mut ParsingContext<'_, T::Item>) {
+        if consume_expected_chars!(context, '(').is_none() {
+            // git compatible shorthands: %Cred, %Cgreen, %Cblue, %Creset
+            for name in ["red", "green", "blue", "reset"] {
+                if self.consume_str(context, name) {
+                    let spec = ColorSpec::parse(name).unwrap();
+                    T::process_color_placeholder(context, &spec);
+                    return;
+                }
+            }
+            T::error(context);
+            return;
+        }
+
+        let Some(literal) = gather!(context, ('0'..='9') | ('a'..='z') | ('A'..='F') | ' ' | '#')
+        else {
+            T::error(context);
+            return;
+        };
+
+        if consume_expected_chars!(context, ')').is_none() {
+            T::error(context);
+            return;
+        }
+
+        let spec_str: String = literal.into_iter().collect();
+        let Some(spec) = ColorSpec::parse(&spec_str) else {
+            T::error(context);
+            return;
+        };
+        T::process_color_placeholder(context, &spec);
+    }
+
+    fn process_rule_placeholder<T: ParsingTask>(&self, context: &mut ParsingContext<'_, T::Item>) {
+        if consume_expected_chars!(context, '(').is_none() {
+            T::error(context);
+            return;
+        }
+        skip_until_neg_char_match!(context, ' '); // consume whitespaces
+
+        let Some(width) = self.parse_format_width(context, false) else {
+            T::error(context);
+            return;
+        };
+        skip_until_neg_char_match!(context, ' '); // consume whitespaces
+
+        let mut fill = '-';
+        if consume_expected_chars!(context, ',').is_some() {
+            skip_until_neg_char_match!(context, ' '); // consume whitespaces
+            if consume_expected_chars!(context, '\'').is_none() {
+                T::error(context);
+                return;
+            }
+            let Some(ch) = context.iter.next() else {
+                T::error(context);
+                return;
+            };
+            if consume_expected_chars!(context, '\'').is_none() {
+                T::error(context);
+                return;
+            }
+            fill = ch;
+            skip_until_neg_char_match!(context, ' '); // consume whitespaces
+        }
+
+        if consume_expected_chars!(context, ')').is_none() {
+            T::error(context);
+            return;
+        }
+
+        if !context.reserve_output(width as usize) {
+            return;
+        }
+        for idx in 0..width as usize {
+            if idx % QUOTA_CHECK_INTERVAL == 0 && !context.reserve_output(0) {
+                return;
+            }
+            T::process_char_placeholder(context, fill);
+        }
+    }
+
+    fn process_tab_stop_placeholder<T: ParsingTask>(
+        &self,
+        context: &mut ParsingContext<'_, T::Item>,
+    ) {
+        if consume_expected_chars!(context, '(').is_none() {
+            T::error(context);
+            return;
+        }
+        skip_until_neg_char_match!(context, ' '); // consume whitespaces
+
+        let Some(tab_width) = self.parse_decimal_number(context) else {
+            T::error(context);
+            return;
+        };
+        skip_until_neg_char_match!(context, ' '); // consume whitespaces
+
+        if consume_expected_chars!(context, ')').is_none() {
+            T::error(context);
+            return;
+        }
+
+        // advance to the next tab stop, at least one column
+        let tab_width = tab_width as usize;
+        let next_stop = (context.column / tab_width + 1) * tab_width;
+        if !context.reserve_output(next_stop - context.column) {
+            return;
+        }
+        for idx in 0..next_stop - context.column {
+            if idx % QUOTA_CHECK_INTERVAL == 0 && !context.reserve_output(0) {
+                return;
+            }
+            T::process_char_placeholder(context, ' ');
+        }
+    }
+
+    /// Processes a block indent placeholder like `%I(4)` or `%I("> ")`, which prefixes each line
+    /// of the subsequent value with spaces or a string.
+    fn process_block_indent_placeholder<T: ParsingTask>(
+        &self,
+        context: &mut ParsingContext<'_, T::Item>,
+    ) {
+        if consume_expected_chars!(context, '(').is_none() {
+            T::error(context);
+            return;
+        }
+        skip_until_neg_char_match!(context, ' '); // consume whitespaces
+
+        let indent = if consume_expected_chars!(context, '"').is_some() {
+            let mut prefix = String::new();
+            loop {
+                match context.iter.next() {
+                    Some('"') => break Some(prefix),
+                    Some(ch) => prefix.push(ch),
+                    None => break None,
+                }
+            }
+        } else {
+            self.parse_decimal_number(context)
+                .filter(|&count| context.reserve_output(count as usize))
+                .map(|count| " ".repeat(count as usize))
+        };
+        let Some(indent) = indent else {
+            T::error(context);
+            return;
+        };
+        skip_until_neg_char_match!(context, ' '); // consume whitespaces
+
+        if consume_expected_chars!(context, ')').is_none() {
+            T::error(context);
+            return;
+        }
+        context.block_indent = Some(indent);
+    }
+
+    /// Processes a line wrapping placeholder like `%w(72,4,2)`, which switches the wrapping of the
+    /// subsequent output. `%w(0)` switches it off.
+    fn process_wrap_placeholder<T: ParsingTask>(&self, context: &mut ParsingContext<'_, T::Item>) {
+        if consume_expected_chars!(context, '(').is_none() {
+            T::error(context);
+            return;
+        }
+        let mut args = Vec::<usize>::new();
+        loop {
+            skip_until_neg_char_match!(context, ' '); // consume whitespaces
+            let Some(digits) = gather!(context, '0'..='9') else {
+                T::error(context);
+                return;
+            };
+            let Ok(arg) = digits.into_iter().collect::<String>().parse::<usize>() else {
+                T::error(context);
+                return;
+            };
+            args.push(arg);
+            skip_until_neg_char_match!(context, ' '); // consume whitespaces
+            if args.len() == 3 || consume_expected_chars!(context, ',').is_none() {
+                break;
+            }
+        }
+        if consume_expected_chars!(context, ')').is_none() {
+            T::error(context);
+            return;
+        }
+        if !context.is_active() {
+            return;
+        }
+
+        args.resize(3, 0);
+        context.wrap = args
+            .iter()
+            .any(|&arg| arg > 0)
+            .then(|| LineWrap::new(args[0], args[1], args[2]));
+    }
+
+    /// Processes an alignment anchor like `%|(sep)`, which pads the output to the column of the
+    /// rightmost anchor of the same name in the template.
+    fn process_anchor_placeholder<T: ParsingTask>(
+        &self,
+        context: &mut ParsingContext<'_, T::Item>,
+    ) {
+        if consume_expected_chars!(context, '(').is_none() {
+            T::error(context);
+            return;
+        }
+        let Some(name) = self.gather_until_bracket(context) else {
+            T::error(context);
+            return;
+        };
+        if !context.is_active() {
+            return;
+        }
+
+        let column = context.column;
+        let target = context.anchor_targets.get(&name).copied().unwrap_or(column);
+        context.anchors.push((name, column));
+        for _ in column..target {
+            T::process_char_placeholder(context, ' ');
+        }
+    }
+
+    fn process_hex_char_placeholder<T: ParsingTask>(
+        &self,
+        context: &mut ParsingContext<'_, T::Item>,
+    ) {
+        let mut hex = String::new();
+        for _ in 0..2 {
+            let Some(digit) = consume_expected_chars!(context, '0'..='9' | 'a'..='f' | 'A'..='F')
+            else {
+                T::error(context);
+                return;
+            };
+            hex.push(digit);
+        }
+
+        let code = u8::from_str_radix(&hex, 16).unwrap();
+        if !code.is_ascii() {
+            T::error(context);
+            return;
+        }
+        T::process_char_placeholder(context, code as char);
+    }
+
+    fn process_placeholder<T: ParsingTask>(&self, context: &mut ParsingContext<'_, T::Item>) {
+        let Some(ch) = context.iter.next() else {
+            T::error(context);
+            return;
+        };
+
+        match ch {
+            '(' => {
+                self.process_bracket_placeholder::<T>(context);
+            }
+            '<' => {
+                self.process_format_left_placeholder::<T>(context);
+            }
+            '>' => {
+                self.process_format_right_placeholder::<T>(context);
+            }
+            #[cfg(feature = "colors")]
+            'C' => {
+                self.process_color_placeholder::<T>(context);
+            }
+            '-' if self.starts_with_bracketed_key(context) => {
+                self.process_prefixed_placeholder::<T>(context, WhitespacePrefix::RemoveLineFeeds);
+            }
+            '-' => {
+                self.process_rule_placeholder::<T>(context);
+            }
+            '+' => {
+                self.process_prefixed_placeholder::<T>(context, WhitespacePrefix::LineFeed);
+            }
+            ' ' => {
+                self.process_prefixed_placeholder::<T>(context, WhitespacePrefix::Space);
+            }
+            'T' => {
+                self.process_tab_stop_placeholder::<T>(context);
+            }
+            'w' => {
+                self.process_wrap_placeholder::<T>(context);
+            }
+            'I' => {
+                self.process_block_indent_placeholder::<T>(context);
+            }
+            '|' => {
+                self.process_anchor_placeholder::<T>(context);
+            }
+            'n' => {
+                T::process_char_placeholder(context, '\n');
+            }
+            't' => {
+                T::process_char_placeholder(context, '\t');
+            }
+            'r' => {
+                T::process_char_placeholder(context, '\r');
+            }
+            'e' => {
+                T::process_char_placeholder(context, '\x1b');
+            }
+            's' => {
+                T::process_char_placeholder(context, '\u{a0}');
+            }
+            '_' => {
+                T::process_char_placeholder(context, ' ');
+            }
+            '0' => {
+                T::process_char_placeholder(context, '\0');
+            }
+            'x' => {
+                self.process_hex_char_placeholder::<T>(context);
+            }
+            '%' => {
+                T::process_char_placeholder(context, '%');
+            }
+            _ => {
+                self.process_custom_char_placeholder::<T>(context, ch);
+            }
+        }
+    }
+
+    fn process_custom_char_placeholder<T: ParsingTask>(
+        &self,
+        context: &mut ParsingContext<'_, T::Item>,
+        ch: char,
+    ) {
+        let Some(handler) = self.char_placeholders.get(&ch) else {
+            T::error(context);
+            return;
+        };
+        if !context.allows(Capability::Extensions) {
+            return;
+        }
+
+        let value = handler(&context.visible_key_value());
+        let namespace = format!("%{}", ch);
+        self.process_computed_value::<T>(context, &namespace, namespace.clone(), value);
+    }
+
+    fn parse_generic<T: ParsingTask>(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+    ) -> T::Output {
+        let context = self.init_context::<T>(key_value, inp);
+        self.parse_context::<T>(context)
+    }
+
+    /// Renders the template with the terminal style of each output char. `*` widths resolve
+    /// against `width`.
+    #[cfg(feature = "ratatui")]
+    fn render_styled(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+        width: usize,
+    ) -> Vec<(char, ratatui::style::Style)> {
+        let mut context = self.init_context::<ParsingTaskStyled>(key_value, inp);
+        context.target_width = Some(width);
+        context.monospace = true;
+        self.parse_context::<ParsingTaskStyled>(context)
+    }
+
+    /// Renders the templates with value placeholders without format left-aligned to the widest
+    /// value at the same position. `monospace` measures widths in display columns.
+    ///
+    /// Cells spanning several columns like `%<(span=2)` are laid out in a second pass: their
+    /// width is the distance between the spanned columns in a template which has them as
+    /// single cells. A wider value widens the last spanned column.
+    #[cfg(feature = "tables")]
+    fn render_aligned(
+        &self,
+        key_value: &HashMap<&str, String>,
+        templates: &[&str],
+        monospace: bool,
+    ) -> Vec<String> {
+        let layouts: Vec<_> = templates
+            .iter()
+            .map(|template| self.cell_layout(key_value, template, monospace, Vec::new()))
+            .collect();
+        let mut column_widths = Vec::<usize>::new();
+        for layout in &layouts {
+            for (column, cell) in Self::columns(layout) {
+                if cell.span > 1 {
+                    continue;
+                }
+                let cell_width = cell.end.saturating_sub(cell.start);
+                match column_widths.get_mut(column) {
+                    Some(width) => *width = (*width).max(cell_width),
+                    None => column_widths.push(cell_width),
+                }
+            }
+        }
+
+        // combined widths of the spanning cells by first column and span
+        let mut span_widths = HashMap::<(usize, usize), usize>::new();
+        let spans: Vec<_> = layouts
+            .iter()
+            .flat_map(|layout| Self::columns(layout).filter(|(_, cell)| cell.span > 1))
+            .collect();
+        if !spans.is_empty() {
+            let positioned: Vec<_> = templates
+                .iter()
+                .zip(&layouts)
+                .map(|(template, layout)| {
+                    let widths = Self::cell_widths(layout, &column_widths, &span_widths);
+                    self.cell_layout(key_value, template, monospace, widths)
+                })
+                .collect();
+            for (column, cell) in spans {
+                let last = column + cell.span - 1;
+                let natural = cell.end.saturating_sub(cell.start);
+                let combined = positioned
+                    .iter()
+                    .filter_map(|layout| {
+                        let cells: HashMap<_, _> = Self::columns(layout)
+                            .filter(|(_, cell)| cell.span == 1)
+                            .collect();
+                        Some(
+                            cells
+                                .get(&last)?
+                                .end
+                                .saturating_sub(cells.get(&column)?.start),
+                        )
+                    })
+                    .max()
+                    .unwrap_or_else(|| column_widths.iter().skip(column).take(cell.span).sum());
+                if natural > combined && last < column_widths.len() {
+                    column_widths[last] += natural - combined;
+                }
+                let width = span_widths.entry((column, cell.span)).or_default();
+                *width = (*width).max(combined).max(natural);
+            }
+        }
+
+        templates
+            .iter()
+            .zip(&layouts)
+            .map(|(template, layout)| {
+                let mut context =
+                    self.init_context::<ParsingTaskReplacePlaceholders>(key_value, template);
+                context.monospace = monospace;
+                context.cell_widths = Self::cell_widths(layout, &column_widths, &span_widths);
+                self.parse_context::<ParsingTaskReplacePlaceholders>(context)
+            })
+            .collect()
+    }
+
+    /// Returns the layout of the value placeholders of a template rendered with `cell_widths`.
+    #[cfg(feature = "tables")]
+    fn cell_layout(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+        monospace: bool,
+        cell_widths: Vec<usize>,
+    ) -> Vec<CellLayout> {
+        let mut context = self.init_context::<ParsingTaskCellLayout>(key_value, inp);
+        context.monospace = monospace;
+        context.cell_widths = cell_widths;
+        self.parse_context::<ParsingTaskCellLayout>(context)
+    }
+
+    /// Returns the cells of a layout with the index of their first table column.
+    #[cfg(feature = "tables")]
+    fn columns(layout: &[CellLayout]) -> impl Iterator<Item = (usize, CellLayout)> + '_ {
+        layout.iter().scan(0, |column, cell| {
+            let first = *column;
+            *column += cell.span;
+            Some((first, *cell))
+        })
+    }
+
+    /// Returns the widths of the value placeholders of a layout: the width of their column or
+    /// the combined width of their spanned columns.
+    #[cfg(feature = "tables")]
+    fn cell_widths(
+        layout: &[CellLayout],
+        column_widths: &[usize],
+        span_widths: &HashMap<(usize, usize), usize>,
+    ) -> Vec<usize> {
+        let mut widths = Vec::new();
+        for (column, cell) in Self::columns(layout) {
+            let width = match cell.span {
+                1 => column_widths.get(column),
+                span => span_widths.get(&(column, span)),
+            };
+            match width {
+                Some(&width) => widths.push(width),
+                None => break,
+            }
+        }
+        widths
+    }
+
+    /// Creates the parsing context of task `T` configured according to this instance, with the
+    /// target columns of the alignment anchors of the template.
+    fn init_context<'a, T: ParsingTask>(
+        &'a self,
+        key_value: &'a HashMap<&'a str, String>,
+        inp: &'a str,
+    ) -> ParsingContext<'a, T::Item> {
+        let mut context = self.init_unaligned_context::<T>(key_value, inp);
+        self.align_anchors(&mut context, inp);
+        context
+    }
+
+    /// Sets the target columns of the alignment anchors of the template, measured with the
+    /// values and access policy of the context.
+    fn align_anchors<I>(&self, context: &mut ParsingContext<'_, I>, inp: &str) {
+        if inp.contains("%|(") {
+            context.anchor_targets = self.anchor_targets(context.key_value, inp, context.policy);
+        }
+    }
+
+    /// Returns the target column of each alignment anchor: the rightmost column an anchor of the
+    /// same name is rendered at. Aligning an anchor moves the subsequent anchors of its line, so
+    /// the columns are measured again until they are stable, at most once per anchor name.
+    fn anchor_targets(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+        policy: Option<&KeyPolicy>,
+    ) -> HashMap<String, usize> {
+        let mut targets = HashMap::<String, usize>::new();
+        let mut passes = 0;
+        loop {
+            let mut context =
+                self.init_unaligned_context::<ParsingTaskMeasureAnchors>(key_value, inp);
+            context.anchor_targets = targets.clone();
+            context.policy = policy;
+            let mut measured = HashMap::<String, usize>::new();
+            for (name, column) in self.parse_context::<ParsingTaskMeasureAnchors>(context) {
+                let target = measured.entry(name).or_default();
+                *target = (*target).max(column);
+            }
+            passes += 1;
+            if measured == targets || passes > measured.len() {
+                return measured;
+            }
+            targets = measured;
+        }
+    }
+
+    /// Creates the parsing context of task `T` configured according to this instance.
+    fn init_unaligned_context<'a, T: ParsingTask>(
+        &'a self,
+        key_value: &'a HashMap<&'a str, String>,
+        inp: &'a str,
+    ) -> ParsingContext<'a, T::Item> {
+        let mut context = T::init(inp, key_value);
+        context.right_to_left = self.right_to_left;
+        context.target_width = self.target_width;
+        if inp.contains("width_of:") {
+            context.rendered_widths = Some(HashMap::new());
+        }
+        context.number_symbols = self.number_symbols;
+        context.ambient = ambient::snapshot(&self.ambient);
+        #[cfg(feature = "chrono")]
+        {
+            context.clock = Some(&self.clock);
+        }
+        context.random = Some(&self.random);
+        context.missing_key = &self.missing_key;
+        if self.canonical {
+            // Pinned rather than `ELLIPSIS`, so the canonical output survives a new default.
+            context.ellipsis = '…';
+            #[cfg(feature = "chrono")]
+            {
+                context.clock = None;
+            }
+            context.random = None;
+        }
+        #[cfg(feature = "tracing")]
+        {
+            context.template_id = template_id(inp);
+        }
+        #[cfg(feature = "colors")]
+        {
+            context.color_target = self.color_target;
+        }
+        context
+    }
+
+    fn parse_context<T: ParsingTask>(&self, mut context: ParsingContext<'_, T::Item>) -> T::Output {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("render", template_id = %context.template_id).entered();
+        while let Some(ch) = context.iter.peek() {
+            #[cfg(feature = "sandbox")]
+            if let Some(quota) = context.quota.as_ref() {
+                if !quota.consume_token(context.vout.len()) {
+                    break;
+                }
+            }
+            match ch {
+                '%' => {
+                    context.iter.mark(); // mark position of placeholder start
+                    context.iter.next();
+                    self.process_placeholder::<ParsingTaskBlockGate<T>>(&mut context);
+                }
+                _ => {
+                    context.iter.next();
+                    ParsingTaskBlockGate::<T>::process_char(&mut context, ch);
+                }
+            }
+        }
+        T::done(context)
+    }
+
+    /// Renders a compiled template with task `T`, like `parse_context` for the template the
+    /// instructions were compiled from. The iterator is moved to the source of each instruction,
+    /// so the tasks see the same positions as while parsing.
+    fn run_instructions<T: ParsingTask>(
+        &self,
+        mut context: ParsingContext<'_, T::Item>,
+        instructions: &[Instruction],
+    ) -> T::Output {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("render", template_id = %context.template_id).entered();
+        for instruction in instructions {
+            match instruction {
+                Instruction::Literal(span) => {
+                    context.iter.set_position(span.start);
+                    for _ in span.clone() {
+                        let Some(ch) = context.iter.next() else {
+                            break;
+                        };
+                        T::process_char(&mut context, ch);
+                    }
+                }
+                Instruction::Char(ch, span) => {
+                    context.iter.select(span.clone());
+                    T::process_char_placeholder(&mut context, *ch);
+                }
+                Instruction::Value(placeholder, format, span) => {
+                    context.iter.select(span.clone());
+                    context.format = *format;
+                    self.render_value::<T>(&mut context, placeholder.clone());
+                }
+                #[cfg(feature = "colors")]
+                Instruction::Color(spec, span) => {
+                    context.iter.select(span.clone());
+                    T::process_color_placeholder(&mut context, spec);
+                }
+            }
+        }
+        T::done(context)
+    }
+}
+
+impl PlaceholderFormatter for Formatify {
+    fn replace_placeholders(&self, key_value: &HashMap<&str, String>, inp: &str) -> String {
+        self.parse_generic::<ParsingTaskReplacePlaceholders>(key_value, inp)
+    }
+
+    fn replace_placeholders_values(
+        &self,
+        values: &HashMap<&str, FormatValue>,
+        inp: &str,
+    ) -> String {
+        // rejected values are left out, so their placeholders are treated as missing
+        let tree = values
+            .iter()
+            .fold(ValueTree::branch(), |tree, (key, value)| {
+                match value.to_value_tree_with(self.invalid_utf8) {
+                    Some(child) => tree.with(key, child),
+                    None => tree,
+                }
+            });
+        self.replace_placeholders_tree(&tree, inp)
+    }
+
+    fn replace_placeholders_fixed_width(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+        width: usize,
+        policy: MultiBytePolicy,
+    ) -> Result<String, RenderError> {
+        let violations = self.parse_generic::<ParsingTaskCheckFixedWidth>(key_value, inp);
+        if let Some(violation) = violations.into_iter().next() {
+            return Err(violation);
+        }
+
+        let out = self.parse_generic::<ParsingTaskReplacePlaceholders>(key_value, inp);
+        let found = policy.measure(&out)?;
+
+        if found != width {
+            return Err(RenderError::WidthMismatch {
+                expected: width,
+                found,
+            });
+        }
+        Ok(out)
+    }
+
+    fn replace_placeholders_strict(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+    ) -> Result<String, RenderError> {
+        let violations = self.parse_generic::<ParsingTaskCheckLiterals>(key_value, inp);
+        if let Some(violation) = violations.into_iter().next() {
+            return Err(violation);
+        }
+        Ok(self.parse_generic::<ParsingTaskReplacePlaceholders>(key_value, inp))
+    }
+
+    #[cfg(feature = "sandbox")]
+    fn replace_placeholders_sandboxed(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+        quotas: &Quotas,
+    ) -> Result<String, RenderError> {
+        let quota = Rc::new(QuotaState::new(quotas.clone()));
+        let mut context = self.init_context::<ParsingTaskReplacePlaceholders>(key_value, inp);
+        context.quota = Some(quota.clone());
+        let out = self.parse_context::<ParsingTaskReplacePlaceholders>(context);
+        quota.check_output(&out);
+        if let Some(capability) = quota.denied() {
+            return Err(RenderError::CapabilityDenied { capability });
+        }
+        match quota.exceeded() {
+            Some(quota) => Err(RenderError::QuotaExceeded { quota }),
+            None => Ok(out),
+        }
+    }
+
+    #[cfg(feature = "tables")]
+    fn replace_placeholders_joined(
+        &self,
+        key_value: &HashMap<&str, String>,
+        templates: &[&str],
+    ) -> String {
+        self.render_aligned(key_value, templates, false).join("\n")
+    }
+
+    #[cfg(feature = "tables")]
+    fn replace_placeholders_code_block(
+        &self,
+        key_value: &HashMap<&str, String>,
+        templates: &[&str],
+    ) -> String {
+        let body = self.render_aligned(key_value, templates, true).join("\n");
+        fence_code_block(&body)
+    }
+
+    fn replace_placeholders_negotiated(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+        negotiate: &dyn Fn(&[usize]) -> Vec<usize>,
+    ) -> String {
+        let lengths = self.measure_lengths(key_value, inp);
+        let widths = negotiate(&lengths[1..]);
+
+        let mut context = self.init_context::<ParsingTaskReplacePlaceholders>(key_value, inp);
+        context.exact_cell_widths = widths;
+        self.parse_context::<ParsingTaskReplacePlaceholders>(context)
+    }
+
+    fn replace_placeholders_with_policy(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+        policy: &KeyPolicy,
+    ) -> String {
+        let mut context =
+            self.init_unaligned_context::<ParsingTaskReplacePlaceholders>(key_value, inp);
+        context.policy = Some(policy);
+        self.align_anchors(&mut context, inp);
+        self.parse_context::<ParsingTaskReplacePlaceholders>(context)
+    }
+
+    fn replace_placeholders_with_source_map(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+    ) -> (String, Vec<SourceMapEntry>) {
+        self.parse_generic::<ParsingTaskSourceMap>(key_value, inp)
+    }
+
+    fn replace_placeholders_with_truncations(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+    ) -> (String, Vec<Truncation>) {
+        self.parse_generic::<ParsingTaskTruncations>(key_value, inp)
+    }
+
+    fn replace_placeholders_with_audit(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+    ) -> (String, Vec<AuditEntry>) {
+        self.parse_generic::<ParsingTaskAudit>(key_value, inp)
+    }
+
+    fn replace_placeholders_checked(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+    ) -> (String, Vec<(String, usize)>) {
+        self.parse_generic::<ParsingTaskMissingKeys>(key_value, inp)
+    }
+
+    #[cfg(feature = "html")]
+    fn replace_placeholders_html(&self, key_value: &HashMap<&str, String>, inp: &str) -> String {
+        self.parse_generic::<ParsingTaskHtml>(key_value, inp)
+    }
+
+    #[cfg(feature = "markdown")]
+    fn replace_placeholders_markdown(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+        flavor: MarkdownFlavor,
+    ) -> String {
+        let mut context = self.init_context::<ParsingTaskMarkdown>(key_value, inp);
+        context.markdown_flavor = Some(flavor);
+        self.parse_context::<ParsingTaskMarkdown>(context)
+    }
+
+    fn measure_lengths(&self, key_value: &HashMap<&str, String>, inp: &str) -> Vec<usize> {
+        self.parse_generic::<ParsingTaskMeasureLengths>(key_value, inp)
+    }
+
+    fn measure_and_render(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+    ) -> (String, Vec<usize>) {
+        self.parse_generic::<ParsingTaskMeasureAndRender>(key_value, inp)
+    }
+
+    fn extract_placeholder_keys(&self, inp: &str) -> Vec<String> {
+        let key_value = HashMap::<&str, String>::new();
+        self.parse_generic::<ParsingTaskExtractPlaceholderKeys>(&key_value, inp)
+    }
+
+    fn parse(&self, inp: &str) -> Vec<Segment> {
+        let key_value = HashMap::<&str, String>::new();
+        self.parse_generic::<ParsingTaskSegments>(&key_value, inp)
+    }
+}
+
+impl Default for Formatify {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests_extract_placeholder_keys {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let parser = Formatify::new();
+                let out_str = parser.extract_placeholder_keys($inp);
+                assert_eq!(out_str, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_empty_input_returns_empty_vec,
+        "",
+        Vec::<String>::new()
+    );
+
+    test!(
+        test_with_length_placeholder_returns_key,
+        "%(#title) %(#count)",
+        vec!["title", "count"]
+    );
+
+    test!(
+        test_with_plain_string_returns_empty_vec,
+        "Conventional string",
+        Vec::<String>::new()
+    );
+
+    test!(
+        test_with_unicode_string_returns_empty_vec,
+        "Smiley 😊 Smiley",
+        Vec::<String>::new()
+    );
+
+    test!(
+        test_with_single_placeholder_returns_one_placeholder,
+        "Hello %(var1)", // replaces to "Hello world"
+        vec!["var1"]
+    );
+
+    test!(
+        test_with_multiple_placeholders_return_two_placeholders,
+        "Hello %(var1). Hallo %(var2).", // "Hello world. Hallo welt."
+        vec!["var1", "var2"]
+    );
+
+    test!(
+        test_with_undefined_second_placeholder_returns_two_placeholders,
+        "Hallo %(var1)%(vara)",
+        vec!["var1", "vara"]
+    );
+
+    test!(
+        test_with_incomplete_placeholder_syntax_returns_empty_vec,
+        "Hallo %(var1",
+        Vec::<String>::new()
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_conditional_blocks_returns_keys_of_all_branches,
+        "%(?var1)%(var2)%(:else)%(var3)%(/var1)",
+        vec!["var1", "var2", "var3"]
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_if_blocks_returns_keys_of_all_branches,
+        "%(if:var1)%(var2)%(else)%(var3)%(end)",
+        vec!["var1", "var2", "var3"]
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_comparison_returns_keys_of_both_sides,
+        "%(?var1 > var2)%(var3)%(/var1)",
+        vec!["var1", "var2", "var3"]
+    );
+
+    test!(
+        test_with_fallback_keys_returns_all_keys,
+        "Hallo %(var1|var2:-x)",
+        vec!["var1", "var2"]
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_modifiers_returns_key,
+        "Hallo %(var1|upper|trim)",
+        vec!["var1"]
+    );
+
+    test!(
+        test_with_default_value_returns_key,
+        "Hallo %(var1:-unknown)",
+        vec!["var1"]
+    );
+
+    #[cfg(feature = "colors")]
+    test!(
+        test_with_color_placeholders_ignores_them,
+        "%C(red)Hallo %(var1)%C(reset)",
+        vec!["var1"]
+    );
+}
+
+#[cfg(test)]
+mod tests_count_placeholder_keys {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let parser = Formatify::new();
+                let expected: Vec<(String, usize)> = $expected_output
+                    .into_iter()
+                    .map(|(key, count): (&str, usize)| (key.to_string(), count))
+                    .collect();
+                assert_eq!(parser.count_placeholder_keys($inp), expected);
+                let unique: Vec<String> = expected.into_iter().map(|(key, _)| key).collect();
+                assert_eq!(parser.extract_unique_placeholder_keys($inp), unique);
+            }
+        };
+    }
+
+    test!(
+        test_with_empty_input_returns_empty_vec,
+        "",
+        Vec::<(&str, usize)>::new()
+    );
+
+    test!(
+        test_with_repeated_keys_counts_them,
+        "%(b) %(a) %(b|a) %(#b)",
+        vec![("b", 3), ("a", 2)]
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_condition_and_branches_counts_all,
+        "%(?a)%(a)%(:else)%(b)%(/a)%(for:item in a)%(item)%(end)",
+        vec![("a", 3), ("b", 1)]
+    );
+}
+
+#[cfg(test)]
+mod tests_unused_keys {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("name", "Alice".into());
+                key_value.insert("items", join_list(["a", "b"]));
+                key_value.insert("flag", "1".into());
+                key_value.insert("user.city", "Berlin".into());
+                let parser = Formatify::new();
+                let out = parser.unused_keys(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_plain_string_returns_all_keys_sorted,
+        "plain",
+        vec!["flag", "items", "name", "user.city"]
+    );
+
+    test!(
+        test_with_fallback_and_index_marks_keys_used,
+        "%(nick|name) %(items[0])",
+        vec!["flag", "user.city"]
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_condition_and_inactive_branch_marks_keys_used,
+        "%(if:flag>1)%(user.city)%(end)%(for:item in items)%(item)%(end)",
+        vec!["name"]
+    );
+}
+
+#[cfg(test)]
+mod tests_measure_lengths {
+    use std::collections::HashMap;
+
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("var1", "world".into());
+                key_value.insert("var2", "welt".into());
+                key_value.insert("str4", "1234".into());
+                key_value.insert("str10", "1234567890".into());
+                key_value.insert("str14", "1234567890ABCD".into());
+                key_value.insert("umlaute", "äöü".into());
+                key_value.insert("umlaute_bigger", "äöü12345678".into());
+                key_value.insert("empty", "".into());
+                let parser = Formatify::new();
+                let out_str = parser.measure_lengths(&key_value, $inp);
+                assert_eq!(out_str, $expected_output);
+            }
+        };
+    }
+
+    test!(test_with_empty_input_returns_vec0, "", vec![0usize]);
+    test!(
+        test_with_plain_string_returns_correct_length,
+        "Conventional string",
+        vec![19usize]
+    );
+
+    test!(
+        test_with_unicode_string_returns_correct_length,
+        "Smiley 😊 Smiley",
+        vec![15usize]
+    );
+
+    test!(
+        test_with_single_placeholder_measures_correctly,
+        "Hello %(var1)", // replaces to "Hello world"
+        vec![11usize, 5usize]
+    );
+
+    test!(
+        test_with_invalid_token_type_counts_length_of_unreplaced_string,
+        "Hallo %z", // replaces nothing -> "Hallo %z"
+        vec![8usize]
+    );
+
+    test!(
+        test_with_one_char_token_type_counts_length_of_replaced_char,
+        "abcde %%", // replaces nothing -> "abcde %"
+        vec![7usize]
+    );
+
+    test!(
+        test_with_multiple_placeholders_return_correct_length_of_string_and_placeholders,
+        "Hello %(var1). Hallo %(var2).", // "Hello world. Hallo welt."
+        vec![24usize, 5usize, 4usize]
+    );
+
+    test!(
+        test_with_undefined_second_placeholder_return_correct_length_of_string_and_placeholders,
+        "Hallo %(var1)%(vara)", // "Hallo world%(vara)"
+        vec![18usize, 5usize]
+    );
+
+    test!(
+        test_with_left_alignment_placeholder_and_shorter_value_returns_correct_length,
+        "Hallo %<(10)%(str4)xx", // "Hallo 1234      xx"
+        vec![18usize, 10usize]
+    );
+
+    test!(
+        test_with_right_alignment_placeholder_and_shorter_returns_correct_length,
+        "Hallo %>(10)%(str4)xx", // "Hallo       1234xx"
+        vec![18usize, 10usize]
+    );
+
+    test!(
+        test_with_left_alignment_placeholder_and_exact_length_value_returns_correct_length,
+        "Hallo %<(10)%(str10)xx", // "Hallo 1234567890xx"
+        vec![18usize, 10usize]
+    );
+
+    test!(
+        test_with_right_alignment_placeholder_and_exact_length_value_returns_correct_length,
+        "Hallo %>(10)%(str10)xx", // "Hallo 1234567890xx"
+        vec![18usize, 10usize]
+    );
+
+    test!(
+        test_with_left_alignment_placeholder_and_longer_value_returns_correct_length,
+        "Hallo %<(10)%(str14)xx", // "Hallo 1234567890ABCDxx"
+        vec![22usize, 14usize]
+    );
+
+    test!(
+        test_with_right_alignment_placeholder_and_longer_value_returns_correct_length,
+        "Hallo %>(10)%(str14)xx", // "Hallo 1234567890ABCDxx"
+        vec![22usize, 14usize]
+    );
+
+    test!(
+        test_with_right_align_truncate_placeholder_and_shorter_value_with_umlauts_returns_correct_length,
+        "Hallo %>(10,trunc)%(umlaute)xx", // "Hallo äöü       xx"
+        vec![18usize, 10usize]
+    );
+
+    test!(
+        test_with_left_align_truncate_placeholder_and_shorter_value_with_umlauts_returns_correct_length,
+        "Hallo %<(10,trunc)%(umlaute)xx", // "Hallo äöü       xx"
+        vec![18usize, 10usize]
+    );
+
+    test!(
+        test_with_left_align_truncate_placeholder_and_exact_length_value_returns_correct_length,
+        "Hallo %<(10,trunc)%(str10)xx", // "Hallo 1234567890xx"
+        vec![18usize, 10usize]
+    );
+
+    test!(
+        test_with_left_align_truncate_placeholder_and_longer_value_returns_correct_length,
+        "Hallo %<(10,trunc)%(str14)xx", // "Hallo 123456789…xx"
+        vec![18usize, 10usize]
+    );
+
+    test!(
+        test_with_right_align_truncate_placeholder_and_longer_value_returns_correct_length,
+        "Hallo %>(10,trunc)%(str14)xx", // "Hallo 123456789…xx"
+        vec![18usize, 10usize]
+    );
+
+    test!(
+        test_with_absolute_left_alignment_placeholder_returns_correct_length,
+        "ab%<|(8)%(str4)|", // "ab1234  |"
+        vec![9usize, 6usize]
+    );
+
+    test!(
+        test_with_absolute_alignment_placeholder_after_newline_returns_correct_length,
+        "Hallo%n%>|(6)%(str4)|", // "Hallo\n  1234|"
+        vec![13usize, 6usize]
+    );
+
+    test!(
+        test_with_escaped_control_chars_returns_correct_length,
+        "a%t%r%e%x1b", // "a\t\r\x1b\x1b"
+        vec![5usize]
+    );
+
+    test!(
+        test_with_tab_stop_placeholder_returns_correct_length,
+        "%(var1)%T(8)|", // "world   |"
+        vec![9usize, 5usize]
+    );
+
+    test!(
+        test_with_fallback_keys_measures_first_present_value,
+        "Hallo %(vara|str4)", // "Hallo 1234"
+        vec![10usize, 4usize]
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_modifier_measures_modified_value,
+        "Hallo %(str14|ltrim)", // "Hallo 1234567890ABCD"
+        vec![20usize, 14usize]
+    );
+
+    test!(
+        test_with_default_value_and_missing_key_measures_default,
+        "Hallo %(vara:-unknown)", // "Hallo unknown"
+        vec![13usize, 7usize]
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_inactive_conditional_block_skips_its_length,
+        "Hallo%(?vara) %(str10)%(:else) %(var1)%(/vara)", // "Hallo world"
+        vec![11usize, 5usize]
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_inactive_if_block_skips_its_length,
+        "Hallo%(if:vara) %(str10)%(else) %(var1)%(end)", // "Hallo world"
+        vec![11usize, 5usize]
+    );
+
+    test!(
+        test_with_rule_placeholder_returns_correct_length,
+        "ab%-(5,'=')", // "ab====="
+        vec![7usize]
+    );
+
+    #[test]
+    fn test_with_wrapped_value_measures_the_rendered_output() {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert(
+            "multi",
+            "the quick brown fox jumps over the lazy dog".into(),
+        );
+        let parser = Formatify::new();
+        let inp = "%w(10,2,4)%(multi)";
+        let out_str = parser.replace_placeholders(&key_value, inp);
+        assert_eq!(
+            out_str,
+            "  the\n    quick\n    brown\n    fox\n    jumps\n    over\n    the\n    lazy\n    dog"
+        );
+        assert_eq!(
+            parser.measure_lengths(&key_value, inp),
+            vec![77usize, 77usize]
+        );
+    }
+
+    test!(
+        test_with_justified_long_word_measures_the_truncated_word,
+        "%<(4,justify)%(str10)", // "123…"
+        vec![4usize, 4usize]
+    );
+
+    #[test]
+    fn test_with_justified_multi_line_value_measures_the_rendered_block() {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("lines", "one\ntwo\nthree".into());
+        let parser = Formatify::new();
+        let inp = "> %<(8,justify)%(lines)";
+        let out_str = parser.replace_placeholders(&key_value, inp);
+        assert_eq!(out_str, "> one  two\n  three   ");
+        assert_eq!(
+            parser.measure_lengths(&key_value, inp),
+            vec![21usize, 19usize]
+        );
+    }
+
+    test!(
+        test_with_empty_value_removing_line_feeds_skips_their_length,
+        "%(str4)%n%n%-(empty)", // "1234"
+        vec![4usize, 4usize, 0usize]
+    );
+
+    test!(
+        test_with_block_indent_counts_the_indent,
+        "%I(2)%(var1)", // "  world"
+        vec![7usize, 7usize]
+    );
+
+    test!(
+        test_with_block_indent_counts_the_indent_of_each_value_line,
+        "%I(2)x\n%(str4)", // "x\n  1234"
+        vec![8usize, 6usize]
+    );
+
+    #[cfg(feature = "colors")]
+    test!(
+        test_with_color_placeholders_counts_them_as_zero_width,
+        "%C(bold red)Hallo%Creset %(var1)", // "Hallo world" + escape sequences
+        vec![11usize, 5usize]
+    );
+
+    #[cfg(feature = "colors")]
+    test!(
+        test_with_invalid_color_placeholder_counts_length_of_unreplaced_string,
+        "%C(purple)xx", // "%C(purple)xx"
+        vec![12usize]
+    );
+}
+
+#[cfg(test)]
+mod tests_replace_placeholders {
+    use crate::*;
+    use std::collections::HashMap;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("var1", "world".into());
+                key_value.insert("var2", "welt".into());
+                key_value.insert("str4", "1234".into());
+                key_value.insert("str10", "1234567890".into());
+                key_value.insert("str14", "1234567890ABCD".into());
+                key_value.insert("umlaute", "äöü".into());
+                key_value.insert("umlaute_bigger", "äöü12345678".into());
+                key_value.insert("empty", "".into());
+                key_value.insert("padded", "  welt ".into());
+                key_value.insert("blank", "   ".into());
+                key_value.insert("zero", "0".into());
+                key_value.insert("one", "1".into());
+                key_value.insert("minus_one", "-1".into());
+                key_value.insert("markup", "<a href=\"x\">Tom & 'Jerry'</a>".into());
+                key_value.insert("control", "a\\b\n\t\u{1}".into());
+                key_value.insert("path", "docs/Größe & Preis/a+b.md".into());
+                key_value.insert("header", "content-TYPE".into());
+                key_value.insert("acronym", "parseHTTPResponse_code".into());
+                key_value.insert("phrase", "User ID v2".into());
+                key_value.insert("star", "★".into());
+                key_value.insert("spaced", " \ta  b\n\n c ".into());
+                let parser = Formatify::new();
+                let out_str = parser.replace_placeholders(&key_value, $inp);
+                assert_eq!(out_str, $expected_output);
+            }
+        };
+    }
+
+    test!(test_with_empty_input_returns_empty_string, "", "");
+
+    test!(
+        test_with_plain_string_returns_same_string,
+        "Conventional string",
+        "Conventional string"
+    );
+
+    test!(
+        test_with_unicode_string_returns_same_string,
+        "Smiley 😊 Smiley",
+        "Smiley 😊 Smiley"
+    );
+
+    test!(
+        test_with_single_placeholder_replaces_correctly,
+        "Hello %(var1)",
+        "Hello world"
+    );
+
+    test!(
+        test_with_single_placeholder_alternative_value_replaces_correctly,
+        "Hello %(var2)",
+        "Hello welt"
+    );
+
+    test!(
+        test_with_one_char_token_type_replaces_correctly,
+        "abcde %%", // replaces nothing -> "abcde %"
+        "abcde %"
+    );
+
+    test!(
+        test_with_invalid_token_type_leaves_token_unreplaced,
+        "Hallo %z",
+        "Hallo %z"
+    );
+
+    test!(
+        test_with_multiple_placeholders_replaces_all_correctly,
+        "Hello %(var1). Hallo %(var2).",
+        "Hello world. Hallo welt."
+    );
+
+    test!(
+        test_with_multiple_placeholders_and_delimiters_replaces_correctly,
+        "|%(var1)|%(var2)|",
+        "|world|welt|"
+    );
+
+    test!(
+        test_with_undefined_second_placeholder_keeps_it_unreplaced,
+        "Hallo %(var1)%(vara)",
+        "Hallo world%(vara)"
+    );
+
+    test!(
+        test_with_undefined_first_placeholder_keeps_it_unreplaced,
+        "Hallo %(vara)%(var2)",
+        "Hallo %(vara)welt"
+    );
+
+    test!(
+        test_with_incorrect_placeholder_syntax_keeps_it_unreplaced,
+        "Hallo %var1",
+        "Hallo %var1"
+    );
+
+    test!(
+        test_with_incomplete_placeholder_syntax_keeps_it_unreplaced,
+        "Hallo %(var1",
+        "Hallo %(var1"
+    );
+
+    test!(
+        test_with_default_value_and_present_key_renders_value,
+        "Hallo %(var1:-unknown)!",
+        "Hallo world!"
+    );
+
+    test!(
+        test_with_default_value_and_missing_key_renders_default,
+        "Hallo %(vara:-unknown)!",
+        "Hallo unknown!"
+    );
+
+    test!(
+        test_with_default_value_and_empty_value_renders_default,
+        "Hallo %(empty:-nobody, really)!",
+        "Hallo nobody, really!"
+    );
+
+    test!(
+        test_with_empty_default_value_and_missing_key_renders_nothing,
+        "Hallo %(vara:-)!",
+        "Hallo !"
+    );
+
+    test!(
+        test_with_aligned_default_value_pads_default,
+        "%<(9)%(vara:-unknown)|",
+        "unknown  |"
+    );
+
+    test!(
+        test_with_unterminated_default_value_keeps_it_unchanged,
+        "Hallo %(vara:-unknown",
+        "Hallo %(vara:-unknown"
+    );
+
+    test!(
+        test_with_fallback_keys_uses_first_present_key,
+        "Hallo %(vara|varb|var2|var1)!",
+        "Hallo welt!"
+    );
+
+    test!(
+        test_with_fallback_keys_and_present_first_key_uses_it,
+        "Hallo %(var1|var2)!",
+        "Hallo world!"
+    );
+
+    test!(
+        test_with_fallback_keys_and_empty_first_value_uses_it,
+        "Hallo %(empty|var1)!",
+        "Hallo !"
+    );
+
+    test!(
+        test_with_fallback_keys_and_default_value_renders_default,
+        "Hallo %(vara|varb:-unknown)!",
+        "Hallo unknown!"
+    );
+
+    test!(
+        test_with_missing_fallback_keys_keeps_placeholder_unchanged,
+        "Hallo %(vara|varb)!",
+        "Hallo %(vara|varb)!"
+    );
+
+    test!(
+        test_with_empty_fallback_key_keeps_placeholder_unchanged,
+        "Hallo %(var1|)!",
+        "Hallo %(var1|)!"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_upper_modifier_converts_value,
+        "Hallo %(var1|upper)!",
+        "Hallo WORLD!"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_modifier_chain_applies_modifiers_in_order,
+        "Hallo %(padded|trim|capitalize)!",
+        "Hallo Welt!"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_modifier_applies_before_alignment,
+        "%<(6)%(padded|trim)|",
+        "welt  |"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_fallback_keys_and_modifier_converts_fallback_value,
+        "Hallo %(vara|var2|upper)!",
+        "Hallo WELT!"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_modifier_and_empty_result_renders_default,
+        "Hallo %(blank|trim:-nobody)!",
+        "Hallo nobody!"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_radix_modifiers_renders_integer_in_radix,
+        "%(str4|hex) %(str4|oct) %(str4|bin)",
+        "4d2 2322 10011010010"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_prefixed_radix_modifiers_adds_prefix,
+        "%(str4|0x) %(str4|0o) %(str4|0b)",
+        "0x4d2 0o2322 0b10011010010"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_radix_modifier_width_zero_pads_digits,
+        "%(str4|hex8) %(str4|0x6) %(str4|bin4)",
+        "000004d2 0x0004d2 10011010010"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_radix_modifier_and_non_integer_renders_value_unchanged,
+        "%(var1|hex) %(str14|0b)",
+        "world 1234567890ABCD"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_bytes_modifier_renders_binary_units,
+        "%(str4|bytes) %(str10|bytes) %(str14|bytes)",
+        "1.2 KiB 1.1 GiB 1234567890ABCD"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_bytes_si_modifier_renders_si_units,
+        "%(str4|bytes_si) %(str10|bytes_si)",
+        "1.2 kB 1.2 GB"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_bytes_modifier_precision_sets_decimal_places,
+        "%(str4|bytes_si2) %(str10|bytes0) %(str4|bytes3)",
+        "1.23 kB 1 GiB 1.205 KiB"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_bytes_modifier_and_formatted_size_renders_value_unchanged,
+        "%(str4|bytes_si|bytes)",
+        "1.2 kB"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_trim_start_and_trim_end_modifiers,
+        "[%(padded|trim_start)] [%(padded|trim_end)] [%(blank|trim_end)]",
+        "[welt ] [  welt] []"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_trim_collapse_modifier_collapses_whitespace_runs,
+        "[%<(12)%(spaced|trim:collapse)] [%(blank|trim:collapse:-none)]",
+        "[a b c       ] [none]"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_repeat_modifier_repeats_value,
+        "[%(star|repeat:5)] [%(star|repeat:0)] [%<(4)%(star|repeat:2)]",
+        "[★★★★★] [] [★★  ]"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_repeat_modifier_takes_count_from_key,
+        "%(star|repeat:one)|%(star|repeat:zero)|",
+        "★||"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_repeat_modifier_invalid_count_keeps_placeholder_unchanged,
+        "%(star|repeat:missing) %(star|repeat:var1)",
+        "%(star|repeat:missing) %(star|repeat:var1)"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_replace_modifier_replaces_literal,
+        "%(path|replace:/,\\) %(header|replace:TYPE,type) %(header|replace:.,!)",
+        "docs\\Größe & Preis\\a+b.md content-type content-TYPE"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_replace_modifier_without_target_keeps_placeholder_unchanged,
+        "%(path|replace:/) %(path|replace:,x)",
+        "%(path|replace:/) %(path|replace:,x)"
+    );
+
+    #[cfg(feature = "regex")]
+    test!(
+        test_with_replace_re_modifier_replaces_matches,
+        "%(path|replace_re:[^a-z/.]+,_) %(header|replace_re:(\\w+)-(\\w+),$2-$1)",
+        "docs/_r_e_reis/a_b.md TYPE-content"
+    );
+
+    #[cfg(feature = "regex")]
+    test!(
+        test_with_replace_re_alternation_in_group_replaces_matches,
+        "%(header|replace_re:(content|TYPE),x)",
+        "x-x"
+    );
+
+    #[cfg(feature = "regex")]
+    test!(
+        test_with_replace_re_invalid_pattern_keeps_placeholder_unchanged,
+        "%(path|replace_re:[a-,_)",
+        "%(path|replace_re:[a-,_)"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_trim_unknown_option_keeps_placeholder_unchanged,
+        "%(spaced|trim:all)",
+        "%(spaced|trim:all)"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_title_modifier_capitalizes_words,
+        "%(padded|title) %(header|title) %(acronym|title)",
+        "  Welt  Content-Type Parsehttpresponse_Code"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_snake_and_kebab_modifiers_join_words,
+        "%(acronym|snake) %(header|snake) %(phrase|kebab) %(padded|kebab)",
+        "parse_http_response_code content_type user-id-v2 welt"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_camel_modifier_joins_words,
+        "%(acronym|camel) %(phrase|camel) %(header|camel)",
+        "parseHttpResponseCode userIdV2 contentType"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_case_modifier_and_alignment,
+        "[%<(12)%(phrase|snake)] %(empty|snake:-none)",
+        "[user_id_v2  ] none"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_html_modifier_escapes_value,
+        "<p>%(markup|html)</p>",
+        "<p>&lt;a href=&quot;x&quot;&gt;Tom &amp; &#39;Jerry&#39;&lt;/a&gt;</p>"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_html_modifier_keeps_default_and_plain_value,
+        "%(var1|html) %(missing|var2|html) %(missing|html:-<none>)",
+        "world welt <none>"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_json_modifier_quotes_and_escapes_value,
+        "{\"a\": %(markup|json), \"b\": %(control|json), \"c\": %(empty|json)}",
+        "{\"a\": \"<a href=\\\"x\\\">Tom & 'Jerry'</a>\", \"b\": \"a\\\\b\\n\\t\\u0001\", \"c\": \"\"}"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_shell_modifier_quotes_value,
+        "echo %(markup|shell) %(var1|shell) %(empty|shell)",
+        "echo '<a href=\"x\">Tom & '\\''Jerry'\\''</a>' 'world' ''"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_base64_modifier_encodes_value,
+        "%(var1|base64) %(str4|base64) %(umlaute|base64) %(empty|base64)|",
+        "d29ybGQ= MTIzNA== w6TDtsO8 |"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_base64url_modifier_encodes_value_without_padding,
+        "%(var1|base64url) %(umlaute_bigger|base64url) %(control|base64url)",
+        "d29ybGQ w6TDtsO8MTIzNDU2Nzg YVxiCgkB"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_urlencode_modifier_encodes_component,
+        "%(path|urlencode) %(str4|urlencode)",
+        "docs%2FGr%C3%B6%C3%9Fe%20%26%20Preis%2Fa%2Bb.md 1234"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_urlencode_path_and_query_modifiers,
+        "/%(path|urlencode:path)?q=%(path|urlencode:query)",
+        "/docs/Gr%C3%B6%C3%9Fe%20%26%20Preis/a%2Bb.md?q=docs%2FGr%C3%B6%C3%9Fe+%26+Preis%2Fa%2Bb.md"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_urlencode_unknown_semantics_keeps_placeholder_unchanged,
+        "%(path|urlencode:form)",
+        "%(path|urlencode:form)"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_plural_suffix_modifier_picks_form,
+        "%(one) file%(one|plural:s), %(str4) file%(str4|plural:s)",
+        "1 file, 1234 files"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_plural_forms_modifier_picks_form,
+        "%(one|plural:child,children) %(zero|plural:child,children) %(minus_one|plural:child,children)",
+        "child children child"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_plural_modifier_and_non_numeric_value_renders_value_unchanged,
+        "%(var1|plural:s)",
+        "world"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_plural_modifier_and_following_modifier_applies_it,
+        "%(zero|plural:file,files|upper)",
+        "FILES"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_invalid_modifier_argument_keeps_placeholder_unchanged,
+        "%(one|plural:a,b,c) %(one|upper:x) %(one|plural:s",
+        "%(one|plural:a,b,c) %(one|upper:x) %(one|plural:s"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_key_after_modifier_keeps_placeholder_unchanged,
+        "Hallo %(var1|upper|var2)!",
+        "Hallo %(var1|upper|var2)!"
+    );
+
+    test!(
+        test_with_trailing_percent_keeps_it_unchanged,
+        "100%",
+        "100%"
+    );
+
+    test!(
+        test_with_newline_placeholder_inserts_newline,
+        "Hallo %nWelt",
+        "Hallo \nWelt"
+    );
+
+    test!(
+        test_with_escaped_percent_sign_keeps_it_unchanged,
+        "Hallo %%(var1)",
+        "Hallo %(var1)"
+    );
+
+    test!(
+        test_with_newline_placeholder_at_end_inserts_newline,
+        "Hallo Welt %n",
+        "Hallo Welt \n"
+    );
+
+    test!(
+        test_with_left_alignment_placeholder_and_shorter_value_pads_correctly,
+        "Hallo %<(10)%(str4)xx",
+        "Hallo 1234      xx"
+    );
+
+    test!(
+        test_with_right_alignment_placeholder_and_shorter_value_pads_correctly,
+        "Hallo %>(10)%(str4)xx",
+        "Hallo       1234xx"
+    );
+
+    test!(
+        test_with_left_alignment_placeholder_and_exact_length_value_keeps_it_unchanged,
+        "Hallo %<(10)%(str10)xx",
+        "Hallo 1234567890xx"
+    );
+
+    test!(
+        test_with_right_alignment_placeholder_and_exact_length_value_keeps_it_unchanged,
+        "Hallo %>(10)%(str10)xx",
+        "Hallo 1234567890xx"
+    );
+
+    test!(
+        test_with_left_alignment_placeholder_and_longer_value_keeps_it_unchanged,
+        "Hallo %<(10)%(str14)xx",
+        "Hallo 1234567890ABCDxx"
+    );
+
+    test!(
+        test_with_right_alignment_placeholder_and_longer_value_keeps_it_unchanged,
+        "Hallo %>(10)%(str14)xx",
+        "Hallo 1234567890ABCDxx"
+    );
+
+    test!(
+        test_with_left_align_truncate_placeholder_and_exact_length_value_keeps_it_unchanged,
+        "Hallo %<(10,trunc)%(str10)xx",
+        "Hallo 1234567890xx"
+    );
+
+    test!(
+        test_with_right_align_truncate_placeholder_and_exact_length_value_keeps_it_unchanged,
+        "Hallo %>(10,trunc)%(str10)xx",
+        "Hallo 1234567890xx"
+    );
+
+    test!(
+        test_with_left_align_truncate_placeholder_and_exact_length_value_with_spaces_keeps_it_unchanged,
+        "Hallo %<(  10  ,  trunc   )%(str10)xx",
+        "Hallo 1234567890xx"
+    );
+
+    test!(
+        test_with_right_align_truncate_placeholder_and_longer_value_truncates_correctly,
+        "Hallo %>(10,trunc)%(str14)xx",
+        "Hallo 123456789…xx"
+    );
+
+    test!(
+        test_with_right_align_left_truncate_placeholder_and_longer_value_truncates_correctly,
+        "Hallo %>(10,ltrunc)%(str14)xx",
+        "Hallo …67890ABCDxx"
+    );
+
+    test!(
+        test_with_left_align_truncate_placeholder_and_longer_value_truncates_correctly,
+        "Hallo %<(10,trunc)%(str14)xx",
+        "Hallo 123456789…xx"
+    );
+
+    test!(
+        test_with_left_align_left_truncate_placeholder_and_longer_value_truncates_correctly,
+        "Hallo %<(10,ltrunc)%(str14)xx",
+        "Hallo …67890ABCDxx"
+    );
+
+    test!(
+        test_with_right_align_truncate_placeholder_and_shorter_value_with_umlauts_pads_correctly,
+        "Hallo %>(10,trunc)%(umlaute)xx",
+        "Hallo        äöüxx"
+    );
+
+    test!(
+        test_with_right_align_left_truncate_placeholder_and_shorter_value_with_umlauts_pads_correctly,
+        "Hallo %>(10,ltrunc)%(umlaute)xx",
+        "Hallo        äöüxx"
+    );
+
+    test!(
+        test_with_left_align_truncate_placeholder_and_shorter_value_with_umlauts_pads_correctly,
+        "Hallo %<(10,trunc)%(umlaute)xx",
+        "Hallo äöü       xx"
+    );
+
+    test!(
+        test_with_left_align_left_truncate_placeholder_and_shorter_value_with_umlauts_pads_correctly,
+        "Hallo %<(10,ltrunc)%(umlaute)xx",
+        "Hallo äöü       xx"
+    );
+
+    test!(
+        test_with_left_align_truncate_placeholder_and_longer_value_with_umlauts_truncates_correctly,
+        "Hallo %<(10,trunc)%(umlaute_bigger)xx",
+        "Hallo äöü123456…xx"
+    );
+
+    test!(
+        test_with_invalid_left_align_placeholder_keeps_format_specifier_unchanged,
+        "Hallo %<(a10)%(str14)xx",
+        "Hallo %<(a10)1234567890ABCDxx"
+    );
+
+    test!(
+        test_with_absolute_left_alignment_placeholder_pads_to_column,
+        "ab%<|(8)%(str4)|",
+        "ab1234  |"
+    );
+
+    test!(
+        test_with_absolute_right_alignment_placeholder_pads_to_column,
+        "Hallo %>|(12)%(str4)|",
+        "Hallo   1234|"
+    );
+
+    test!(
+        test_with_absolute_alignment_placeholder_after_newline_pads_to_column,
+        "Hallo%n%<|(6)%(str4)|",
+        "Hallo\n1234  |"
+    );
+
+    test!(
+        test_with_absolute_alignment_placeholder_with_column_already_passed_keeps_value_unchanged,
+        "Hallo Welt %<|(4)%(str4)|",
+        "Hallo Welt 1234|"
+    );
+
+    test!(
+        test_with_absolute_alignment_placeholder_after_padded_value_pads_to_column,
+        "%<(6)%(str4)%<|(10)%(var2)|",
+        "1234  welt|"
+    );
+
+    test!(
+        test_with_absolute_alignment_truncate_placeholder_truncates_to_column,
+        "abc%<|(8,trunc)%(str14)|",
+        "abc1234…|"
+    );
+
+    test!(
+        test_with_rule_placeholder_emits_dashes,
+        "ab%-(5)cd",
+        "ab-----cd"
+    );
+
+    test!(
+        test_with_rule_placeholder_and_fill_char_emits_fill_char,
+        "%-( 3 , '═' )|",
+        "═══|"
+    );
+
+    test!(
+        test_with_incomplete_rule_placeholder_keeps_it_unchanged,
+        "%-(3,'=)",
+        "%-(3,'=)"
+    );
+
+    test!(
+        test_with_rule_placeholder_without_target_width_keeps_it_unchanged,
+        "%-(*)",
+        "%-(*)"
+    );
+
+    test!(test_with_tab_placeholder_inserts_tab, "a%tb", "a\tb");
+
+    test!(
+        test_with_carriage_return_placeholder_inserts_carriage_return,
+        "a%r%n",
+        "a\r\n"
+    );
+
+    test!(
+        test_with_escape_placeholders_insert_escape_char,
+        "%e[1m%x1B[m",
+        "\x1b[1m\x1b[m"
+    );
+
+    test!(
+        test_with_whitespace_placeholders_insert_chars,
+        "a%sb%_c%0d",
+        "a\u{a0}b c\0d"
+    );
+
+    test!(
+        test_with_hex_placeholder_inserts_ascii_char,
+        "%x41%x7e",
+        "A~"
+    );
+
+    test!(
+        test_with_invalid_hex_placeholder_keeps_it_unchanged,
+        "%x4g %x",
+        "%x4g %x"
+    );
+
+    test!(
+        test_with_non_ascii_hex_placeholder_keeps_it_unchanged,
+        "%xff",
+        "%xff"
+    );
+
+    test!(
+        test_with_tab_stop_placeholder_pads_to_next_stop,
+        "ab%T(4)cd%T(4)|",
+        "ab  cd  |"
+    );
+
+    test!(
+        test_with_tab_stop_placeholder_at_stop_advances_to_next_stop,
+        "abcd%T(4)|",
+        "abcd    |"
+    );
+
+    test!(
+        test_with_tab_stop_placeholder_after_value_and_newline_uses_line_column,
+        "x%nHallo %(var1)%T(8)|",
+        "x\nHallo world     |"
+    );
+
+    test!(
+        test_with_invalid_tab_stop_placeholder_keeps_it_unchanged,
+        "a%T(0)b",
+        "a%T(0)b"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_conditional_block_and_present_key_renders_content,
+        "Hallo%(?var1) %(var1)%(/var1)!",
+        "Hallo world!"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_conditional_block_and_missing_key_skips_content,
+        "Hallo%(?vara) %(vara)%(/vara)!",
+        "Hallo!"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_conditional_block_and_empty_value_skips_content,
+        "Hallo%(?empty) [%(empty)]%(/empty)!",
+        "Hallo!"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_conditional_else_block_and_present_key_renders_first_branch,
+        "%(?var1)%(var1)%(:else)-%(/var1)",
+        "world"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_conditional_else_block_and_missing_key_renders_else_branch,
+        "%(?vara)%(vara)%(:else)-%(/vara)",
+        "-"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_nested_conditional_blocks_renders_inner_content,
+        "%(?var1)a%(?vara)b%(:else)c%(/vara)d%(:else)e%(/var1)",
+        "acd"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_nested_conditional_blocks_in_inactive_branch_skips_everything,
+        "%(?vara)a%(?var1)b%(:else)c%(/var1)d%(:else)e%(/vara)",
+        "e"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_numeric_comparison_renders_matching_branch,
+        "%(?str4>999)big%(:else)small%(/str4)",
+        "big"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_numeric_comparison_compares_numerically,
+        "%(?str4<10000)a%(/str4)%(?str4<=1234)b%(/str4)%(?str4>=1235)c%(/str4)",
+        "ab"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_string_comparison_renders_matching_branch,
+        "%(?var1==\"world\")yes%(:else)no%(/var1)|%(?var1!=\"world\")yes%(:else)no%(/var1)",
+        "yes|no"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_quoted_bracket_in_comparison_renders_matching_branch,
+        "%(?var1==\"a)\")yes%(:else)no%(/var1)",
+        "no"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_comparison_of_two_keys_renders_matching_branch,
+        "%(?str4 < str10)yes%(:else)no%(/str4)",
+        "yes"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_comparison_and_missing_key_renders_else_branch,
+        "%(?vara==\"x\")yes%(:else)no%(/vara)",
+        "no"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_and_operator_requires_both_conditions,
+        "%(?var1 && vara)a%(/var1)%(?var1 && var2)b%(/var1)",
+        "b"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_or_operator_requires_one_condition,
+        "%(?vara || var1)a%(/vara)%(?vara || varb)b%(/vara)",
+        "a"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_not_operator_negates_condition,
+        "%(if:!vara)a%(end)%(if:!var1)b%(end)%(if:!(str4 > 5))c%(end)",
+        "a"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_logical_operators_and_without_brackets_binds_and_stronger,
+        "%(if:var1 || vara && varb)a%(else)b%(end)",
+        "a"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_logical_operators_and_brackets_respects_grouping,
+        "%(if:(var1 || vara) && varb)a%(else)b%(end)",
+        "b"
+    );
+
+    test!(
+        test_with_unbalanced_brackets_in_condition_keeps_it_unchanged,
+        "%(if:(var1)a",
+        "%(if:(var1)a"
+    );
+
+    test!(
+        test_with_invalid_comparison_keeps_it_unchanged,
+        "%(?str4=>1)x",
+        "%(?str4=>1)x"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_if_block_and_present_key_renders_content,
+        "Hallo%(if:var1) %(var1)%(end)!",
+        "Hallo world!"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_if_else_block_and_missing_key_renders_else_branch,
+        "%(if:vara)%(vara)%(else)-%(end)",
+        "-"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_if_block_and_comparison_renders_matching_branch,
+        "%(if:str4 == 1234)eq%(else)ne%(end)",
+        "eq"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_nested_if_and_question_mark_blocks_renders_inner_content,
+        "%(if:var1)a%(?vara)b%(:else)c%(/vara)d%(else)e%(end)",
+        "acd"
+    );
+
+    test!(
+        test_with_end_tag_outside_of_block_keeps_it_unchanged,
+        "a%(end)b",
+        "a%(end)b"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_format_placeholder_in_inactive_branch_ignores_it,
+        "%(?vara)%<(10)%(/vara)%(str4)|",
+        "1234|"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_mismatching_close_tag_keeps_it_unchanged,
+        "%(?var1)a%(/var2)b%(/var1)",
+        "a%(/var2)b"
+    );
+
+    test!(
+        test_with_else_tag_outside_of_block_keeps_it_unchanged,
+        "a%(:else)b",
+        "a%(:else)b"
+    );
+
+    #[test]
+    fn test_with_keys_named_like_block_tags_outside_of_blocks_renders_values() {
+        let key_value = HashMap::from([
+            ("end", "e".to_string()),
+            ("else", "x".to_string()),
+            ("default", "d".to_string()),
+        ]);
+        let parser = Formatify::new();
+        let inp = "%(end)%(else)%(default)";
+        assert_eq!(parser.replace_placeholders(&key_value, inp), "exd");
+        assert_eq!(
+            parser.extract_placeholder_keys(inp),
+            ["end", "else", "default"]
+        );
+        #[cfg(feature = "blocks")]
+        assert_eq!(
+            parser.replace_placeholders(&key_value, "%(if:end)[%(end)%(end)|%(end)"),
+            "[e|e"
+        );
+    }
+
+    #[cfg(feature = "colors")]
+    test!(
+        test_with_color_placeholder_emits_ansi_sequence,
+        "%C(red)Hallo%C(reset)",
+        "\x1b[31mHallo\x1b[m"
+    );
+
+    #[cfg(feature = "colors")]
+    test!(
+        test_with_color_placeholder_with_attribute_and_background_emits_ansi_sequence,
+        "%C(bold blue white)Hallo",
+        "\x1b[1;34;47mHallo"
+    );
+
+    #[cfg(feature = "colors")]
+    test!(
+        test_with_bright_palette_and_rgb_color_placeholders_emits_ansi_sequence,
+        "%C(brightred)%C(208)%C(#ff8000)",
+        "\x1b[91m\x1b[38;5;208m\x1b[38;2;255;128;0m"
+    );
+
+    #[cfg(feature = "colors")]
+    test!(
+        test_with_color_shorthand_placeholders_emits_ansi_sequence,
+        "%Cgreen%(var1)%Creset",
+        "\x1b[32mworld\x1b[m"
+    );
+
+    #[cfg(feature = "colors")]
+    test!(
+        test_with_color_placeholder_before_aligned_placeholder_keeps_alignment,
+        "%C(red)%<(6)%(str4)|",
+        "\x1b[31m1234  |"
+    );
+
+    #[cfg(feature = "colors")]
+    test!(
+        test_with_unknown_color_keeps_placeholder_unchanged,
+        "%C(purple)Hallo",
+        "%C(purple)Hallo"
+    );
+
+    #[cfg(feature = "colors")]
+    test!(
+        test_with_unknown_color_shorthand_keeps_placeholder_unchanged,
+        "%Cpurple",
+        "%Cpurple"
+    );
+}
+
+#[cfg(test)]
+mod tests_replace_placeholders_fixed_width {
+    use crate::*;
+    use std::collections::HashMap;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $width:expr, $policy:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("str4", "1234".into());
+                key_value.insert("str14", "1234567890ABCD".into());
+                key_value.insert("umlaute", "äöü".into());
+                let parser = Formatify::new();
+                let out =
+                    parser.replace_placeholders_fixed_width(&key_value, $inp, $width, $policy);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_truncated_fields_and_matching_width_returns_output,
+        "%<(6,trunc)%(str4)|%>(10,ltrunc)%(str14)",
+        17,
+        MultiBytePolicy::Chars,
+        Ok("1234  |…67890ABCD".to_string())
+    );
+
+    test!(
+        test_with_literal_only_input_and_mismatching_width_returns_width_mismatch,
+        "abc",
+        4,
+        MultiBytePolicy::Chars,
+        Err(RenderError::WidthMismatch {
+            expected: 4,
+            found: 3
+        })
+    );
+
+    test!(
+        test_with_unbounded_field_returns_unbounded_field,
+        "%<(6)%(str4)",
+        6,
+        MultiBytePolicy::Chars,
+        Err(RenderError::UnboundedField { key: "str4".into() })
+    );
+
+    test!(
+        test_with_missing_key_returns_missing_key,
+        "%<(6,trunc)%(unknown)",
+        6,
+        MultiBytePolicy::Chars,
+        Err(RenderError::MissingKey {
+            key: "unknown".into(),
+            span: 11..21
+        })
+    );
+
+    test!(
+        test_with_umlauts_and_chars_policy_returns_output,
+        "%<(5,trunc)%(umlaute)",
+        5,
+        MultiBytePolicy::Chars,
+        Ok("äöü  ".to_string())
+    );
+
+    test!(
+        test_with_umlauts_and_bytes_policy_returns_width_mismatch,
+        "%<(5,trunc)%(umlaute)",
+        5,
+        MultiBytePolicy::Bytes,
+        Err(RenderError::WidthMismatch {
+            expected: 5,
+            found: 8
+        })
+    );
+
+    test!(
+        test_with_umlauts_and_reject_policy_returns_non_ascii_output,
+        "x%<(5,trunc)%(umlaute)",
+        6,
+        MultiBytePolicy::RejectNonAscii,
+        Err(RenderError::NonAsciiOutput { position: 1 })
+    );
+}
+
+#[cfg(all(test, feature = "sandbox"))]
+mod tests_replace_placeholders_sandboxed {
+    use crate::*;
+    use std::time::Duration;
+
+    fn render(inp: &str, quotas: Quotas) -> Result<String, RenderError> {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("name", "Alice".into());
+        key_value.insert("items", join_list(["a", "b", "c"]));
+        key_value.insert("outer", "[%(inner)]".into());
+        key_value.insert("inner", "<%(name)>".into());
+        let parser = Formatify::builder().recursive_expansion(8).build();
+        parser.replace_placeholders_sandboxed(&key_value, inp, &quotas)
+    }
+
+    fn exceeded(quota: Quota) -> Result<String, RenderError> {
+        Err(RenderError::QuotaExceeded { quota })
+    }
+
+    #[test]
+    #[cfg(feature = "blocks")]
+    fn test_without_quotas_renders_like_replace_placeholders() {
+        assert_eq!(
+            render(
+                "%(outer) %(for:item in items)%(item)%(end) %(missing)",
+                Quotas::new()
+            ),
+            Ok("[<Alice>] abc %(missing)".into())
+        );
+    }
+
+    #[test]
+    fn test_within_quotas_renders_output() {
+        let quotas = Quotas::new()
+            .max_tokens(9)
+            .max_expansion_depth(2)
+            .max_output_bytes(9)
+            .time_budget(Duration::from_secs(60));
+        #[cfg(feature = "blocks")]
+        let quotas = quotas.max_loop_iterations(3);
+        assert_eq!(render("%(outer)", quotas), Ok("[<Alice>]".into()));
+    }
+
+    #[test]
+    fn test_with_too_many_tokens_fails() {
+        assert_eq!(
+            render("0123456789", Quotas::new().max_tokens(9)),
+            exceeded(Quota::Tokens)
+        );
+        assert_eq!(
+            render("0123456789", Quotas::new().max_tokens(10)),
+            Ok("0123456789".into())
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "blocks")]
+    fn test_with_too_many_loop_iterations_fails() {
+        let inp = "%(for:a in items)%(for:b in items)%(a)%(b) %(end)%(end)";
+        assert_eq!(
+            render(inp, Quotas::new().max_loop_iterations(11)),
+            exceeded(Quota::LoopIterations)
+        );
+        assert!(render(inp, Quotas::new().max_loop_iterations(12)).is_ok());
+    }
+
+    #[test]
+    fn test_with_too_deep_expansion_fails() {
+        assert_eq!(
+            render("%(outer)", Quotas::new().max_expansion_depth(1)),
+            exceeded(Quota::ExpansionDepth)
+        );
+    }
+
+    #[test]
+    fn test_with_too_large_output_fails() {
+        // ä has two bytes
+        assert_eq!(
+            render("ääääa", Quotas::new().max_output_bytes(8)),
+            exceeded(Quota::OutputBytes)
+        );
+        assert_eq!(
+            render("ääää", Quotas::new().max_output_bytes(8)),
+            Ok("ääää".into())
+        );
+    }
+
+    #[test]
+    fn test_with_huge_width_fails_before_generating_output() {
+        let quotas = Quotas::new()
+            .max_output_bytes(100)
+            .time_budget(Duration::from_secs(60));
+        for inp in [
+            "%<(2000000000)%(name)",
+            "%>(2000000000,trunc)%(name)",
+            "%-(2000000000)",
+            "%T(2000000000)",
+            "%<|(2000000000)%(name)",
+            "%I(2000000000)%(name)",
+            "%w(10,2000000000)%(name)",
+        ] {
+            assert_eq!(
+                render(inp, quotas.clone()),
+                exceeded(Quota::OutputBytes),
+                "{inp}"
+            );
+        }
+        assert_eq!(render("%<(7)%(name)|", quotas), Ok("Alice  |".into()));
+    }
+
+    #[test]
+    fn test_with_overflowing_width_renders_placeholder_unchanged() {
+        let quotas = Quotas::new().max_output_bytes(100);
+        for (inp, expected) in [
+            ("%<(99999999999)%(name)", "%<(99999999999)Alice"),
+            ("%>(99999999999,trunc)%(name)", "%>(99999999999,trunc)Alice"),
+            ("%-(99999999999)", "%-(99999999999)"),
+            ("%I(99999999999)%(name)", "%I(99999999999)Alice"),
+            (
+                "%w(99999999999999999999999)%(name)",
+                "%w(99999999999999999999999)Alice",
+            ),
+        ] {
+            assert_eq!(render(inp, quotas.clone()), Ok(expected.into()), "{inp}");
+        }
+        assert_eq!(
+            render("%w(10,99999999999)%(name)", quotas),
+            exceeded(Quota::OutputBytes)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "modifiers")]
+    fn test_with_huge_repeat_count_fails_before_repeating() {
+        let quotas = Quotas::new()
+            .max_output_bytes(100)
+            .time_budget(Duration::from_secs(60));
+        for inp in [
+            "%(name|repeat:100000000)",
+            "%(name|repeat:18446744073709551615)",
+        ] {
+            assert_eq!(
+                render(inp, quotas.clone()),
+                exceeded(Quota::OutputBytes),
+                "{inp}"
+            );
+        }
+        assert_eq!(render("%(name|repeat:2)", quotas), Ok("AliceAlice".into()));
+    }
+
+    #[test]
+    fn test_with_huge_width_stops_at_time_budget() {
+        let quotas = Quotas::new().time_budget(Duration::from_millis(50));
+        for inp in ["%-(2000000000)", "%<(2000000000)%(name)"] {
+            assert_eq!(render(inp, quotas.clone()), exceeded(Quota::Time), "{inp}");
+        }
+    }
+
+    #[test]
+    fn test_with_exhausted_time_budget_fails() {
+        assert_eq!(
+            render("%(name)", Quotas::new().time_budget(Duration::ZERO)),
+            exceeded(Quota::Time)
+        );
+    }
+
+    #[test]
+    fn test_with_denied_capability_fails() {
+        let cases = [
+            #[cfg(feature = "blocks")]
+            ("%(for:item in items)%(item)%(end)", Capability::Loops),
+            ("%(outer)", Capability::Expansion),
+            ("%(uuid)", Capability::DynamicBuiltins),
+            ("%(app)", Capability::Ambient),
+            ("%y", Capability::Extensions),
+        ];
+        for (inp, capability) in cases {
+            let mut key_value = HashMap::<&str, String>::new();
+            key_value.insert("items", join_list(["a", "b"]));
+            key_value.insert("outer", "[%(items)]".into());
+            let mut parser = Formatify::builder().recursive_expansion(8).build();
+            parser.set_ambient("app", "formatify");
+            parser
+                .register_char_placeholder('y', |_| "2024".into())
+                .unwrap();
+
+            let quotas = Quotas::new().deny(capability);
+            assert_eq!(
+                parser.replace_placeholders_sandboxed(&key_value, inp, &quotas),
+                Err(RenderError::CapabilityDenied { capability }),
+                "{inp}"
+            );
+            let others = Quotas::new()
+                .deny(Capability::Expansion)
+                .deny(Capability::DynamicBuiltins)
+                .deny(Capability::Ambient)
+                .deny(Capability::Extensions);
+            #[cfg(feature = "blocks")]
+            let others = others.deny(Capability::Loops);
+            assert!(parser
+                .replace_placeholders_sandboxed(&key_value, "%(items) %(missing)", &others)
+                .is_ok());
+        }
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_with_denied_dynamic_builtins_reldate_fails() {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("date", "1700000000".into());
+        let parser = Formatify::new();
+        let quotas = Quotas::new().deny(Capability::DynamicBuiltins);
+        assert_eq!(
+            parser.replace_placeholders_sandboxed(&key_value, "%(date|reldate)", &quotas),
+            Err(RenderError::CapabilityDenied {
+                capability: Capability::DynamicBuiltins
+            })
+        );
+        assert_eq!(
+            parser.replace_placeholders_sandboxed(&key_value, "%(date|date:%Y)", &quotas),
+            Ok("2023".into())
+        );
+    }
+
+    #[test]
+    fn test_with_denied_ambient_key_of_key_value_map_renders() {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("app", "mine".into());
+        let mut parser = Formatify::new();
+        parser.set_ambient("app", "formatify");
+        let quotas = Quotas::new().deny(Capability::Ambient);
+        assert_eq!(
+            parser.replace_placeholders_sandboxed(&key_value, "%(app)", &quotas),
+            Ok("mine".into())
+        );
+    }
+
+    #[test]
+    fn test_quota_exceeded_display() {
+        let error = RenderError::QuotaExceeded {
+            quota: Quota::ExpansionDepth,
+        };
+        assert_eq!(error.to_string(), "quota exceeded: expansion depth");
+        let error = RenderError::CapabilityDenied {
+            capability: Capability::DynamicBuiltins,
+        };
+        assert_eq!(error.to_string(), "capability denied: dynamic built-ins");
+    }
+}
+
+#[cfg(test)]
+mod tests_replace_placeholders_strict {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("name", "count".into());
+                key_value.insert("flag", "".into());
+                let parser = Formatify::new();
+                let out = parser.replace_placeholders_strict(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_plain_code_preserves_it,
+        "fn main() {\n    let x = [1, 2];\t// (%%)\n}",
+        Ok("fn main() {\n    let x = [1, 2];\t// (%)\n}".to_string())
+    );
+
+    test!(
+        test_with_valid_placeholders_renders_them,
+        "let %<(7)%(name)= %(missing:-0);",
+        Ok("let count  = 0;".to_string())
+    );
+
+    test!(
+        test_with_unknown_single_char_placeholder_returns_error,
+        "printf(\"%d\");",
+        Err(RenderError::MalformedPlaceholder {
+            position: 8,
+            found: "%d".into()
+        })
+    );
+
+    test!(
+        test_with_unterminated_placeholder_returns_error,
+        "x = %(name",
+        Err(RenderError::MalformedPlaceholder {
+            position: 4,
+            found: "%(name".into()
+        })
+    );
+
+    test!(
+        test_with_trailing_percent_returns_error,
+        "x = 5 %",
+        Err(RenderError::MalformedPlaceholder {
+            position: 6,
+            found: "%".into()
+        })
+    );
+
+    test!(
+        test_with_missing_key_returns_error,
+        "x = %(missing);",
+        Err(RenderError::MissingKey {
+            key: "missing".into(),
+            span: 4..14
+        })
+    );
+
+    test!(
+        test_with_missing_key_after_format_returns_byte_span_of_placeholder,
+        "ä %<(4)%(missing)",
+        Err(RenderError::MissingKey {
+            key: "missing".into(),
+            span: 8..18
+        })
+    );
+
+    #[test]
+    fn test_with_missing_key_span_matches_annotation() {
+        let parser = Formatify::new();
+        let inp = "Grüße %+(name|missing) %(name)";
+        let Err(RenderError::MissingKey { span, .. }) =
+            parser.replace_placeholders_strict(&HashMap::new(), inp)
+        else {
+            panic!("missing key expected");
+        };
+        assert_eq!(span, parser.annotate(inp)[0].bytes);
+        assert_eq!(&inp[span], "%+(name|missing)");
+    }
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_missing_key_in_inactive_block_renders,
+        "x%(if:flag)%(missing)%(end);",
+        Ok("x;".to_string())
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_unclosed_block_returns_error,
+        "x%(if:name)y",
+        Err(RenderError::UnclosedBlock {
+            name: "name".into()
+        })
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_stray_close_tag_returns_error,
+        "x%(/flag)",
+        Err(RenderError::MalformedPlaceholder {
+            position: 1,
+            found: "%(/flag)".into()
+        })
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_end_outside_of_block_returns_missing_key,
+        "x%(end)",
+        Err(RenderError::MissingKey {
+            key: "end".into(),
+            span: 1..7
+        })
+    );
+}
+
+#[cfg(all(test, feature = "modifiers"))]
+mod tests_register_modifier {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("title", "Hello World".into());
+                let mut parser = Formatify::new();
+                parser.register_modifier("slug", |s| s.to_lowercase().replace(' ', "-"));
+                parser.register_modifier("upper", |s| format!("<{}>", s));
+                let out = parser.replace_placeholders(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_registered_modifier_applies_it,
+        "%(title|slug)",
+        "hello-world"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_registered_and_built_in_modifier_applies_both,
+        "%(title|slug|capitalize)",
+        "Hello-world"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_registered_modifier_overrides_built_in_modifier,
+        "%(title|upper)",
+        "<Hello World>"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_registered_modifier_applies_before_alignment,
+        "%<(13)%(title|slug)|",
+        "hello-world  |"
+    );
+
+    #[cfg(feature = "modifiers")]
+    test!(
+        test_with_unknown_modifier_keeps_placeholder_unchanged,
+        "%(title|slug|shout)",
+        "%(title|slug|shout)"
+    );
+}
+
+#[cfg(test)]
+mod tests_default_spec {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("sha", "3f2a9c1d7e".into());
+                key_value.insert("id", "42".into());
+                key_value.insert("name", "Alice".into());
+                let mut parser = Formatify::new();
+                parser.default_spec("sha", "<8,trunc").unwrap();
+                parser.default_spec("id", " > 5 ").unwrap();
+                let out = parser.replace_placeholders(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_without_format_applies_default_spec,
+        "%(sha)|%(id)|%(name)|",
+        "3f2a9c1…|   42|Alice|"
+    );
+
+    test!(
+        test_with_format_in_template_overrides_default_spec,
+        "%>(12)%(sha)|%<(3)%(id)|",
+        "  3f2a9c1d7e|42 |"
+    );
+
+    test!(
+        test_with_fallback_key_uses_spec_of_first_key,
+        "%(missing|sha)|%(id|sha)|",
+        "3f2a9c1d7e|   42|"
+    );
+
+    test!(
+        test_with_missing_key_keeps_placeholder_unchanged,
+        "%(sha[3])",
+        "%(sha[3])"
+    );
+
+    #[test]
+    fn test_with_default_spec_measure_lengths_uses_spec() {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("id", "42".into());
+        let mut parser = Formatify::new();
+        parser.default_spec("id", ">5").unwrap();
+        assert_eq!(parser.measure_lengths(&key_value, "%(id)"), vec![5, 5]);
+    }
+
+    #[test]
+    fn test_with_invalid_spec_returns_error() {
+        let mut parser = Formatify::new();
+        for spec in ["", "<", "8", "<x", "<8,", "<8,cut", "^8", "<*"] {
+            assert_eq!(
+                parser.default_spec("id", spec),
+                Err(RenderError::MalformedPlaceholder {
+                    position: 0,
+                    found: spec.into(),
+                })
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_message_format {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("zero", "0".into());
+                key_value.insert("one", "1".into());
+                key_value.insert("many", "1234".into());
+                key_value.insert("gender", "female".into());
+                key_value.insert("text", "abc".into());
+                let parser = Formatify::new();
+                let out = parser.replace_placeholders(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_plural_selects_category,
+        "%(one,plural,one{# Datei}other{# Dateien}) %(many,plural,one{# Datei}other{# Dateien})",
+        "1 Datei 1234 Dateien"
+    );
+
+    test!(
+        test_plural_exact_match_takes_precedence,
+        "%(zero,plural,one{# Datei}=0{keine Dateien}other{# Dateien}) %(one,plural,=1{eine Datei}one{# Datei}other{# Dateien})",
+        "keine Dateien eine Datei"
+    );
+
+    test!(
+        test_plural_with_whitespace_between_branches,
+        "%(one,plural, one {# file} other {# files} )",
+        "1 file"
+    );
+
+    test!(
+        test_plural_with_numeric_format_replaces_hash_with_formatted_value,
+        "%(many,num=.1,plural,one{# Datei}other{# Dateien})",
+        "1234.0 Dateien"
+    );
+
+    test!(
+        test_plural_with_non_numeric_value_uses_other,
+        "%(text,plural,one{# item}other{# items})",
+        "abc items"
+    );
+
+    test!(
+        test_plural_with_format_and_default,
+        "[%<(10)%(one,plural,one{# Datei}other{# Dateien})] %(missing,plural,one{#}other{#}:-none)",
+        "[1 Datei   ] none"
+    );
+
+    test!(
+        test_select_matches_value,
+        "%(gender,select,female{sie}male{er}other{es}) %(text,select,female{sie}other{es})",
+        "sie es"
+    );
+
+    test!(
+        test_select_keeps_hash_and_brackets,
+        "%(gender,select,female{(#1)}other{-})",
+        "(#1)"
+    );
+
+    test!(
+        test_without_other_branch_keeps_placeholder_unchanged,
+        "%(one,plural,one{# Datei})",
+        "%(one,plural,one{# Datei})"
+    );
+
+    test!(
+        test_with_unknown_plural_category_keeps_placeholder_unchanged,
+        "%(one,plural,few{#}other{#})",
+        "%(one,plural,few{#}other{#})"
+    );
+
+    test!(
+        test_with_nested_braces_keeps_placeholder_unchanged,
+        "%(one,plural,one{{#}}other{#})",
+        "%(one,plural,one{{#}}other{#})"
+    );
+
+    test!(
+        test_with_unclosed_branch_keeps_placeholder_unchanged,
+        "%(one,plural,one{#",
+        "%(one,plural,one{#"
+    );
+}
+
+#[cfg(test)]
+mod tests_random {
+    use crate::*;
+
+    fn render(parser: &Formatify, inp: &str) -> String {
+        let key_value = HashMap::<&str, String>::new();
+        parser.replace_placeholders(&key_value, inp)
+    }
+
+    #[test]
+    fn test_with_same_seed_renders_same_output() {
+        let inp = "%(uuid) %(uuid) %(rand)";
+        let first = render(&Formatify::builder().seed(7).build(), inp);
+        let second = render(&Formatify::builder().seed(7).build(), inp);
+        assert_eq!(first, second);
+        assert_ne!(first, render(&Formatify::builder().seed(8).build(), inp));
+    }
+
+    #[test]
+    fn test_uuid_has_version_4_layout() {
+        let parser = Formatify::builder().seed(7).build();
+        let out = render(&parser, "%(uuid)|%(uuid)");
+        let (first, second) = out.split_once('|').unwrap();
+        assert_ne!(first, second);
+        for uuid in [first, second] {
+            let groups: Vec<_> = uuid.split('-').map(str::len).collect();
+            assert_eq!(groups, vec![8, 4, 4, 4, 12]);
+            assert_eq!(&uuid[14..15], "4");
+            assert!(matches!(&uuid[19..20], "8" | "9" | "a" | "b"));
+        }
+    }
+
+    #[test]
+    fn test_with_random_source_renders_its_numbers() {
+        let parser = Formatify::builder().random_source(|| 0).build();
+        assert_eq!(
+            render(&parser, "%(rand) %(uuid)"),
+            "0 00000000-0000-4000-8000-000000000000"
+        );
+    }
+
+    #[test]
+    fn test_key_of_key_value_map_shadows_builtin() {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("uuid", "fixed".into());
+        let parser = Formatify::new();
+        assert_eq!(parser.replace_placeholders(&key_value, "%(uuid)"), "fixed");
+    }
+}
+
+#[cfg(test)]
+mod tests_ambient {
+    use crate::*;
+
+    fn formatter() -> Formatify {
+        let mut parser = Formatify::new();
+        parser.set_ambient("app", "formatify");
+        parser.set_ambient("version", "1.4.2");
+        parser
+    }
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("version", "2.0.0".into());
+                key_value.insert("name", "Alice".into());
+                let out = formatter().replace_placeholders(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_ambient_key_uses_ambient_value,
+        "%(name) via %(app)",
+        "Alice via formatify"
+    );
+
+    test!(
+        test_with_key_of_key_value_map_shadows_ambient_value,
+        "%(version)",
+        "2.0.0"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_ambient_key_in_fallback_and_condition,
+        "%(missing|app)%(if:app) ok%(end)",
+        "formatify ok"
+    );
+
+    test!(
+        test_with_ambient_key_and_format,
+        "[%<(12)%(app)]",
+        "[formatify   ]"
+    );
+
+    #[test]
+    fn test_override_ambient_is_reverted_when_guard_is_dropped() {
+        let key_value = HashMap::<&str, String>::new();
+        let parser = formatter();
+        {
+            let _outer = parser.override_ambient("app", "outer");
+            {
+                let _inner = parser.override_ambient("app", "inner");
+                assert_eq!(parser.replace_placeholders(&key_value, "%(app)"), "inner");
+            }
+            assert_eq!(parser.replace_placeholders(&key_value, "%(app)"), "outer");
+        }
+        assert_eq!(
+            parser.replace_placeholders(&key_value, "%(app)"),
+            "formatify"
+        );
+    }
+
+    #[test]
+    fn test_override_ambient_of_unset_key_is_removed_when_guard_is_dropped() {
+        let key_value = HashMap::<&str, String>::new();
+        let parser = formatter();
+        let guard = parser.override_ambient("region", "eu");
+        assert_eq!(parser.replace_placeholders(&key_value, "%(region)"), "eu");
+        drop(guard);
+        assert_eq!(
+            parser.replace_placeholders(&key_value, "%(region)"),
+            "%(region)"
+        );
+    }
+
+    #[test]
+    fn test_remove_ambient() {
+        let key_value = HashMap::<&str, String>::new();
+        let mut parser = formatter();
+        assert_eq!(parser.remove_ambient("app"), Some("formatify".into()));
+        assert_eq!(parser.remove_ambient("app"), None);
+        assert_eq!(parser.replace_placeholders(&key_value, "%(app)"), "%(app)");
+    }
+}
+
+#[cfg(test)]
+mod tests_register_preset {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("sha", "3f2a9c1d7e".into());
+                key_value.insert("id", "42".into());
+                let mut parser = Formatify::new();
+                parser.register_preset("idcol", "8,trunc").unwrap();
+                parser.register_preset("num", " 5 ").unwrap();
+                parser.register_preset("tail", "6,ltrunc").unwrap();
+                let out = parser.replace_placeholders(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_left_preset_applies_format,
+        "%<(preset:idcol)%(sha)|%<(preset:num)%(id)|",
+        "3f2a9c1…|42   |"
+    );
+
+    test!(
+        test_with_right_preset_mirrors_alignment,
+        "%>(preset:num)%(id)|%>(preset:tail)%(sha)|",
+        "   42|…c1d7e|"
+    );
+
+    test!(
+        test_with_unknown_preset_keeps_placeholder_unchanged,
+        "%<(preset:missing)%(id)|",
+        "%<(preset:missing)42|"
+    );
+
+    test!(
+        test_with_absolute_preset_keeps_placeholder_unchanged,
+        "%<|(preset:num)%(id)|",
+        "%<|(preset:num)42|"
+    );
+
+    #[test]
+    fn test_with_preset_measure_lengths_uses_preset() {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("id", "42".into());
+        let mut parser = Formatify::new();
+        parser.register_preset("num", "5").unwrap();
+        assert_eq!(
+            parser.measure_lengths(&key_value, "%>(preset:num)%(id)"),
+            vec![5, 5]
+        );
+    }
+
+    #[test]
+    fn test_with_invalid_preset_returns_error() {
+        let mut parser = Formatify::new();
+        for spec in ["", "<8", "x", "8,cut", "*"] {
+            assert_eq!(
+                parser.register_preset("idcol", spec),
+                Err(RenderError::MalformedPlaceholder {
+                    position: 0,
+                    found: spec.into(),
+                })
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_register_char_placeholder {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("hash", "3f2a9c1d7e".into());
+                let mut parser = Formatify::new();
+                parser
+                    .register_char_placeholder('h', |kv| kv["hash"].chars().take(7).collect())
+                    .unwrap();
+                parser
+                    .register_char_placeholder('d', |_| "2024-01-31".into())
+                    .unwrap();
+                let out = parser.replace_placeholders(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_registered_placeholder_renders_handler_value,
+        "%h %d",
+        "3f2a9c1 2024-01-31"
+    );
+
+    test!(
+        test_with_registered_placeholder_applies_format,
+        "%>(9)%h|",
+        "  3f2a9c1|"
+    );
+
+    test!(test_with_built_in_placeholder_renders_it, "a%nb", "a\nb");
+
+    test!(
+        test_with_unregistered_placeholder_keeps_it_unchanged,
+        "%q",
+        "%q"
+    );
+
+    #[test]
+    fn test_with_reserved_char_returns_error() {
+        let mut parser = Formatify::new();
+        for ch in RESERVED_CHARS.chars() {
+            assert_eq!(
+                parser.register_char_placeholder(ch, |_| "never".into()),
+                Err(RenderError::ReservedChar { ch })
+            );
+        }
+        assert_eq!(
+            parser.replace_placeholders(&HashMap::new(), "a%nb%%"),
+            "a\nb%"
+        );
+        assert_eq!(
+            RenderError::ReservedChar { ch: 'n' }.to_string(),
+            "'%n' is a built-in placeholder and can't be registered"
+        );
+    }
+
+    #[test]
+    fn test_with_registered_placeholder_extract_keys_ignores_it() {
+        let mut parser = Formatify::new();
+        parser
+            .register_char_placeholder('d', |_| "2024-01-31".into())
+            .unwrap();
+        assert_eq!(
+            parser.extract_placeholder_keys("%d %(name)"),
+            vec!["name".to_string()]
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests_register_extension {
+    use crate::*;
+
+    struct Lookup;
+
+    impl PlaceholderExtension for Lookup {
+        fn sigil(&self) -> &str {
+            "len"
+        }
+
+        fn parse(&self, arg: &str) -> bool {
+            !arg.is_empty()
+        }
+
+        fn render(&self, arg: &str, key_value: &HashMap<&str, String>) -> String {
+            key_value
+                .get(arg)
+                .map_or(0, |value| value.chars().count())
+                .to_string()
+        }
+    }
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("name", "Alice".into());
+                let mut parser = Formatify::new();
+                parser.register_extension(Lookup);
+                let out = parser.replace_placeholders(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_extension_placeholder_renders_extension_value,
+        "%(name) has %(len:name) chars",
+        "Alice has 5 chars"
+    );
+
+    test!(
+        test_with_extension_placeholder_applies_format,
+        "%>(3)%(len:name)|",
+        "  5|"
+    );
+
+    test!(
+        test_with_rejected_argument_keeps_placeholder_unchanged,
+        "%(len:)",
+        "%(len:)"
+    );
+
+    test!(
+        test_with_unterminated_extension_placeholder_keeps_it_unchanged,
+        "%(len:name",
+        "%(len:name"
+    );
+
+    test!(
+        test_with_unregistered_sigil_keeps_placeholder_unchanged,
+        "%(qr:name)",
+        "%(qr:name)"
+    );
+}
+
+#[cfg(test)]
+mod tests_recursive_expansion {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $max_depth:expr, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("name", "Alice".into());
+                key_value.insert("greeting", "Hello %(name)".into());
+                key_value.insert("line", "[%(greeting)]".into());
+                key_value.insert("ping", "ping %(pong)".into());
+                key_value.insert("pong", "pong %(ping)".into());
+                key_value.insert("self", "me %(self)".into());
+                let parser = Formatify::builder().recursive_expansion($max_depth).build();
+                let out = parser.replace_placeholders(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(test_with_plain_value_renders_it, 4, "%(name)!", "Alice!");
+
+    test!(
+        test_with_nested_placeholder_expands_it,
+        4,
+        "%(greeting)!",
+        "Hello Alice!"
+    );
+
+    test!(
+        test_with_two_levels_expands_both,
+        4,
+        "%(line)",
+        "[Hello Alice]"
+    );
+
+    test!(
+        test_with_depth_limit_inserts_deeper_values_unchanged,
+        1,
+        "%(line)",
+        "[Hello %(name)]"
+    );
+
+    test!(
+        test_with_expanded_value_applies_format_to_expansion,
+        4,
+        "%<(14)%(greeting)|",
+        "Hello Alice   |"
+    );
+
+    test!(
+        test_with_cycle_keeps_cyclic_placeholder_unchanged,
+        8,
+        "%(ping)",
+        "ping pong %(ping)"
+    );
+
+    test!(
+        test_with_self_reference_keeps_it_unchanged,
+        8,
+        "%(self)",
+        "me %(self)"
+    );
+
+    #[test]
+    fn test_without_recursive_expansion_inserts_value_unchanged() {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("greeting", "Hello %(name)".into());
+        key_value.insert("name", "Alice".into());
+        let parser = Formatify::new();
+        let out = parser.replace_placeholders(&key_value, "%(greeting)");
+        assert_eq!(out, "Hello %(name)");
+    }
+}
+
+#[cfg(test)]
+mod tests_replace_placeholders_provider {
+    use crate::*;
+    #[cfg(feature = "blocks")]
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+
+    #[cfg(feature = "blocks")]
+    #[test]
+    fn test_queries_referenced_keys_once() {
+        let queried = RefCell::new(Vec::<String>::new());
+        let provider = |key: &str| {
+            queried.borrow_mut().push(key.to_string());
+            key.starts_with('k').then(|| key.to_uppercase())
+        };
+        let parser = Formatify::new();
+        let out = parser.replace_placeholders_provider(
+            &provider,
+            "%(k1) %(k1) %(x|k2) %(?k3)yes%(:else)no%(/k3) %(missing)",
+        );
+        assert_eq!(out, "K1 K1 K2 yes %(missing)");
+        assert_eq!(*queried.borrow(), ["k1", "x", "k2", "k3", "missing"]);
+    }
+
+    #[test]
+    fn test_with_scoped_providers_routes_by_prefix() {
+        let mut config = BTreeMap::new();
+        config.insert("port".to_string(), "8080".to_string());
+        let mut fallback = HashMap::<&str, String>::new();
+        fallback.insert("host", "localhost".into());
+        fallback.insert("config.port", "80".into());
+        let providers: [&dyn KeyProvider; 2] = [&ScopedProvider::new("config", config), &fallback];
+        let parser = Formatify::new();
+        let out = parser.replace_placeholders_provider(
+            &providers,
+            "%(host):%(config.port) %(config.host:-none)",
+        );
+        assert_eq!(out, "localhost:8080 none");
+    }
+}
+
+#[cfg(test)]
+mod tests_value_tree {
+    use crate::*;
+
+    fn tree() -> ValueTree {
+        let author = ValueTree::branch()
+            .with("name", "Alice")
+            .with("email", "alice@example.com");
+        ValueTree::branch().with("title", "Fix parser").with(
+            "commit",
+            ValueTree::branch()
+                .with("author", author)
+                .with("parents", join_list(["a1", "b2"])),
+        )
+    }
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let parser = Formatify::new();
+                let out = parser.replace_placeholders_tree(&tree(), $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_top_level_key_renders_value,
+        "%(title)",
+        "Fix parser"
+    );
+
+    test!(
+        test_with_dotted_path_resolves_nested_value,
+        "%(commit.author.name) <%(commit.author.email)>",
+        "Alice <alice@example.com>"
+    );
+
+    test!(
+        test_with_dotted_path_applies_format,
+        "%<(8)%(commit.author.name)|",
+        "Alice   |"
+    );
+
+    test!(
+        test_with_path_to_branch_keeps_placeholder_unchanged,
+        "%(commit.author)",
+        "%(commit.author)"
+    );
+
+    test!(
+        test_with_unknown_path_keeps_placeholder_unchanged,
+        "%(commit.committer.name)",
+        "%(commit.committer.name)"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_dotted_path_in_condition_evaluates_nested_value,
+        "%(if:commit.author.name)by %(commit.author.name)%(end)",
+        "by Alice"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_loop_over_dotted_path_repeats_body,
+        "%(for:p in commit.parents)%(p) %(end)",
+        "a1 b2 "
+    );
+
+    #[test]
+    fn test_get_with_dotted_path_returns_nested_value() {
+        assert_eq!(
+            tree().get("commit.author.email"),
+            Some(&ValueTree::Leaf("alice@example.com".into()))
+        );
+        assert_eq!(tree().get("title.length"), None);
+    }
+
+    #[test]
+    fn test_with_dotted_key_in_flat_map_renders_value() {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("user.name", "Bob".into());
+        let parser = Formatify::new();
+        let out = parser.replace_placeholders(&key_value, "%(user.name)");
+        assert_eq!(out, "Bob");
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests_replace_placeholders_json {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let json = serde_json::json!({
+                    "id": 42,
+                    "draft": false,
+                    "reviewer": null,
+                    "author": {"name": "Alice", "email": "alice@example.com"},
+                    "labels": ["bug", "ui"],
+                    "files": [{"path": "src/lib.rs"}, {"path": "README.md"}]
+                });
+                let parser = Formatify::new();
+                let out = parser.replace_placeholders_json(&json, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_scalars_renders_them,
+        "#%(id) draft=%(draft)",
+        "#42 draft=false"
+    );
+
+    test!(
+        test_with_dotted_path_resolves_nested_value,
+        "%(author.name) <%(author.email)>",
+        "Alice <alice@example.com>"
+    );
+
+    test!(
+        test_with_index_into_scalar_array_renders_item,
+        "%(labels[0]) %(labels[-1])",
+        "bug ui"
+    );
+
+    test!(
+        test_with_loop_over_scalar_array_repeats_body,
+        "%(for:l in labels)[%(l)]%(end)",
+        "[bug][ui]"
+    );
+
+    test!(
+        test_with_path_into_object_array_renders_value,
+        "%(files.1.path)",
+        "README.md"
+    );
+
+    test!(
+        test_with_null_value_renders_default,
+        "%(reviewer:-nobody)",
+        "nobody"
+    );
+}
+
+#[cfg(test)]
+mod tests_replace_placeholders_values {
+    use crate::*;
+    use std::collections::BTreeMap;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut user = BTreeMap::new();
+                user.insert("name".to_string(), FormatValue::from("Alice"));
+                user.insert("age".to_string(), FormatValue::from(42));
+
+                let mut values = HashMap::<&str, FormatValue>::new();
+                values.insert("title", "Report".into());
+                values.insert("count", FormatValue::Int(-7));
+                values.insert("ratio", FormatValue::Float(0.25));
+                values.insert("done", FormatValue::Bool(false));
+                values.insert("tags", FormatValue::List(vec!["x".into(), 1.into()]));
+                values.insert("user", FormatValue::Map(user));
+                let parser = Formatify::new();
+                let out = parser.replace_placeholders_values(&values, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_scalar_values_renders_them,
+        "%(title): %(count) %(ratio) %(done)",
+        "Report: -7 0.25 false"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_list_value_supports_index_and_loop,
+        "%(tags[-1]) %(for:t in tags)<%(t)>%(end)",
+        "1 <x><1>"
+    );
+
+    test!(
+        test_with_map_value_resolves_dotted_path,
+        "%(user.name) (%(user.age))",
+        "Alice (42)"
+    );
+
+    test!(test_with_format_pads_typed_value, "%>(4)%(count)|", "  -7|");
+}
+
+#[cfg(test)]
+mod tests_invalid_utf8 {
+    use crate::*;
+    use std::collections::BTreeMap;
+
+    macro_rules! test {
+        ($test_name:ident, $policy:expr, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut file = BTreeMap::new();
+                file.insert("name".to_string(), FormatValue::from(&b"r\xe9sum\xe9"[..]));
+
+                let mut values = HashMap::<&str, FormatValue>::new();
+                values.insert("valid", "Grüße".as_bytes().into());
+                values.insert("invalid", b"caf\xe9!".to_vec().into());
+                values.insert("file", FormatValue::Map(file));
+                values.insert("parts", FormatValue::List(vec![b"a\xff".to_vec().into()]));
+                let parser = Formatify::builder().invalid_utf8($policy).build();
+                let out = parser.replace_placeholders_values(&values, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_valid_utf8_renders_bytes_unchanged,
+        InvalidUtf8Policy::Error,
+        "%(valid)",
+        "Grüße"
+    );
+
+    test!(
+        test_with_lossy_policy_renders_replacement_char,
+        InvalidUtf8Policy::Lossy,
+        "%(invalid) %(file.name)",
+        "caf\u{fffd}! r\u{fffd}sum\u{fffd}"
+    );
+
+    test!(
+        test_with_hex_escape_policy_renders_escaped_bytes,
+        InvalidUtf8Policy::HexEscape,
+        "%(invalid) %(parts)",
+        "caf\\xe9! a\\xff"
+    );
+
+    test!(
+        test_with_hex_escape_policy_measures_escaped_width,
+        InvalidUtf8Policy::HexEscape,
+        "%<(8)%(invalid)|",
+        "caf\\xe9!|"
+    );
+
+    test!(
+        test_with_error_policy_treats_value_as_missing,
+        InvalidUtf8Policy::Error,
+        "%(invalid) %(file.name:-?) %(parts:-none)",
+        "%(invalid) ? none"
+    );
+
+    #[test]
+    fn test_default_policy_is_lossy() {
+        let mut values = HashMap::<&str, FormatValue>::new();
+        values.insert("invalid", FormatValue::Bytes(vec![0xff]));
+        let out = Formatify::new().replace_placeholders_values(&values, "%(invalid)");
+        assert_eq!(out, "\u{fffd}");
+    }
+}
+
+#[cfg(test)]
+mod tests_missing_key_policy {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $policy:expr, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("name", "Alice".into());
+                let parser = Formatify::builder().missing_key($policy).build();
+                let out = parser.replace_placeholders(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_keep_policy_renders_placeholder_unchanged,
+        MissingKeyPolicy::Keep,
+        "%(name) %(title)",
+        "Alice %(title)"
+    );
+
+    test!(
+        test_with_empty_policy_renders_empty_value,
+        MissingKeyPolicy::Empty,
+        "%(name)|%(title)|",
+        "Alice||"
+    );
+
+    test!(
+        test_with_empty_policy_aligns_empty_value,
+        MissingKeyPolicy::Empty,
+        "%<(4)%(title)|",
+        "    |"
+    );
+
+    test!(
+        test_with_default_policy_renders_value,
+        MissingKeyPolicy::Default("n/a".into()),
+        "%(title) %>(5)%(title)",
+        "n/a   n/a"
+    );
+
+    test!(
+        test_with_custom_policy_renders_value_of_handler,
+        MissingKeyPolicy::custom(|key| key.to_uppercase()),
+        "%(name) %(title)",
+        "Alice TITLE"
+    );
+
+    test!(
+        test_with_default_value_and_fallback_key_ignores_policy,
+        MissingKeyPolicy::Default("n/a".into()),
+        "%(title:-none) %(title|name)",
+        "none Alice"
+    );
+
+    test!(
+        test_with_malformed_placeholder_ignores_policy,
+        MissingKeyPolicy::Empty,
+        "%(title",
+        "%(title"
+    );
+}
+
+#[cfg(all(test, feature = "chrono"))]
+mod tests_date_modifier {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("created", "2024-03-09T14:05:00+01:00".into());
+                key_value.insert("epoch", "1700000000".into());
+                key_value.insert("name", "Alice".into());
+                let parser = Formatify::new();
+                let out = parser.replace_placeholders(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_rfc3339_value_reformats_date,
+        "%(created|date:%Y-%m-%d %H:%M)",
+        "2024-03-09 14:05"
+    );
+
+    test!(
+        test_with_epoch_value_reformats_date_in_utc,
+        "%(epoch|date:%d.%m.%Y %H:%M:%S %z)",
+        "14.11.2023 22:13:20 +0000"
+    );
+
+    test!(
+        test_with_non_timestamp_renders_value_unchanged,
+        "%(name|date:%Y)",
+        "Alice"
+    );
+
+    test!(
+        test_with_following_modifier_applies_it_to_date,
+        "%(created|date:%b %e|upper)",
+        "MAR  9"
+    );
+
+    test!(
+        test_with_default_value_renders_default_for_missing_key,
+        "%(missing|date:%Y-%m-%d:-never) %(created|date:%Y:-never)",
+        "never 2024"
+    );
+
+    test!(
+        test_with_alignment_aligns_formatted_date,
+        "%>(12)%(created|date:%Y-%m-%d)|",
+        "  2024-03-09|"
+    );
+
+    test!(
+        test_with_invalid_pattern_keeps_placeholder_unchanged,
+        "%(created|date:%Q) %(created|date:%Y",
+        "%(created|date:%Q) %(created|date:%Y"
+    );
+}
+
+#[cfg(all(test, feature = "chrono"))]
+mod tests_relative_date {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("just", "1699999990".into());
+                key_value.insert("minutes", "1699999000".into());
+                key_value.insert("yesterday", "2023-11-12T20:00:00Z".into());
+                key_value.insert("weeks", "1698000000".into());
+                key_value.insert("months", "1690000000".into());
+                key_value.insert("years", "1600000000".into());
+                key_value.insert("future", "1700180000".into());
+                key_value.insert("name", "Alice".into());
+                let now = chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+                let parser = Formatify::builder().clock(move || now).build();
+                let out = parser.replace_placeholders(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_recent_timestamps_renders_small_units,
+        "%(just|reldate), %(minutes|reldate)",
+        "10 seconds ago, 17 minutes ago"
+    );
+
+    test!(
+        test_with_old_timestamps_renders_large_units,
+        "%(yesterday|reldate), %(weeks|reldate), %(months|reldate), %(years|reldate)",
+        "2 days ago, 3 weeks ago, 4 months ago, 3 years ago"
+    );
+
+    test!(
+        test_with_future_timestamp_renders_in,
+        "%(future|reldate)",
+        "in 2 days"
+    );
+
+    test!(
+        test_with_non_timestamp_renders_value_unchanged,
+        "%(name|reldate)",
+        "Alice"
+    );
+
+    test!(
+        test_now_renders_time_of_clock,
+        "%(now) %(now|date:%Y) %(now|reldate)",
+        "2023-11-14T22:13:20Z 2023 0 seconds ago"
+    );
+
+    #[test]
+    fn test_now_of_key_value_map_takes_precedence() {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("now", "later".into());
+        let parser = Formatify::new();
+        assert_eq!(parser.replace_placeholders(&key_value, "%(now)"), "later");
+    }
+
+    #[test]
+    fn test_registered_modifier_shadows_reldate() {
+        let key_value = HashMap::<&str, String>::new();
+        let mut parser = Formatify::new();
+        parser.register_modifier("reldate", |_| "custom".into());
+        assert_eq!(
+            parser.replace_placeholders(&key_value, "%(now|reldate)"),
+            "custom"
+        );
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests_tracing {
+    use crate::*;
+    use std::fmt::Debug;
+    use std::sync::Mutex;
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Level, Metadata, Subscriber};
+
+    /// Subscriber which records the fields of all events as `name=value` pairs.
+    #[derive(Default)]
+    struct Recorder {
+        events: Arc<Mutex<Vec<(Level, String)>>>,
+    }
+
+    struct Fields(Vec<String>);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0.push(format!("{}={:?}", field.name(), value));
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, _span: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+        fn record(&self, _span: &Id, _values: &Record<'_>) {}
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Fields(Vec::new());
+            event.record(&mut fields);
+            let level = *event.metadata().level();
+            self.events
+                .lock()
+                .unwrap()
+                .push((level, fields.0.join(" ")));
+        }
+        fn enter(&self, _span: &Id) {}
+        fn exit(&self, _span: &Id) {}
+    }
+
+    fn render_events(inp: &str) -> (String, Vec<(Level, String)>) {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("sha", "3f2a9c1d7e".into());
+        let recorder = Recorder::default();
+        let events = recorder.events.clone();
+        let out = tracing::subscriber::with_default(recorder, || {
+            Formatify::new().replace_placeholders(&key_value, inp)
+        });
+        let events = events.lock().unwrap().clone();
+        (out, events)
+    }
+
+    #[test]
+    fn test_missing_key_emits_warning() {
+        let (out, events) = render_events("%(sha) %(missing)");
+        assert_eq!(out, "3f2a9c1d7e %(missing)");
+        let id = template_id("%(sha) %(missing)");
+        assert_eq!(
+            events,
+            vec![(
+                Level::WARN,
+                format!("message=missing key template_id={id} position=7 key=missing")
+            )]
+        );
+    }
+
+    #[test]
+    fn test_invalid_placeholder_emits_warning() {
+        let (out, events) = render_events("%<(x)%(sha)");
+        assert_eq!(out, "%<(x)3f2a9c1d7e");
+        let id = template_id("%<(x)%(sha)");
+        assert_eq!(
+            events,
+            vec![(
+                Level::WARN,
+                format!("message=invalid placeholder template_id={id} position=0 found=%<(")
+            )]
+        );
+    }
+
+    #[test]
+    fn test_truncation_emits_debug_event() {
+        let (out, events) = render_events("%<(5,trunc)%(sha)");
+        assert_eq!(out, "3f2a…");
+        let id = template_id("%<(5,trunc)%(sha)");
+        assert_eq!(
+            events,
+            vec![(
+                Level::DEBUG,
+                format!("message=truncated value template_id={id} key=sha width=5 value_len=10")
+            )]
+        );
+    }
+
+    #[test]
+    fn test_valid_render_emits_no_events() {
+        let (_, events) = render_events("%<(12)%(sha)%(?missing)%(missing)%(/missing)");
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_template_id_is_stable() {
+        assert_eq!(template_id(""), "cbf29ce484222325");
+        assert_ne!(template_id("%(a)"), template_id("%(b)"));
+    }
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests_metrics {
+    use crate::*;
+    use metrics::{
+        Counter, CounterFn, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder,
+        SharedString, Unit,
+    };
+    use std::sync::Mutex;
+
+    /// Recorder which collects all counter increments and histogram values by metric name.
+    #[derive(Default)]
+    struct Collector {
+        values: Arc<Mutex<Vec<(String, f64)>>>,
+    }
+
+    struct Handle {
+        name: String,
+        values: Arc<Mutex<Vec<(String, f64)>>>,
+    }
+
+    impl CounterFn for Handle {
+        fn increment(&self, value: u64) {
+            self.values
+                .lock()
+                .unwrap()
+                .push((self.name.clone(), value as f64));
+        }
+        fn absolute(&self, _value: u64) {}
+    }
+
+    impl HistogramFn for Handle {
+        fn record(&self, value: f64) {
+            self.values.lock().unwrap().push((self.name.clone(), value));
+        }
+    }
+
+    impl Collector {
+        fn handle(&self, key: &Key) -> Arc<Handle> {
+            Arc::new(Handle {
+                name: key.name().to_string(),
+                values: self.values.clone(),
+            })
+        }
+    }
+
+    impl Recorder for Collector {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            Counter::from_arc(self.handle(key))
+        }
+        fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::noop()
+        }
+        fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::from_arc(self.handle(key))
+        }
+    }
+
+    fn collect(render: impl FnOnce(&Formatify, &HashMap<&str, String>)) -> Vec<(String, f64)> {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("name", "Alice".into());
+        key_value.insert("nested", "<%(name)>".into());
+        let parser = Formatify::builder().recursive_expansion(2).build();
+        let collector = Collector::default();
+        let values = collector.values.clone();
+        metrics::with_local_recorder(&collector, || render(&parser, &key_value));
+        let values = values.lock().unwrap().clone();
+        values
+    }
+
+    fn metric(name: &str, value: f64) -> (String, f64) {
+        (name.to_string(), value)
+    }
+
+    #[test]
+    fn test_render_records_count_and_output_bytes() {
+        let values = collect(|parser, key_value| {
+            parser.replace_placeholders(key_value, "Hi %(nested)!");
+        });
+        assert_eq!(
+            values,
+            vec![
+                metric("formatify_renders_total", 1.0),
+                metric("formatify_output_bytes", 11.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_missing_key_is_counted() {
+        let values = collect(|parser, key_value| {
+            parser.replace_placeholders(key_value, "%(a)%(b)");
+        });
+        assert_eq!(
+            values,
+            vec![
+                metric("formatify_missing_keys_total", 1.0),
+                metric("formatify_missing_keys_total", 1.0),
+                metric("formatify_renders_total", 1.0),
+                metric("formatify_output_bytes", 8.0)
+            ]
+        );
+    }
+
+    #[cfg(feature = "tables")]
+    #[test]
+    fn test_each_template_of_joined_render_is_counted() {
+        let values = collect(|parser, key_value| {
+            parser.replace_placeholders_joined(key_value, &["%(name)", "x"]);
+            parser.replace_placeholders_with_source_map(key_value, "%(name)");
+        });
+        let renders = values
+            .iter()
+            .filter(|(name, _)| name == "formatify_renders_total")
+            .count();
+        assert_eq!(renders, 3);
+    }
+
+    #[test]
+    fn test_measure_lengths_is_not_counted() {
+        let values = collect(|parser, key_value| {
+            parser.measure_lengths(key_value, "%(name)");
+        });
+        assert!(values.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod tests_number_format {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("amount", "3.14159".into());
+                key_value.insert("count", "1234567".into());
+                key_value.insert("debt", "-9876543.216".into());
+                key_value.insert("small", "999".into());
+                key_value.insert("name", "Alice".into());
+                let parser = Formatify::new();
+                let out = parser.replace_placeholders(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(test_with_precision_rounds_value, "%(amount,num=.2)", "3.14");
+
+    test!(test_with_precision_pads_integer, "%(small,num=.1)", "999.0");
+
+    test!(
+        test_with_thousands_groups_integer_digits,
+        "%(count,num=,) %(small,num=,)",
+        "1,234,567 999"
+    );
+
+    test!(
+        test_with_thousands_and_precision_formats_negative_value,
+        "%(debt,num=,.2)",
+        "-9,876,543.22"
+    );
+
+    test!(
+        test_with_non_numeric_value_renders_value_unchanged,
+        "%(name,num=.2)",
+        "Alice"
+    );
+
+    test!(
+        test_with_format_aligns_formatted_value,
+        "%>(10)%(count,num=,)|",
+        " 1,234,567|"
+    );
+
+    test!(
+        test_with_fallback_and_default_formats_fallback_value,
+        "%(missing|amount,num=.1:-none) %(missing,num=.1:-none)",
+        "3.1 none"
+    );
+
+    test!(
+        test_with_invalid_spec_keeps_placeholder_unchanged,
+        "%(amount,num=) %(amount,num=.x) %(amount,num=2)",
+        "%(amount,num=) %(amount,num=.x) %(amount,num=2)"
+    );
+
+    #[test]
+    fn test_with_number_format_measure_lengths_uses_formatted_value() {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("count", "1234567".into());
+        let parser = Formatify::new();
+        let lengths = parser.measure_lengths(&key_value, "%(count,num=,)");
+        assert_eq!(lengths, vec![9, 9]);
+    }
+}
+
+#[cfg(all(test, feature = "num-format"))]
+mod tests_locale {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $locale:expr, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("amount", "1234567.891".into());
+                key_value.insert("count", "-12345678".into());
+                let parser = FormatifyBuilder::new().locale($locale).build();
+                let out = parser.replace_placeholders(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_english_locale_uses_default_separators,
+        "en",
+        "%(amount,num=,.2)",
+        "1,234,567.89"
+    );
+
+    test!(
+        test_with_german_locale_swaps_separators,
+        "de-DE",
+        "%(amount,num=,.2) %(amount,num=.1)",
+        "1.234.567,89 1234567,9"
+    );
+
+    test!(
+        test_with_swiss_locale_uses_region_separators,
+        "de-CH",
+        "%(amount,num=,.2)",
+        "1’234’567.89"
+    );
+
+    test!(
+        test_with_indian_locale_uses_indian_grouping,
+        "en-IN",
+        "%(count,num=,)",
+        "-1,23,45,678"
+    );
+
+    test!(
+        test_with_unknown_locale_uses_default_separators,
+        "xx-YY",
+        "%(amount,num=,.2)",
+        "1,234,567.89"
+    );
+
+    test!(
+        test_without_numeric_format_renders_value_unchanged,
+        "de-DE",
+        "%(amount)",
+        "1234567.891"
+    );
+}
+
+#[cfg(test)]
+mod tests_list_index {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("parents", join_list(["a1", "b2", "c3"]));
+                key_value.insert("name", "Alice".into());
+                key_value.insert("none", "".into());
+                let parser = Formatify::new();
+                let out = parser.replace_placeholders(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_first_index_renders_first_item,
+        "%(parents[0])",
+        "a1"
+    );
+
+    test!(
+        test_with_negative_index_counts_from_end,
+        "%(parents[-1]) %(parents[-3])",
+        "c3 a1"
+    );
+
+    test!(
+        test_with_index_on_plain_value_renders_value,
+        "%(name[0])",
+        "Alice"
+    );
+
+    test!(
+        test_with_index_out_of_range_keeps_placeholder_unchanged,
+        "%(parents[3]) %(parents[-4]) %(none[0])",
+        "%(parents[3]) %(parents[-4]) %(none[0])"
+    );
+
+    test!(
+        test_with_index_out_of_range_renders_default,
+        "%(parents[5]:-none)",
+        "none"
+    );
+
+    test!(
+        test_with_index_applies_format_to_item,
+        "%>(4)%(parents[1])|",
+        "  b2|"
+    );
+
+    test!(
+        test_with_index_and_fallback_uses_fallback_if_out_of_range,
+        "%(parents[7]|name)",
+        "Alice"
+    );
+
+    test!(
+        test_with_malformed_index_keeps_placeholder_unchanged,
+        "%(parents[x]) %(parents[1)",
+        "%(parents[x]) %(parents[1)"
+    );
+
+    #[test]
+    fn test_with_index_extract_keys_returns_key() {
+        let parser = Formatify::new();
+        assert_eq!(
+            parser.extract_placeholder_keys("%(parents[0])"),
+            vec!["parents"]
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests_dyn_formatter {
+    use crate::*;
+    use std::sync::Arc;
+
+    fn render(formatter: &dyn PlaceholderFormatter) -> String {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("name", "Alice".into());
+        formatter.replace_placeholders(&key_value, "Hi %(name)")
+    }
+
+    fn render_generic(formatter: impl PlaceholderFormatter) -> String {
+        render(&formatter)
+    }
+
+    #[test]
+    fn test_with_dyn_formatter_renders() {
+        assert_eq!(render(&Formatify::new()), "Hi Alice");
+    }
+
+    #[test]
+    fn test_with_arc_formatter_renders() {
+        let formatter: ArcFormatter = Arc::new(Formatify::new());
+        assert_eq!(render(&formatter), "Hi Alice");
+        assert_eq!(render_generic(formatter.clone()), "Hi Alice");
+    }
+
+    #[test]
+    fn test_with_reference_and_box_renders() {
+        let formatter = Formatify::new();
+        assert_eq!(render_generic(&formatter), "Hi Alice");
+        let boxed: Box<dyn PlaceholderFormatter> = Box::new(Formatify::new());
+        assert_eq!(render_generic(boxed), "Hi Alice");
+    }
+
+    #[test]
+    fn test_with_dyn_formatter_uses_default_methods() {
+        let formatter: Box<dyn PlaceholderFormatter> = Box::new(Formatify::new());
+        let tree = ValueTree::branch().with("user", ValueTree::branch().with("name", "Bob"));
+        assert_eq!(
+            formatter.replace_placeholders_tree(&tree, "%(user.name)"),
+            "Bob"
+        );
+    }
+}
+
+#[cfg(all(test, feature = "test-util"))]
+mod tests_mock_formatter {
+    use crate::*;
+
+    #[test]
+    fn test_with_scripted_template_returns_scripted_output() {
+        let mock = MockPlaceholderFormatter::new().returning("%(a)", "A");
+        let formatter: &dyn PlaceholderFormatter = &mock;
+        assert_eq!(formatter.replace_placeholders(&HashMap::new(), "%(a)"), "A");
+        assert_eq!(
+            formatter.replace_placeholders_strict(&HashMap::new(), "%(a)"),
+            Ok("A".to_string())
+        );
+    }
+
+    #[test]
+    fn test_with_unscripted_template_returns_template() {
+        let mock = MockPlaceholderFormatter::new();
+        assert_eq!(mock.replace_placeholders(&HashMap::new(), "%(b)"), "%(b)");
+        assert_eq!(mock.measure_lengths(&HashMap::new(), "%(b)"), vec![4]);
+    }
+
+    #[test]
+    fn test_records_calls_in_order() {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("a", "1".into());
+        let mock = MockPlaceholderFormatter::new();
+        mock.replace_placeholders(&key_value, "%(a)");
+        mock.replace_placeholders_vertical(&key_value, "x");
+        assert_eq!(mock.extract_placeholder_keys("%(a) %(b)"), vec!["a", "b"]);
+
+        let calls = mock.calls();
+        let methods: Vec<_> = calls.iter().map(|call| call.method).collect();
+        assert_eq!(
+            methods,
+            vec![
+                "replace_placeholders",
+                "replace_placeholders_vertical",
+                "extract_placeholder_keys"
+            ]
+        );
+        assert_eq!(calls[0].key_value["a"], "1");
+        assert_eq!(calls[1].template, "x");
+        assert!(calls[2].key_value.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod tests_measure_and_render {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr) => {
+            test!($test_name, Formatify::new(), $inp);
+        };
+        ($test_name:ident, $parser:expr, $inp:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("name", "Alice".into());
+                key_value.insert("id", "42".into());
+                key_value.insert("long", "abcdefghijklmnop".into());
+                key_value.insert("text", "the quick brown fox jumps over the lazy dog".into());
+                key_value.insert("lines", "first\nsecond".into());
+                key_value.insert("empty", "".into());
+                let parser = $parser;
+                let out = parser.measure_and_render(&key_value, $inp);
+                let expected = (
+                    parser.replace_placeholders(&key_value, $inp),
+                    parser.measure_lengths(&key_value, $inp),
+                );
+                assert_eq!(out, expected);
+            }
+        };
+    }
+
+    test!(test_with_plain_string_matches_separate_calls, "Hello");
+
+    test!(
+        test_with_placeholders_matches_separate_calls,
+        "%(id): %(name)!"
+    );
+
+    test!(
+        test_with_formats_matches_separate_calls,
+        "%<(8)%(name)|%>(4)%(id)|%<(6,trunc)%(long)|%>(6,ltrunc)%(long)"
+    );
+
+    test!(
+        test_with_invalid_placeholders_matches_separate_calls,
+        "%(missing) %z %(name"
+    );
+
+    test!(
+        test_with_blocks_matches_separate_calls,
+        "%(if:name)%(name)%(else)%(id)%(end) %(if:missing)%(id)%(end)"
+    );
+
+    test!(
+        test_with_block_indent_matches_separate_calls,
+        "%I(2)%(lines)|%I(\"> \")%<(8)%(lines)"
+    );
+
+    test!(
+        test_with_wrap_matches_separate_calls,
+        "%w(10,2,4)%(text)%w(0) %(name)"
+    );
+
+    test!(
+        test_with_justify_matches_separate_calls,
+        "> %<(12,justify)%(text)|%<(4,justify)%(long)|%<(8,justify)%(lines)"
+    );
+
+    test!(
+        test_with_removed_line_feeds_matches_separate_calls,
+        "%(name)%n%n%-(empty)|%(id)%n%-(name)"
+    );
+
+    test!(
+        test_with_fill_widths_matches_separate_calls,
+        Formatify::builder().target_width(20).build(),
+        "%(lines)%>(*)%(id)%n%(name)%t%<(*,trunc)%(long)%n%-(*,'=')"
+    );
+
+    test!(
+        test_with_right_to_left_matches_separate_calls,
+        Formatify::builder().right_to_left(true).build(),
+        "%<(8)%(name)|%(id)"
+    );
+
+    #[cfg(feature = "colors")]
+    test!(
+        test_with_color_placeholders_matches_separate_calls,
+        "%C(red)%(name)%C(reset)"
+    );
+}
+
+#[cfg(test)]
+mod tests_render_error {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $error:expr, $expected_message:expr) => {
+            #[test]
+            fn $test_name() {
+                assert_eq!($error.to_string(), $expected_message);
+            }
+        };
+    }
+
+    test!(
+        test_width_mismatch_displays_expected_and_found,
+        RenderError::WidthMismatch {
+            expected: 10,
+            found: 12
+        },
+        "expected output width 10, found 12"
+    );
+
+    test!(
+        test_malformed_placeholder_displays_found_and_position,
+        RenderError::MalformedPlaceholder {
+            position: 3,
+            found: "%z".into()
+        },
+        "malformed placeholder '%z' at position 3"
+    );
+
+    test!(
+        test_unclosed_block_displays_name,
+        RenderError::UnclosedBlock { name: "if".into() },
+        "block 'if' is not closed"
+    );
+}
+
+#[cfg(test)]
+mod tests_aggregate {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("amounts", join_list(["12.50", "7", "-3.25"]));
+                key_value.insert("sizes", join_list(["10", "n/a", "250", "3"]));
+                key_value.insert("empty", String::new());
+                let parser = Formatify::new();
+                let out = parser.replace_placeholders(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(test_sum_keeps_decimal_places, "%(sum:amounts)", "16.25");
+    test!(
+        test_key_named_like_aggregate_with_default_value,
+        "%(count:-0)|%(sum:-none)",
+        "0|none"
+    );
+
+    test!(test_avg_rounds_and_trims_zeros, "%(avg:amounts)", "5.4167");
+
+    test!(
+        test_min_and_max_render_items_as_written,
+        "%(min:amounts) %(max:amounts)",
+        "-3.25 12.50"
+    );
+
+    test!(
+        test_with_non_numeric_items_skips_them,
+        "%(sum:sizes) %(avg:sizes) %(count:sizes)",
+        "263 87.67 4"
+    );
+
+    test!(
+        test_with_numeric_format_formats_aggregate,
+        "%(avg:amounts,num=.1)",
+        "5.4"
+    );
+
+    test!(
+        test_with_format_placeholder_aligns_aggregate,
+        "%>(8)%(sum:amounts)|",
+        "   16.25|"
+    );
+
+    test!(
+        test_without_numbers_uses_default,
+        "%(max:empty:-n/a) %(count:empty)",
+        "n/a 0"
+    );
+
+    test!(
+        test_with_fallback_key_aggregates_it,
+        "%(sum:missing|sizes)",
+        "263"
+    );
+
+    test!(
+        test_with_missing_key_keeps_placeholder_unchanged,
+        "%(sum:missing)",
+        "%(sum:missing)"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_in_table_footer_sums_rows,
+        "%(for:a in amounts)%>(6)%(a)%n%(end)%-(6)%n%>(6)%(sum:amounts)",
+        " 12.50\n     7\n -3.25\n------\n 16.25"
+    );
+}
+
+#[cfg(test)]
+mod tests_width_of {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("title", "Release Notes".into());
+                key_value.insert("name", "Bob".into());
+                let parser = Formatify::new();
+                let out = parser.replace_placeholders(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_rendered_key_renders_its_width,
+        "%(title): %(width_of:title)",
+        "Release Notes: 13"
+    );
+
+    test!(
+        test_with_formatted_key_includes_padding,
+        "%<(6)%(name)|%(width_of:name)",
+        "Bob   |6"
+    );
+
+    test!(
+        test_with_rule_width_underlines_title,
+        "%(title)%n%-(%(width_of:title),'=')",
+        "Release Notes\n============="
+    );
+
+    test!(
+        test_with_format_width_aligns_to_rendered_width,
+        "%(title)%n%>(%(width_of:title))%(name)|",
+        "Release Notes\n          Bob|"
+    );
+
+    test!(
+        test_with_key_rendered_later_keeps_placeholder_unchanged,
+        "%(width_of:title) %(title)",
+        "%(width_of:title) Release Notes"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_key_in_inactive_block_keeps_placeholder_unchanged,
+        "%(?missing)%(name)%(/missing)%(width_of:name)",
+        "%(width_of:name)"
+    );
+
+    test!(
+        test_with_default_value_measures_rendered_default,
+        "%(nick:-anonymous) %(width_of:nick)",
+        "anonymous 9"
+    );
+}
+
+#[cfg(all(test, feature = "tables"))]
+mod tests_replace_placeholders_joined {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $templates:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("h_name", "Name".into());
+                key_value.insert("h_age", "Age".into());
+                key_value.insert("name", "Alice".into());
+                key_value.insert("age", "42".into());
+                key_value.insert("total", "Total".into());
+                key_value.insert("sum", "1042".into());
+                key_value.insert("title", "People".into());
+                key_value.insert("long", "All registered people".into());
+                key_value.insert("lines", "abc\nd".into());
+                let parser = Formatify::new();
+                let out = parser.replace_placeholders_joined(&key_value, $templates);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(test_with_no_templates_returns_empty_string, &[], "");
+
+    test!(
+        test_with_single_template_renders_it,
+        &["%(name) | %(age)"],
+        "Alice | 42"
+    );
+
+    test!(
+        test_with_several_templates_pads_cells_to_widest_piece,
+        &[
+            "%(h_name) | %(h_age)",
+            "%(name) | %(age)",
+            "%(total) | %(sum)"
+        ],
+        "Name  | Age \nAlice | 42  \nTotal | 1042"
+    );
+
+    test!(
+        test_with_explicit_format_keeps_it,
+        &["%>(6)%(h_name)|%(h_age)", "%(name)|%(age)"],
+        "  Name|Age\nAlice |42 "
+    );
+
+    test!(
+        test_with_templates_of_different_length_pads_common_cells,
+        &["%(h_name)", "%(name) | %(age)"],
+        "Name \nAlice | 42"
+    );
+
+    test!(
+        test_with_header_wider_than_values_uses_header_as_minimum,
+        &["%(h_age)|", "%(age)|"],
+        "Age|\n42 |"
+    );
+
+    test!(
+        test_with_truncated_row_keeps_header_untruncated,
+        &["%(h_name)|", "%<(2,trunc)%(name)|"],
+        "Name|\nA…|"
+    );
+
+    test!(
+        test_with_explicitly_truncated_column_truncates_header,
+        &["%<(3,trunc)%(h_name)|%(h_age)", "%<(3,trunc)%(name)|%(age)"],
+        "Na…|Age\nAl…|42 "
+    );
+
+    test!(
+        test_with_spanning_cell_aligns_over_combined_width,
+        &[
+            "%<(span=2)%(title)|",
+            "%(h_name) | %(h_age)|",
+            "%(name) | %(age)|"
+        ],
+        "People     |\nName  | Age|\nAlice | 42 |"
+    );
+
+    test!(
+        test_with_right_aligned_spanning_cell_aligns_right,
+        &["%>(span=2)%(title)|", "%(h_name) | %(h_age)|"],
+        "    People|\nName | Age|"
+    );
+
+    test!(
+        test_with_wide_spanning_cell_widens_last_column,
+        &[
+            "%<(span=2)%(long)|",
+            "%(h_name) | %(h_age)|",
+            "%(name) | %(age)|"
+        ],
+        "All registered people|\nName  | Age          |\nAlice | 42           |"
+    );
+
+    test!(
+        test_with_spanning_cell_shifts_subsequent_columns,
+        &["%<(span=2)%(title)|%(h_age)", "%(name)|%(age)|%(sum)"],
+        "People  |Age \nAlice|42|1042"
+    );
+
+    test!(
+        test_with_spanning_cell_without_columns_uses_natural_width,
+        &["%<(span=2)%(name)|"],
+        "Alice|"
+    );
+
+    test!(
+        test_with_multi_line_value_measures_widest_line,
+        &["ab %(lines)", "%(age)"],
+        "ab abc\nd\n42 "
+    );
+
+    test!(
+        test_with_wrapped_value_measures_widest_line,
+        &["%(long)%w(10,2,4)%(long)", "%(name)|"],
+        "All registered people  All\n    registered\n    people\nAlice                |"
+    );
+}
+
+#[cfg(all(test, feature = "tables"))]
+mod tests_replace_placeholders_code_block {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $templates:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("header", "City".into());
+                key_value.insert("ascii", "Berlin".into());
+                key_value.insert("wide", "東京".into());
+                key_value.insert("combining", "Cafe\u{301}".into());
+                key_value.insert("code", "a```b".into());
+                key_value.insert("long", "All registered people".into());
+                let parser = Formatify::new();
+                let out = parser.replace_placeholders_code_block(&key_value, &$templates);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_ascii_values_aligns_columns_in_fence,
+        ["%(header)|", "%(ascii)|"],
+        "```\nCity  |\nBerlin|\n```"
+    );
+
+    test!(
+        test_with_wide_chars_pads_by_display_width,
+        ["%(header)|", "%(ascii)|", "%(wide)|"],
+        "```\nCity  |\nBerlin|\n東京  |\n```"
+    );
+
+    test!(
+        test_with_combining_mark_counts_zero_width,
+        ["%(combining)|", "%(ascii)|"],
+        "```\nCafe\u{301}  |\nBerlin|\n```"
+    );
+
+    test!(
+        test_with_truncation_truncates_by_display_width,
+        ["%<(4,trunc)%(wide)|", "%<(4,trunc)%(ascii)|"],
+        "```\n東京|\nBer…|\n```"
+    );
+
+    test!(
+        test_with_wide_char_not_fitting_pads_truncation,
+        [
+            "%<(2,trunc)%(wide)|",
+            "%>(2,ltrunc)%(wide)|",
+            "%<(3,trunc)%(wide)|"
+        ],
+        "```\n… |\n… |\n東…|\n```"
+    );
+
+    test!(
+        test_with_wrapped_value_measures_widest_line,
+        ["%(header)%w(10,2,4)%(long)", "%(wide)|"],
+        "```\nCity  All\n    registered\n    people\n東京|\n```"
+    );
+
+    test!(
+        test_with_backticks_in_output_uses_longer_fence,
+        ["%(code)"],
+        "````\na```b\n````"
+    );
+}
+
+#[cfg(test)]
+mod tests_replace_placeholders_fitting {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $templates:expr, $width:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("name", "Alice".into());
+                key_value.insert("city", "東京".into());
+                let parser = Formatify::new();
+                let out = parser.replace_placeholders_fitting(&key_value, &$templates, $width);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_enough_width_renders_first_template,
+        ["%(name) from %(city)", "%(name)"],
+        15,
+        "Alice from 東京"
+    );
+
+    test!(
+        test_with_wide_chars_measures_display_columns,
+        ["%(name) from %(city)", "%(name)"],
+        14,
+        "Alice"
+    );
+
+    test!(
+        test_with_multiple_lines_measures_widest_line,
+        ["%(name)%n%(name) %(name)", "%(name)%n%(name)"],
+        8,
+        "Alice\nAlice"
+    );
+
+    test!(
+        test_with_escape_sequences_ignores_them,
+        ["%e[1m%(name)%e[0m!", "-"],
+        6,
+        "\x1b[1mAlice\x1b[0m!"
+    );
+
+    test!(
+        test_without_fitting_template_renders_last_template,
+        ["%(name) %(name)", "%(name)"],
+        2,
+        "Alice"
+    );
+
+    test!(
+        test_without_templates_renders_empty_string,
+        [] as [&str; 0],
+        80,
+        ""
+    );
+}
+
+#[cfg(test)]
+mod tests_replace_placeholders_negotiated {
+    use crate::*;
+    use std::cell::RefCell;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $negotiate:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("id", "42".into());
+                key_value.insert("name", "Alice".into());
+                key_value.insert("title", "A rather long title".into());
+                let parser = Formatify::new();
+                let out = parser.replace_placeholders_negotiated(&key_value, $inp, &$negotiate);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_unchanged_widths_renders_naturally,
+        "%>(4)%(id) %(name)",
+        |widths: &[usize]| widths.to_vec(),
+        "  42 Alice"
+    );
+
+    test!(
+        test_with_shrunk_width_truncates_value,
+        "%(name)|%(title)|",
+        |widths: &[usize]| vec![widths[0], 8],
+        "Alice|A rathe…|"
+    );
+
+    test!(
+        test_with_grown_width_keeps_alignment,
+        "%>(4)%(id)|%(name)|",
+        |_: &[usize]| vec![6, 7],
+        "    42|Alice  |"
+    );
+
+    test!(
+        test_with_left_truncating_format_keeps_truncation_side,
+        "%<(30,ltrunc)%(title)|",
+        |_: &[usize]| vec![6],
+        "…title|"
+    );
+
+    test!(
+        test_with_missing_widths_keeps_natural_width,
+        "%(id)|%(name)|",
+        |_: &[usize]| vec![3],
+        "42 |Alice|"
+    );
+
+    #[test]
+    fn test_negotiate_receives_natural_widths() {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("id", "42".into());
+        key_value.insert("name", "Alice".into());
+        let reported = RefCell::new(Vec::new());
+        let parser = Formatify::new();
+        parser.replace_placeholders_negotiated(&key_value, "%>(4)%(id) %(name)", &|widths| {
+            reported.replace(widths.to_vec());
+            widths.to_vec()
+        });
+        assert_eq!(reported.into_inner(), vec![4, 5]);
+    }
+}
+
+#[cfg(all(test, feature = "colors"))]
+mod tests_color_target {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $target:expr, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("name", "Alice".into());
+                let parser = FormatifyBuilder::new().color_target($target).build();
+                let out = parser.replace_placeholders(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_ansi_target_emits_escape_sequence,
+        ColorTarget::Ansi,
+        "%C(bold red)%(name)%Creset",
+        "\x1b[1;31mAlice\x1b[m"
+    );
+
+    test!(
+        test_with_irc_target_emits_control_codes,
+        ColorTarget::Irc,
+        "%C(bold red)%(name)%Creset!",
+        "\x02\x0305Alice\x0f!"
+    );
+
+    test!(
+        test_with_irc_target_and_background_emits_color_pair,
+        ColorTarget::Irc,
+        "%C(brightwhite blue)%(name)",
+        "\x0300,02Alice"
+    );
+
+    test!(
+        test_with_irc_target_maps_rgb_to_nearest_color,
+        ColorTarget::Irc,
+        "%C(#ff8000)%(name) %C(normal yellow)%(name)",
+        "\x0307Alice \x0399,07Alice"
+    );
+
+    test!(
+        test_with_irc_target_skips_unsupported_attributes,
+        ColorTarget::Irc,
+        "%C(dim blink)%(name)",
+        "Alice"
+    );
+
+    test!(
+        test_with_bbcode_target_emits_tags,
+        ColorTarget::BbCode,
+        "%C(bold ul red)%(name)%C(reset)!",
+        "[b][u][color=red]Alice[/color][/u][/b]!"
+    );
+
+    test!(
+        test_with_bbcode_target_closes_previous_tags,
+        ColorTarget::BbCode,
+        "%C(italic)a%C(#ff8000)b",
+        "[i]a[/i][color=#ff8000]b[/color]"
+    );
+
+    test!(
+        test_with_bbcode_target_closes_open_tags_at_end,
+        ColorTarget::BbCode,
+        "%C(brightred strike)%(name)",
+        "[s][color=#ff0000]Alice[/color][/s]"
+    );
+
+    test!(
+        test_with_bbcode_target_keeps_alignment,
+        ColorTarget::BbCode,
+        "%Cgreen%<(7)%(name)|",
+        "[color=green]Alice  |[/color]"
+    );
+
+    #[test]
+    fn test_with_bbcode_target_measure_and_render_excludes_tags() {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("name", "Alice".into());
+        let parser = FormatifyBuilder::new()
+            .color_target(ColorTarget::BbCode)
+            .build();
+        let (out, lengths) = parser.measure_and_render(&key_value, "%C(bold)%(name)");
+        assert_eq!(out, "[b]Alice[/b]");
+        assert_eq!(lengths, vec![5, 5]);
+    }
+}
+
+#[cfg(all(test, feature = "html"))]
+mod tests_replace_placeholders_html {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            test!($test_name, Formatify::new(), $inp, $expected_output);
+        };
+        ($test_name:ident, $parser:expr, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("name", "Alice".into());
+                key_value.insert("id", "42".into());
+                key_value.insert("markup", "<b>\"x\" & 'y'</b>".into());
+                key_value.insert("empty", "".into());
+                let out = $parser.replace_placeholders_html(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_plain_string_escapes_text,
+        "a < b & c",
+        "a &lt; b &amp; c"
+    );
+
+    test!(
+        test_with_value_wraps_value_in_span,
+        "Hi %(name)!",
+        "Hi <span class=\"fmt-value\" data-key=\"name\">Alice</span>!"
+    );
+
+    test!(
+        test_with_markup_value_escapes_value,
+        "%(markup)",
+        "<span class=\"fmt-value\" data-key=\"markup\">&lt;b&gt;&quot;x&quot; &amp; &#39;y&#39;&lt;/b&gt;</span>"
+    );
+
+    test!(
+        test_with_padding_includes_padding_in_span,
+        "%>(4)%(id)|",
+        "<span class=\"fmt-value\" data-key=\"id\">  42</span>|"
+    );
+
+    test!(
+        test_with_adjacent_values_renders_separate_spans,
+        "%(id)%(name)",
+        "<span class=\"fmt-value\" data-key=\"id\">42</span><span class=\"fmt-value\" data-key=\"name\">Alice</span>"
+    );
+
+    test!(
+        test_with_empty_value_renders_empty_span,
+        "[%(empty)]",
+        "[<span class=\"fmt-value\" data-key=\"empty\"></span>]"
+    );
+
+    test!(
+        test_with_default_value_omits_key,
+        "%(missing:-none)",
+        "<span class=\"fmt-value\">none</span>"
+    );
+
+    test!(
+        test_with_missing_key_keeps_placeholder_unchanged,
+        "%(missing)",
+        "%(missing)"
+    );
+
+    test!(
+        test_with_fallback_key_names_providing_key,
+        "%(missing|name)",
+        "<span class=\"fmt-value\" data-key=\"name\">Alice</span>"
+    );
+
+    test!(
+        test_with_right_to_left_mirrors_spans,
+        FormatifyBuilder::new().right_to_left(true).build(),
+        "%(id)|%(name)",
+        "<span class=\"fmt-value\" data-key=\"name\">Alice</span>|<span class=\"fmt-value\" data-key=\"id\">42</span>"
+    );
+
+    test!(
+        test_with_right_to_left_and_empty_value_nests_spans,
+        FormatifyBuilder::new().right_to_left(true).build(),
+        "%(id)%-(empty)%(id)",
+        "<span class=\"fmt-value\" data-key=\"id\">42</span><span class=\"fmt-value\" data-key=\"empty\"></span><span class=\"fmt-value\" data-key=\"id\">42</span>"
+    );
+
+    test!(
+        test_with_right_to_left_moves_empty_span_to_mirrored_position,
+        FormatifyBuilder::new().right_to_left(true).build(),
+        "%(empty)|%(id)|%(name)",
+        "<span class=\"fmt-value\" data-key=\"name\">Alice</span>|<span class=\"fmt-value\" data-key=\"id\">42</span>|<span class=\"fmt-value\" data-key=\"empty\"></span>"
+    );
+
+    #[cfg(feature = "colors")]
+    test!(
+        test_with_color_adds_style_classes,
+        "%C(bold red)%(name)%Creset %(id)",
+        "<span class=\"fmt-value fmt-bold fmt-fg-red\" data-key=\"name\">Alice</span> <span class=\"fmt-value\" data-key=\"id\">42</span>"
+    );
+
+    #[cfg(feature = "colors")]
+    test!(
+        test_with_extended_colors_adds_style_classes,
+        "%C(brightblue #ff8000)%(name)%C(ul 208)%(id)",
+        "<span class=\"fmt-value fmt-fg-brightblue fmt-bg-ff8000\" data-key=\"name\">Alice</span><span class=\"fmt-value fmt-ul fmt-fg-208\" data-key=\"id\">42</span>"
+    );
+}
+
+#[cfg(all(test, feature = "markdown"))]
+mod tests_replace_placeholders_markdown {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $flavor:expr, $inp:expr, $expected_output:expr) => {
+            test!(
+                $test_name,
+                Formatify::new(),
+                $flavor,
+                $inp,
+                $expected_output
+            );
+        };
+        ($test_name:ident, $parser:expr, $flavor:expr, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("name", "Alice".into());
+                key_value.insert("id", "42".into());
+                key_value.insert("markup", "a_b *c* <d> & `e`".into());
+                key_value.insert("empty", "".into());
+                let out = $parser.replace_placeholders_markdown(&key_value, $inp, $flavor);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_template_markdown_passes_it_through,
+        MarkdownFlavor::Slack,
+        "*Build* _%(name)_",
+        "*Build* _Alice_"
+    );
+
+    test!(
+        test_with_slack_flavor_escapes_entities,
+        MarkdownFlavor::Slack,
+        "%(markup)",
+        "a_b *c* &lt;d&gt; &amp; `e`"
+    );
+
+    test!(
+        test_with_discord_flavor_escapes_markdown_chars,
+        MarkdownFlavor::Discord,
+        "%(markup)",
+        "a\\_b \\*c\\* <d\\> & \\`e\\`"
+    );
+
+    test!(
+        test_with_aligned_value_wraps_value_in_code_span,
+        MarkdownFlavor::Slack,
+        "%>(4)%(id)|%<(6)%(name)|",
+        "`  42`|`Alice `|"
+    );
+
+    test!(
+        test_with_aligned_value_replaces_backticks,
+        MarkdownFlavor::Discord,
+        "%<(20)%(markup)|",
+        "`a_b *c* <d> & 'e'   `|"
+    );
+
+    test!(
+        test_with_empty_value_renders_nothing,
+        MarkdownFlavor::Slack,
+        "[%(empty)]",
+        "[]"
+    );
+
+    test!(
+        test_with_missing_key_keeps_placeholder_unchanged,
+        MarkdownFlavor::Slack,
+        "%(missing)",
+        "%(missing)"
+    );
+
+    test!(
+        test_with_right_to_left_mirrors_values,
+        FormatifyBuilder::new().right_to_left(true).build(),
+        MarkdownFlavor::Slack,
+        "%>(4)%(id)|%(name)",
+        "Alice|`42  `"
+    );
+
+    test!(
+        test_with_right_to_left_and_empty_value_renders_values,
+        FormatifyBuilder::new().right_to_left(true).build(),
+        MarkdownFlavor::Slack,
+        "%(id)%-(empty)%(id)",
+        "4242"
+    );
+
+    test!(
+        test_with_right_to_left_and_empty_value_keeps_code_spans,
+        FormatifyBuilder::new().right_to_left(true).build(),
+        MarkdownFlavor::Slack,
+        "%>(4)%(id)|%(empty)|%<(6)%(name)",
+        "` Alice`||`42  `"
+    );
+
+    #[cfg(feature = "colors")]
+    test!(
+        test_with_slack_flavor_and_color_sets_value_in_bold,
+        MarkdownFlavor::Slack,
+        "%C(red)%(name)%Creset %(id)",
+        "*Alice* 42"
+    );
+
+    #[cfg(feature = "colors")]
+    test!(
+        test_with_discord_flavor_and_color_sets_aligned_value_in_bold,
+        MarkdownFlavor::Discord,
+        "%C(bold)%<(6)%(name)|",
+        "**`Alice `**|"
+    );
+}
+
+#[cfg(test)]
+mod tests_truncations {
+    use crate::*;
+
+    fn truncation(output: std::ops::Range<usize>, key: Option<&str>, value: &str) -> Truncation {
+        Truncation {
+            output,
+            key: key.map(|key| key.to_string()),
+            value: value.into(),
+        }
+    }
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr, $expected_truncations:expr) => {
+            test!(
+                $test_name,
+                Formatify::new(),
+                $inp,
+                $expected_output,
+                $expected_truncations
+            );
+        };
+        ($test_name:ident, $parser:expr, $inp:expr, $expected_output:expr, $expected_truncations:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("name", "Alice".into());
+                key_value.insert("id", "42".into());
+                let (out, truncations) =
+                    $parser.replace_placeholders_with_truncations(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+                assert_eq!(truncations, $expected_truncations);
+            }
+        };
+    }
+
+    test!(
+        test_without_truncation_records_nothing,
+        "%<(8,trunc)%(name)|%(id)",
+        "Alice   |42",
+        Vec::<Truncation>::new()
+    );
+
+    test!(
+        test_with_right_truncation_records_value,
+        "x%<(4,trunc)%(name)|",
+        "xAli…|",
+        vec![truncation(1..5, Some("name"), "Alice")]
+    );
+
+    test!(
+        test_with_left_truncation_records_value,
+        "%>(3,ltrunc)%(name)",
+        "…ce",
+        vec![truncation(0..3, Some("name"), "Alice")]
+    );
+
+    test!(
+        test_with_exact_fit_records_nothing,
+        "%<(5,trunc)%(name)",
+        "Alice",
+        Vec::<Truncation>::new()
+    );
+
+    test!(
+        test_with_truncated_default_records_without_key,
+        "%<(4,trunc)%(missing:-unknown)",
+        "unk…",
+        vec![truncation(0..4, None, "unknown")]
+    );
+
+    test!(
+        test_with_right_to_left_records_mirrored_range,
+        FormatifyBuilder::new().right_to_left(true).build(),
+        "%<(3,trunc)%(name)|%(id)",
+        "42|Al…",
+        vec![truncation(3..6, Some("name"), "Alice")]
+    );
+}
+
+#[cfg(test)]
+mod tests_source_map {
+    use crate::*;
+
+    fn template(range: std::ops::Range<usize>) -> SourceOrigin {
+        SourceOrigin::Template(range)
+    }
+
+    fn value(key: &str, range: std::ops::Range<usize>) -> SourceOrigin {
+        SourceOrigin::Value {
+            key: key.into(),
+            range,
+        }
+    }
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr, $expected_map:expr) => {
+            test!(
+                $test_name,
+                Formatify::new(),
+                $inp,
+                $expected_output,
+                $expected_map
+            );
+        };
+        ($test_name:ident, $parser:expr, $inp:expr, $expected_output:expr, $expected_map:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("name", "Alice".into());
+                key_value.insert("id", "42".into());
+                let (out, source_map) =
+                    $parser.replace_placeholders_with_source_map(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+                let entries: Vec<_> = source_map
+                    .into_iter()
+                    .map(|entry| (entry.output, entry.origin))
+                    .collect();
+                assert_eq!(entries, $expected_map);
+            }
+        };
+    }
+
+    test!(
+        test_with_plain_string_maps_to_template,
+        "Hello",
+        "Hello",
+        vec![(0..5, template(0..5))]
+    );
+
+    test!(
+        test_with_value_placeholder_maps_to_value,
+        "Hi %(name)!",
+        "Hi Alice!",
+        vec![
+            (0..3, template(0..3)),
+            (3..8, value("name", 0..5)),
+            (8..9, template(10..11))
+        ]
+    );
+
+    test!(
+        test_with_single_char_placeholder_maps_to_placeholder,
+        "a%nb",
+        "a\nb",
+        vec![(0..3, template(0..4))]
+    );
+
+    test!(
+        test_with_padding_leaves_gap,
+        "%>(4)%(id)|",
+        "  42|",
+        vec![(2..4, value("id", 0..2)), (4..5, template(10..11))]
+    );
+
+    test!(
+        test_with_left_truncation_maps_to_value_suffix,
+        "%<(4,ltrunc)%(name)",
+        "…ice",
+        vec![(1..4, value("name", 2..5))]
+    );
+
+    test!(
+        test_with_default_value_maps_to_template,
+        "%(missing:-x)",
+        "x",
+        vec![(0..1, template(0..13))]
+    );
+
+    test!(
+        test_with_unknown_placeholder_maps_to_template,
+        "%(missing)",
+        "%(missing)",
+        vec![(0..10, template(0..10))]
+    );
+
+    test!(
+        test_with_right_to_left_maps_mirrored_output,
+        Formatify::builder().right_to_left(true).build(),
+        "%(id) %(name)",
+        "Alice 42",
+        vec![
+            (0..5, value("name", 0..5)),
+            (5..6, template(5..6)),
+            (6..8, value("id", 0..2))
+        ]
+    );
+}
+
+#[cfg(test)]
+mod tests_right_to_left {
+    use crate::*;
+    use std::collections::HashMap;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("var1", "world".into());
+                key_value.insert("var2", "welt".into());
+                key_value.insert("str4", "1234".into());
+                key_value.insert("str14", "1234567890ABCD".into());
+                let parser = Formatify::builder().right_to_left(true).build();
+                let out_str = parser.replace_placeholders(&key_value, $inp);
+                assert_eq!(out_str, $expected_output);
+            }
+        };
+    }
+
+    test!(test_with_plain_string_returns_same_string, "abc", "abc");
+
+    test!(
+        test_with_two_placeholders_reverses_order,
+        "|%(var1)|%(var2)|",
+        "|welt|world|"
+    );
+
+    test!(
+        test_with_aligned_placeholders_reverses_order_and_swaps_alignment,
+        "%<(6)%(str4)|%>(8)%(var2)",
+        "welt    |  1234"
+    );
+
+    test!(
+        test_with_truncated_placeholder_swaps_alignment,
+        "%<(10,trunc)%(str14)|%(var1)",
+        "world|123456789…"
+    );
+
+    test!(
+        test_with_multiple_lines_mirrors_each_line,
+        "%(var1) %(var2)%n%(str4) %(var1)",
+        "welt world\nworld 1234"
+    );
+
+    test!(
+        test_with_unknown_placeholder_keeps_it_in_place,
+        "%(unknown) %(var1) %(var2)",
+        "%(unknown) welt world"
+    );
+}
+
+#[cfg(test)]
+mod tests_target_width {
+    use crate::*;
+    use std::collections::HashMap;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("var1", "world".into());
+                key_value.insert("str4", "1234".into());
+                key_value.insert("str14", "1234567890ABCD".into());
+                key_value.insert("lines", "first line\nab".into());
+                let parser = Formatify::builder().target_width(12).build();
+                let out_str = parser.replace_placeholders(&key_value, $inp);
+                assert_eq!(out_str, $expected_output);
+                let lengths = parser.measure_lengths(&key_value, $inp);
+                assert_eq!(lengths[0], out_str.chars().count());
+            }
+        };
+    }
+
+    test!(
+        test_with_right_fill_placeholder_pushes_value_to_edge,
+        "Hallo%>(*)%(str4)",
+        "Hallo   1234"
+    );
+
+    test!(
+        test_with_left_fill_placeholder_pads_value_to_edge,
+        "ab%<(*)%(str4)",
+        "ab1234      "
+    );
+
+    test!(
+        test_with_fill_placeholder_on_second_line_uses_line_column,
+        "Hallo Welt%n%>(*)%(var1)",
+        "Hallo Welt\n       world"
+    );
+
+    test!(
+        test_with_fill_truncate_placeholder_truncates_to_edge,
+        "Hallo %<(*,trunc)%(str14)",
+        "Hallo 12345…"
+    );
+
+    test!(
+        test_with_fill_rule_placeholder_fills_to_edge,
+        "Title %-(*,'=')",
+        "Title ======"
+    );
+
+    test!(
+        test_with_column_beyond_target_width_keeps_value_unchanged,
+        "Hallo Welt 123%>(*)%(str4)",
+        "Hallo Welt 1231234"
+    );
+
+    test!(
+        test_with_fill_placeholder_after_multi_line_value_uses_column_of_last_line,
+        "%<(4)%(lines)%>(*)%(str4)",
+        "first line\nab      1234"
+    );
+
+    test!(
+        test_with_fill_placeholder_after_tab_counts_it_as_one_column,
+        "ab%t%>(*)%(str4)",
+        "ab\t     1234"
+    );
+
+    #[test]
+    fn test_without_target_width_keeps_fill_placeholder_unchanged() {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("str4", "1234".into());
+        let parser = Formatify::new();
+        let out_str = parser.replace_placeholders(&key_value, "ab%>(*)%(str4)");
+        assert_eq!(out_str, "ab%>(*)1234");
+    }
+}
+
+#[cfg(test)]
+mod tests_replace_placeholders_vertical {
+    use crate::*;
+    use std::collections::HashMap;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("var1", "world".into());
+                key_value.insert("var2", "welt".into());
+                key_value.insert("umlaute", "äöü".into());
+                let parser = Formatify::new();
+                let out_str = parser.replace_placeholders_vertical(&key_value, $inp);
+                assert_eq!(out_str, $expected_output);
+            }
+        };
+    }
+
+    test!(test_with_empty_input_returns_empty_string, "", "");
+
+    test!(
+        test_with_plain_string_returns_empty_string,
+        "Conventional string",
+        ""
+    );
+
+    test!(
+        test_with_multiple_placeholders_aligns_names_to_the_right,
+        "%<(10)%(var1)|%>(5,trunc)%(umlaute)",
+        "   var1: world\numlaute: äöü"
+    );
+
+    test!(
+        test_with_repeated_placeholder_lists_it_once,
+        "%(var1) %(var2) %(var1)",
+        "var1: world\nvar2: welt"
+    );
+
+    test!(
+        test_with_undefined_placeholder_keeps_it_unreplaced,
+        "%(var1) %(vara)",
+        "var1: world\nvara: %(vara)"
+    );
+}
+
+#[cfg(all(test, feature = "blocks"))]
+mod tests_loop_blocks {
+    use crate::*;
+    use std::collections::HashMap;
+
+    fn key_value() -> HashMap<&'static str, String> {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("var1", "world".into());
+        key_value.insert("parents", join_list(["a1b2", "c3d4"]));
+        key_value.insert("rows", join_list(["x", "y", "z"]));
+        key_value.insert("single", "one".into());
+        key_value.insert("empty", "".into());
+        key_value
+    }
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let parser = Formatify::new();
+                let out_str = parser.replace_placeholders(&key_value(), $inp);
+                assert_eq!(out_str, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_list_value_renders_body_per_element,
+        "%(for:p in parents)%(p)%n%(end)",
+        "a1b2\nc3d4\n"
+    );
+
+    test!(
+        test_with_single_value_renders_body_once,
+        "%(for:s in single)[%(s)]%(end)",
+        "[one]"
+    );
+
+    test!(
+        test_with_empty_list_skips_body,
+        "a%(for:e in empty)[%(e)]%(end)b",
+        "ab"
+    );
+
+    test!(
+        test_with_missing_list_and_else_branch_renders_else_branch,
+        "%(for:e in vara)[%(e)]%(else)none%(end)",
+        "none"
+    );
+
+    test!(
+        test_with_list_and_else_branch_skips_else_branch,
+        "%(for:p in parents)%(p) %(else)none%(end)",
+        "a1b2 c3d4 "
+    );
+
+    test!(
+        test_with_nested_loops_renders_all_combinations,
+        "%(for:p in parents)%(for:r in rows)%(p)%(r) %(end)%(end)",
+        "a1b2x a1b2y a1b2z c3d4x c3d4y c3d4z "
+    );
+
+    test!(
+        test_with_loop_variable_in_condition_renders_matching_elements,
+        "%(for:r in rows)%(if:r != \"y\")%(r)%(end)%(end)",
+        "xz"
+    );
+
+    test!(
+        test_with_aligned_loop_variable_pads_each_element,
+        "%(for:r in rows)%<(3)%(r)|%(/rows)",
+        "x  |y  |z  |"
+    );
+
+    test!(
+        test_with_global_key_inside_loop_renders_it,
+        "%(for:r in rows)%(var1)%(end)",
+        "worldworldworld"
+    );
+
+    test!(
+        test_with_incomplete_loop_tag_keeps_it_unchanged,
+        "%(for:r rows)x",
+        "%(for:r rows)x"
+    );
+
+    #[test]
+    fn test_measure_lengths_with_loop_counts_each_iteration() {
+        let parser = Formatify::new();
+        let lengths = parser.measure_lengths(&key_value(), "%(for:p in parents)%(p),%(end)");
+        assert_eq!(lengths, vec![10usize, 4usize, 4usize]);
+    }
+
+    #[test]
+    fn test_extract_placeholder_keys_with_loop_returns_list_key_but_not_loop_variable() {
+        let parser = Formatify::new();
+        let keys = parser.extract_placeholder_keys("%(for:p in parents)%(p)%(var1)%(end)");
+        assert_eq!(keys, vec!["parents", "var1"]);
+    }
+}
+
+#[cfg(all(test, feature = "blocks"))]
+mod tests_switch_blocks {
+    use crate::*;
+    use std::collections::HashMap;
+
+    macro_rules! test {
+        ($test_name:ident, $level:expr, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("msg", "disk full".into());
+                if let Some(level) = $level {
+                    key_value.insert("level", String::from(level));
+                }
+                let parser = Formatify::new();
+                let out_str = parser.replace_placeholders(&key_value, $inp);
+                assert_eq!(out_str, $expected_output);
+            }
+        };
+    }
+
+    const TEMPLATE: &str =
+        "%(switch:level)%(case:error)E%(case:warn)W%(default)I%(/switch): %(msg)";
+
+    test!(
+        test_with_first_case_matching_renders_first_case,
+        Some("error"),
+        TEMPLATE,
+        "E: disk full"
+    );
+
+    test!(
+        test_with_second_case_matching_renders_second_case,
+        Some("warn"),
+        TEMPLATE,
+        "W: disk full"
+    );
+
+    test!(
+        test_with_no_case_matching_renders_default,
+        Some("debug"),
+        TEMPLATE,
+        "I: disk full"
+    );
+
+    test!(
+        test_with_missing_key_renders_default,
+        None::<&str>,
+        TEMPLATE,
+        "I: disk full"
+    );
+
+    test!(
+        test_with_no_case_matching_and_no_default_renders_nothing,
+        Some("debug"),
+        "[%(switch:level)%(case:error)E%(end)]",
+        "[]"
+    );
+
+    test!(
+        test_with_content_before_first_case_ignores_it,
+        Some("warn"),
+        "%(switch:level)ignored%(case:warn)W%(end)",
+        "W"
+    );
+
+    test!(
+        test_with_duplicate_case_renders_first_matching_case_only,
+        Some("warn"),
+        "%(switch:level)%(case:warn)1%(case:warn)2%(end)",
+        "1"
+    );
+
+    test!(
+        test_with_nested_switch_in_inactive_case_skips_it,
+        Some("warn"),
+        "%(switch:level)%(case:error)%(switch:level)%(default)X%(end)%(case:warn)W%(end)",
+        "W"
+    );
+
+    test!(
+        test_with_case_outside_of_switch_keeps_it_unchanged,
+        Some("warn"),
+        "a%(case:warn)b",
+        "a%(case:warn)b"
+    );
+
+    #[test]
+    fn test_extract_placeholder_keys_with_switch_returns_keys_of_all_cases() {
+        let parser = Formatify::new();
+        let keys = parser.extract_placeholder_keys(
+            "%(switch:level)%(case:error)%(err)%(default)%(msg)%(/switch)",
+        );
+        assert_eq!(keys, vec!["level", "err", "msg"]);
+    }
+}
+
+#[cfg(test)]
+mod tests_canonical {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("sha", "3f2a9c1d7e".into());
+                key_value.insert("amount", "1234567.891".into());
+                key_value.insert("date", "1699992800".into());
+                let parser = Formatify::builder().canonical().build();
+                let out = parser.replace_placeholders(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_truncation_uses_pinned_marker,
+        "[%<(5,trunc)%(sha)] [%>(5,ltrunc)%(sha)]",
+        "[3f2a…] […1d7e]"
+    );
+
+    test!(
+        test_with_number_format_uses_default_separators,
+        "%(amount,num=,.2)",
+        "1,234,567.89"
+    );
+
+    test!(
+        test_with_dynamic_builtins_keeps_placeholders_unresolved,
+        "%(now) %(uuid) %(rand)",
+        "%(now) %(uuid) %(rand)"
+    );
+
+    #[cfg(feature = "chrono")]
+    test!(
+        test_with_reldate_keeps_placeholder_unchanged,
+        "%(date|reldate)",
+        "%(date|reldate)"
+    );
+
+    #[cfg(feature = "num-format")]
+    #[test]
+    fn test_with_locale_ignores_locale_separators() {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("amount", "1234.5".into());
+        let parser = Formatify::builder().locale("de-DE").canonical().build();
+        let out = parser.replace_placeholders(&key_value, "%(amount,num=,.2)");
+        assert_eq!(out, "1,234.50");
+    }
+
+    #[test]
+    fn test_with_seed_ignores_random_source() {
+        let key_value = HashMap::<&str, String>::new();
+        let parser = Formatify::builder().seed(42).canonical().build();
+        let out = parser.replace_placeholders(&key_value, "%(uuid)");
+        assert_eq!(out, "%(uuid)");
+    }
+}
+
+#[cfg(test)]
+mod tests_render_and_diff {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $delta:expr, $expected_edits:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("name", "Alice".into());
+                key_value.insert("cpu", "12".into());
+                key_value.insert("mem", "40".into());
+                key_value.insert("rows", join_list(["a", "b"]));
+                let parser = Formatify::new();
+                let previous = parser.replace_placeholders(&key_value, $inp);
+                let delta: HashMap<&str, String> =
+                    $delta.iter().map(|(k, v)| (*k, v.to_string())).collect();
+                let edits = parser.render_and_diff(&previous, &key_value, &delta, $inp);
+                assert_eq!(edits, $expected_edits);
+
+                // applying the edits yields the complete new render
+                let mut updated = key_value.clone();
+                updated.extend(delta.clone());
+                let mut chars: Vec<char> = previous.chars().collect();
+                for edit in edits.iter().rev() {
+                    chars.splice(edit.range.clone(), edit.text.chars());
+                }
+                let applied: String = chars.into_iter().collect();
+                assert_eq!(applied, parser.replace_placeholders(&updated, $inp));
+            }
+        };
+    }
+
+    test!(
+        test_with_unchanged_values_returns_no_edits,
+        "%(name): %(cpu)",
+        [("cpu", "12")],
+        vec![]
+    );
+
+    test!(
+        test_with_fixed_width_cells_returns_edit_per_cell,
+        "%<(6)%(name)|%>(3)%(cpu)|%>(3)%(mem)",
+        [("cpu", "7"), ("mem", "99")],
+        vec![
+            OutputEdit {
+                range: 8..10,
+                text: " 7".into()
+            },
+            OutputEdit {
+                range: 12..14,
+                text: "99".into()
+            }
+        ]
+    );
+
+    test!(
+        test_with_changed_line_length_replaces_between_prefix_and_suffix,
+        "%(name) uses %(cpu)%",
+        [("name", "Bob")],
+        vec![OutputEdit {
+            range: 0..5,
+            text: "Bob".into()
+        }]
+    );
+
+    test!(
+        test_with_later_lines_offsets_ranges,
+        "%(name)%n%(cpu)%n%(mem)",
+        [("mem", "41")],
+        vec![OutputEdit {
+            range: 10..11,
+            text: "1".into()
+        }]
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_added_lines_appends_lines,
+        "%(for:row in rows)%(row)%n%(end)",
+        [("rows", "a\u{1f}b\u{1f}c")],
+        vec![OutputEdit {
+            range: 4..4,
+            text: "c\n".into()
+        }]
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_removed_lines_removes_lines,
+        "%(for:row in rows)%(row)%n%(end)",
+        [("rows", "a")],
+        vec![OutputEdit {
+            range: 2..4,
+            text: "".into()
+        }]
+    );
+}
+
+#[cfg(all(test, feature = "ratatui"))]
+mod tests_template_region {
+    use crate::*;
+    use ratatui::buffer::Buffer;
+    use ratatui::layout::Rect;
+    use ratatui::style::{Color, Style};
+
+    fn key_value() -> HashMap<&'static str, String> {
+        let mut key_value = HashMap::new();
+        key_value.insert("name", "Alice".into());
+        key_value.insert("cpu", "12".into());
+        key_value.insert("city", "東京".into());
+        key_value
+    }
+
+    #[test]
+    fn test_with_first_render_writes_all_cells() {
+        let area = Rect::new(0, 0, 8, 2);
+        let mut buf = Buffer::empty(area);
+        let mut region = TemplateRegion::new("%(name)%n%>(*)%(cpu)", area);
+        let written = region.render(&Formatify::new(), &key_value(), &mut buf);
+        assert_eq!(written, 16);
+        assert_eq!(buf, Buffer::with_lines(["Alice   ", "      12"]));
+    }
+
+    #[test]
+    fn test_with_changed_value_writes_changed_cells_only() {
+        let area = Rect::new(0, 0, 8, 2);
+        let mut buf = Buffer::empty(area);
+        let mut region = TemplateRegion::new("%(name)%n%>(*)%(cpu)", area);
+        let formatter = Formatify::new();
+        let mut key_value = key_value();
+        region.render(&formatter, &key_value, &mut buf);
+
+        assert_eq!(region.render(&formatter, &key_value, &mut buf), 0);
+        key_value.insert("name", "Alma".into());
+        key_value.insert("cpu", "7".into());
+        assert_eq!(region.render(&formatter, &key_value, &mut buf), 5);
+        assert_eq!(buf, Buffer::with_lines(["Alma    ", "       7"]));
+    }
+
+    #[test]
+    fn test_with_invalidate_repaints_all_cells() {
+        let area = Rect::new(0, 0, 8, 1);
+        let mut buf = Buffer::empty(area);
+        let mut region = TemplateRegion::new("%(name)", area);
+        let formatter = Formatify::new();
+        region.render(&formatter, &key_value(), &mut buf);
+        region.invalidate();
+        assert_eq!(region.render(&formatter, &key_value(), &mut buf), 8);
+    }
+
+    #[test]
+    fn test_with_offset_area_clips_lines_and_chars() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 6, 3));
+        let mut region = TemplateRegion::new("%(name)%n%(cpu)%nhidden", Rect::new(2, 1, 3, 2));
+        region.render(&Formatify::new(), &key_value(), &mut buf);
+        assert_eq!(buf, Buffer::with_lines(["      ", "  Ali ", "  12  "]));
+    }
+
+    #[test]
+    fn test_with_wide_chars_occupies_two_cells() {
+        let area = Rect::new(0, 0, 7, 1);
+        let mut buf = Buffer::empty(area);
+        let mut region = TemplateRegion::new("%<(5)%(city)|", area);
+        region.render(&Formatify::new(), &key_value(), &mut buf);
+        assert_eq!(buf, Buffer::with_lines(["東京 | "]));
+    }
+
+    #[test]
+    fn test_with_base_style_styles_all_cells() {
+        let area = Rect::new(0, 0, 3, 1);
+        let mut buf = Buffer::empty(area);
+        let style = Style::default().bg(Color::Blue);
+        let mut region = TemplateRegion::new("%(cpu)", area).style(style);
+        region.render(&Formatify::new(), &key_value(), &mut buf);
+        assert_eq!(buf[(0, 0)].bg, Color::Blue);
+        assert_eq!(buf[(2, 0)].bg, Color::Blue);
+    }
+
+    #[cfg(feature = "colors")]
+    #[test]
+    fn test_with_color_placeholders_styles_subsequent_cells() {
+        let area = Rect::new(0, 0, 6, 1);
+        let mut buf = Buffer::empty(area);
+        let mut region = TemplateRegion::new("%C(bold red)%(cpu)%C(reset)|%(cpu)", area);
+        region.render(&Formatify::new(), &key_value(), &mut buf);
+        assert_eq!(buf[(0, 0)].symbol(), "1");
+        assert_eq!(buf[(0, 0)].fg, Color::Red);
+        assert!(buf[(1, 0)]
+            .modifier
+            .contains(ratatui::style::Modifier::BOLD));
+        assert_eq!(buf[(2, 0)].symbol(), "|");
+        assert_eq!(buf[(2, 0)].fg, Color::Reset);
+        assert!(!buf[(3, 0)]
+            .modifier
+            .contains(ratatui::style::Modifier::BOLD));
+    }
+
+    #[cfg(feature = "colors")]
+    #[test]
+    fn test_with_changed_style_writes_restyled_cells() {
+        let area = Rect::new(0, 0, 2, 1);
+        let mut buf = Buffer::empty(area);
+        let template = "%(?hot)%C(red)%(:else)%C(green)%(/hot)%(cpu)";
+        let mut region = TemplateRegion::new(template, area);
+        let formatter = Formatify::new();
+        let mut key_value = key_value();
+        region.render(&formatter, &key_value, &mut buf);
+        key_value.insert("hot", "yes".into());
+        assert_eq!(region.render(&formatter, &key_value, &mut buf), 2);
+        assert_eq!(buf[(1, 0)].fg, Color::Red);
+    }
+}
+
+#[cfg(test)]
+mod tests_length_placeholder {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("title", "Release notes".into());
+                key_value.insert("city", "東京".into());
+                key_value.insert("empty", "".into());
+                let parser = Formatify::new();
+                let out = parser.replace_placeholders(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_key_renders_char_count,
+        "%(title) (%(#title)) %(#city) %(#empty)",
+        "Release notes (13) 2 0"
+    );
+
+    test!(test_with_format_aligns_length, "[%>(4)%(#title)]", "[  13]");
+
+    test!(
+        test_with_missing_key_keeps_placeholder_unchanged,
+        "%(#missing) %(#) %(#title",
+        "%(#missing) %(#) %(#title"
+    );
+
+    #[cfg(feature = "tables")]
+    #[test]
+    fn test_with_monospace_target_renders_display_width() {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("city", "東京".into());
+        let parser = Formatify::new();
+        let out = parser.replace_placeholders_code_block(&key_value, &["%(#city)"]);
+        assert_eq!(out, "```\n4\n```");
+    }
+}
+
+#[cfg(test)]
+mod tests_alignment_anchors {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("id", "42".into());
+                key_value.insert("author", "Alice".into());
+                key_value.insert("x", "1".into());
+                key_value.insert("long", "12345".into());
+                let parser = Formatify::new();
+                let out = parser.replace_placeholders(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_anchors_on_different_lines_aligns_columns,
+        "id%|(sep): %(id)%nauthor%|(sep): %(author)",
+        "id    : 42\nauthor: Alice"
+    );
+
+    test!(
+        test_with_several_anchor_names_on_same_line_aligns_each,
+        "%(x)%|(a)=%(long)%|(b);%n%(long)%|(a)=%(x)%|(b);",
+        "1    =12345;\n12345=1    ;"
+    );
+
+    test!(
+        test_with_single_anchor_leaves_output_unchanged,
+        "%(id)%|(sep): %(author)",
+        "42: Alice"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_anchor_in_inactive_block_is_ignored,
+        "%(?missing)author%|(sep)%(/missing)%nid%|(sep):",
+        "\nid:"
+    );
+
+    test!(
+        test_with_anchor_after_format_placeholder_uses_padded_column,
+        "%<(6)%(id)%|(c)|%n%(author)%|(c)|",
+        "42    |\nAlice |"
+    );
+
+    test!(
+        test_with_invalid_anchor_keeps_placeholder_unchanged,
+        "%|sep %|(sep",
+        "%|sep %|(sep"
+    );
+
+    test!(
+        test_with_repeated_anchor_on_same_line_terminates,
+        "a%|(x)b%|(x)c",
+        "a  bc"
+    );
+
+    #[test]
+    fn test_with_translated_labels_aligns_per_locale() {
+        let template = "%(l_name)%|(l): %(name)%n%(l_date)%|(l): %(date)";
+        let parser = Formatify::new();
+        let render = |labels: [(&'static str, &str); 2]| {
+            let mut key_value: HashMap<&str, String> = labels
+                .iter()
+                .map(|(key, label)| (*key, label.to_string()))
+                .collect();
+            key_value.insert("name", "Alice".into());
+            key_value.insert("date", "2024-05-01".into());
+            parser.replace_placeholders(&key_value, template)
+        };
+
+        let en = render([("l_name", "Name"), ("l_date", "Date")]);
+        assert_eq!(en, "Name: Alice\nDate: 2024-05-01");
+        let de = render([("l_name", "Name"), ("l_date", "Erstellungsdatum")]);
+        assert_eq!(de, "Name            : Alice\nErstellungsdatum: 2024-05-01");
+    }
+
+    #[test]
+    fn test_with_anchors_measure_lengths_includes_padding() {
+        let key_value = HashMap::<&str, String>::new();
+        let parser = Formatify::new();
+        let lengths = parser.measure_lengths(&key_value, "id%|(sep):%nauthor%|(sep):");
+        assert_eq!(lengths, vec![15]);
+    }
+
+    #[test]
+    fn test_with_anchors_extract_placeholder_keys_ignores_anchors() {
+        let parser = Formatify::new();
+        let keys = parser.extract_placeholder_keys("%(id)%|(sep): %(author)");
+        assert_eq!(keys, vec!["id", "author"]);
+    }
+}
+
+#[cfg(test)]
+mod tests_whitespace_prefix {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("subject", "Fix parser".into());
+                key_value.insert("body", "Details".into());
+                key_value.insert("trailers", "Signed-off-by: Alice".into());
+                key_value.insert("paragraph", "Details\n".into());
+                key_value.insert("empty", "".into());
+                key_value.insert("4", "".into());
+                let parser = Formatify::new();
+                let out = parser.replace_placeholders(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_line_feed_prefix_and_value_inserts_line_feed,
+        "%(subject)%+(body)",
+        "Fix parser\nDetails"
+    );
+
+    test!(
+        test_with_line_feed_prefix_and_empty_value_inserts_nothing,
+        "%(subject)%+(empty)|",
+        "Fix parser|"
+    );
+
+    test!(
+        test_with_space_prefix_inserts_space_for_non_empty_value,
+        "[%(subject)% (body)% (empty)]",
+        "[Fix parser Details]"
+    );
+
+    test!(
+        test_with_remove_prefix_and_empty_value_removes_line_feeds,
+        "%(subject)%n%n%-(empty)|",
+        "Fix parser|"
+    );
+
+    test!(
+        test_with_remove_prefix_and_value_keeps_line_feeds,
+        "%(subject)%n%n%-(trailers)",
+        "Fix parser\n\nSigned-off-by: Alice"
+    );
+
+    test!(
+        test_with_remove_prefix_keeps_line_feeds_of_values,
+        "%(paragraph)%-(empty)|",
+        "Details\n|"
+    );
+
+    test!(
+        test_with_prefix_and_missing_key_keeps_placeholder_unchanged,
+        "%(subject)%+(missing)%n%-(missing)",
+        "Fix parser%+(missing)\n%-(missing)"
+    );
+
+    test!(
+        test_with_rule_placeholder_is_not_a_prefix,
+        "%-(3)%-(3,'=')",
+        "---==="
+    );
+
+    test!(
+        test_with_numeric_key_is_a_rule_width,
+        "%(subject)%n%-(4)|%+(4)|%-(4th)",
+        "Fix parser\n----||%-(4th)"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_prefix_in_inactive_block_is_ignored,
+        "%(subject)%n%(?missing)%-(empty)%(/missing)|",
+        "Fix parser\n|"
+    );
+
+    #[test]
+    fn test_with_prefix_extract_placeholder_keys_returns_keys() {
+        let parser = Formatify::new();
+        let keys = parser.extract_placeholder_keys("%+(body)% (note)%-(trailers)");
+        assert_eq!(keys, vec!["body", "note", "trailers"]);
+    }
+}
+
+#[cfg(test)]
+mod tests_replace_placeholders_with_policy {
+    use crate::*;
+
+    fn policy(key: &str, value: &str) -> KeyAccess {
+        match key {
+            "salary" => KeyAccess::Mask("***".into()),
+            "ssn" => KeyAccess::Deny,
+            _ if value.contains("secret") => KeyAccess::Deny,
+            _ => KeyAccess::Allow,
+        }
+    }
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("name", "Alice".into());
+                key_value.insert("salary", "85000".into());
+                key_value.insert("ssn", "123-45-6789".into());
+                key_value.insert("note", "top secret".into());
+                let parser = Formatify::new();
+                let out = parser.replace_placeholders_with_policy(&key_value, $inp, &policy);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_masked_key_renders_mask,
+        "%(name): %>(6)%(salary)",
+        "Alice:    ***"
+    );
+
+    test!(
+        test_with_denied_key_renders_as_missing,
+        "%(ssn) %(ssn:-hidden) %(ssn|name)",
+        "%(ssn) hidden Alice"
+    );
+
+    test!(
+        test_with_policy_based_on_value_denies_value,
+        "[%(note:-)]",
+        "[]"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_denied_key_takes_else_branch,
+        "%(?ssn)ssn%(:else)no ssn%(/ssn)",
+        "no ssn"
+    );
+
+    test!(
+        test_with_masked_key_aligns_anchors_by_mask,
+        "%(salary)%|(a)|%n%(name)%|(a)|",
+        "***  |\nAlice|"
+    );
+
+    #[test]
+    fn test_with_char_placeholder_sees_filtered_values() {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("salary", "85000".into());
+        key_value.insert("ssn", "123-45-6789".into());
+        let mut parser = Formatify::new();
+        parser
+            .register_char_placeholder('v', |kv| {
+                format!("{}/{}", kv["salary"], kv.contains_key("ssn"))
+            })
+            .unwrap();
+        let out = parser.replace_placeholders_with_policy(&key_value, "%v", &policy);
+        assert_eq!(out, "***/false");
+    }
+
+    #[test]
+    fn test_with_recursive_expansion_applies_policy_to_nested_keys() {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("summary", "earns %(salary)".into());
+        key_value.insert("salary", "85000".into());
+        let parser = Formatify::builder().recursive_expansion(2).build();
+        let out = parser.replace_placeholders_with_policy(&key_value, "%(summary)", &policy);
+        assert_eq!(out, "earns ***");
+    }
+
+    #[test]
+    fn test_without_policy_restrictions_equals_replace_placeholders() {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("name", "Alice".into());
+        let parser = Formatify::new();
+        let inp = "%<(8)%(name)|%(missing)";
+        let out =
+            parser.replace_placeholders_with_policy(&key_value, inp, &|_, _| KeyAccess::Allow);
+        assert_eq!(out, parser.replace_placeholders(&key_value, inp));
+    }
+}
+
+#[cfg(test)]
+mod tests_line_wrap {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("text", "the quick brown fox jumps over the lazy dog".into());
+                key_value.insert("word", "abcdefghijklmnop".into());
+                let parser = Formatify::new();
+                let out = parser.replace_placeholders(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(
+        test_with_width_wraps_at_last_space,
+        "%w(15)%(text)",
+        "the quick brown\nfox jumps over\nthe lazy dog"
+    );
+
+    test!(
+        test_with_indents_indents_first_and_continuation_lines,
+        "%w(16,2,4)%(text)",
+        "  the quick\n    brown fox\n    jumps over\n    the lazy dog"
+    );
+
+    test!(
+        test_with_zero_width_only_indents,
+        "%w(0,2,4)first%nsecond",
+        "  first\n    second"
+    );
+
+    test!(
+        test_with_literal_text_wraps_across_values,
+        "%w(12)Message: %(text)",
+        "Message: the\nquick brown\nfox jumps\nover the\nlazy dog"
+    );
+
+    test!(
+        test_with_long_word_is_not_broken,
+        "%w(10)a %(word) b",
+        "a\nabcdefghijklmnop\nb"
+    );
+
+    test!(
+        test_with_hard_spaces_does_not_break_at_them,
+        "%w(10)a%_b%_c%_d%_e%_f g",
+        "a b c d e f\ng"
+    );
+
+    test!(
+        test_with_wrap_switched_off_stops_wrapping,
+        "%w(10)%(text)%w(0) %(text)",
+        "the quick\nbrown fox\njumps over\nthe lazy\ndog the quick brown fox jumps over the lazy dog"
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_wrap_in_inactive_block_is_ignored,
+        "%(?missing)%w(10)%(/missing)%(word) %(word)",
+        "abcdefghijklmnop abcdefghijklmnop"
+    );
+
+    test!(
+        test_with_invalid_wrap_renders_unchanged,
+        "%w(x)%w(10",
+        "%w(x)%w(10"
+    );
+
+    test!(
+        test_with_line_feed_placeholder_forgets_space_of_previous_line,
+        "%w(6)ab cd%nxyzxyzxyz",
+        "ab cd\nxyzxyzxyz"
+    );
+
+    #[cfg(feature = "tables")]
+    #[test]
+    fn test_with_wrap_in_code_block_wraps_at_display_width() {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("wide", "日本語 日本語 日本語".into());
+        let parser = Formatify::new();
+        let out = parser.replace_placeholders_code_block(
+            &key_value,
+            &["%w(10,2,4)%(wide)", "%w(8)ab cd%nefghijkl mn"],
+        );
+        assert_eq!(
+            out,
+            "```\n  日本語\n    日本語\n    日本語\nab cd\nefghijkl\nmn\n```"
+        );
+    }
+
+    #[cfg(feature = "html")]
+    #[test]
+    fn test_with_wrapped_value_html_spans_follow_line_breaks() {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("a", "aaaa".into());
+        key_value.insert("b", "bb".into());
+        let parser = Formatify::new();
+        let out = parser.replace_placeholders_html(&key_value, "%w(6,0,2)%(a) %(b)");
+        assert_eq!(
+            out,
+            "<span class=\"fmt-value\" data-key=\"a\">aaaa</span>\n  <span class=\"fmt-value\" data-key=\"b\">bb</span>"
+        );
+    }
+
+    #[test]
+    fn test_with_wrapped_value_source_map_skips_indentation() {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("a", "aaaa bb".into());
+        let parser = Formatify::new();
+        let (out, source_map) =
+            parser.replace_placeholders_with_source_map(&key_value, "%w(5,0,1)%(a)");
+        assert_eq!(out, "aaaa\n bb");
+        let outputs: Vec<_> = source_map
+            .iter()
+            .map(|entry| entry.output.clone())
+            .collect();
+        assert_eq!(outputs, vec![0..5, 6..8]);
+    }
+}
+
+#[cfg(test)]
+mod tests_audit {
+    use crate::*;
+
+    fn entry(key: &str, source: ValueSource, value: &str) -> AuditEntry {
+        AuditEntry {
+            key: key.into(),
+            source,
+            value: value.into(),
+            transformed: false,
+            truncated: false,
+        }
+    }
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr, $expected_audit:expr) => {
+            test!(
+                $test_name,
+                Formatify::new(),
+                $inp,
+                $expected_output,
+                $expected_audit
+            );
+        };
+        ($test_name:ident, $parser:expr, $inp:expr, $expected_output:expr, $expected_audit:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("name", "Alice".into());
+                key_value.insert("items", join_list(["a", "b"]));
+                key_value.insert("amount", "1234.5".into());
+                let (out, audit) = $parser.replace_placeholders_with_audit(&key_value, $inp);
+                assert_eq!(out, $expected_output);
+                assert_eq!(audit, $expected_audit);
+            }
+        };
     }
 
-    fn measure_lengths(&self, key_value: &HashMap<&str, String>, inp: &str) -> Vec<usize> {
-        self.parse_generic::<ParsingTaskMeasureLengths>(key_value, inp)
+    test!(
+        test_with_value_lists_key_value_source,
+        "Hi %(name)!",
+        "Hi Alice!",
+        vec![entry("name", ValueSource::KeyValue, "Alice")]
+    );
+
+    test!(
+        test_with_fallback_key_lists_providing_key,
+        "%(nick|name)",
+        "Alice",
+        vec![entry("name", ValueSource::KeyValue, "Alice")]
+    );
+
+    test!(
+        test_with_missing_key_lists_nothing,
+        "%(missing)",
+        "%(missing)",
+        Vec::<AuditEntry>::new()
+    );
+
+    test!(
+        test_with_default_lists_default_source,
+        "%(missing:-none)",
+        "none",
+        vec![entry("missing", ValueSource::Default, "none")]
+    );
+
+    test!(
+        test_with_truncation_marks_entry_truncated,
+        "%<(3,trunc)%(name)",
+        "Al…",
+        vec![AuditEntry {
+            truncated: true,
+            ..entry("name", ValueSource::KeyValue, "Alice")
+        }]
+    );
+
+    test!(
+        test_with_numeric_format_marks_entry_transformed,
+        "%(amount,num=,.2)",
+        "1,234.50",
+        vec![AuditEntry {
+            transformed: true,
+            ..entry("amount", ValueSource::KeyValue, "1234.5")
+        }]
+    );
+
+    test!(
+        test_with_list_index_lists_selected_item,
+        "%(items[1])",
+        "b",
+        vec![entry("items", ValueSource::KeyValue, "b")]
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_loop_lists_binding_source,
+        "%(for:item in items)%(item)%(end)",
+        "ab",
+        vec![
+            entry("item", ValueSource::Binding, "a"),
+            entry("item", ValueSource::Binding, "b"),
+        ]
+    );
+
+    test!(
+        test_with_length_lists_resolver_source,
+        "%(#name)",
+        "5",
+        vec![entry("#name", ValueSource::Resolver("#".into()), "5")]
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_inactive_block_lists_only_rendered_values,
+        "%(?missing)%(name)%(:else)%(amount)%(/missing)",
+        "1234.5",
+        vec![entry("amount", ValueSource::KeyValue, "1234.5")]
+    );
+
+    test!(
+        test_with_ambient_value_lists_ambient_source,
+        {
+            let mut parser = Formatify::new();
+            parser.set_ambient("tenant", "acme");
+            parser
+        },
+        "%(tenant)",
+        "acme",
+        vec![entry("tenant", ValueSource::Ambient, "acme")]
+    );
+
+    #[test]
+    fn test_with_builtin_lists_builtin_source() {
+        let key_value = HashMap::<&str, String>::new();
+        let parser = Formatify::builder().seed(7).build();
+        let (out, audit) = parser.replace_placeholders_with_audit(&key_value, "%(rand)");
+        assert_eq!(audit, vec![entry("rand", ValueSource::Builtin, &out)]);
     }
 
-    fn extract_placeholder_keys(&self, inp: &str) -> Vec<String> {
+    #[test]
+    fn test_with_char_placeholder_lists_resolver_source() {
         let key_value = HashMap::<&str, String>::new();
-        self.parse_generic::<ParsingTaskExtractPlaceholderKeys>(&key_value, inp)
+        let mut parser = Formatify::new();
+        parser
+            .register_char_placeholder('d', |_| "2024-01-01".into())
+            .unwrap();
+        let (_, audit) = parser.replace_placeholders_with_audit(&key_value, "%d");
+        assert_eq!(
+            audit,
+            vec![entry(
+                "%d",
+                ValueSource::Resolver("%d".into()),
+                "2024-01-01"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_with_recursive_expansion_keeps_source_of_key() {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("greeting", "Hi %(name)".into());
+        key_value.insert("name", "Bob".into());
+        let parser = Formatify::builder().recursive_expansion(2).build();
+        let (out, audit) = parser.replace_placeholders_with_audit(&key_value, "%(greeting)");
+        assert_eq!(out, "Hi Bob");
+        assert_eq!(
+            audit,
+            vec![entry("greeting", ValueSource::KeyValue, "Hi Bob")]
+        );
     }
 }
 
-impl Default for Formatify {
-    fn default() -> Self {
-        Self::new()
+#[cfg(test)]
+mod tests_replace_placeholders_checked {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr, $expected_missing:expr) => {
+            #[test]
+            fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("name", "Alice".into());
+                key_value.insert("items", join_list(["a", "b"]));
+                let parser = Formatify::new();
+                let (out, missing) = parser.replace_placeholders_checked(&key_value, $inp);
+                assert_eq!(out, parser.replace_placeholders(&key_value, $inp));
+                assert_eq!(out, $expected_output);
+                let expected: Vec<(String, usize)> = $expected_missing
+                    .into_iter()
+                    .map(|(key, count): (&str, usize)| (key.to_string(), count))
+                    .collect();
+                assert_eq!(missing, expected);
+            }
+        };
+    }
+
+    test!(
+        test_without_missing_keys_returns_empty_list,
+        "%(name) %(nick|name) %(city:-?)",
+        "Alice Alice ?",
+        Vec::<(&str, usize)>::new()
+    );
+
+    test!(
+        test_with_missing_keys_counts_them_in_order_of_appearance,
+        "%(zip)%(city)%<(4)%(zip)",
+        "%(zip)%(city)%(zip)",
+        vec![("zip", 2), ("city", 1)]
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_missing_key_in_loop_counts_each_iteration,
+        "%(for:item in items)%(item)%(price)%(end)",
+        "a%(price)b%(price)",
+        vec![("price", 2)]
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_missing_key_in_inactive_block_ignores_it,
+        "%(?city)%(zip)%(/city)",
+        "",
+        Vec::<(&str, usize)>::new()
+    );
+
+    #[test]
+    fn test_with_missing_key_policy_still_lists_key() {
+        let parser = Formatify::builder()
+            .missing_key(MissingKeyPolicy::Empty)
+            .build();
+        let (out, missing) = parser.replace_placeholders_checked(&HashMap::new(), "[%(city)]");
+        assert_eq!(out, "[]");
+        assert_eq!(missing, vec![("city".to_string(), 1)]);
     }
 }
 
 #[cfg(test)]
-mod tests_extract_placeholder_keys {
+mod tests_block_indent {
     use crate::*;
 
     macro_rules! test {
         ($test_name:ident, $inp:expr, $expected_output:expr) => {
             #[test]
             fn $test_name() {
+                let mut key_value = HashMap::<&str, String>::new();
+                key_value.insert("body", "first\nsecond\nthird".into());
+                key_value.insert("name", "Alice".into());
+                key_value.insert("empty", "".into());
                 let parser = Formatify::new();
-                let out_str = parser.extract_placeholder_keys($inp);
-                assert_eq!(out_str, $expected_output);
+                let out = parser.replace_placeholders(&key_value, $inp);
+                assert_eq!(out, $expected_output);
             }
         };
     }
 
     test!(
-        test_with_empty_input_returns_empty_vec,
-        "",
-        Vec::<String>::new()
+        test_with_count_indents_each_line,
+        "Body:%n%I(4)%(body)%nend",
+        "Body:\n    first\n    second\n    third\nend"
     );
 
     test!(
-        test_with_plain_string_returns_empty_vec,
-        "Conventional string",
-        Vec::<String>::new()
+        test_with_prefix_quotes_each_line,
+        "%I(\"> \")%(body)",
+        "> first\n> second\n> third"
     );
 
     test!(
-        test_with_unicode_string_returns_empty_vec,
-        "Smiley 😊 Smiley",
-        Vec::<String>::new()
+        test_with_single_line_value_prefixes_it,
+        "%I(2)%(name)|",
+        "  Alice|"
     );
 
     test!(
-        test_with_single_placeholder_returns_one_placeholder,
-        "Hello %(var1)", // replaces to "Hello world"
-        vec!["var1"]
+        test_with_alignment_prefix_precedes_field,
+        "%I(\"# \")%>(7)%(name)|",
+        "#   Alice|"
     );
 
     test!(
-        test_with_multiple_placeholders_return_two_placeholders,
-        "Hello %(var1). Hallo %(var2).", // "Hello world. Hallo welt."
-        vec!["var1", "var2"]
+        test_with_default_value_indents_it,
+        "%I(2)%(missing:-none)",
+        "  none"
     );
 
+    test!(test_with_empty_value_adds_nothing, "[%I(4)%(empty)]", "[]");
+
     test!(
-        test_with_undefined_second_placeholder_returns_two_placeholders,
-        "Hallo %(var1)%(vara)",
-        vec!["var1", "vara"]
+        test_with_subsequent_value_is_not_indented,
+        "%I(2)%(name) %(body)",
+        "  Alice first\nsecond\nthird"
     );
 
     test!(
-        test_with_incomplete_placeholder_syntax_returns_empty_vec,
-        "Hallo %(var1",
-        Vec::<String>::new()
+        test_with_invalid_indent_renders_unchanged,
+        "%I(x)%I(\"> )",
+        "%I(x)%I(\"> )"
     );
 }
 
 #[cfg(test)]
-mod tests_measure_lengths {
-    use std::collections::HashMap;
-
+mod tests_justify {
     use crate::*;
 
     macro_rules! test {
@@ -531,357 +9102,1014 @@ mod tests_measure_lengths {
             #[test]
             fn $test_name() {
                 let mut key_value = HashMap::<&str, String>::new();
-                key_value.insert("var1", "world".into());
-                key_value.insert("var2", "welt".into());
-                key_value.insert("str4", "1234".into());
-                key_value.insert("str10", "1234567890".into());
-                key_value.insert("str14", "1234567890ABCD".into());
-                key_value.insert("umlaute", "äöü".into());
-                key_value.insert("umlaute_bigger", "äöü12345678".into());
+                key_value.insert("short", "a bc d".into());
+                key_value.insert("word", "single".into());
+                key_value.insert("text", "the quick brown fox jumps over the lazy dog".into());
+                key_value.insert("spaced", "  a \n b  ".into());
+                key_value.insert("long", "a b longest c".into());
+                key_value.insert("lines", "one\ntwo\nthree".into());
                 let parser = Formatify::new();
-                let out_str = parser.measure_lengths(&key_value, $inp);
-                assert_eq!(out_str, $expected_output);
+                let out = parser.replace_placeholders(&key_value, $inp);
+                assert_eq!(out, $expected_output);
             }
         };
     }
 
-    test!(test_with_empty_input_returns_vec0, "", vec![0usize]);
     test!(
-        test_with_plain_string_returns_correct_length,
-        "Conventional string",
-        vec![19usize]
+        test_with_short_value_widens_gaps_to_width,
+        "[%<(10,justify)%(short)]",
+        "[a   bc   d]"
     );
 
     test!(
-        test_with_unicode_string_returns_correct_length,
-        "Smiley 😊 Smiley",
-        vec![15usize]
+        test_with_uneven_extra_widens_left_gaps_first,
+        "[%<(9,justify)%(short)]",
+        "[a   bc  d]"
     );
 
     test!(
-        test_with_single_placeholder_measures_correctly,
-        "Hello %(var1)", // replaces to "Hello world"
-        vec![11usize, 5usize]
+        test_with_single_word_pads_it,
+        "[%<(8,justify)%(word)]",
+        "[single  ]"
     );
 
     test!(
-        test_with_invalid_token_type_counts_length_of_unreplaced_string,
-        "Hallo %z", // replaces nothing -> "Hallo %z"
-        vec![8usize]
+        test_with_whitespace_runs_collapses_them,
+        "[%<(5,justify)%(spaced)]",
+        "[a   b]"
     );
 
     test!(
-        test_with_one_char_token_type_counts_length_of_replaced_char,
-        "abcde %%", // replaces nothing -> "abcde %"
-        vec![7usize]
+        test_with_long_value_renders_justified_block,
+        "> %<(16,justify)%(text)|",
+        "> the  quick brown\n  fox  jumps  over\n  the lazy dog    |"
     );
 
     test!(
-        test_with_multiple_placeholders_return_correct_length_of_string_and_placeholders,
-        "Hello %(var1). Hallo %(var2).", // "Hello world. Hallo welt."
-        vec![24usize, 5usize, 4usize]
+        test_with_long_word_truncates_it,
+        "[%<(4,justify)%(word)]",
+        "[sin…]"
     );
 
     test!(
-        test_with_undefined_second_placeholder_return_correct_length_of_string_and_placeholders,
-        "Hallo %(var1)%(vara)", // "Hallo world%(vara)"
-        vec![18usize, 5usize]
+        test_with_long_word_in_block_truncates_it_on_its_own_line,
+        "[%<(5,justify)%(long)]",
+        "[a   b\n long…\n c    ]"
     );
 
     test!(
-        test_with_left_alignment_placeholder_and_shorter_value_returns_correct_length,
-        "Hallo %<(10)%(str4)xx", // "Hallo 1234      xx"
-        vec![18usize, 10usize]
+        test_with_multi_line_value_justifies_its_words,
+        "[%<(8,justify)%(lines)]",
+        "[one  two\n three   ]"
     );
 
     test!(
-        test_with_right_alignment_placeholder_and_shorter_returns_correct_length,
-        "Hallo %>(10)%(str4)xx", // "Hallo       1234xx"
-        vec![18usize, 10usize]
+        test_with_missing_value_renders_unchanged,
+        "%<(10,justify)%(missing)",
+        "%(missing)"
+    );
+
+    test!(
+        test_with_right_justify_is_invalid,
+        "%>(10,justify)%(short)",
+        "%>(10,justify)a bc d"
+    );
+
+    #[test]
+    fn test_with_justify_preset_justifies_value() {
+        let mut key_value = HashMap::<&str, String>::new();
+        key_value.insert("short", "a bc d".into());
+        let mut parser = Formatify::new();
+        parser.register_preset("block", "10,justify").unwrap();
+        let out = parser.replace_placeholders(&key_value, "%<(preset:block)%(short)|");
+        assert_eq!(out, "a   bc   d|");
+    }
+}
+
+#[cfg(test)]
+mod tests_parse {
+    use crate::*;
+
+    fn literal(text: &str, span: std::ops::Range<usize>) -> Segment {
+        Segment::Literal {
+            text: text.into(),
+            span,
+        }
+    }
+
+    fn key(key: &str, format: Option<&str>, span: std::ops::Range<usize>) -> Segment {
+        Segment::KeyPlaceholder {
+            key: key.into(),
+            format: format.map(String::from),
+            span,
+        }
+    }
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let parser = Formatify::new();
+                let out = parser.parse($inp);
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(test_with_empty_template_returns_no_segments, "", vec![]);
+
+    test!(
+        test_with_literal_and_key_returns_both,
+        "Hi %(name)!",
+        vec![
+            literal("Hi ", 0..3),
+            key("name", None, 3..10),
+            literal("!", 10..11)
+        ]
+    );
+
+    test!(
+        test_with_format_returns_spec_of_key,
+        "%>(4,trunc)%(id)",
+        vec![
+            Segment::Directive { span: 0..11 },
+            key("id", Some(">4,trunc"), 11..16)
+        ]
+    );
+
+    test!(
+        test_with_char_placeholders_names_them,
+        "%n%%%x41",
+        vec![
+            Segment::CharPlaceholder {
+                ch: 'n',
+                span: 0..2
+            },
+            Segment::CharPlaceholder {
+                ch: '%',
+                span: 2..4
+            },
+            Segment::CharPlaceholder {
+                ch: 'x',
+                span: 4..8
+            }
+        ]
+    );
+
+    test!(
+        test_with_rule_returns_single_directive,
+        "%-(3)a",
+        vec![Segment::Directive { span: 0..5 }, literal("a", 5..6)]
+    );
+
+    test!(
+        test_with_invalid_placeholder_returns_error,
+        "a%<(x)b",
+        vec![
+            literal("a", 0..1),
+            Segment::Error { span: 1..4 },
+            literal("x)b", 4..7)
+        ]
+    );
+
+    #[cfg(feature = "blocks")]
+    test!(
+        test_with_block_returns_keys_of_all_branches,
+        "%(?a)x%(else)%(b)%(end)",
+        vec![
+            Segment::Directive { span: 0..5 },
+            literal("x", 5..6),
+            Segment::Directive { span: 6..13 },
+            key("b", None, 13..17),
+            Segment::Directive { span: 17..23 }
+        ]
+    );
+
+    test!(
+        test_with_length_placeholder_returns_directive,
+        "%(#title)",
+        vec![Segment::Directive { span: 0..9 }]
+    );
+
+    #[test]
+    fn test_with_default_spec_returns_it() {
+        let mut parser = Formatify::new();
+        parser.default_spec("sha", "<8,trunc").unwrap();
+        assert_eq!(
+            parser.parse("%(sha)"),
+            vec![key("sha", Some("<8,trunc"), 0..6)]
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests_annotate {
+    use crate::*;
+
+    #[test]
+    fn test_with_multi_byte_literals_returns_byte_ranges() {
+        let parser = Formatify::new();
+        let annotations = parser.annotate("äö%(näme)ü%n");
+        assert_eq!(
+            annotations,
+            vec![
+                Annotation {
+                    segment: Segment::KeyPlaceholder {
+                        key: "näme".into(),
+                        format: None,
+                        span: 2..9,
+                    },
+                    bytes: 4..12,
+                },
+                Annotation {
+                    segment: Segment::CharPlaceholder {
+                        ch: 'n',
+                        span: 10..12
+                    },
+                    bytes: 14..16,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_with_invalid_placeholder_returns_error_range() {
+        let parser = Formatify::new();
+        let inp = "ok %(a) %<(x";
+        let errors: Vec<_> = parser
+            .annotate(inp)
+            .into_iter()
+            .filter(|annotation| matches!(annotation.segment, Segment::Error { .. }))
+            .map(|annotation| &inp[annotation.bytes])
+            .collect();
+        assert_eq!(errors, vec!["%<("]);
+    }
+
+    #[test]
+    fn test_without_placeholders_returns_empty_list() {
+        let parser = Formatify::new();
+        assert_eq!(parser.annotate("plain text"), vec![]);
+    }
+}
+
+#[cfg(test)]
+mod tests_extract_placeholders {
+    use crate::*;
+
+    fn info(key: &str, format: Option<&str>, span: std::ops::Range<usize>) -> PlaceholderInfo {
+        PlaceholderInfo {
+            key: key.into(),
+            format: format.map(Into::into),
+            span,
+        }
+    }
+
+    #[test]
+    fn test_with_default_spec_returns_it_as_format() {
+        let mut parser = Formatify::new();
+        parser.default_spec("sha", ">7,ltrunc").unwrap();
+        let placeholders = parser.extract_placeholders("%(sha) %<(4)%(sha)");
+        assert_eq!(
+            placeholders,
+            vec![
+                info("sha", Some(">7,ltrunc"), 0..6),
+                info("sha", Some("<4"), 12..18)
+            ]
+        );
+    }
+
+    #[cfg(feature = "blocks")]
+    #[test]
+    fn test_with_blocks_returns_placeholders_of_all_branches() {
+        let parser = Formatify::new();
+        let placeholders = parser.extract_placeholders("%(?a)%(b)%(:else)%(c)%(/a)%(d");
+        assert_eq!(
+            placeholders,
+            vec![info("b", None, 5..9), info("c", None, 17..21)]
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests_validate {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let parser = Formatify::new();
+                let out: Vec<_> = parser
+                    .validate($inp)
+                    .err()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|error| (error.kind, error.span))
+                    .collect();
+                assert_eq!(out, $expected_output);
+            }
+        };
+    }
+
+    test!(test_valid_template, "Hi %<(8,trunc)%(name)%n", vec![]);
+    test!(
+        test_bad_width,
+        "a%<(x)%(b)",
+        vec![(TemplateErrorKind::BadWidth, 1..4)]
+    );
+    test!(
+        test_unknown_argument,
+        "%>(8,foo)%(b)",
+        vec![(TemplateErrorKind::UnknownArgument, 0..9)]
+    );
+    test!(
+        test_unterminated,
+        "ä %(näme",
+        vec![(TemplateErrorKind::Unterminated, 3..10)]
     );
+    test!(
+        test_unknown_char,
+        "%q",
+        vec![(TemplateErrorKind::Malformed, 0..2)]
+    );
+    #[cfg(feature = "blocks")]
+    test!(
+        test_unclosed_block,
+        "%(?a)x",
+        vec![(TemplateErrorKind::UnclosedBlock, 6..6)]
+    );
+    #[cfg(feature = "blocks")]
+    test!(
+        test_error_in_inactive_branch,
+        "%(?a)%<(x)%(/a)",
+        vec![(TemplateErrorKind::BadWidth, 5..8)]
+    );
+
+    #[cfg(feature = "blocks")]
+    #[test]
+    fn test_message() {
+        let parser = Formatify::new();
+        let errors = parser.validate("%(if:a)%(name").unwrap_err();
+        let messages: Vec<_> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "unterminated placeholder '%(name' at bytes 7..13",
+                "block 'a' is not closed at bytes 13..13",
+            ]
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests_equivalent {
+    use crate::*;
+
+    fn samples() -> Vec<HashMap<&'static str, String>> {
+        vec![
+            HashMap::from([("name", "Alice".to_string()), ("n", "2".to_string())]),
+            HashMap::from([("name", "Bob".to_string()), ("n", "1".to_string())]),
+        ]
+    }
+
+    #[test]
+    fn test_with_equivalent_templates_returns_ok() {
+        let parser = Formatify::new();
+        let result = parser.equivalent("%(name)%%%n", "%(name)%x25%x0a", &samples());
+        assert_eq!(result, Ok(()));
+    }
+
+    #[cfg(feature = "blocks")]
+    #[test]
+    fn test_with_differing_sample_returns_first_mismatch() {
+        let parser = Formatify::new();
+        let result = parser.equivalent("%(n) item%(?n>1)s%(/n)", "%(n) items", &samples());
+        assert_eq!(
+            result,
+            Err(TemplateMismatch {
+                sample: 1,
+                left: "1 item".into(),
+                right: "1 items".into(),
+                diff: vec![OutputEdit {
+                    range: 6..6,
+                    text: "s".into()
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn test_without_samples_returns_ok() {
+        let parser = Formatify::new();
+        assert_eq!(parser.equivalent("a", "b", &[]), Ok(()));
+    }
+}
+
+#[cfg(test)]
+mod tests_tokenize {
+    use crate::*;
+
+    macro_rules! test {
+        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+            #[test]
+            fn $test_name() {
+                let out: Vec<_> = tokenize($inp)
+                    .map(|token| (token.kind, token.text))
+                    .collect();
+                let expected: Vec<(TokenKind, &str)> = $expected_output;
+                assert_eq!(out, expected);
+            }
+        };
+    }
 
-    test!(
-        test_with_left_alignment_placeholder_and_exact_length_value_returns_correct_length,
-        "Hallo %<(10)%(str10)xx", // "Hallo 1234567890xx"
-        vec![18usize, 10usize]
-    );
+    use TokenKind::*;
 
-    test!(
-        test_with_right_alignment_placeholder_and_exact_length_value_returns_correct_length,
-        "Hallo %>(10)%(str10)xx", // "Hallo 1234567890xx"
-        vec![18usize, 10usize]
-    );
+    test!(test_with_empty_template_returns_no_tokens, "", vec![]);
 
     test!(
-        test_with_left_alignment_placeholder_and_longer_value_returns_correct_length,
-        "Hallo %<(10)%(str14)xx", // "Hallo 1234567890ABCDxx"
-        vec![22usize, 14usize]
+        test_with_char_placeholders_returns_sigils,
+        "a%n%%%x1b%Cred",
+        vec![
+            (Literal, "a"),
+            (Percent, "%"),
+            (Sigil, "n"),
+            (Percent, "%"),
+            (Sigil, "%"),
+            (Percent, "%"),
+            (Sigil, "x"),
+            (Arg, "1b"),
+            (Percent, "%"),
+            (Sigil, "C"),
+            (Arg, "red")
+        ]
     );
 
     test!(
-        test_with_right_alignment_placeholder_and_longer_value_returns_correct_length,
-        "Hallo %>(10)%(str14)xx", // "Hallo 1234567890ABCDxx"
-        vec![22usize, 14usize]
+        test_with_value_placeholder_splits_arguments,
+        "%(nick|name:-a|b)",
+        vec![
+            (Percent, "%"),
+            (Open, "("),
+            (Key, "nick"),
+            (Separator, "|"),
+            (Arg, "name"),
+            (Separator, ":-"),
+            (Arg, "a|b"),
+            (Close, ")")
+        ]
     );
 
     test!(
-        test_with_right_align_truncate_placeholder_and_shorter_value_with_umlauts_returns_correct_length,
-        "Hallo %>(10,trunc)%(umlaute)xx", // "Hallo äöü       xx"
-        vec![18usize, 10usize]
+        test_with_block_tags_returns_sigils,
+        "%(?a || b)%(/a)",
+        vec![
+            (Percent, "%"),
+            (Open, "("),
+            (Sigil, "?"),
+            (Arg, "a || b"),
+            (Close, ")"),
+            (Percent, "%"),
+            (Open, "("),
+            (Sigil, "/"),
+            (Key, "a"),
+            (Close, ")")
+        ]
     );
 
     test!(
-        test_with_left_align_truncate_placeholder_and_shorter_value_with_umlauts_returns_correct_length,
-        "Hallo %<(10,trunc)%(umlaute)xx", // "Hallo äöü       xx"
-        vec![18usize, 10usize]
+        test_with_nested_placeholder_tokenizes_it,
+        "%-( %(width_of:t) , '=')",
+        vec![
+            (Percent, "%"),
+            (Sigil, "-"),
+            (Open, "("),
+            (Percent, "%"),
+            (Open, "("),
+            (Sigil, "width_of:"),
+            (Key, "t"),
+            (Close, ")"),
+            (Separator, ","),
+            (Arg, "'='"),
+            (Close, ")")
+        ]
     );
 
     test!(
-        test_with_left_align_truncate_placeholder_and_exact_length_value_returns_correct_length,
-        "Hallo %<(10,trunc)%(str10)xx", // "Hallo 1234567890xx"
-        vec![18usize, 10usize]
+        test_with_format_prefix_returns_sigil,
+        "%>|(preset:id)%-(key)",
+        vec![
+            (Percent, "%"),
+            (Sigil, ">|"),
+            (Open, "("),
+            (Sigil, "preset:"),
+            (Arg, "id"),
+            (Close, ")"),
+            (Percent, "%"),
+            (Sigil, "-"),
+            (Open, "("),
+            (Key, "key"),
+            (Close, ")")
+        ]
     );
 
     test!(
-        test_with_left_align_truncate_placeholder_and_longer_value_returns_correct_length,
-        "Hallo %<(10,trunc)%(str14)xx", // "Hallo 123456789…xx"
-        vec![18usize, 10usize]
+        test_with_unterminated_placeholder_stops_at_end,
+        "%(name",
+        vec![(Percent, "%"), (Open, "("), (Key, "name")]
     );
 
-    test!(
-        test_with_right_align_truncate_placeholder_and_longer_value_returns_correct_length,
-        "Hallo %>(10,trunc)%(str14)xx", // "Hallo 123456789…xx"
-        vec![18usize, 10usize]
-    );
+    #[test]
+    fn test_spans_are_byte_offsets() {
+        let spans: Vec<_> = tokenize("ä%(ü)").map(|token| token.span).collect();
+        assert_eq!(spans, vec![0..2, 2..3, 3..4, 4..6, 6..7]);
+    }
+}
+
+#[cfg(all(test, feature = "tables"))]
+mod tests_column_plan {
+    use crate::*;
+
+    fn row(name: &str, size: &str) -> HashMap<&'static str, String> {
+        HashMap::from([("name", name.to_string()), ("size", size.to_string())])
+    }
+
+    #[test]
+    fn test_with_explicit_widths_pads_and_truncates() {
+        let parser = Formatify::new();
+        let plan = ColumnPlan::new(&parser, "%(name)|%>(1)%(size)|", vec![4, 3]);
+        assert_eq!(plan.render_row(&row("ab", "7")), "ab  |  7|");
+        assert_eq!(plan.render_row(&row("abcdef", "12345")), "abc…|12…|");
+    }
+
+    #[test]
+    fn test_with_sampled_rows_uses_widest_values() {
+        let parser = Formatify::new();
+        let rows = [row("Name", "Size"), row("readme.md", "3")];
+        let plan = ColumnPlan::sample(&parser, "%(name) %(size)", &rows);
+        assert_eq!(plan.widths(), [9, 4]);
+        assert_eq!(plan.render_row(&row("a", "1")), "a         1   ");
+    }
+
+    #[test]
+    fn test_with_missing_widths_keeps_natural_width() {
+        let parser = Formatify::new();
+        let plan = ColumnPlan::new(&parser, "%(name)|%(size)", vec![3]);
+        assert_eq!(plan.render_row(&row("a", "12345")), "a  |12345");
+    }
+
+    #[test]
+    fn test_without_samples_keeps_natural_widths() {
+        let parser = Formatify::new();
+        let plan = ColumnPlan::sample(&parser, "%(name)|%(size)", &[]);
+        assert!(plan.widths().is_empty());
+        assert_eq!(plan.render_row(&row("ab", "1")), "ab|1");
+    }
 }
 
 #[cfg(test)]
-mod tests_replace_placeholders {
+mod tests_template {
     use crate::*;
-    use std::collections::HashMap;
+
+    fn records() -> Vec<HashMap<&'static str, String>> {
+        let mut first = HashMap::<&str, String>::new();
+        first.insert("name", "Alice".into());
+        first.insert("count", "3".into());
+        first.insert("items", join_list(["x", "y"]));
+        let mut second = HashMap::<&str, String>::new();
+        second.insert("name", "Bob the Builder".into());
+        second.insert("count", "1".into());
+        vec![first, second, HashMap::new()]
+    }
 
     macro_rules! test {
-        ($test_name:ident, $inp:expr, $expected_output:expr) => {
+        ($test_name:ident, $inp:expr) => {
             #[test]
             fn $test_name() {
-                let mut key_value = HashMap::<&str, String>::new();
-                key_value.insert("var1", "world".into());
-                key_value.insert("var2", "welt".into());
-                key_value.insert("str4", "1234".into());
-                key_value.insert("str10", "1234567890".into());
-                key_value.insert("str14", "1234567890ABCD".into());
-                key_value.insert("umlaute", "äöü".into());
-                key_value.insert("umlaute_bigger", "äöü12345678".into());
                 let parser = Formatify::new();
-                let out_str = parser.replace_placeholders(&key_value, $inp);
-                assert_eq!(out_str, $expected_output);
+                let template = Template::parse(&parser, $inp);
+                for key_value in records() {
+                    assert_eq!(
+                        template.render(&key_value),
+                        parser.replace_placeholders(&key_value, $inp)
+                    );
+                    assert_eq!(
+                        template.render_with(&key_value),
+                        parser.replace_placeholders_provider(&key_value, $inp)
+                    );
+                    assert_eq!(
+                        template.measure(&key_value),
+                        parser.measure_lengths(&key_value, $inp)
+                    );
+                }
+                assert_eq!(template.keys(), parser.extract_placeholder_keys($inp));
             }
         };
     }
 
-    test!(test_with_empty_input_returns_empty_string, "", "");
-
     test!(
-        test_with_plain_string_returns_same_string,
-        "Conventional string",
-        "Conventional string"
+        test_with_values_renders_like_replace_placeholders,
+        "Hi %(name), %(count)!"
     );
 
     test!(
-        test_with_unicode_string_returns_same_string,
-        "Smiley 😊 Smiley",
-        "Smiley 😊 Smiley"
+        test_with_formats_renders_like_replace_placeholders,
+        "%<(8,trunc)%(name)|%>(3)%(count)|%<(4)%(missing:-n/a)"
     );
 
     test!(
-        test_with_single_placeholder_replaces_correctly,
-        "Hello %(var1)",
-        "Hello world"
+        test_with_blocks_renders_like_replace_placeholders,
+        "%(?count>1)%(count) items%(:else)one item%(/count)%(for:item in items)[%(item)]%(end)"
     );
 
     test!(
-        test_with_single_placeholder_alternative_value_replaces_correctly,
-        "Hello %(var2)",
-        "Hello welt"
+        test_with_invalid_placeholders_renders_like_replace_placeholders,
+        "%(name %<(x)%(|) %% %n %T(8)."
     );
 
     test!(
-        test_with_one_char_token_type_replaces_correctly,
-        "abcde %%", // replaces nothing -> "abcde %"
-        "abcde %"
+        test_with_anchors_renders_like_replace_placeholders,
+        "%(name)%|(a): x%n%(count)%|(a): y"
     );
 
+    #[cfg(feature = "modifiers")]
     test!(
-        test_with_invalid_token_type_leaves_token_unreplaced,
-        "Hallo %z",
-        "Hallo %z"
+        test_with_modifiers_renders_like_replace_placeholders,
+        "%(nick|name|upper:-anon) %(name|repeat:count)"
     );
 
     test!(
-        test_with_multiple_placeholders_replaces_all_correctly,
-        "Hello %(var1). Hallo %(var2).",
-        "Hello world. Hallo welt."
+        test_with_char_placeholders_renders_like_replace_placeholders,
+        "%(name)%n%t%_%x41%-(4)%-(3,'=')%%"
     );
 
     test!(
-        test_with_multiple_placeholders_and_delimiters_replaces_correctly,
-        "|%(var1)|%(var2)|",
-        "|world|welt|"
+        test_with_prefixes_renders_like_replace_placeholders,
+        "a%+(name)b% (missing)c%n%n%-(missing)d"
     );
 
     test!(
-        test_with_undefined_second_placeholder_keeps_it_unreplaced,
-        "Hallo %(var1)%(vara)",
-        "Hallo world%(vara)"
+        test_with_column_dependent_placeholders_renders_like_replace_placeholders,
+        "%(name)%T(20)|%<|(30)%(count)%-(*)|%w(10)%(name) %(name)%I(2)%(items)"
     );
 
+    #[cfg(feature = "colors")]
     test!(
-        test_with_undefined_first_placeholder_keeps_it_unreplaced,
-        "Hallo %(vara)%(var2)",
-        "Hallo %(vara)welt"
+        test_with_colors_renders_like_replace_placeholders,
+        "%C(red)%(name)%Creset %<(8)%C(bold)%(count)%C(reset)"
     );
 
-    test!(
-        test_with_incorrect_placeholder_syntax_keeps_it_unreplaced,
-        "Hallo %var1",
-        "Hallo %var1"
-    );
+    #[test]
+    fn test_with_configured_formatter_renders_like_replace_placeholders() {
+        let mut parser = Formatify::builder()
+            .right_to_left(true)
+            .target_width(30)
+            .build();
+        parser.default_spec("count", ">4").unwrap();
+        parser.register_preset("col", "12,trunc").unwrap();
+        for inp in [
+            "%(name)|%(count)|%<(preset:col)%(name)%n%(missing)",
+            "%(name)|%<(*)%(count)",
+        ] {
+            let template = Template::parse(&parser, inp);
+            for key_value in records() {
+                assert_eq!(
+                    template.render(&key_value),
+                    parser.replace_placeholders(&key_value, inp)
+                );
+                assert_eq!(
+                    template.render_fixed(&key_value, &[3, 6]),
+                    parser.replace_placeholders_negotiated(&key_value, inp, &|_| vec![3, 6])
+                );
+            }
+        }
+    }
 
-    test!(
-        test_with_incomplete_placeholder_syntax_keeps_it_unreplaced,
-        "Hallo %(var1",
-        "Hallo %(var1"
-    );
+    #[test]
+    fn test_with_repeated_renders_resolves_values_of_each_render() {
+        let parser = Formatify::new();
+        let template = Template::parse(&parser, "%(name)");
+        let outputs: Vec<_> = records().iter().map(|kv| template.render(kv)).collect();
+        assert_eq!(outputs, ["Alice", "Bob the Builder", "%(name)"]);
+        assert_eq!(template.source(), "%(name)");
+    }
 
-    test!(
-        test_with_newline_placeholder_inserts_newline,
-        "Hallo %nWelt",
-        "Hallo \nWelt"
-    );
+    #[test]
+    fn test_with_provider_queries_each_key_once() {
+        let queried = std::cell::RefCell::new(Vec::<String>::new());
+        let provider = |key: &str| {
+            queried.borrow_mut().push(key.to_string());
+            (key == "name").then(|| "Alice".to_string())
+        };
+        let parser = Formatify::new();
+        let template = Template::parse(&parser, "%(name) %(nick|name) %(missing) %(missing)");
+        assert_eq!(
+            template.render_with(&provider),
+            "Alice Alice %(missing) %(missing)"
+        );
+        assert_eq!(*queried.borrow(), ["name", "nick", "missing"]);
+    }
 
-    test!(
-        test_with_escaped_percent_sign_keeps_it_unchanged,
-        "Hallo %%(var1)",
-        "Hallo %(var1)"
-    );
+    #[test]
+    fn test_with_nul_delimited_records_joins_outputs_with_nul() {
+        let parser = Formatify::new();
+        let template = Template::parse(&parser, "%(name)%n%(count:-0)");
+        let out = template.render_nul_delimited(&records());
+        assert_eq!(out, "Alice\n3\0Bob the Builder\n1\0%(name)\n0");
+        assert_eq!(template.render_nul_delimited(&Vec::new()), "");
+    }
 
-    test!(
-        test_with_newline_placeholder_at_end_inserts_newline,
-        "Hallo Welt %n",
-        "Hallo Welt \n"
-    );
+    #[test]
+    fn test_with_nul_delimited_records_escapes_embedded_nul() {
+        let parser = Formatify::new();
+        let template = Template::parse(&parser, "%(name)%0");
+        let records = [
+            HashMap::from([("name", "a\0b".to_string())]),
+            HashMap::new(),
+        ];
+        let out = template.render_nul_delimited(&records);
+        assert_eq!(out, "a\\0b\0\0%(name)\0");
+        assert_eq!(out.split('\0').count(), 2 * records.len());
+    }
 
-    test!(
-        test_with_left_alignment_placeholder_and_shorter_value_pads_correctly,
-        "Hallo %<(10)%(str4)xx",
-        "Hallo 1234      xx"
-    );
+    #[test]
+    fn test_with_nul_delimited_records_unescapes_to_rendered_items() {
+        fn unescape(item: &str) -> String {
+            let mut out = String::new();
+            let mut chars = item.chars();
+            while let Some(ch) = chars.next() {
+                match (ch, chars.clone().next()) {
+                    ('\\', Some('0')) => out.push('\0'),
+                    ('\\', Some('\\')) => out.push('\\'),
+                    _ => {
+                        out.push(ch);
+                        continue;
+                    }
+                }
+                chars.next();
+            }
+            out
+        }
 
-    test!(
-        test_with_right_alignment_placeholder_and_shorter_value_pads_correctly,
-        "Hallo %>(10)%(str4)xx",
-        "Hallo       1234xx"
-    );
+        let parser = Formatify::new();
+        let template = Template::parse(&parser, "C:\\0%<(6)%(a)%0%(b)");
+        let records = [
+            HashMap::from([("a", "x\0\\0".to_string()), ("b", "\\\0".to_string())]),
+            HashMap::from([("a", "\\".to_string())]),
+        ];
+        let out = template.render_nul_delimited(&records);
+        let items: Vec<String> = out.split('\0').map(unescape).collect();
+        assert_eq!(items, ["C:\\0x\0\\0  ", "\\\0", "C:\\0\\     ", "%(b)"]);
+    }
 
-    test!(
-        test_with_left_alignment_placeholder_and_exact_length_value_keeps_it_unchanged,
-        "Hallo %<(10)%(str10)xx",
-        "Hallo 1234567890xx"
-    );
+    #[test]
+    fn test_with_extension_renders_like_replace_placeholders() {
+        struct Upper;
+        impl PlaceholderExtension for Upper {
+            fn sigil(&self) -> &str {
+                "up"
+            }
+            fn render(&self, arg: &str, _key_value: &HashMap<&str, String>) -> String {
+                arg.to_uppercase()
+            }
+        }
+        let mut parser = Formatify::new();
+        parser.register_extension(Upper);
+        let inp = "%(up:abc) %(#name) %(name)";
+        let template = Template::parse(&parser, inp);
+        for key_value in records() {
+            assert_eq!(
+                template.render(&key_value),
+                parser.replace_placeholders(&key_value, inp)
+            );
+        }
+    }
+}
 
-    test!(
-        test_with_right_alignment_placeholder_and_exact_length_value_keeps_it_unchanged,
-        "Hallo %>(10)%(str10)xx",
-        "Hallo 1234567890xx"
-    );
+#[cfg(test)]
+mod tests_placeholder_formatter_defaults {
+    use crate::*;
+    use std::collections::HashMap;
 
-    test!(
-        test_with_left_alignment_placeholder_and_longer_value_keeps_it_unchanged,
-        "Hallo %<(10)%(str14)xx",
-        "Hallo 1234567890ABCDxx"
-    );
+    /// Implements only the required methods, like formatters of other crates.
+    struct Minimal(Formatify);
 
-    test!(
-        test_with_right_alignment_placeholder_and_longer_value_keeps_it_unchanged,
-        "Hallo %>(10)%(str14)xx",
-        "Hallo 1234567890ABCDxx"
-    );
+    impl PlaceholderFormatter for Minimal {
+        fn replace_placeholders(&self, key_value: &HashMap<&str, String>, inp: &str) -> String {
+            self.0.replace_placeholders(key_value, inp)
+        }
 
-    test!(
-        test_with_left_align_truncate_placeholder_and_exact_length_value_keeps_it_unchanged,
-        "Hallo %<(10,trunc)%(str10)xx",
-        "Hallo 1234567890xx"
-    );
+        fn extract_placeholder_keys(&self, inp: &str) -> Vec<String> {
+            self.0.extract_placeholder_keys(inp)
+        }
+    }
 
-    test!(
-        test_with_right_align_truncate_placeholder_and_exact_length_value_keeps_it_unchanged,
-        "Hallo %>(10,trunc)%(str10)xx",
-        "Hallo 1234567890xx"
-    );
+    fn key_value() -> HashMap<&'static str, String> {
+        HashMap::from([
+            ("name", "<Alice>".to_string()),
+            ("salary", "85000".to_string()),
+        ])
+    }
 
-    test!(
-        test_with_left_align_truncate_placeholder_and_exact_length_value_with_spaces_keeps_it_unchanged,
-        "Hallo %<(  10  ,  trunc   )%(str10)xx",
-        "Hallo 1234567890xx"
-    );
+    #[test]
+    fn test_with_checked_renders_list_missing_keys() {
+        let formatter = Minimal(Formatify::new());
+        let (out, missing) =
+            formatter.replace_placeholders_checked(&key_value(), "%(name) %(city)");
+        assert_eq!(out, "<Alice> %(city)");
+        assert_eq!(missing, vec![("city".to_string(), 1)]);
+        assert_eq!(
+            formatter.replace_placeholders_strict(&key_value(), "%(name) = %(city)"),
+            Err(RenderError::MissingKey {
+                key: "city".into(),
+                span: 10..17
+            })
+        );
+    }
 
-    test!(
-        test_with_right_align_truncate_placeholder_and_longer_value_truncates_correctly,
-        "Hallo %>(10,trunc)%(str14)xx",
-        "Hallo 123456789…xx"
-    );
+    #[test]
+    fn test_with_fixed_width_measures_output() {
+        let formatter = Minimal(Formatify::new());
+        let inp = "%<(8,trunc)%(name)|";
+        assert_eq!(
+            formatter.replace_placeholders_fixed_width(
+                &key_value(),
+                inp,
+                9,
+                MultiBytePolicy::Chars
+            ),
+            Ok("<Alice> |".to_string())
+        );
+        assert_eq!(
+            formatter.replace_placeholders_fixed_width(
+                &key_value(),
+                inp,
+                8,
+                MultiBytePolicy::Bytes
+            ),
+            Err(RenderError::WidthMismatch {
+                expected: 8,
+                found: 9
+            })
+        );
+    }
 
-    test!(
-        test_with_right_align_left_truncate_placeholder_and_longer_value_truncates_correctly,
-        "Hallo %>(10,ltrunc)%(str14)xx",
-        "Hallo …67890ABCDxx"
-    );
+    #[cfg(feature = "sandbox")]
+    #[test]
+    fn test_with_sandboxed_renders_checks_output_size() {
+        let formatter = Minimal(Formatify::new());
+        let quotas = Quotas::new().max_output_bytes(10);
+        assert_eq!(
+            formatter.replace_placeholders_sandboxed(&key_value(), "%(name)", &quotas),
+            Ok("<Alice>".to_string())
+        );
+        assert_eq!(
+            formatter.replace_placeholders_sandboxed(&key_value(), "%(name)%(name)", &quotas),
+            Err(RenderError::QuotaExceeded {
+                quota: Quota::OutputBytes
+            })
+        );
+    }
 
-    test!(
-        test_with_left_align_truncate_placeholder_and_longer_value_truncates_correctly,
-        "Hallo %<(10,trunc)%(str14)xx",
-        "Hallo 123456789…xx"
-    );
+    #[cfg(feature = "sandbox")]
+    #[test]
+    fn test_with_sandboxed_ignores_token_quota_and_denied_capabilities() {
+        let quotas = Quotas::new()
+            .max_tokens(1)
+            .deny(Capability::DynamicBuiltins);
+        let inp = "%(name) %(uuid)";
+        assert!(Minimal(Formatify::new())
+            .replace_placeholders_sandboxed(&key_value(), inp, &quotas)
+            .is_ok());
+        assert!(Formatify::new()
+            .replace_placeholders_sandboxed(&key_value(), inp, &quotas)
+            .is_err());
+    }
 
-    test!(
-        test_with_left_align_left_truncate_placeholder_and_longer_value_truncates_correctly,
-        "Hallo %<(10,ltrunc)%(str14)xx",
-        "Hallo …67890ABCDxx"
-    );
+    #[test]
+    fn test_with_policy_masks_and_denies_values() {
+        let formatter = Minimal(Formatify::new());
+        let out = formatter.replace_placeholders_with_policy(
+            &key_value(),
+            "%(name): %(salary)",
+            &|key, _| match key {
+                "salary" => KeyAccess::Mask("***".into()),
+                _ => KeyAccess::Deny,
+            },
+        );
+        assert_eq!(out, "%(name): ***");
+    }
 
-    test!(
-        test_with_right_align_truncate_placeholder_and_shorter_value_with_umlauts_pads_correctly,
-        "Hallo %>(10,trunc)%(umlaute)xx",
-        "Hallo        äöüxx"
-    );
+    #[cfg(feature = "html")]
+    #[test]
+    fn test_with_html_escapes_output() {
+        let formatter = Minimal(Formatify::new());
+        assert_eq!(
+            formatter.replace_placeholders_html(&key_value(), "<b>%(name)</b>"),
+            "&lt;b&gt;&lt;Alice&gt;&lt;/b&gt;"
+        );
+    }
 
-    test!(
-        test_with_right_align_left_truncate_placeholder_and_shorter_value_with_umlauts_pads_correctly,
-        "Hallo %>(10,ltrunc)%(umlaute)xx",
-        "Hallo        äöüxx"
-    );
+    #[cfg(feature = "markdown")]
+    #[test]
+    fn test_with_markdown_escapes_values() {
+        let formatter = Minimal(Formatify::new());
+        assert_eq!(
+            formatter.replace_placeholders_markdown(
+                &key_value(),
+                "*%(name)*",
+                MarkdownFlavor::Slack
+            ),
+            "*&lt;Alice&gt;*"
+        );
+    }
 
-    test!(
-        test_with_left_align_truncate_placeholder_and_shorter_value_with_umlauts_pads_correctly,
-        "Hallo %<(10,trunc)%(umlaute)xx",
-        "Hallo äöü       xx"
-    );
+    #[cfg(feature = "tables")]
+    #[test]
+    fn test_with_code_block_fences_joined_output() {
+        let formatter = Minimal(Formatify::new());
+        assert_eq!(
+            formatter.replace_placeholders_code_block(&key_value(), &["a`b", "%(salary)"]),
+            "```\na`b\n85000\n```"
+        );
+    }
 
-    test!(
-        test_with_left_align_left_truncate_placeholder_and_shorter_value_with_umlauts_pads_correctly,
-        "Hallo %<(10,ltrunc)%(umlaute)xx",
-        "Hallo äöü       xx"
-    );
+    #[test]
+    fn test_with_measure_lengths_measures_output_and_values() {
+        let formatter = Minimal(Formatify::new());
+        assert_eq!(
+            formatter.measure_lengths(&key_value(), "Hi %(name) %(city)"),
+            vec![18, 7]
+        );
+    }
 
-    test!(
-        test_with_left_align_truncate_placeholder_and_longer_value_with_umlauts_truncates_correctly,
-        "Hallo %<(10,trunc)%(umlaute_bigger)xx",
-        "Hallo äöü123456…xx"
-    );
+    #[test]
+    fn test_with_measure_lengths_differs_from_formatify() {
+        let minimal = Minimal(Formatify::new());
+        let formatify = Formatify::new();
+        let inp = "%<(3,trunc)%(name)";
+        assert_eq!(minimal.measure_lengths(&key_value(), inp), vec![3, 7]);
+        assert_eq!(formatify.measure_lengths(&key_value(), inp), vec![3, 3]);
+    }
 
-    test!(
-        test_with_invalid_left_align_placeholder_keeps_format_specifier_unchanged,
-        "Hallo %<(a10)%(str14)xx",
-        "Hallo %<(a10)1234567890ABCDxx"
-    );
+    #[cfg(feature = "blocks")]
+    #[test]
+    fn test_with_measure_lengths_measures_inactive_branches() {
+        let formatter = Minimal(Formatify::new());
+        let inp = "%(if:city)%(salary)%(end)%(name)";
+        assert_eq!(formatter.measure_lengths(&key_value(), inp), vec![7, 5, 7]);
+        assert_eq!(
+            Formatify::new().measure_lengths(&key_value(), inp),
+            vec![7, 7]
+        );
+    }
+
+    #[test]
+    fn test_with_parse_differs_from_formatify() {
+        let minimal = Minimal(Formatify::new());
+        let formatify = Formatify::new();
+        assert_eq!(
+            minimal.parse("%<(x)%(name)")[0],
+            Segment::Directive { span: 0..5 }
+        );
+        assert_eq!(
+            formatify.parse("%<(x)%(name)")[0],
+            Segment::Error { span: 0..3 }
+        );
+        let key = Segment::KeyPlaceholder {
+            key: "name".into(),
+            format: None,
+            span: 5..12,
+        };
+        assert_eq!(minimal.parse("%<(6)%(name)")[1], key);
+        assert_ne!(formatify.parse("%<(6)%(name)")[1], key);
+    }
+
+    #[test]
+    fn test_with_parse_groups_tokens() {
+        let formatter = Minimal(Formatify::new());
+        assert_eq!(
+            formatter.parse("Hi %<(6)%C(red)%(name)%n%(x"),
+            vec![
+                Segment::Literal {
+                    text: "Hi ".into(),
+                    span: 0..3
+                },
+                Segment::Directive { span: 3..15 },
+                Segment::KeyPlaceholder {
+                    key: "name".into(),
+                    format: None,
+                    span: 15..22
+                },
+                Segment::CharPlaceholder {
+                    ch: 'n',
+                    span: 22..24
+                },
+                Segment::Error { span: 24..27 },
+            ]
+        );
+        assert_eq!(
+            formatter.extract_placeholders("Né %(name)")[0].span,
+            Formatify::new().extract_placeholders("Né %(name)")[0].span
+        );
+    }
 }
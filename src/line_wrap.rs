@@ -0,0 +1,27 @@
+/// Line wrapping of the output switched on by `%w(width,indent1,indent2)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineWrap {
+    /// Column after which lines are wrapped, including the indentation. 0 only indents lines.
+    pub width: usize,
+    /// Indentation of the first line.
+    pub indent1: usize,
+    /// Indentation of the continuation lines.
+    pub indent2: usize,
+    /// The first line was indented.
+    pub started: bool,
+    /// Output position and column after the last space of the current line, where the line is
+    /// broken if a subsequent word exceeds the width.
+    pub space: Option<(usize, usize)>,
+}
+
+impl LineWrap {
+    pub fn new(width: usize, indent1: usize, indent2: usize) -> Self {
+        Self {
+            width,
+            indent1,
+            indent2,
+            started: false,
+            space: None,
+        }
+    }
+}
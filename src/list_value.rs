@@ -0,0 +1,39 @@
+/// Separator used to encode list values in the `String` values of the key-value map.
+///
+/// Loop blocks like `%(for:item in items)` split the value of `items` at this separator
+/// (the ASCII unit separator). Use [`join_list`] to create such values.
+pub const LIST_SEPARATOR: char = '\u{1f}';
+
+/// Joins the given items into a list value which can be iterated by loop blocks or
+/// aggregated, e.g. with `%(count:tags)`.
+///
+/// # Examples
+/// ```
+/// # use formatify::{join_list, Formatify, PlaceholderFormatter};
+/// # use std::collections::HashMap;
+/// let mut key_value : HashMap<&str, String> = HashMap::new();
+/// key_value.insert("tags", join_list(["v1.0", "stable"]));
+/// let formatter = Formatify::new();
+/// let out = formatter.replace_placeholders(&key_value, "%(count:tags) tags");
+/// assert_eq!(out, "2 tags");
+/// ```
+pub fn join_list<I, S>(items: I) -> String
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let items: Vec<S> = items.into_iter().collect();
+    let items: Vec<&str> = items.iter().map(|item| item.as_ref()).collect();
+    items.join(&LIST_SEPARATOR.to_string())
+}
+
+/// Splits a list value into its items. An empty value is an empty list.
+pub fn split_list(value: &str) -> Vec<String> {
+    if value.is_empty() {
+        return Vec::new();
+    }
+    value
+        .split(LIST_SEPARATOR)
+        .map(|item| item.to_string())
+        .collect()
+}
@@ -0,0 +1,86 @@
+use super::value_span::ValueSpan;
+
+/// Markdown dialect produced by `replace_placeholders_markdown`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkdownFlavor {
+    /// Slack mrkdwn: bold is `*text*`, `&`, `<` and `>` are escaped as entities.
+    Slack,
+    /// Discord markdown: bold is `**text**`, markdown characters are escaped with `\`.
+    Discord,
+}
+
+impl MarkdownFlavor {
+    fn bold(&self) -> &'static str {
+        match self {
+            MarkdownFlavor::Slack => "*",
+            MarkdownFlavor::Discord => "**",
+        }
+    }
+
+    /// Escapes characters of a value with a special meaning.
+    pub(crate) fn escape(&self, value: &str) -> String {
+        let mut out = String::with_capacity(value.len());
+        for ch in value.chars() {
+            self.push_escaped(&mut out, ch);
+        }
+        out
+    }
+
+    /// Appends a char of a value to `out`, escaping characters with a special meaning.
+    fn push_escaped(&self, out: &mut String, ch: char) {
+        match (self, ch) {
+            (MarkdownFlavor::Slack, '&') => out.push_str("&amp;"),
+            (MarkdownFlavor::Slack, '<') => out.push_str("&lt;"),
+            (MarkdownFlavor::Slack, '>') => out.push_str("&gt;"),
+            (MarkdownFlavor::Discord, '\\' | '*' | '_' | '~' | '`' | '|' | '>') => {
+                out.push('\\');
+                out.push(ch);
+            }
+            _ => out.push(ch),
+        }
+    }
+}
+
+/// Renders the output chars as markdown. Literal text of the template is passed through, so
+/// templates may contain markdown themselves. Values are escaped; aligned values are wrapped in
+/// code spans to keep their padding and styled values are set in bold. Spans must be ordered by
+/// output position; empty spans and spans overlapping a previous one are skipped.
+pub fn render_markdown(
+    chars: impl IntoIterator<Item = char>,
+    spans: &[ValueSpan],
+    flavor: MarkdownFlavor,
+) -> String {
+    let chars: Vec<char> = chars.into_iter().collect();
+    let mut out = String::with_capacity(chars.len());
+    let mut pos = 0;
+    for span in spans {
+        if span.output.is_empty() || span.output.start < pos {
+            continue;
+        }
+        out.extend(&chars[pos..span.output.start]);
+        pos = span.output.end;
+
+        let bold = if span.classes.is_empty() {
+            ""
+        } else {
+            flavor.bold()
+        };
+        out.push_str(bold);
+        if span.aligned {
+            // code spans can't contain escaped backticks
+            out.push('`');
+            out.extend(chars[span.output.clone()].iter().map(|&ch| match ch {
+                '`' => '\'',
+                _ => ch,
+            }));
+            out.push('`');
+        } else {
+            for &ch in &chars[span.output.clone()] {
+                flavor.push_escaped(&mut out, ch);
+            }
+        }
+        out.push_str(bold);
+    }
+    out.extend(&chars[pos..]);
+    out
+}
@@ -0,0 +1,92 @@
+/// ICU MessageFormat-style selection of a value placeholder like
+/// `%(count,plural,one{# file}other{# files})` or `%(gender,select,female{she}other{they})`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MessageFormat {
+    /// Selects a branch by the plural category of a numeric value. `#` in a branch is
+    /// replaced by the value.
+    Plural(Vec<(PluralSelector, String)>),
+    /// Selects the branch whose selector equals the value.
+    Select(Vec<(String, String)>),
+}
+
+/// Selector of a plural branch.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PluralSelector {
+    /// Matches a value equal to the number, e.g. `=0`.
+    Exact(f64),
+    /// Matches the value 1.
+    One,
+    /// Matches all values.
+    Other,
+}
+
+impl PluralSelector {
+    fn parse(selector: &str) -> Option<Self> {
+        match selector {
+            "one" => Some(Self::One),
+            "other" => Some(Self::Other),
+            _ => {
+                let number = selector.strip_prefix('=')?;
+                Some(Self::Exact(number.parse().ok()?))
+            }
+        }
+    }
+
+    fn matches(&self, number: Option<f64>) -> bool {
+        match self {
+            Self::Exact(exact) => number == Some(*exact),
+            Self::One => number == Some(1.0),
+            Self::Other => true,
+        }
+    }
+}
+
+impl MessageFormat {
+    /// Creates the format of `kind` (`plural` or `select`) from the branches as pairs of
+    /// selector and text. `None` if the kind or a selector is unknown or the `other` branch
+    /// is missing.
+    pub fn new(kind: &str, branches: Vec<(String, String)>) -> Option<Self> {
+        if !branches.iter().any(|(selector, _)| selector == "other") {
+            return None;
+        }
+        match kind {
+            "plural" => {
+                let branches = branches
+                    .into_iter()
+                    .map(|(selector, text)| Some((PluralSelector::parse(&selector)?, text)))
+                    .collect::<Option<_>>()?;
+                Some(Self::Plural(branches))
+            }
+            "select" => Some(Self::Select(branches)),
+            _ => None,
+        }
+    }
+
+    /// Returns the text of the branch selected by `value`. In plural branches `#` is replaced
+    /// by `display`, the value as rendered without selection.
+    pub fn apply(&self, value: &str, display: &str) -> String {
+        match self {
+            Self::Plural(branches) => {
+                let number = value.trim().parse::<f64>().ok();
+                // Exact matches take precedence over categories
+                let exact = branches.iter().find(|(selector, _)| {
+                    matches!(selector, PluralSelector::Exact(_)) && selector.matches(number)
+                });
+                exact
+                    .or_else(|| {
+                        branches
+                            .iter()
+                            .find(|(selector, _)| selector.matches(number))
+                    })
+                    .map(|(_, text)| text.replace('#', display))
+                    .unwrap_or_default()
+            }
+            Self::Select(branches) => branches
+                .iter()
+                .find(|(selector, _)| selector == value)
+                .or_else(|| branches.iter().find(|(selector, _)| selector == "other"))
+                .map(|(_, text)| text.clone())
+                .unwrap_or_default(),
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use std::fmt;
+use std::sync::Arc;
+
+/// Handler which renders the value of a missing key, see `MissingKeyPolicy::custom`.
+pub type MissingKeyFn = Arc<dyn Fn(&str) -> String + Send + Sync>;
+
+/// Defines how value placeholders whose key is missing are rendered, see
+/// `FormatifyBuilder::missing_key`.
+///
+/// The policy applies after fallback keys, default values like `%(key:-default)` and ambient
+/// values, so it only decides about placeholders which can't be resolved at all. The substituted
+/// value is aligned like a resolved value.
+#[derive(Clone, Default)]
+pub enum MissingKeyPolicy {
+    /// The placeholder is rendered unchanged, e.g. `%(key)`.
+    #[default]
+    Keep,
+    /// The placeholder is rendered as empty value.
+    Empty,
+    /// The placeholder is rendered as the given value.
+    Default(String),
+    /// The placeholder is rendered as the value returned by the handler for the missing key.
+    Custom(MissingKeyFn),
+}
+
+impl MissingKeyPolicy {
+    /// Creates a policy which renders the value returned by `handler` for the missing key.
+    pub fn custom<F>(handler: F) -> Self
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        MissingKeyPolicy::Custom(Arc::new(handler))
+    }
+
+    /// Returns the value substituted for the missing `key`. `None` if the placeholder is kept.
+    pub fn substitute(&self, key: &str) -> Option<String> {
+        match self {
+            MissingKeyPolicy::Keep => None,
+            MissingKeyPolicy::Empty => Some(String::new()),
+            MissingKeyPolicy::Default(value) => Some(value.clone()),
+            MissingKeyPolicy::Custom(handler) => Some(handler(key)),
+        }
+    }
+}
+
+impl fmt::Debug for MissingKeyPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MissingKeyPolicy::Keep => write!(f, "Keep"),
+            MissingKeyPolicy::Empty => write!(f, "Empty"),
+            MissingKeyPolicy::Default(value) => f.debug_tuple("Default").field(value).finish(),
+            MissingKeyPolicy::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use super::audit::AuditEntry;
+use super::key_access::KeyPolicy;
+#[cfg(feature = "markdown")]
+use super::markdown::MarkdownFlavor;
+use super::multi_byte_policy::MultiBytePolicy;
+use super::placeholder_formatter::PlaceholderFormatter;
+#[cfg(feature = "sandbox")]
+use super::quota::Quotas;
+use super::render_error::RenderError;
+use super::segment::Segment;
+use super::source_map::SourceMapEntry;
+use super::truncation::Truncation;
+use super::Formatify;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// A call of a `PlaceholderFormatter` method recorded by `MockPlaceholderFormatter`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockCall {
+    /// Name of the called method, e.g. `replace_placeholders`.
+    pub method: &'static str,
+    /// The template passed to the method. Joined with newlines for `replace_placeholders_joined`.
+    pub template: String,
+    /// Snapshot of the key-value map passed to the method.
+    pub key_value: HashMap<String, String>,
+}
+
+/// Formatter with scripted outputs which records all calls, for unit-testing code which
+/// depends on `dyn PlaceholderFormatter`.
+///
+/// Rendering methods return the output scripted for the template, or the template itself if
+/// no output is scripted. `measure_lengths` measures the output, `extract_placeholder_keys`
+/// and `parse` parse the template like `Formatify`.
+///
+/// # Examples
+/// ```
+/// # use formatify::{MockPlaceholderFormatter, PlaceholderFormatter};
+/// # use std::collections::HashMap;
+/// let mock = MockPlaceholderFormatter::new().returning("Hi %(name)", "<greeting>");
+/// let formatter: &dyn PlaceholderFormatter = &mock;
+///
+/// let mut key_value : HashMap<&str, String> = HashMap::new();
+/// key_value.insert("name", "Alice".into());
+/// assert_eq!(formatter.replace_placeholders(&key_value, "Hi %(name)"), "<greeting>");
+///
+/// let calls = mock.calls();
+/// assert_eq!(calls.len(), 1);
+/// assert_eq!(calls[0].template, "Hi %(name)");
+/// assert_eq!(calls[0].key_value["name"], "Alice");
+/// ```
+#[derive(Debug, Default)]
+pub struct MockPlaceholderFormatter {
+    outputs: HashMap<String, String>,
+    calls: Mutex<Vec<MockCall>>,
+}
+
+impl MockPlaceholderFormatter {
+    /// Creates a mock without scripted outputs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Scripts the output returned for `template`.
+    pub fn returning(mut self, template: &str, output: &str) -> Self {
+        self.outputs
+            .insert(template.to_string(), output.to_string());
+        self
+    }
+
+    /// Returns all recorded calls in order.
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// Records a call and returns the scripted output for `template`.
+    fn call(
+        &self,
+        method: &'static str,
+        key_value: &HashMap<&str, String>,
+        template: &str,
+    ) -> String {
+        self.calls.lock().unwrap().push(MockCall {
+            method,
+            template: template.to_string(),
+            key_value: key_value
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.clone()))
+                .collect(),
+        });
+        self.outputs
+            .get(template)
+            .cloned()
+            .unwrap_or_else(|| template.to_string())
+    }
+}
+
+impl PlaceholderFormatter for MockPlaceholderFormatter {
+    fn replace_placeholders(&self, key_value: &HashMap<&str, String>, inp: &str) -> String {
+        self.call("replace_placeholders", key_value, inp)
+    }
+
+    fn replace_placeholders_fixed_width(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+        _width: usize,
+        _policy: MultiBytePolicy,
+    ) -> Result<String, RenderError> {
+        Ok(self.call("replace_placeholders_fixed_width", key_value, inp))
+    }
+
+    fn replace_placeholders_strict(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+    ) -> Result<String, RenderError> {
+        Ok(self.call("replace_placeholders_strict", key_value, inp))
+    }
+
+    #[cfg(feature = "sandbox")]
+    fn replace_placeholders_sandboxed(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+        _quotas: &Quotas,
+    ) -> Result<String, RenderError> {
+        Ok(self.call("replace_placeholders_sandboxed", key_value, inp))
+    }
+
+    #[cfg(feature = "tables")]
+    fn replace_placeholders_joined(
+        &self,
+        key_value: &HashMap<&str, String>,
+        templates: &[&str],
+    ) -> String {
+        self.call(
+            "replace_placeholders_joined",
+            key_value,
+            &templates.join("\n"),
+        )
+    }
+
+    #[cfg(feature = "tables")]
+    fn replace_placeholders_code_block(
+        &self,
+        key_value: &HashMap<&str, String>,
+        templates: &[&str],
+    ) -> String {
+        self.call(
+            "replace_placeholders_code_block",
+            key_value,
+            &templates.join("\n"),
+        )
+    }
+
+    fn replace_placeholders_negotiated(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+        _negotiate: &dyn Fn(&[usize]) -> Vec<usize>,
+    ) -> String {
+        self.call("replace_placeholders_negotiated", key_value, inp)
+    }
+
+    fn replace_placeholders_with_policy(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+        _policy: &KeyPolicy,
+    ) -> String {
+        self.call("replace_placeholders_with_policy", key_value, inp)
+    }
+
+    fn replace_placeholders_with_source_map(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+    ) -> (String, Vec<SourceMapEntry>) {
+        let out = self.call("replace_placeholders_with_source_map", key_value, inp);
+        (out, Vec::new())
+    }
+
+    fn replace_placeholders_with_truncations(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+    ) -> (String, Vec<Truncation>) {
+        let out = self.call("replace_placeholders_with_truncations", key_value, inp);
+        (out, Vec::new())
+    }
+
+    fn replace_placeholders_with_audit(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+    ) -> (String, Vec<AuditEntry>) {
+        let out = self.call("replace_placeholders_with_audit", key_value, inp);
+        (out, Vec::new())
+    }
+
+    fn replace_placeholders_checked(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+    ) -> (String, Vec<(String, usize)>) {
+        let out = self.call("replace_placeholders_checked", key_value, inp);
+        (out, Vec::new())
+    }
+
+    #[cfg(feature = "html")]
+    fn replace_placeholders_html(&self, key_value: &HashMap<&str, String>, inp: &str) -> String {
+        self.call("replace_placeholders_html", key_value, inp)
+    }
+
+    #[cfg(feature = "markdown")]
+    fn replace_placeholders_markdown(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+        _flavor: MarkdownFlavor,
+    ) -> String {
+        self.call("replace_placeholders_markdown", key_value, inp)
+    }
+
+    fn replace_placeholders_vertical(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+    ) -> String {
+        self.call("replace_placeholders_vertical", key_value, inp)
+    }
+
+    fn measure_lengths(&self, key_value: &HashMap<&str, String>, inp: &str) -> Vec<usize> {
+        let out = self.call("measure_lengths", key_value, inp);
+        vec![out.chars().count()]
+    }
+
+    fn extract_placeholder_keys(&self, inp: &str) -> Vec<String> {
+        self.call("extract_placeholder_keys", &HashMap::new(), inp);
+        Formatify::new().extract_placeholder_keys(inp)
+    }
+
+    fn parse(&self, inp: &str) -> Vec<Segment> {
+        self.call("parse", &HashMap::new(), inp);
+        Formatify::new().parse(inp)
+    }
+}
@@ -0,0 +1,534 @@
+use super::html;
+#[cfg(feature = "chrono")]
+use chrono::format::{Item, StrftimeItems};
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+#[cfg(feature = "regex")]
+use regex::Regex;
+
+#[cfg(feature = "chrono")]
+use super::clock::{self, ClockFn};
+
+use std::fmt;
+use std::sync::Arc;
+
+/// Function of a user-registered modifier.
+pub type ModifierFn = Arc<dyn Fn(&str) -> String + Send + Sync>;
+
+/// Transformations which can be applied to the value of a placeholder,
+/// e.g. `%(name|upper|trim)`.
+#[derive(Clone)]
+pub enum Modifier {
+    /// Converts the value to uppercase.
+    Upper,
+    /// Converts the value to lowercase.
+    Lower,
+    /// Removes leading and trailing whitespace.
+    Trim,
+    /// Removes leading and trailing whitespace and replaces internal whitespace runs by a
+    /// single space, e.g. `trim:collapse`.
+    TrimCollapse,
+    /// Removes leading whitespace.
+    TrimStart,
+    /// Removes trailing whitespace.
+    TrimEnd,
+    /// Converts the first char of the value to uppercase.
+    Capitalize,
+    /// Converts the first letter of each word to uppercase and the other letters to lowercase,
+    /// e.g. `content-type` to `Content-Type`.
+    Title,
+    /// Joins the lowercase words of the value with `_`, e.g. `userId` to `user_id`.
+    Snake,
+    /// Joins the words of the value in lower camel case, e.g. `user id` to `userId`.
+    Camel,
+    /// Joins the lowercase words of the value with `-`, e.g. `UserId` to `user-id`.
+    Kebab,
+    /// Escapes `<`, `>`, `&` and quotes, so untrusted values are safe in HTML snippets.
+    Html,
+    /// Escapes and quotes the value as a JSON string.
+    Json,
+    /// Quotes the value with POSIX single quotes, so it is a single shell word.
+    Shell,
+    /// Encodes the UTF-8 bytes of the value with Base64: padded with the standard alphabet
+    /// (`base64`) or unpadded with the URL-safe alphabet (`base64url`).
+    Base64 { url_safe: bool },
+    /// Percent-encodes all bytes of the value except unreserved chars, e.g. `urlencode`,
+    /// `urlencode:path` or `urlencode:query`.
+    UrlEncode(UrlEncoding),
+    /// Renders an integer value in another radix, e.g. `hex`, `oct8` or `0b16`. `prefix` adds
+    /// `0x`, `0o` or `0b` and the value is zero-padded to `width` digits.
+    Radix {
+        radix: u32,
+        prefix: bool,
+        width: usize,
+    },
+    /// Renders `singular` if the numeric value is 1 or -1 and `plural` otherwise, e.g.
+    /// `plural:s` or `plural:file,files`.
+    Plural { singular: String, plural: String },
+    /// Renders a byte count with binary (`KiB`) or SI (`kB`) units, e.g. `bytes` or `bytes_si2`.
+    /// Without `precision`, values below 10 get one decimal place, others none.
+    Bytes { si: bool, precision: Option<usize> },
+    /// Repeats the value `n` times, e.g. `repeat:5`.
+    Repeat(usize),
+    /// Replaces all occurrences of the literal `from` by `to`, e.g. `replace:\\,/`.
+    Replace { from: String, to: String },
+    /// Replaces all matches of a regular expression by `to`, which can refer to capture groups
+    /// like `$1`, e.g. `replace_re:[0-9],#`.
+    #[cfg(feature = "regex")]
+    ReplaceRegex { pattern: Regex, to: String },
+    /// Reformats an RFC 3339 or unix epoch timestamp with a strftime pattern, e.g.
+    /// `date:%Y-%m-%d %H:%M`.
+    #[cfg(feature = "chrono")]
+    Date(String),
+    /// Renders an RFC 3339 or unix epoch timestamp relative to the time of the clock, e.g.
+    /// `3 hours ago`.
+    #[cfg(feature = "chrono")]
+    RelDate(ClockFn),
+    /// User-registered transformation.
+    Custom(ModifierFn),
+}
+
+/// Semantics of the URL-encoding modifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UrlEncoding {
+    /// Encodes a single path segment or query value; `/` is encoded as well.
+    Component,
+    /// Encodes a path; `/` separates segments and stays unencoded.
+    Path,
+    /// Encodes a query value like an HTML form; spaces become `+`.
+    Query,
+}
+
+impl fmt::Debug for Modifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Modifier::Upper => write!(f, "Upper"),
+            Modifier::Lower => write!(f, "Lower"),
+            Modifier::Trim => write!(f, "Trim"),
+            Modifier::TrimCollapse => write!(f, "TrimCollapse"),
+            Modifier::TrimStart => write!(f, "TrimStart"),
+            Modifier::TrimEnd => write!(f, "TrimEnd"),
+            Modifier::Capitalize => write!(f, "Capitalize"),
+            Modifier::Title => write!(f, "Title"),
+            Modifier::Snake => write!(f, "Snake"),
+            Modifier::Camel => write!(f, "Camel"),
+            Modifier::Kebab => write!(f, "Kebab"),
+            Modifier::Html => write!(f, "Html"),
+            Modifier::Json => write!(f, "Json"),
+            Modifier::Shell => write!(f, "Shell"),
+            Modifier::Base64 { url_safe } => f
+                .debug_struct("Base64")
+                .field("url_safe", url_safe)
+                .finish(),
+            Modifier::UrlEncode(encoding) => f.debug_tuple("UrlEncode").field(encoding).finish(),
+            Modifier::Radix {
+                radix,
+                prefix,
+                width,
+            } => f
+                .debug_struct("Radix")
+                .field("radix", radix)
+                .field("prefix", prefix)
+                .field("width", width)
+                .finish(),
+            Modifier::Plural { singular, plural } => f
+                .debug_struct("Plural")
+                .field("singular", singular)
+                .field("plural", plural)
+                .finish(),
+            Modifier::Bytes { si, precision } => f
+                .debug_struct("Bytes")
+                .field("si", si)
+                .field("precision", precision)
+                .finish(),
+            Modifier::Repeat(count) => f.debug_tuple("Repeat").field(count).finish(),
+            Modifier::Replace { from, to } => f
+                .debug_struct("Replace")
+                .field("from", from)
+                .field("to", to)
+                .finish(),
+            #[cfg(feature = "regex")]
+            Modifier::ReplaceRegex { pattern, to } => f
+                .debug_struct("ReplaceRegex")
+                .field("pattern", &pattern.as_str())
+                .field("to", to)
+                .finish(),
+            #[cfg(feature = "chrono")]
+            Modifier::Date(pattern) => f.debug_tuple("Date").field(pattern).finish(),
+            #[cfg(feature = "chrono")]
+            Modifier::RelDate(_) => write!(f, "RelDate"),
+            Modifier::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+impl Modifier {
+    /// Returns the built-in modifier with the given name.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "upper" => Some(Modifier::Upper),
+            "lower" => Some(Modifier::Lower),
+            "trim" => Some(Modifier::Trim),
+            "ltrim" | "trim_start" => Some(Modifier::TrimStart),
+            "rtrim" | "trim_end" => Some(Modifier::TrimEnd),
+            "capitalize" => Some(Modifier::Capitalize),
+            "title" => Some(Modifier::Title),
+            "snake" => Some(Modifier::Snake),
+            "camel" => Some(Modifier::Camel),
+            "kebab" => Some(Modifier::Kebab),
+            "html" => Some(Modifier::Html),
+            "json" => Some(Modifier::Json),
+            "shell" => Some(Modifier::Shell),
+            "urlencode" => Some(Modifier::UrlEncode(UrlEncoding::Component)),
+            "base64" => Some(Modifier::Base64 { url_safe: false }),
+            "base64url" => Some(Modifier::Base64 { url_safe: true }),
+            _ => Self::parse_radix(name).or_else(|| Self::parse_bytes(name)),
+        }
+    }
+
+    /// Parses a byte size modifier: `bytes` or `bytes_si`, optionally followed by the number
+    /// of decimal places.
+    fn parse_bytes(name: &str) -> Option<Self> {
+        let (si, precision) = match name.strip_prefix("bytes_si") {
+            Some(precision) => (true, precision),
+            None => (false, name.strip_prefix("bytes")?),
+        };
+        let precision = match precision {
+            "" => None,
+            _ if precision.chars().all(|ch| ch.is_ascii_digit()) => Some(precision.parse().ok()?),
+            _ => return None,
+        };
+        Some(Modifier::Bytes { si, precision })
+    }
+
+    /// Returns the built-in modifier with the given name which takes an argument, like
+    /// `plural:file,files`. `None` if the name is unknown or the argument is invalid.
+    pub fn with_argument(name: &str, argument: &str) -> Option<Self> {
+        match name {
+            "plural" => {
+                let (singular, plural) = match argument.split_once(',') {
+                    Some((_, plural)) if plural.contains(',') => return None,
+                    Some((singular, plural)) => (singular, plural),
+                    None => ("", argument),
+                };
+                Some(Modifier::Plural {
+                    singular: singular.to_string(),
+                    plural: plural.to_string(),
+                })
+            }
+            "repeat" => argument.trim().parse().ok().map(Modifier::Repeat),
+            "replace" => {
+                let (from, to) = argument.split_once(',')?;
+                if from.is_empty() {
+                    return None;
+                }
+                Some(Modifier::Replace {
+                    from: from.to_string(),
+                    to: to.to_string(),
+                })
+            }
+            #[cfg(feature = "regex")]
+            "replace_re" => {
+                let (pattern, to) = argument.split_once(',')?;
+                Some(Modifier::ReplaceRegex {
+                    pattern: Regex::new(pattern).ok()?,
+                    to: to.to_string(),
+                })
+            }
+            "trim" => match argument {
+                "collapse" => Some(Modifier::TrimCollapse),
+                _ => None,
+            },
+            "urlencode" => match argument {
+                "path" => Some(Modifier::UrlEncode(UrlEncoding::Path)),
+                "query" => Some(Modifier::UrlEncode(UrlEncoding::Query)),
+                _ => None,
+            },
+            #[cfg(feature = "chrono")]
+            "date" => Self::date(argument),
+            _ => None,
+        }
+    }
+
+    /// Returns the date modifier for a strftime pattern. `None` if the pattern is invalid.
+    #[cfg(feature = "chrono")]
+    fn date(pattern: &str) -> Option<Self> {
+        if StrftimeItems::new(pattern).any(|item| matches!(item, Item::Error)) {
+            return None;
+        }
+        Some(Modifier::Date(pattern.to_string()))
+    }
+
+    /// Parses a radix modifier: `hex`, `oct` or `bin`, or the prefixed forms `0x`, `0o` or
+    /// `0b`, optionally followed by the number of digits to zero-pad to.
+    fn parse_radix(name: &str) -> Option<Self> {
+        let (radix, prefix, width) = [
+            ("hex", 16, false),
+            ("oct", 8, false),
+            ("bin", 2, false),
+            ("0x", 16, true),
+            ("0o", 8, true),
+            ("0b", 2, true),
+        ]
+        .into_iter()
+        .find_map(|(name_prefix, radix, prefix)| {
+            name.strip_prefix(name_prefix)
+                .map(|width| (radix, prefix, width))
+        })?;
+
+        let width = match width {
+            "" => 0,
+            _ if width.chars().all(|ch| ch.is_ascii_digit()) => width.parse().ok()?,
+            _ => return None,
+        };
+        Some(Modifier::Radix {
+            radix,
+            prefix,
+            width,
+        })
+    }
+
+    /// Parses a unix epoch timestamp in seconds.
+    #[cfg(feature = "chrono")]
+    fn epoch(value: &str) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp(value.parse().ok()?, 0)
+    }
+
+    /// Splits the value into words at non-alphanumeric chars and at case changes, e.g.
+    /// `parseHTTPResponse_code` into `parse`, `HTTP`, `Response` and `code`.
+    fn words(value: &str) -> Vec<&str> {
+        let mut words = Vec::new();
+        let mut start = None;
+        let mut chars = value.char_indices().peekable();
+        let mut prev: Option<char> = None;
+        while let Some((idx, ch)) = chars.next() {
+            if !ch.is_alphanumeric() {
+                if let Some(start) = start.take() {
+                    words.push(&value[start..idx]);
+                }
+                prev = None;
+                continue;
+            }
+            let next = chars.peek().map(|&(_, next)| next);
+            let boundary = match prev {
+                // lowercase or digit followed by uppercase, e.g. `userId`
+                Some(prev) if ch.is_uppercase() && !prev.is_uppercase() => true,
+                // end of an acronym, e.g. `HTTPServer`
+                Some(prev) if ch.is_uppercase() && prev.is_uppercase() => {
+                    next.is_some_and(|next| next.is_lowercase())
+                }
+                _ => false,
+            };
+            if boundary {
+                if let Some(start) = start.replace(idx) {
+                    words.push(&value[start..idx]);
+                }
+            }
+            start.get_or_insert(idx);
+            prev = Some(ch);
+        }
+        if let Some(start) = start {
+            words.push(&value[start..]);
+        }
+        words
+    }
+
+    /// Encodes bytes with Base64. The URL-safe alphabet is used without padding.
+    fn base64(bytes: &[u8], url_safe: bool) -> String {
+        let alphabet: &[u8; 64] = match url_safe {
+            true => b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_",
+            false => b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/",
+        };
+        let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+        for chunk in bytes.chunks(3) {
+            let bits = chunk.iter().enumerate().fold(0u32, |bits, (idx, &byte)| {
+                bits | (byte as u32) << (16 - 8 * idx)
+            });
+            // a chunk of n bytes is encoded by n + 1 chars
+            for idx in 0..=chunk.len() {
+                out.push(alphabet[(bits >> (18 - 6 * idx)) as usize & 0x3f] as char);
+            }
+            if !url_safe {
+                for _ in chunk.len()..3 {
+                    out.push('=');
+                }
+            }
+        }
+        out
+    }
+
+    /// Applies the modifier to the value.
+    pub fn apply(&self, value: &str) -> String {
+        match self {
+            Modifier::Upper => value.to_uppercase(),
+            Modifier::Lower => value.to_lowercase(),
+            Modifier::Trim => value.trim().to_string(),
+            Modifier::TrimCollapse => value.split_whitespace().collect::<Vec<_>>().join(" "),
+            Modifier::TrimStart => value.trim_start().to_string(),
+            Modifier::TrimEnd => value.trim_end().to_string(),
+            Modifier::Capitalize => {
+                let mut chars = value.chars();
+                match chars.next() {
+                    Some(first) => first.to_uppercase().chain(chars).collect(),
+                    None => String::new(),
+                }
+            }
+            Modifier::Title => {
+                let mut out = String::with_capacity(value.len());
+                let mut word_start = true;
+                for ch in value.chars() {
+                    match word_start {
+                        true => out.extend(ch.to_uppercase()),
+                        false => out.extend(ch.to_lowercase()),
+                    }
+                    word_start = !ch.is_alphanumeric();
+                }
+                out
+            }
+            Modifier::Snake => Self::words(value)
+                .iter()
+                .map(|word| word.to_lowercase())
+                .collect::<Vec<_>>()
+                .join("_"),
+            Modifier::Kebab => Self::words(value)
+                .iter()
+                .map(|word| word.to_lowercase())
+                .collect::<Vec<_>>()
+                .join("-"),
+            Modifier::Camel => Self::words(value)
+                .iter()
+                .enumerate()
+                .map(|(idx, word)| {
+                    let word = word.to_lowercase();
+                    match idx {
+                        0 => word,
+                        _ => Modifier::Capitalize.apply(&word),
+                    }
+                })
+                .collect(),
+            Modifier::Html => html::escape(value),
+            Modifier::Json => {
+                let mut out = String::with_capacity(value.len() + 2);
+                out.push('"');
+                for ch in value.chars() {
+                    match ch {
+                        '"' => out.push_str("\\\""),
+                        '\\' => out.push_str("\\\\"),
+                        '\n' => out.push_str("\\n"),
+                        '\r' => out.push_str("\\r"),
+                        '\t' => out.push_str("\\t"),
+                        '\u{08}' => out.push_str("\\b"),
+                        '\u{0c}' => out.push_str("\\f"),
+                        _ if (ch as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", ch as u32)),
+                        _ => out.push(ch),
+                    }
+                }
+                out.push('"');
+                out
+            }
+            // a single quote can't be escaped inside single quotes, so it is closed, escaped
+            // and reopened
+            Modifier::Shell => format!("'{}'", value.replace('\'', "'\\''")),
+            Modifier::Base64 { url_safe } => Self::base64(value.as_bytes(), *url_safe),
+            Modifier::UrlEncode(encoding) => {
+                let mut out = String::with_capacity(value.len());
+                for byte in value.bytes() {
+                    match (byte, encoding) {
+                        (
+                            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~',
+                            _,
+                        )
+                        | (b'/', UrlEncoding::Path) => out.push(byte as char),
+                        (b' ', UrlEncoding::Query) => out.push('+'),
+                        _ => out.push_str(&format!("%{byte:02X}")),
+                    }
+                }
+                out
+            }
+            Modifier::Radix {
+                radix,
+                prefix,
+                width,
+            } => {
+                // values which aren't integers are rendered unchanged
+                let Ok(number) = value.trim().parse::<i128>() else {
+                    return value.to_string();
+                };
+                let digits = match radix {
+                    16 => format!("{:x}", number.unsigned_abs()),
+                    8 => format!("{:o}", number.unsigned_abs()),
+                    _ => format!("{:b}", number.unsigned_abs()),
+                };
+                let sign = if number < 0 { "-" } else { "" };
+                let prefix = match (prefix, radix) {
+                    (false, _) => "",
+                    (true, 16) => "0x",
+                    (true, 8) => "0o",
+                    (true, _) => "0b",
+                };
+                format!("{sign}{prefix}{digits:0>width$}")
+            }
+            Modifier::Plural { singular, plural } => {
+                // values which aren't numbers are rendered unchanged
+                match value.trim().parse::<f64>() {
+                    Ok(number) if number.abs() == 1.0 => singular.clone(),
+                    Ok(_) => plural.clone(),
+                    Err(_) => value.to_string(),
+                }
+            }
+            Modifier::Bytes { si, precision } => {
+                // values which aren't byte counts are rendered unchanged
+                let Ok(count) = value.trim().parse::<u64>() else {
+                    return value.to_string();
+                };
+                let (base, units) = match si {
+                    true => (1000.0, ["B", "kB", "MB", "GB", "TB", "PB", "EB"]),
+                    false => (1024.0, ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"]),
+                };
+                let mut size = count as f64;
+                let mut unit = 0;
+                while size >= base && unit < units.len() - 1 {
+                    size /= base;
+                    unit += 1;
+                }
+                if unit == 0 {
+                    return format!("{} {}", count, units[0]);
+                }
+                let precision = precision.unwrap_or(if size < 10.0 { 1 } else { 0 });
+                format!("{:.*} {}", precision, size, units[unit])
+            }
+            Modifier::Repeat(count) => value.repeat(*count),
+            Modifier::Replace { from, to } => value.replace(from.as_str(), to),
+            #[cfg(feature = "regex")]
+            Modifier::ReplaceRegex { pattern, to } => {
+                pattern.replace_all(value, to.as_str()).into_owned()
+            }
+            #[cfg(feature = "chrono")]
+            Modifier::Date(pattern) => {
+                // values which aren't timestamps are rendered unchanged
+                let trimmed = value.trim();
+                if let Ok(date) = DateTime::parse_from_rfc3339(trimmed) {
+                    return date.format(pattern).to_string();
+                }
+                match Self::epoch(trimmed) {
+                    Some(date) => date.format(pattern).to_string(),
+                    None => value.to_string(),
+                }
+            }
+            #[cfg(feature = "chrono")]
+            Modifier::RelDate(clock) => {
+                // values which aren't timestamps are rendered unchanged
+                let trimmed = value.trim();
+                let date = DateTime::parse_from_rfc3339(trimmed)
+                    .map(|date| date.to_utc())
+                    .ok()
+                    .or_else(|| Self::epoch(trimmed));
+                match date {
+                    Some(date) => clock::relative(date, clock()),
+                    None => value.to_string(),
+                }
+            }
+            Modifier::Custom(modifier) => modifier(value),
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use super::render_error::RenderError;
+
+/// Defines how the width of multi-byte characters is counted when a guaranteed-width
+/// rendering is requested.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MultiBytePolicy {
+    /// Every char counts as one column, regardless of its UTF-8 encoded length.
+    Chars,
+    /// The width is the length of the UTF-8 encoded output in bytes.
+    Bytes,
+    /// Non-ASCII characters are rejected, so chars and bytes are always identical.
+    RejectNonAscii,
+}
+
+impl MultiBytePolicy {
+    /// Returns the width of the output, `RenderError::NonAsciiOutput` if the policy rejects it.
+    pub(crate) fn measure(self, out: &str) -> Result<usize, RenderError> {
+        match self {
+            MultiBytePolicy::Chars => Ok(out.chars().count()),
+            MultiBytePolicy::Bytes => Ok(out.len()),
+            MultiBytePolicy::RejectNonAscii => match out.chars().position(|ch| !ch.is_ascii()) {
+                Some(position) => Err(RenderError::NonAsciiOutput { position }),
+                None => Ok(out.len()),
+            },
+        }
+    }
+}
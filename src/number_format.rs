@@ -0,0 +1,124 @@
+#[cfg(feature = "num-format")]
+use num_format::{Grouping, Locale};
+
+/// Separators used by the numeric format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberSymbols {
+    /// Separator between the integer and the fractional digits.
+    pub decimal: &'static str,
+    /// Separator between digit groups.
+    pub group: &'static str,
+    /// Group the first three digits and all further digits in groups of two (e.g. 1,00,000).
+    pub indian_grouping: bool,
+}
+
+impl Default for NumberSymbols {
+    fn default() -> Self {
+        Self {
+            decimal: ".",
+            group: ",",
+            indian_grouping: false,
+        }
+    }
+}
+
+#[cfg(feature = "num-format")]
+impl NumberSymbols {
+    /// Returns the separators of a locale like `de-DE` or `en`. A locale which is unknown with
+    /// its region falls back to its language. `None` if the language is unknown as well.
+    pub fn from_locale(name: &str) -> Option<Self> {
+        let locale = Locale::from_name(name)
+            .or_else(|_| Locale::from_name(name.split(['-', '_']).next().unwrap_or(name)))
+            .ok()?;
+        let group = match locale.grouping() {
+            Grouping::Posix => "",
+            _ => locale.separator(),
+        };
+        Some(Self {
+            decimal: locale.decimal(),
+            group,
+            indian_grouping: locale.grouping() == Grouping::Indian,
+        })
+    }
+}
+
+/// Numeric format of a value placeholder like `%(amount,num=.2)` or `%(count,num=,)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberFormat {
+    /// Number of decimal places the value is rounded to.
+    pub precision: Option<usize>,
+    /// Group the integer digits in thousands.
+    pub thousands: bool,
+}
+
+impl NumberFormat {
+    /// Parses the specification following `num=`: an optional `,` for thousands separators
+    /// followed by an optional `.N` precision, e.g. `,`, `.2` or `,.2`.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (thousands, rest) = match spec.strip_prefix(',') {
+            Some(rest) => (true, rest),
+            None => (false, spec),
+        };
+        let precision = match rest {
+            "" => None,
+            _ => {
+                let digits = rest.strip_prefix('.')?;
+                if digits.is_empty() || !digits.chars().all(|ch| ch.is_ascii_digit()) {
+                    return None;
+                }
+                Some(digits.parse().ok()?)
+            }
+        };
+        if !thousands && precision.is_none() {
+            return None;
+        }
+        Some(Self {
+            precision,
+            thousands,
+        })
+    }
+
+    /// Formats the value with the given separators if it is a decimal number. Other values are
+    /// returned unchanged.
+    pub fn apply(&self, value: &str, symbols: &NumberSymbols) -> String {
+        let trimmed = value.trim();
+        let is_number = !trimmed.is_empty()
+            && trimmed
+                .chars()
+                .all(|ch| ch.is_ascii_digit() || matches!(ch, '-' | '+' | '.' | 'e' | 'E'));
+        let Some(number) = is_number.then(|| trimmed.parse::<f64>().ok()).flatten() else {
+            return value.to_string();
+        };
+
+        let formatted = match self.precision {
+            Some(precision) => format!("{number:.precision$}"),
+            None => trimmed.trim_start_matches('+').to_string(),
+        };
+        let (sign, unsigned) = match formatted.strip_prefix('-') {
+            Some(unsigned) => ("-", unsigned),
+            None => ("", formatted.as_str()),
+        };
+        let split = unsigned.find(['.', 'e', 'E']).unwrap_or(unsigned.len());
+        let (integer, fraction) = unsigned.split_at(split);
+
+        let fraction = fraction.replacen('.', symbols.decimal, 1);
+        if !self.thousands {
+            return format!("{sign}{integer}{fraction}");
+        }
+
+        let mut grouped = String::with_capacity(formatted.len() + integer.len() / 2);
+        let digits = integer.len();
+        for (idx, ch) in integer.chars().enumerate() {
+            let remaining = digits - idx;
+            let boundary = match symbols.indian_grouping {
+                true if remaining > 3 => (remaining - 3) % 2 == 0,
+                _ => remaining % 3 == 0,
+            };
+            if idx > 0 && boundary {
+                grouped.push_str(symbols.group);
+            }
+            grouped.push(ch);
+        }
+        format!("{sign}{grouped}{fraction}")
+    }
+}
@@ -0,0 +1,96 @@
+use std::ops::Range;
+
+/// Replacement of a char range of a previously rendered output, e.g. to repaint a changed cell
+/// of a TUI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputEdit {
+    /// Char range of the previous output which is replaced.
+    pub range: Range<usize>,
+    /// Text which replaces the range.
+    pub text: String,
+}
+
+/// Returns the edits which turn `previous` into `current`, ordered by their range.
+///
+/// Lines of equal length are compared char by char, so each changed cell of a fixed-width layout
+/// becomes its own edit. Other lines are replaced between their common prefix and suffix.
+pub fn diff_outputs(previous: &str, current: &str) -> Vec<OutputEdit> {
+    let previous_lines: Vec<Vec<char>> =
+        previous.split('\n').map(|l| l.chars().collect()).collect();
+    let current_lines: Vec<Vec<char>> = current.split('\n').map(|l| l.chars().collect()).collect();
+
+    let mut edits = Vec::new();
+    let mut offset = 0;
+    for (old, new) in previous_lines.iter().zip(&current_lines) {
+        if old.len() == new.len() {
+            diff_cells(old, new, offset, &mut edits);
+        } else {
+            diff_line(old, new, offset, &mut edits);
+        }
+        offset += old.len() + 1;
+    }
+
+    // lines which were added or removed at the end, starting at the newline of the last
+    // common line
+    let common = previous_lines.len().min(current_lines.len());
+    if previous_lines.len() != current_lines.len() {
+        let text = current_lines[common..]
+            .iter()
+            .map(|line| format!("\n{}", line.iter().collect::<String>()))
+            .collect();
+        edits.push(OutputEdit {
+            range: offset - 1..previous.chars().count(),
+            text,
+        });
+    }
+    merge_adjacent(edits)
+}
+
+/// Merges edits whose ranges touch into a single edit.
+fn merge_adjacent(edits: Vec<OutputEdit>) -> Vec<OutputEdit> {
+    let mut merged = Vec::<OutputEdit>::with_capacity(edits.len());
+    for edit in edits {
+        match merged.last_mut() {
+            Some(last) if last.range.end == edit.range.start => {
+                last.range.end = edit.range.end;
+                last.text.push_str(&edit.text);
+            }
+            _ => merged.push(edit),
+        }
+    }
+    merged
+}
+
+/// Pushes an edit for each run of differing chars of two lines of equal length.
+fn diff_cells(old: &[char], new: &[char], offset: usize, edits: &mut Vec<OutputEdit>) {
+    let mut idx = 0;
+    while idx < old.len() {
+        if old[idx] == new[idx] {
+            idx += 1;
+            continue;
+        }
+        let start = idx;
+        while idx < old.len() && old[idx] != new[idx] {
+            idx += 1;
+        }
+        edits.push(OutputEdit {
+            range: offset + start..offset + idx,
+            text: new[start..idx].iter().collect(),
+        });
+    }
+}
+
+/// Pushes an edit replacing the part between the common prefix and suffix of two lines.
+fn diff_line(old: &[char], new: &[char], offset: usize, edits: &mut Vec<OutputEdit>) {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    edits.push(OutputEdit {
+        range: offset + prefix..offset + old.len() - suffix,
+        text: new[prefix..new.len() - suffix].iter().collect(),
+    });
+}
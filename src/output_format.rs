@@ -1,3 +1,4 @@
+#[derive(Clone, Copy)]
 pub enum OutputFormat {
     None,
     LeftAlign(u32),
@@ -6,4 +7,78 @@ pub enum OutputFormat {
     RightAlign(u32),
     RightAlignTrunc(u32),
     RightAlignLTrunc(u32),
+    Justify(u32),
+}
+
+impl OutputFormat {
+    /// Parses a format specification like `<8`, `>12` or `<8,trunc`, i.e. the syntax of a
+    /// format placeholder without `%` and brackets.
+    pub fn parse_spec(spec: &str) -> Option<Self> {
+        let (align, rest) = spec.trim().split_at_checked(1)?;
+        let (width, arg) = match rest.split_once(',') {
+            Some((width, arg)) => (width, Some(arg.trim())),
+            None => (rest, None),
+        };
+        let width = width.trim();
+        if width.is_empty() || !width.chars().all(|ch| ch.is_ascii_digit()) {
+            return None;
+        }
+        let width: u32 = width.parse().ok()?;
+        match (align, arg) {
+            ("<", None) => Some(OutputFormat::LeftAlign(width)),
+            ("<", Some("trunc")) => Some(OutputFormat::LeftAlignTrunc(width)),
+            ("<", Some("ltrunc")) => Some(OutputFormat::LeftAlignLTrunc(width)),
+            ("<", Some("justify")) => Some(OutputFormat::Justify(width)),
+            (">", None) => Some(OutputFormat::RightAlign(width)),
+            (">", Some("trunc")) => Some(OutputFormat::RightAlignTrunc(width)),
+            (">", Some("ltrunc")) => Some(OutputFormat::RightAlignLTrunc(width)),
+            _ => None,
+        }
+    }
+
+    /// Returns the format specification like `<8,trunc`, the inverse of `parse_spec`. `None`
+    /// if no format is set.
+    pub fn spec(self) -> Option<String> {
+        let spec = match self {
+            OutputFormat::None => return None,
+            OutputFormat::LeftAlign(w) => format!("<{w}"),
+            OutputFormat::LeftAlignTrunc(w) => format!("<{w},trunc"),
+            OutputFormat::LeftAlignLTrunc(w) => format!("<{w},ltrunc"),
+            OutputFormat::RightAlign(w) => format!(">{w}"),
+            OutputFormat::RightAlignTrunc(w) => format!(">{w},trunc"),
+            OutputFormat::RightAlignLTrunc(w) => format!(">{w},ltrunc"),
+            OutputFormat::Justify(w) => format!("<{w},justify"),
+        };
+        Some(spec)
+    }
+
+    /// Returns the format with left and right alignment swapped.
+    pub fn mirrored(self) -> Self {
+        match self {
+            OutputFormat::None => OutputFormat::None,
+            OutputFormat::LeftAlign(w) => OutputFormat::RightAlign(w),
+            OutputFormat::LeftAlignTrunc(w) => OutputFormat::RightAlignTrunc(w),
+            OutputFormat::LeftAlignLTrunc(w) => OutputFormat::RightAlignLTrunc(w),
+            OutputFormat::RightAlign(w) => OutputFormat::LeftAlign(w),
+            OutputFormat::RightAlignTrunc(w) => OutputFormat::LeftAlignTrunc(w),
+            OutputFormat::RightAlignLTrunc(w) => OutputFormat::LeftAlignLTrunc(w),
+            OutputFormat::Justify(w) => OutputFormat::Justify(w),
+        }
+    }
+
+    /// Returns a truncating format of exactly `width` chars which keeps the alignment and
+    /// truncation side of this format. Values are left-aligned if no format is set.
+    pub fn with_exact_width(self, width: u32) -> Self {
+        match self {
+            OutputFormat::None
+            | OutputFormat::LeftAlign(_)
+            | OutputFormat::LeftAlignTrunc(_)
+            | OutputFormat::Justify(_) => OutputFormat::LeftAlignTrunc(width),
+            OutputFormat::LeftAlignLTrunc(_) => OutputFormat::LeftAlignLTrunc(width),
+            OutputFormat::RightAlign(_) | OutputFormat::RightAlignTrunc(_) => {
+                OutputFormat::RightAlignTrunc(width)
+            }
+            OutputFormat::RightAlignLTrunc(_) => OutputFormat::RightAlignLTrunc(width),
+        }
+    }
 }
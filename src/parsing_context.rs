@@ -1,11 +1,415 @@
+use super::audit::{AuditEntry, ValueSource};
+#[cfg(feature = "blocks")]
+use super::block_state::BlockState;
+#[cfg(feature = "chrono")]
+use super::clock::{self, ClockFn};
+#[cfg(feature = "colors")]
+use super::color_spec::{ColorSpec, ColorTarget};
+use super::display_width::char_width;
+use super::key_access::{KeyAccess, KeyPolicy};
+use super::line_wrap::LineWrap;
+#[cfg(feature = "markdown")]
+use super::markdown::MarkdownFlavor;
+use super::missing_key_policy::MissingKeyPolicy;
+use super::number_format::NumberSymbols;
 use super::output_format::OutputFormat;
+#[cfg(feature = "tables")]
+use super::parsing_task_cell_layout::CellLayout;
 use super::peek_char_iterator::PeekCharIterator;
+use super::quota::Capability;
+#[cfg(feature = "sandbox")]
+use super::quota::{Quota, QuotaState};
+use super::random::{self, RandomFn};
+use super::source_map::CharOrigin;
+use super::str_placeholder::StrPlaceholder;
+use super::template::CompiledPlaceholders;
+use super::truncation::Truncation;
+#[cfg(feature = "html")]
+use super::value_span::ValueSpan;
 
+use std::borrow::Cow;
 use std::collections::HashMap;
+#[cfg(feature = "sandbox")]
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Marker which replaces the cut-off part of a truncated value.
+pub const ELLIPSIS: char = '…';
 
 pub struct ParsingContext<'a, T> {
     pub key_value: &'a HashMap<&'a str, String>,
     pub iter: PeekCharIterator,
+    /// Value placeholders of the template parsed in advance. `None` if the template isn't
+    /// compiled.
+    pub compiled: Option<&'a CompiledPlaceholders>,
     pub vout: Vec<T>,
     pub format: OutputFormat,
+    /// Prefix of each line of the subsequent value, e.g. set by `%I(4)`.
+    pub block_indent: Option<String>,
+    /// Column of the output position in the current line. Maintained by the parsing tasks.
+    pub column: usize,
+    /// Line width used to resolve `*` widths. `None` if no target width is configured.
+    pub target_width: Option<usize>,
+    /// Mirror the layout of each line for right-to-left locales.
+    pub right_to_left: bool,
+    /// Line wrapping of the subsequent output. `None` if lines aren't wrapped.
+    pub wrap: Option<LineWrap>,
+    /// Output ranges of the rendered value placeholders. Used to mirror lines.
+    pub cells: Vec<(usize, usize)>,
+    /// Number of output items without visible width, e.g. of color sequences.
+    pub zero_width: usize,
+    /// Stack of the currently open blocks.
+    #[cfg(feature = "blocks")]
+    pub blocks: Vec<BlockState>,
+    /// Values bound by loop blocks. They shadow the values of the key-value map.
+    pub bindings: Vec<(String, String)>,
+    /// Source of the value of the innermost binding if it isn't a loop variable, e.g. of a
+    /// computed value.
+    pub binding_source: Option<ValueSource>,
+    /// Values of the formatter which are used if neither a loop binding nor the key-value map
+    /// provides a key.
+    pub ambient: Arc<HashMap<String, String>>,
+    /// Clock source of `%(now)`. `None` if no clock is configured.
+    #[cfg(feature = "chrono")]
+    pub clock: Option<&'a ClockFn>,
+    /// Random source of `%(uuid)` and `%(rand)`. `None` if no source is configured.
+    pub random: Option<&'a RandomFn>,
+    /// Rendering of value placeholders whose key can't be resolved.
+    pub missing_key: &'a MissingKeyPolicy,
+    /// Access policy which vetoes or masks values. `None` if all values are visible.
+    pub policy: Option<&'a KeyPolicy>,
+    /// Quota consumption of a sandboxed render. `None` if the render has no quotas.
+    #[cfg(feature = "sandbox")]
+    pub quota: Option<Rc<QuotaState>>,
+    /// Marker of truncated values.
+    pub ellipsis: char,
+    /// Target columns of the alignment anchors like `%|(sep)` by name.
+    pub anchor_targets: HashMap<String, usize>,
+    /// Names and columns of the rendered alignment anchors before they were aligned.
+    pub anchors: Vec<(String, usize)>,
+    /// Widths of the value placeholders rendered so far by key, referenced by
+    /// `%(width_of:key)`. `None` if the template doesn't reference widths.
+    pub rendered_widths: Option<HashMap<String, usize>>,
+    /// Keys whose values are currently being expanded recursively, outermost first.
+    pub expansions: Vec<String>,
+    /// Separators used by the numeric format.
+    pub number_symbols: NumberSymbols,
+    /// Count widths in monospace display columns instead of chars.
+    pub monospace: bool,
+    /// Widths of the value placeholders in order of appearance. Applied to value placeholders
+    /// without explicit format.
+    #[cfg(feature = "tables")]
+    pub cell_widths: Vec<usize>,
+    /// Number of table columns the next value placeholder spans, set by `%<(span=n)`.
+    #[cfg(feature = "tables")]
+    pub cell_span: usize,
+    /// Columns and spans of the rendered value placeholders. `None` if the layout isn't
+    /// requested.
+    #[cfg(feature = "tables")]
+    pub cell_layout: Option<Vec<CellLayout>>,
+    /// Exact widths of the value placeholders in order of appearance. Values are padded or
+    /// truncated to these widths regardless of their format.
+    pub exact_cell_widths: Vec<usize>,
+    /// Origin of each output char. `None` if no source map is requested.
+    pub origins: Option<Vec<CharOrigin>>,
+    /// Truncated values. `None` if truncations aren't requested.
+    pub truncations: Option<Vec<Truncation>>,
+    /// Spans of the values rendered by the markup targets. `None` if no markup is requested.
+    #[cfg(feature = "html")]
+    pub value_spans: Option<Vec<ValueSpan>>,
+    /// Keys of the placeholders with missing values and how often they were rendered, in order
+    /// of their first appearance. `None` if missing keys aren't requested.
+    pub missing_keys: Option<Vec<(String, usize)>>,
+    /// Resolved values in order of appearance. `None` if no audit is requested.
+    pub audit: Option<Vec<AuditEntry>>,
+    /// Dialect of the markdown target. `None` if markdown isn't requested.
+    #[cfg(feature = "markdown")]
+    pub markdown_flavor: Option<MarkdownFlavor>,
+    /// Stable identity of the template, attached to diagnostics.
+    #[cfg(feature = "tracing")]
+    pub template_id: String,
+    /// The output is rendered to be measured only, so no diagnostics or metrics are emitted.
+    pub measuring: bool,
+    /// Style classes of the last color placeholder. Used by the HTML target.
+    #[cfg(all(feature = "colors", feature = "html"))]
+    pub style_classes: Vec<String>,
+    /// Output format of color placeholders.
+    #[cfg(feature = "colors")]
+    pub color_target: ColorTarget,
+    /// Color specification whose BBCode tags are currently open.
+    #[cfg(feature = "colors")]
+    pub open_style: Option<ColorSpec>,
+    /// Color specifications with the output position they apply from. `None` if styles aren't
+    /// requested.
+    #[cfg(feature = "colors")]
+    pub style_runs: Option<Vec<(usize, ColorSpec)>>,
+}
+
+impl<'a, T> ParsingContext<'a, T> {
+    /// Creates a new context for parsing `inp` with `vout` as initial output.
+    pub fn new(inp: &str, key_value: &'a HashMap<&'a str, String>, vout: Vec<T>) -> Self {
+        let vec: Vec<_> = inp.chars().collect();
+        Self {
+            key_value,
+            iter: PeekCharIterator::new(vec),
+            compiled: None,
+            vout,
+            format: OutputFormat::None,
+            block_indent: None,
+            column: 0,
+            target_width: None,
+            right_to_left: false,
+            wrap: None,
+            cells: Vec::new(),
+            zero_width: 0,
+            #[cfg(feature = "blocks")]
+            blocks: Vec::new(),
+            bindings: Vec::new(),
+            binding_source: None,
+            ambient: Arc::default(),
+            #[cfg(feature = "chrono")]
+            clock: None,
+            random: None,
+            missing_key: &MissingKeyPolicy::Keep,
+            policy: None,
+            #[cfg(feature = "sandbox")]
+            quota: None,
+            ellipsis: ELLIPSIS,
+            anchor_targets: HashMap::new(),
+            anchors: Vec::new(),
+            rendered_widths: None,
+            expansions: Vec::new(),
+            number_symbols: NumberSymbols::default(),
+            monospace: false,
+            #[cfg(feature = "tables")]
+            cell_widths: Vec::new(),
+            #[cfg(feature = "tables")]
+            cell_span: 1,
+            #[cfg(feature = "tables")]
+            cell_layout: None,
+            exact_cell_widths: Vec::new(),
+            origins: None,
+            truncations: None,
+            #[cfg(feature = "html")]
+            value_spans: None,
+            missing_keys: None,
+            audit: None,
+            #[cfg(feature = "markdown")]
+            markdown_flavor: None,
+            #[cfg(feature = "tracing")]
+            template_id: String::new(),
+            measuring: false,
+            #[cfg(all(feature = "colors", feature = "html"))]
+            style_classes: Vec::new(),
+            #[cfg(feature = "colors")]
+            color_target: ColorTarget::Ansi,
+            #[cfg(feature = "colors")]
+            open_style: None,
+            #[cfg(feature = "colors")]
+            style_runs: None,
+        }
+    }
+
+    /// Looks up the value of a key. Loop bindings take precedence over the key-value map, which
+    /// takes precedence over the ambient values. The built-ins like `now` or `uuid` are used if
+    /// no other value is present.
+    pub fn lookup(&self, key: &str) -> Option<String> {
+        if let Some((_, value)) = self.bindings.iter().rev().find(|(name, _)| name == key) {
+            return Some(value.clone());
+        }
+        if let Some(value) = self.key_value.get(key) {
+            return self.police(key, value.clone());
+        }
+        match self.ambient.get(key) {
+            Some(value) if self.allows(Capability::Ambient) => self.police(key, value.clone()),
+            Some(_) => None,
+            None => self.builtin(key).and_then(|value| self.police(key, value)),
+        }
+    }
+
+    /// Returns the source of the value of a key, following the lookup order of `lookup`.
+    pub fn source(&self, key: &str) -> Option<ValueSource> {
+        if let Some(idx) = self.bindings.iter().rposition(|(name, _)| name == key) {
+            return match &self.binding_source {
+                Some(source) if idx + 1 == self.bindings.len() => Some(source.clone()),
+                _ => Some(ValueSource::Binding),
+            };
+        }
+        if self.key_value.contains_key(key) {
+            Some(ValueSource::KeyValue)
+        } else if self.ambient.contains_key(key) {
+            Some(ValueSource::Ambient)
+        } else {
+            self.builtin(key).map(|_| ValueSource::Builtin)
+        }
+    }
+
+    /// Applies the access policy to the value of a key. `None` if the access is denied.
+    fn police(&self, key: &str, value: String) -> Option<String> {
+        let Some(policy) = self.policy else {
+            return Some(value);
+        };
+        match policy(key, &value) {
+            KeyAccess::Allow => Some(value),
+            KeyAccess::Deny => None,
+            KeyAccess::Mask(mask) => Some(mask),
+        }
+    }
+
+    /// Returns the key-value map as seen through the access policy, e.g. for extensions: denied
+    /// keys are removed and masked values replaced.
+    pub fn visible_key_value(&self) -> Cow<'a, HashMap<&'a str, String>> {
+        if self.policy.is_none() {
+            return Cow::Borrowed(self.key_value);
+        }
+        Cow::Owned(
+            self.key_value
+                .iter()
+                .filter_map(|(key, value)| Some((*key, self.police(key, value.clone())?)))
+                .collect(),
+        )
+    }
+
+    /// Returns true if the capability is allowed by the quotas of a sandboxed render. A denied
+    /// capability is recorded, which aborts the render.
+    #[cfg(feature = "sandbox")]
+    pub fn allows(&self, capability: Capability) -> bool {
+        self.quota
+            .as_ref()
+            .is_none_or(|quota| quota.allows(capability))
+    }
+
+    /// Without the `sandbox` feature there are no quotas, so all capabilities are allowed.
+    #[cfg(not(feature = "sandbox"))]
+    pub fn allows(&self, _capability: Capability) -> bool {
+        true
+    }
+
+    /// Returns true if `count` further output items stay within the quotas of a sandboxed
+    /// render. Loops which generate output check it in advance and again every
+    /// `QUOTA_CHECK_INTERVAL` items with `count` 0, which keeps the time budget.
+    #[cfg(feature = "sandbox")]
+    pub fn reserve_output(&self, count: usize) -> bool {
+        self.quota
+            .as_ref()
+            .is_none_or(|quota| quota.reserve_output(self.vout.len(), count))
+    }
+
+    /// Without the `sandbox` feature there are no quotas, so all output is allowed.
+    #[cfg(not(feature = "sandbox"))]
+    pub fn reserve_output(&self, _count: usize) -> bool {
+        true
+    }
+
+    /// Returns the value of a built-in key: `now` for the time of the clock, `uuid` for a random
+    /// UUID and `rand` for a random number.
+    fn builtin(&self, key: &str) -> Option<String> {
+        if !matches!(key, "now" | "uuid" | "rand") || !self.allows(Capability::DynamicBuiltins) {
+            return None;
+        }
+        match key {
+            #[cfg(feature = "chrono")]
+            "now" => self.clock.map(clock::now),
+            "uuid" => self.random.map(random::uuid),
+            "rand" => self.random.map(|random| random().to_string()),
+            _ => None,
+        }
+    }
+
+    /// Counts a rendered placeholder whose key is missing, if missing keys are requested.
+    pub fn record_missing_key(&mut self, key: &str) {
+        let Some(missing_keys) = self.missing_keys.as_mut() else {
+            return;
+        };
+        match missing_keys.iter_mut().find(|(missing, _)| missing == key) {
+            Some((_, count)) => *count += 1,
+            None => missing_keys.push((key.to_string(), 1)),
+        }
+    }
+
+    /// Resolves the value of a value placeholder, taking its default into account.
+    pub fn resolve(&self, placeholder: &StrPlaceholder) -> Option<String> {
+        #[cfg(feature = "sandbox")]
+        let max_len = self
+            .quota
+            .as_ref()
+            .map_or(usize::MAX, |quota| quota.remaining_output(self.vout.len()));
+        #[cfg(not(feature = "sandbox"))]
+        let max_len = usize::MAX;
+        let value = placeholder
+            .find_value(|key| self.lookup(key))
+            .map(|(_, value)| {
+                placeholder
+                    .transform_within(value, &self.number_symbols, max_len)
+                    .unwrap_or_else(|| {
+                        // a repeated value would exceed the output quota of a sandboxed render
+                        #[cfg(feature = "sandbox")]
+                        if let Some(quota) = self.quota.as_ref() {
+                            quota.exceed(Quota::OutputBytes);
+                        }
+                        String::new()
+                    })
+            });
+        match &placeholder.default {
+            Some(default) if value.as_ref().is_none_or(|value| value.is_empty()) => {
+                Some(default.clone())
+            }
+            _ => value,
+        }
+    }
+
+    /// Returns the key which provides the value of a value placeholder. `None` if the value is
+    /// missing or the default is used.
+    pub fn resolved_key<'p>(&self, placeholder: &'p StrPlaceholder) -> Option<&'p str> {
+        let (key, value) = placeholder.find_value(|key| self.lookup(key))?;
+        if placeholder.default.is_some()
+            && placeholder
+                .transform(value, &self.number_symbols)
+                .is_empty()
+        {
+            return None;
+        }
+        Some(key)
+    }
+
+    /// Returns true if the key is bound by a loop block.
+    pub fn is_bound(&self, key: &str) -> bool {
+        self.bindings.iter().any(|(name, _)| name == key)
+    }
+
+    /// Returns true if the current position is not part of an inactive block branch.
+    #[cfg(feature = "blocks")]
+    pub fn is_active(&self) -> bool {
+        self.blocks.last().is_none_or(|block| block.active)
+    }
+
+    /// Without the `blocks` feature there are no inactive block branches.
+    #[cfg(not(feature = "blocks"))]
+    pub fn is_active(&self) -> bool {
+        true
+    }
+
+    /// Returns the width of a char: one, or its display width in monospace mode.
+    pub fn char_width(&self, ch: char) -> usize {
+        if self.monospace {
+            char_width(ch)
+        } else {
+            1
+        }
+    }
+
+    /// Returns the width of a string: its number of chars, or its display width in monospace
+    /// mode.
+    pub fn width(&self, value: &str) -> usize {
+        value.chars().map(|ch| self.char_width(ch)).sum()
+    }
+
+    /// Advances the output column by the width of the given char.
+    pub fn advance_column(&mut self, ch: char) {
+        if ch == '\n' {
+            self.column = 0;
+        } else {
+            self.column += self.char_width(ch);
+        }
+    }
 }
@@ -1,4 +1,7 @@
+#[cfg(feature = "colors")]
+use super::color_spec::ColorSpec;
 use super::parsing_context::ParsingContext;
+use super::str_placeholder::StrPlaceholder;
 
 use std::collections::HashMap;
 
@@ -6,6 +9,10 @@ pub trait ParsingTask {
     type Item;
     type Output;
 
+    /// If true, the content of all block branches is processed regardless of their conditions.
+    #[cfg(feature = "blocks")]
+    const VISIT_ALL_BRANCHES: bool = false;
+
     /// Initializes the parsing context at the start of parsing.
     fn init<'a>(
         inp: &'a str,
@@ -21,9 +28,22 @@ pub trait ParsingTask {
     /// Copies a character from the input to the output as is.
     fn process_char(context: &mut ParsingContext<'_, Self::Item>, ch: char);
 
+    /// Removes the line feeds at the end of the output, e.g. for `%-(key)` with an empty value.
+    fn remove_trailing_line_feeds(_context: &mut ParsingContext<'_, Self::Item>) {}
+
     /// Processes a single character placeholder.
     fn process_char_placeholder(context: &mut ParsingContext<'_, Self::Item>, ch: char);
 
     /// Processes a placeholder represented by a string.
-    fn process_str_placeholder(context: &mut ParsingContext<'_, Self::Item>, arg: String);
+    fn process_str_placeholder(
+        context: &mut ParsingContext<'_, Self::Item>,
+        placeholder: StrPlaceholder,
+    );
+
+    /// Processes a color placeholder. Color placeholders have no visible width.
+    #[cfg(feature = "colors")]
+    fn process_color_placeholder(context: &mut ParsingContext<'_, Self::Item>, spec: &ColorSpec);
+
+    /// Called for keys which are referenced by the template without being rendered, e.g. conditions.
+    fn process_key_reference(_context: &mut ParsingContext<'_, Self::Item>, _key: &str) {}
 }
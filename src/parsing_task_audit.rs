@@ -0,0 +1,114 @@
+use super::audit::{AuditEntry, ValueSource};
+#[cfg(feature = "colors")]
+use super::color_spec::ColorSpec;
+use super::parsing_context::ParsingContext;
+use super::parsing_task::ParsingTask;
+use super::parsing_task_replace_placeholders::ParsingTaskReplacePlaceholders;
+use super::str_placeholder::StrPlaceholder;
+
+use std::collections::HashMap;
+
+/// Replaces placeholders like `ParsingTaskReplacePlaceholders` and additionally records each
+/// resolved value with its source.
+pub struct ParsingTaskAudit;
+
+impl ParsingTaskAudit {
+    /// Returns the audit entry of a value placeholder. `None` if its value is missing.
+    fn audit_entry(
+        context: &ParsingContext<'_, char>,
+        placeholder: &StrPlaceholder,
+    ) -> Option<AuditEntry> {
+        let rendered = context.resolve(placeholder)?;
+        let Some(key) = context.resolved_key(placeholder) else {
+            return Some(AuditEntry {
+                key: placeholder.key.clone(),
+                source: ValueSource::Default,
+                value: rendered,
+                transformed: false,
+                truncated: false,
+            });
+        };
+        let (_, value) = placeholder.find_value(|key| context.lookup(key))?;
+        Some(AuditEntry {
+            key: key.to_string(),
+            source: context.source(key)?,
+            transformed: rendered != value,
+            value,
+            truncated: false,
+        })
+    }
+}
+
+impl ParsingTask for ParsingTaskAudit {
+    type Item = char;
+    type Output = (String, Vec<AuditEntry>);
+
+    /// Called in case the context should be initialized
+    fn init<'a>(
+        inp: &'a str,
+        key_value: &'a HashMap<&'a str, String>,
+    ) -> ParsingContext<'a, Self::Item> {
+        let mut context = ParsingTaskReplacePlaceholders::init(inp, key_value);
+        context.audit = Some(Vec::new());
+        context.truncations = Some(Vec::new());
+        context
+    }
+
+    fn error(context: &mut ParsingContext<'_, Self::Item>) {
+        ParsingTaskReplacePlaceholders::error(context);
+    }
+
+    fn process_char(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
+        ParsingTaskReplacePlaceholders::process_char(context, ch);
+    }
+
+    fn remove_trailing_line_feeds(context: &mut ParsingContext<'_, Self::Item>) {
+        ParsingTaskReplacePlaceholders::remove_trailing_line_feeds(context);
+    }
+
+    fn process_char_placeholder(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
+        ParsingTaskReplacePlaceholders::process_char_placeholder(context, ch);
+    }
+
+    fn process_str_placeholder(
+        context: &mut ParsingContext<'_, Self::Item>,
+        placeholder: StrPlaceholder,
+    ) {
+        // the value is bound while it is rendered, so dynamic built-ins like `uuid` render the
+        // audited value
+        let pinned = context
+            .resolved_key(&placeholder)
+            .and_then(|key| Some((key.to_string(), context.lookup(key)?)));
+        let is_pinned = pinned.is_some();
+        let binding_source = context.binding_source.clone();
+        if let Some((key, value)) = pinned {
+            context.binding_source = context.source(&key);
+            context.bindings.push((key, value));
+        }
+        let entry = Self::audit_entry(context, &placeholder);
+        let truncation_count = context.truncations.as_ref().map_or(0, Vec::len);
+        ParsingTaskReplacePlaceholders::process_str_placeholder(context, placeholder);
+        if is_pinned {
+            context.bindings.pop();
+            context.binding_source = binding_source;
+        }
+        let Some(mut entry) = entry else {
+            return; // the placeholder was rendered unchanged
+        };
+
+        entry.truncated = context.truncations.as_ref().map_or(0, Vec::len) > truncation_count;
+        if let Some(audit) = context.audit.as_mut() {
+            audit.push(entry);
+        }
+    }
+
+    #[cfg(feature = "colors")]
+    fn process_color_placeholder(context: &mut ParsingContext<'_, Self::Item>, spec: &ColorSpec) {
+        ParsingTaskReplacePlaceholders::process_color_placeholder(context, spec);
+    }
+
+    fn done(mut context: ParsingContext<'_, Self::Item>) -> Self::Output {
+        let audit = context.audit.take().unwrap_or_default();
+        (ParsingTaskReplacePlaceholders::done(context), audit)
+    }
+}
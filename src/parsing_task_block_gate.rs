@@ -0,0 +1,78 @@
+#[cfg(feature = "colors")]
+use super::color_spec::ColorSpec;
+use super::parsing_context::ParsingContext;
+use super::parsing_task::ParsingTask;
+use super::str_placeholder::StrPlaceholder;
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// Forwards all calls to the parsing task `T` as long as the current position is not
+/// part of an inactive block branch. Content of inactive branches is parsed but not processed.
+pub struct ParsingTaskBlockGate<T> {
+    _task: PhantomData<T>,
+}
+
+impl<T: ParsingTask> ParsingTask for ParsingTaskBlockGate<T> {
+    type Item = T::Item;
+    type Output = T::Output;
+    #[cfg(feature = "blocks")]
+    const VISIT_ALL_BRANCHES: bool = T::VISIT_ALL_BRANCHES;
+
+    fn init<'a>(
+        inp: &'a str,
+        key_value: &'a HashMap<&'a str, String>,
+    ) -> ParsingContext<'a, Self::Item> {
+        T::init(inp, key_value)
+    }
+
+    fn done(context: ParsingContext<'_, Self::Item>) -> Self::Output {
+        T::done(context)
+    }
+
+    fn error(context: &mut ParsingContext<'_, Self::Item>) {
+        if context.is_active() {
+            T::error(context);
+        }
+    }
+
+    fn process_char(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
+        if context.is_active() {
+            T::process_char(context, ch);
+        }
+    }
+
+    fn remove_trailing_line_feeds(context: &mut ParsingContext<'_, Self::Item>) {
+        if context.is_active() {
+            T::remove_trailing_line_feeds(context);
+        }
+    }
+
+    fn process_char_placeholder(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
+        if context.is_active() {
+            T::process_char_placeholder(context, ch);
+        }
+    }
+
+    fn process_str_placeholder(
+        context: &mut ParsingContext<'_, Self::Item>,
+        placeholder: StrPlaceholder,
+    ) {
+        if context.is_active() {
+            T::process_str_placeholder(context, placeholder);
+        }
+    }
+
+    #[cfg(feature = "colors")]
+    fn process_color_placeholder(context: &mut ParsingContext<'_, Self::Item>, spec: &ColorSpec) {
+        if context.is_active() {
+            T::process_color_placeholder(context, spec);
+        }
+    }
+
+    fn process_key_reference(context: &mut ParsingContext<'_, Self::Item>, key: &str) {
+        if context.is_active() {
+            T::process_key_reference(context, key);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+#[cfg(feature = "colors")]
+use super::color_spec::ColorSpec;
+use super::parsing_context::ParsingContext;
+use super::parsing_task::ParsingTask;
+use super::parsing_task_replace_placeholders::ParsingTaskReplacePlaceholders;
+use super::str_placeholder::StrPlaceholder;
+
+use std::collections::HashMap;
+
+/// Columns of a rendered value placeholder within its line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellLayout {
+    /// Column the value starts at.
+    pub start: usize,
+    /// Column following the widest line of the value, including its padding.
+    pub end: usize,
+    /// Number of table columns the value spans, see `%<(span=n)`.
+    pub span: usize,
+}
+
+/// Replaces placeholders like `ParsingTaskReplacePlaceholders` and returns the layout of the
+/// rendered value placeholders instead of the output. Used to lay out joined tables.
+pub struct ParsingTaskCellLayout;
+
+impl ParsingTask for ParsingTaskCellLayout {
+    type Item = char;
+    type Output = Vec<CellLayout>;
+
+    /// Called in case the context should be initialized
+    fn init<'a>(
+        inp: &'a str,
+        key_value: &'a HashMap<&'a str, String>,
+    ) -> ParsingContext<'a, Self::Item> {
+        let mut context = ParsingTaskReplacePlaceholders::init(inp, key_value);
+        context.cell_layout = Some(Vec::new());
+        context
+    }
+
+    fn error(context: &mut ParsingContext<'_, Self::Item>) {
+        ParsingTaskReplacePlaceholders::error(context);
+    }
+
+    fn process_char(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
+        ParsingTaskReplacePlaceholders::process_char(context, ch);
+    }
+
+    fn remove_trailing_line_feeds(context: &mut ParsingContext<'_, Self::Item>) {
+        ParsingTaskReplacePlaceholders::remove_trailing_line_feeds(context);
+    }
+
+    fn process_char_placeholder(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
+        ParsingTaskReplacePlaceholders::process_char_placeholder(context, ch);
+    }
+
+    fn process_str_placeholder(
+        context: &mut ParsingContext<'_, Self::Item>,
+        placeholder: StrPlaceholder,
+    ) {
+        let start = context.column;
+        let cell_count = context.cells.len();
+        ParsingTaskReplacePlaceholders::process_str_placeholder(context, placeholder);
+        let Some(&(first, last)) = context.cells.get(cell_count) else {
+            return; // the placeholder was rendered unchanged
+        };
+
+        // values with line feeds or wrapped lines are as wide as their widest line
+        let width = context.vout[first..last]
+            .split(|&ch| ch == '\n')
+            .map(|line| line.iter().map(|&ch| context.char_width(ch)).sum())
+            .max()
+            .unwrap_or(0);
+        let cell = CellLayout {
+            start,
+            end: start + width,
+            span: context.cell_span,
+        };
+        if let Some(layout) = context.cell_layout.as_mut() {
+            layout.push(cell);
+        }
+    }
+
+    #[cfg(feature = "colors")]
+    fn process_color_placeholder(context: &mut ParsingContext<'_, Self::Item>, spec: &ColorSpec) {
+        ParsingTaskReplacePlaceholders::process_color_placeholder(context, spec);
+    }
+
+    fn done(context: ParsingContext<'_, Self::Item>) -> Self::Output {
+        context.cell_layout.unwrap_or_default()
+    }
+}
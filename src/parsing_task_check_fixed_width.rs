@@ -0,0 +1,61 @@
+#[cfg(feature = "colors")]
+use super::color_spec::ColorSpec;
+use super::output_format::OutputFormat;
+use super::parsing_context::ParsingContext;
+use super::parsing_task::ParsingTask;
+use super::render_error::RenderError;
+use super::str_placeholder::StrPlaceholder;
+
+use std::collections::HashMap;
+
+/// Collects all placeholders which would prevent a guaranteed-width rendering.
+pub struct ParsingTaskCheckFixedWidth;
+
+impl ParsingTask for ParsingTaskCheckFixedWidth {
+    type Item = RenderError;
+    type Output = Vec<RenderError>;
+
+    /// Called in case the context should be initialized
+    fn init<'a>(
+        inp: &'a str,
+        key_value: &'a HashMap<&'a str, String>,
+    ) -> ParsingContext<'a, Self::Item> {
+        ParsingContext::new(inp, key_value, Vec::<RenderError>::new())
+    }
+
+    fn error(_context: &mut ParsingContext<'_, Self::Item>) {}
+
+    fn process_char(_context: &mut ParsingContext<'_, Self::Item>, _ch: char) {}
+
+    fn process_char_placeholder(_context: &mut ParsingContext<'_, Self::Item>, _ch: char) {}
+
+    fn process_str_placeholder(
+        context: &mut ParsingContext<'_, Self::Item>,
+        placeholder: StrPlaceholder,
+    ) {
+        let key = placeholder.key.clone();
+        if context.resolve(&placeholder).is_none() {
+            let span = context.iter.mark2cur_bytes();
+            context.vout.push(RenderError::MissingKey { key, span });
+            return;
+        }
+
+        match context.format {
+            OutputFormat::LeftAlignTrunc(_)
+            | OutputFormat::LeftAlignLTrunc(_)
+            | OutputFormat::RightAlignTrunc(_)
+            | OutputFormat::RightAlignLTrunc(_) => {}
+            _ => {
+                context.vout.push(RenderError::UnboundedField { key });
+            }
+        }
+    }
+
+    #[cfg(feature = "colors")]
+    fn process_color_placeholder(_context: &mut ParsingContext<'_, Self::Item>, _spec: &ColorSpec) {
+    }
+
+    fn done(context: ParsingContext<'_, Self::Item>) -> Self::Output {
+        context.vout
+    }
+}
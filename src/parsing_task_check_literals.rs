@@ -0,0 +1,64 @@
+#[cfg(feature = "colors")]
+use super::color_spec::ColorSpec;
+use super::parsing_context::ParsingContext;
+use super::parsing_task::ParsingTask;
+use super::render_error::RenderError;
+use super::str_placeholder::StrPlaceholder;
+
+use std::collections::HashMap;
+
+/// Collects all sequences which would be passed through unchanged instead of being rendered.
+pub struct ParsingTaskCheckLiterals;
+
+impl ParsingTask for ParsingTaskCheckLiterals {
+    type Item = RenderError;
+    type Output = Vec<RenderError>;
+
+    /// Called in case the context should be initialized
+    fn init<'a>(
+        inp: &'a str,
+        key_value: &'a HashMap<&'a str, String>,
+    ) -> ParsingContext<'a, Self::Item> {
+        ParsingContext::new(inp, key_value, Vec::<RenderError>::new())
+    }
+
+    fn error(context: &mut ParsingContext<'_, Self::Item>) {
+        let position = context.iter.marked_position().unwrap_or(0);
+        let found = context.iter.get_mark2cur().unwrap_or_default();
+        context.vout.push(RenderError::MalformedPlaceholder {
+            position,
+            found: found.into_iter().collect(),
+        });
+    }
+
+    fn process_char(_context: &mut ParsingContext<'_, Self::Item>, _ch: char) {}
+
+    fn process_char_placeholder(_context: &mut ParsingContext<'_, Self::Item>, _ch: char) {}
+
+    fn process_str_placeholder(
+        context: &mut ParsingContext<'_, Self::Item>,
+        placeholder: StrPlaceholder,
+    ) {
+        if context.resolve(&placeholder).is_none() {
+            context.vout.push(RenderError::MissingKey {
+                key: placeholder.key,
+                span: context.iter.mark2cur_bytes(),
+            });
+        }
+    }
+
+    #[cfg(feature = "colors")]
+    fn process_color_placeholder(_context: &mut ParsingContext<'_, Self::Item>, _spec: &ColorSpec) {
+    }
+
+    fn done(context: ParsingContext<'_, Self::Item>) -> Self::Output {
+        #[cfg(feature = "blocks")]
+        if let Some(block) = context.blocks.first() {
+            let name = block.name.clone();
+            let mut errors = context.vout;
+            errors.push(RenderError::UnclosedBlock { name });
+            return errors;
+        }
+        context.vout
+    }
+}
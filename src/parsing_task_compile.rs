@@ -0,0 +1,155 @@
+#[cfg(feature = "colors")]
+use super::color_spec::ColorSpec;
+use super::parsing_context::ParsingContext;
+use super::parsing_task::ParsingTask;
+use super::str_placeholder::StrPlaceholder;
+use super::template::{CompiledPlaceholders, Instruction};
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Compiles a template: collects the parsed value placeholders of all branches by their position
+/// in the template and, if the template is flat, the instructions which render it.
+///
+/// The context has no values, so placeholders whose parsing depends on values, e.g. a repeat
+/// count given by a key, are invalid and remain to be parsed on each render. A template is flat
+/// if it consists of literal text, char, color and value placeholders and static formats like
+/// `%<(8,trunc)` only. Other placeholders, e.g. blocks, depend on the state of the render and are
+/// recorded as `None`.
+pub struct ParsingTaskCompile;
+
+impl ParsingTaskCompile {
+    /// Returns the char range of the placeholder between the mark and the current position.
+    fn placeholder_span<I>(context: &ParsingContext<'_, I>) -> Range<usize> {
+        context.iter.marked_position().unwrap_or(0)..context.iter.position()
+    }
+
+    /// Returns true if the chars of the template which aren't rendered by any instruction are
+    /// static formats like `%<(8)` or `%>(12,ltrunc)`. Formats relative to the column, e.g.
+    /// `%<|(20)` or `%<(*)`, widths of other placeholders and spans depend on the render.
+    fn has_static_formats(chars: &[char], instructions: &[Instruction]) -> bool {
+        let mut pos = 0;
+        let mut directives = Vec::new();
+        for span in instructions.iter().map(Instruction::span) {
+            // the chars of a rule like `%-(8)` share its span
+            if span.start > pos {
+                directives.extend_from_slice(&chars[pos..span.start]);
+            }
+            pos = pos.max(span.end);
+        }
+        directives.extend_from_slice(&chars[pos..]);
+
+        let mut rest = directives.as_slice();
+        while !rest.is_empty() {
+            let ['%', '<' | '>', '(', args @ ..] = rest else {
+                return false;
+            };
+            let Some(close) = args.iter().position(|&ch| ch == ')') else {
+                return false;
+            };
+            if args[..close].iter().any(|ch| matches!(ch, '*' | '%' | '=')) {
+                return false;
+            }
+            rest = &args[close + 1..];
+        }
+        true
+    }
+}
+
+impl ParsingTask for ParsingTaskCompile {
+    type Item = Option<Instruction>;
+    type Output = (CompiledPlaceholders, Option<Vec<Instruction>>);
+    #[cfg(feature = "blocks")]
+    const VISIT_ALL_BRANCHES: bool = true;
+
+    /// Called in case the context should be initialized
+    fn init<'a>(
+        inp: &'a str,
+        key_value: &'a HashMap<&'a str, String>,
+    ) -> ParsingContext<'a, Self::Item> {
+        let vout = Vec::<Self::Item>::new();
+        ParsingContext::new(inp, key_value, vout)
+    }
+
+    /// An invalid placeholder may become valid with values, e.g. `%(key|repeat:count)`.
+    fn error(context: &mut ParsingContext<'_, Self::Item>) {
+        context.vout.push(None);
+    }
+
+    fn process_char(context: &mut ParsingContext<'_, Self::Item>, _ch: char) {
+        let pos = context.iter.position() - 1;
+        match context.vout.last_mut() {
+            Some(Some(Instruction::Literal(range))) if range.end == pos => range.end += 1,
+            _ => context.vout.push(Some(Instruction::Literal(pos..pos + 1))),
+        }
+    }
+
+    fn remove_trailing_line_feeds(context: &mut ParsingContext<'_, Self::Item>) {
+        context.vout.push(None);
+    }
+
+    /// The padding of tab stops like `%T(20)` and alignment anchors like `%|(sep)` and rules like
+    /// `%-(*)` depend on the column.
+    fn process_char_placeholder(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
+        let span = Self::placeholder_span(context);
+        let source = context.iter.get_mark2cur().unwrap_or_default();
+        let instruction = match source[..] {
+            [_, 'T' | '|', ..] => None,
+            [_, '-', ref args @ ..] if args.iter().any(|ch| matches!(ch, '*' | '%')) => None,
+            _ => Some(Instruction::Char(ch, span)),
+        };
+        context.vout.push(instruction);
+    }
+
+    /// Computed values like extensions aren't parsed from the template. Prefixes like `%+(key)`
+    /// depend on the value, so only the placeholder is compiled.
+    fn process_str_placeholder(
+        context: &mut ParsingContext<'_, Self::Item>,
+        placeholder: StrPlaceholder,
+    ) {
+        if context.binding_source.is_some() {
+            context.vout.push(None);
+            return;
+        }
+        let span = Self::placeholder_span(context);
+        let prefixed = context.iter.get_mark2cur().unwrap_or_default().get(1) != Some(&'(');
+        context
+            .vout
+            .push(Some(Instruction::Value(placeholder, context.format, span)));
+        if prefixed {
+            context.vout.push(None);
+        }
+    }
+
+    #[cfg(feature = "colors")]
+    fn process_color_placeholder(context: &mut ParsingContext<'_, Self::Item>, spec: &ColorSpec) {
+        let span = Self::placeholder_span(context);
+        context
+            .vout
+            .push(Some(Instruction::Color(spec.clone(), span)));
+    }
+
+    fn process_key_reference(context: &mut ParsingContext<'_, Self::Item>, _key: &str) {
+        context.vout.push(None);
+    }
+
+    fn done(mut context: ParsingContext<'_, Self::Item>) -> Self::Output {
+        let placeholders = context
+            .vout
+            .iter()
+            .flatten()
+            .filter_map(|instruction| match instruction {
+                Instruction::Value(placeholder, _, span) => {
+                    Some((span.start, (placeholder.clone(), span.end)))
+                }
+                _ => None,
+            })
+            .collect();
+        let instructions = context
+            .vout
+            .drain(..)
+            .collect::<Option<Vec<_>>>()
+            .filter(|instructions| Self::has_static_formats(context.iter.chars(), instructions));
+        (placeholders, instructions)
+    }
+}
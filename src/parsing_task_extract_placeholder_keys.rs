@@ -1,7 +1,8 @@
-use super::output_format::OutputFormat;
+#[cfg(feature = "colors")]
+use super::color_spec::ColorSpec;
 use super::parsing_context::ParsingContext;
 use super::parsing_task::ParsingTask;
-use super::peek_char_iterator::PeekCharIterator;
+use super::str_placeholder::StrPlaceholder;
 
 use std::collections::HashMap;
 
@@ -9,20 +10,16 @@ pub struct ParsingTaskExtractPlaceholderKeys;
 impl ParsingTask for ParsingTaskExtractPlaceholderKeys {
     type Item = String;
     type Output = Vec<String>;
+    #[cfg(feature = "blocks")]
+    const VISIT_ALL_BRANCHES: bool = true;
 
     /// Called in case the context should be initialized
     fn init<'a>(
         inp: &'a str,
         key_value: &'a HashMap<&'a str, String>,
     ) -> ParsingContext<'a, Self::Item> {
-        let vec: Vec<_> = inp.chars().collect();
         let vout = Vec::<Self::Item>::new();
-        ParsingContext::<'_, Self::Item> {
-            key_value,
-            iter: PeekCharIterator::new(vec),
-            vout,
-            format: OutputFormat::None,
-        }
+        ParsingContext::new(inp, key_value, vout)
     }
 
     fn error(_context: &mut ParsingContext<'_, Self::Item>) {}
@@ -31,8 +28,24 @@ impl ParsingTask for ParsingTaskExtractPlaceholderKeys {
 
     fn process_char_placeholder(_context: &mut ParsingContext<'_, Self::Item>, _ch: char) {}
 
-    fn process_str_placeholder(context: &mut ParsingContext<'_, Self::Item>, arg: String) {
-        context.vout.push(arg);
+    fn process_str_placeholder(
+        context: &mut ParsingContext<'_, Self::Item>,
+        placeholder: StrPlaceholder,
+    ) {
+        for key in placeholder.keys() {
+            if context.is_bound(key) {
+                continue; // loop variables are not part of the key-value map
+            }
+            context.vout.push(key.clone());
+        }
+    }
+
+    #[cfg(feature = "colors")]
+    fn process_color_placeholder(_context: &mut ParsingContext<'_, Self::Item>, _spec: &ColorSpec) {
+    }
+
+    fn process_key_reference(context: &mut ParsingContext<'_, Self::Item>, key: &str) {
+        context.vout.push(key.to_string());
     }
 
     fn done(context: ParsingContext<'_, Self::Item>) -> Self::Output {
@@ -0,0 +1,100 @@
+#[cfg(feature = "colors")]
+use super::color_spec::ColorSpec;
+use super::html::render_html;
+#[cfg(feature = "markdown")]
+use super::output_format::OutputFormat;
+use super::parsing_context::ParsingContext;
+use super::parsing_task::ParsingTask;
+use super::parsing_task_replace_placeholders::ParsingTaskReplacePlaceholders;
+#[cfg(feature = "metrics")]
+use super::render_metrics;
+use super::str_placeholder::StrPlaceholder;
+use super::value_span::{mirror_spans, ValueSpan};
+
+use std::collections::HashMap;
+
+/// Replaces placeholders like `ParsingTaskReplacePlaceholders` and renders the output as HTML.
+/// Each substituted value is wrapped in a `<span>` carrying its key name and style classes.
+pub struct ParsingTaskHtml;
+
+impl ParsingTask for ParsingTaskHtml {
+    type Item = char;
+    type Output = String;
+
+    /// Called in case the context should be initialized
+    fn init<'a>(
+        inp: &'a str,
+        key_value: &'a HashMap<&'a str, String>,
+    ) -> ParsingContext<'a, Self::Item> {
+        let mut context = ParsingTaskReplacePlaceholders::init(inp, key_value);
+        context.value_spans = Some(Vec::new());
+        context
+    }
+
+    fn error(context: &mut ParsingContext<'_, Self::Item>) {
+        ParsingTaskReplacePlaceholders::error(context);
+    }
+
+    fn process_char(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
+        ParsingTaskReplacePlaceholders::process_char(context, ch);
+    }
+
+    fn remove_trailing_line_feeds(context: &mut ParsingContext<'_, Self::Item>) {
+        ParsingTaskReplacePlaceholders::remove_trailing_line_feeds(context);
+    }
+
+    fn process_char_placeholder(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
+        ParsingTaskReplacePlaceholders::process_char_placeholder(context, ch);
+    }
+
+    fn process_str_placeholder(
+        context: &mut ParsingContext<'_, Self::Item>,
+        placeholder: StrPlaceholder,
+    ) {
+        let key = context.resolved_key(&placeholder).map(str::to_string);
+        let cell_count = context.cells.len();
+        ParsingTaskReplacePlaceholders::process_str_placeholder(context, placeholder);
+        let Some(&(start, end)) = context.cells.get(cell_count) else {
+            return; // the placeholder was rendered unchanged
+        };
+
+        #[cfg(feature = "colors")]
+        let classes = context.style_classes.clone();
+        #[cfg(not(feature = "colors"))]
+        let classes = Vec::new();
+        #[cfg(feature = "markdown")]
+        let aligned = !matches!(context.format, OutputFormat::None);
+        if let Some(spans) = context.value_spans.as_mut() {
+            spans.push(ValueSpan {
+                output: start..end,
+                key,
+                classes,
+                #[cfg(feature = "markdown")]
+                aligned,
+            });
+        }
+    }
+
+    /// Color placeholders don't emit escape sequences. Their style classes are attached to the
+    /// spans of the subsequent values instead.
+    #[cfg(feature = "colors")]
+    fn process_color_placeholder(context: &mut ParsingContext<'_, Self::Item>, spec: &ColorSpec) {
+        context.style_classes = spec.to_classes();
+    }
+
+    fn done(mut context: ParsingContext<'_, Self::Item>) -> Self::Output {
+        let mut spans = context.value_spans.take().unwrap_or_default();
+        let out = match context.right_to_left {
+            true => {
+                let (order, mirrored) =
+                    ParsingTaskReplacePlaceholders::mirror_layout(&context.vout, &context.cells);
+                mirror_spans(&mut spans, &mirrored);
+                render_html(order.iter().map(|&idx| context.vout[idx]), &spans)
+            }
+            false => render_html(context.vout, &spans),
+        };
+        #[cfg(feature = "metrics")]
+        render_metrics::record_render(&out);
+        out
+    }
+}
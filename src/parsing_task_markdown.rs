@@ -0,0 +1,75 @@
+#[cfg(feature = "colors")]
+use super::color_spec::ColorSpec;
+use super::markdown::{render_markdown, MarkdownFlavor};
+use super::parsing_context::ParsingContext;
+use super::parsing_task::ParsingTask;
+use super::parsing_task_html::ParsingTaskHtml;
+use super::parsing_task_replace_placeholders::ParsingTaskReplacePlaceholders;
+#[cfg(feature = "metrics")]
+use super::render_metrics;
+use super::str_placeholder::StrPlaceholder;
+use super::value_span::mirror_spans;
+
+use std::collections::HashMap;
+
+/// Replaces placeholders like `ParsingTaskReplacePlaceholders` and renders the output as chat
+/// markdown. Collects the value spans like `ParsingTaskHtml`.
+pub struct ParsingTaskMarkdown;
+
+impl ParsingTask for ParsingTaskMarkdown {
+    type Item = char;
+    type Output = String;
+
+    /// Called in case the context should be initialized
+    fn init<'a>(
+        inp: &'a str,
+        key_value: &'a HashMap<&'a str, String>,
+    ) -> ParsingContext<'a, Self::Item> {
+        ParsingTaskHtml::init(inp, key_value)
+    }
+
+    fn error(context: &mut ParsingContext<'_, Self::Item>) {
+        ParsingTaskHtml::error(context);
+    }
+
+    fn process_char(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
+        ParsingTaskHtml::process_char(context, ch);
+    }
+
+    fn remove_trailing_line_feeds(context: &mut ParsingContext<'_, Self::Item>) {
+        ParsingTaskHtml::remove_trailing_line_feeds(context);
+    }
+
+    fn process_char_placeholder(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
+        ParsingTaskHtml::process_char_placeholder(context, ch);
+    }
+
+    fn process_str_placeholder(
+        context: &mut ParsingContext<'_, Self::Item>,
+        placeholder: StrPlaceholder,
+    ) {
+        ParsingTaskHtml::process_str_placeholder(context, placeholder);
+    }
+
+    #[cfg(feature = "colors")]
+    fn process_color_placeholder(context: &mut ParsingContext<'_, Self::Item>, spec: &ColorSpec) {
+        ParsingTaskHtml::process_color_placeholder(context, spec);
+    }
+
+    fn done(mut context: ParsingContext<'_, Self::Item>) -> Self::Output {
+        let mut spans = context.value_spans.take().unwrap_or_default();
+        let flavor = context.markdown_flavor.unwrap_or(MarkdownFlavor::Slack);
+        let out = match context.right_to_left {
+            true => {
+                let (order, mirrored) =
+                    ParsingTaskReplacePlaceholders::mirror_layout(&context.vout, &context.cells);
+                mirror_spans(&mut spans, &mirrored);
+                render_markdown(order.iter().map(|&idx| context.vout[idx]), &spans, flavor)
+            }
+            false => render_markdown(context.vout, &spans, flavor),
+        };
+        #[cfg(feature = "metrics")]
+        render_metrics::record_render(&out);
+        out
+    }
+}
@@ -0,0 +1,58 @@
+#[cfg(feature = "colors")]
+use super::color_spec::ColorSpec;
+use super::parsing_context::ParsingContext;
+use super::parsing_task::ParsingTask;
+use super::parsing_task_replace_placeholders::ParsingTaskReplacePlaceholders;
+use super::str_placeholder::StrPlaceholder;
+
+use std::collections::HashMap;
+
+/// Renders the output like `ParsingTaskReplacePlaceholders` and returns the names and columns of
+/// the alignment anchors like `%|(sep)`.
+pub struct ParsingTaskMeasureAnchors;
+
+impl ParsingTask for ParsingTaskMeasureAnchors {
+    type Item = char;
+    type Output = Vec<(String, usize)>;
+
+    fn init<'a>(
+        inp: &'a str,
+        key_value: &'a HashMap<&'a str, String>,
+    ) -> ParsingContext<'a, Self::Item> {
+        let mut context = ParsingTaskReplacePlaceholders::init(inp, key_value);
+        context.measuring = true;
+        context
+    }
+
+    fn error(context: &mut ParsingContext<'_, Self::Item>) {
+        ParsingTaskReplacePlaceholders::error(context);
+    }
+
+    fn process_char(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
+        ParsingTaskReplacePlaceholders::process_char(context, ch);
+    }
+
+    fn remove_trailing_line_feeds(context: &mut ParsingContext<'_, Self::Item>) {
+        ParsingTaskReplacePlaceholders::remove_trailing_line_feeds(context);
+    }
+
+    fn process_char_placeholder(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
+        ParsingTaskReplacePlaceholders::process_char_placeholder(context, ch);
+    }
+
+    fn process_str_placeholder(
+        context: &mut ParsingContext<'_, Self::Item>,
+        placeholder: StrPlaceholder,
+    ) {
+        ParsingTaskReplacePlaceholders::process_str_placeholder(context, placeholder);
+    }
+
+    #[cfg(feature = "colors")]
+    fn process_color_placeholder(context: &mut ParsingContext<'_, Self::Item>, spec: &ColorSpec) {
+        ParsingTaskReplacePlaceholders::process_color_placeholder(context, spec);
+    }
+
+    fn done(context: ParsingContext<'_, Self::Item>) -> Self::Output {
+        context.anchors
+    }
+}
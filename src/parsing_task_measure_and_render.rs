@@ -0,0 +1,71 @@
+#[cfg(feature = "colors")]
+use super::color_spec::ColorSpec;
+use super::parsing_context::ParsingContext;
+use super::parsing_task::ParsingTask;
+use super::parsing_task_replace_placeholders::ParsingTaskReplacePlaceholders;
+use super::str_placeholder::StrPlaceholder;
+
+use std::collections::HashMap;
+
+/// Replaces placeholders like `ParsingTaskReplacePlaceholders` and additionally returns the
+/// lengths `ParsingTaskMeasureLengths` measures, in a single pass.
+pub struct ParsingTaskMeasureAndRender;
+
+impl ParsingTask for ParsingTaskMeasureAndRender {
+    type Item = char;
+    type Output = (String, Vec<usize>);
+
+    /// Called in case the context should be initialized
+    fn init<'a>(
+        inp: &'a str,
+        key_value: &'a HashMap<&'a str, String>,
+    ) -> ParsingContext<'a, Self::Item> {
+        ParsingTaskReplacePlaceholders::init(inp, key_value)
+    }
+
+    fn error(context: &mut ParsingContext<'_, Self::Item>) {
+        ParsingTaskReplacePlaceholders::error(context);
+    }
+
+    fn process_char(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
+        ParsingTaskReplacePlaceholders::process_char(context, ch);
+    }
+
+    fn remove_trailing_line_feeds(context: &mut ParsingContext<'_, Self::Item>) {
+        ParsingTaskReplacePlaceholders::remove_trailing_line_feeds(context);
+    }
+
+    fn process_char_placeholder(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
+        ParsingTaskReplacePlaceholders::process_char_placeholder(context, ch);
+    }
+
+    fn process_str_placeholder(
+        context: &mut ParsingContext<'_, Self::Item>,
+        placeholder: StrPlaceholder,
+    ) {
+        ParsingTaskReplacePlaceholders::process_str_placeholder(context, placeholder);
+    }
+
+    #[cfg(feature = "colors")]
+    fn process_color_placeholder(context: &mut ParsingContext<'_, Self::Item>, spec: &ColorSpec) {
+        let start = context.vout.len();
+        ParsingTaskReplacePlaceholders::process_color_placeholder(context, spec);
+        context.zero_width += context.vout.len() - start;
+    }
+
+    fn done(context: ParsingContext<'_, Self::Item>) -> Self::Output {
+        let lengths = Self::lengths(&context);
+        (ParsingTaskReplacePlaceholders::done(context), lengths)
+    }
+}
+
+impl ParsingTaskMeasureAndRender {
+    /// Returns the length of the output followed by the length of each rendered value
+    /// placeholder.
+    pub fn lengths(context: &ParsingContext<'_, char>) -> Vec<usize> {
+        // Color sequences are zero-width
+        let mut lengths = vec![context.vout.len() - context.zero_width];
+        lengths.extend(context.cells.iter().map(|(start, end)| end - start));
+        lengths
+    }
+}
@@ -1,13 +1,21 @@
-use super::output_format::OutputFormat;
+#[cfg(feature = "colors")]
+use super::color_spec::ColorSpec;
 use super::parsing_context::ParsingContext;
 use super::parsing_task::ParsingTask;
-use super::peek_char_iterator::PeekCharIterator;
+use super::parsing_task_measure_and_render::ParsingTaskMeasureAndRender;
+use super::str_placeholder::StrPlaceholder;
 
-use std::{cmp::max, collections::HashMap};
+use std::collections::HashMap;
 
+/// Measures the length of the output and the lengths of the rendered value placeholders.
+///
+/// The output is rendered like `ParsingTaskReplacePlaceholders`, so everything which affects the
+/// layout, e.g. line wrapping, block indents, justified blocks, removed line feeds and widths
+/// relative to the column, is measured exactly as it is rendered.
 pub struct ParsingTaskMeasureLengths;
+
 impl ParsingTask for ParsingTaskMeasureLengths {
-    type Item = usize;
+    type Item = char;
     type Output = Vec<usize>;
 
     /// Called in case the context should be initialized
@@ -15,57 +23,40 @@ impl ParsingTask for ParsingTaskMeasureLengths {
         inp: &'a str,
         key_value: &'a HashMap<&'a str, String>,
     ) -> ParsingContext<'a, Self::Item> {
-        let vec: Vec<_> = inp.chars().collect();
-        let vout = vec![0];
-        ParsingContext::<'_, Self::Item> {
-            key_value,
-            iter: PeekCharIterator::new(vec),
-            vout,
-            format: OutputFormat::None,
-        }
+        let mut context = ParsingTaskMeasureAndRender::init(inp, key_value);
+        context.measuring = true;
+        context
     }
 
     fn error(context: &mut ParsingContext<'_, Self::Item>) {
-        context.vout[0] += context.iter.get_mark2cur().unwrap().len();
+        ParsingTaskMeasureAndRender::error(context);
+    }
+
+    fn process_char(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
+        ParsingTaskMeasureAndRender::process_char(context, ch);
     }
 
-    fn process_char(context: &mut ParsingContext<'_, Self::Item>, _ch: char) {
-        context.vout[0] += 1;
+    fn remove_trailing_line_feeds(context: &mut ParsingContext<'_, Self::Item>) {
+        ParsingTaskMeasureAndRender::remove_trailing_line_feeds(context);
     }
 
-    fn process_char_placeholder(context: &mut ParsingContext<'_, Self::Item>, _ch: char) {
-        context.vout[0] += 1;
+    fn process_char_placeholder(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
+        ParsingTaskMeasureAndRender::process_char_placeholder(context, ch);
     }
 
-    fn process_str_placeholder(context: &mut ParsingContext<'_, Self::Item>, arg: String) {
-        let Some(repl_str) = context.key_value.get(arg.as_str()) else {
-            Self::error(context);
-            return;
-        };
-        let repl_c = repl_str.chars().count();
+    fn process_str_placeholder(
+        context: &mut ParsingContext<'_, Self::Item>,
+        placeholder: StrPlaceholder,
+    ) {
+        ParsingTaskMeasureAndRender::process_str_placeholder(context, placeholder);
+    }
 
-        match context.format {
-            OutputFormat::None => {
-                context.vout[0] += repl_c;
-                context.vout.push(repl_c);
-            }
-            OutputFormat::LeftAlign(width) | OutputFormat::RightAlign(width) => {
-                let repl_c_max = max(repl_c, width as usize);
-                context.vout[0] += repl_c_max;
-                context.vout.push(repl_c_max);
-            }
-            OutputFormat::LeftAlignTrunc(width)
-            | OutputFormat::RightAlignTrunc(width)
-            | OutputFormat::LeftAlignLTrunc(width)
-            | OutputFormat::RightAlignLTrunc(width) => {
-                let repl_c = width as usize;
-                context.vout[0] += repl_c;
-                context.vout.push(repl_c);
-            }
-        }
+    #[cfg(feature = "colors")]
+    fn process_color_placeholder(context: &mut ParsingContext<'_, Self::Item>, spec: &ColorSpec) {
+        ParsingTaskMeasureAndRender::process_color_placeholder(context, spec);
     }
 
     fn done(context: ParsingContext<'_, Self::Item>) -> Self::Output {
-        context.vout
+        ParsingTaskMeasureAndRender::lengths(&context)
     }
 }
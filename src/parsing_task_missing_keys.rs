@@ -0,0 +1,60 @@
+#[cfg(feature = "colors")]
+use super::color_spec::ColorSpec;
+use super::parsing_context::ParsingContext;
+use super::parsing_task::ParsingTask;
+use super::parsing_task_replace_placeholders::ParsingTaskReplacePlaceholders;
+use super::str_placeholder::StrPlaceholder;
+
+use std::collections::HashMap;
+
+/// Replaces placeholders like `ParsingTaskReplacePlaceholders` and additionally counts the keys
+/// of the placeholders whose value is missing.
+pub struct ParsingTaskMissingKeys;
+
+impl ParsingTask for ParsingTaskMissingKeys {
+    type Item = char;
+    type Output = (String, Vec<(String, usize)>);
+
+    /// Called in case the context should be initialized
+    fn init<'a>(
+        inp: &'a str,
+        key_value: &'a HashMap<&'a str, String>,
+    ) -> ParsingContext<'a, Self::Item> {
+        let mut context = ParsingTaskReplacePlaceholders::init(inp, key_value);
+        context.missing_keys = Some(Vec::new());
+        context
+    }
+
+    fn error(context: &mut ParsingContext<'_, Self::Item>) {
+        ParsingTaskReplacePlaceholders::error(context);
+    }
+
+    fn process_char(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
+        ParsingTaskReplacePlaceholders::process_char(context, ch);
+    }
+
+    fn remove_trailing_line_feeds(context: &mut ParsingContext<'_, Self::Item>) {
+        ParsingTaskReplacePlaceholders::remove_trailing_line_feeds(context);
+    }
+
+    fn process_char_placeholder(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
+        ParsingTaskReplacePlaceholders::process_char_placeholder(context, ch);
+    }
+
+    fn process_str_placeholder(
+        context: &mut ParsingContext<'_, Self::Item>,
+        placeholder: StrPlaceholder,
+    ) {
+        ParsingTaskReplacePlaceholders::process_str_placeholder(context, placeholder);
+    }
+
+    #[cfg(feature = "colors")]
+    fn process_color_placeholder(context: &mut ParsingContext<'_, Self::Item>, spec: &ColorSpec) {
+        ParsingTaskReplacePlaceholders::process_color_placeholder(context, spec);
+    }
+
+    fn done(mut context: ParsingContext<'_, Self::Item>) -> Self::Output {
+        let missing_keys = context.missing_keys.take().unwrap_or_default();
+        (ParsingTaskReplacePlaceholders::done(context), missing_keys)
+    }
+}
@@ -1,12 +1,416 @@
+#[cfg(feature = "colors")]
+use super::color_spec::{ColorSpec, ColorTarget};
 use super::output_format::OutputFormat;
 use super::parsing_context::ParsingContext;
 use super::parsing_task::ParsingTask;
-use super::peek_char_iterator::PeekCharIterator;
+use super::quota::QUOTA_CHECK_INTERVAL;
+#[cfg(feature = "metrics")]
+use super::render_metrics;
+use super::source_map::CharOrigin;
+use super::str_placeholder::StrPlaceholder;
+use super::truncation::Truncation;
 
 use std::collections::HashMap;
+use std::ops::Range;
+use std::rc::Rc;
 
 pub struct ParsingTaskReplacePlaceholders;
 
+impl ParsingTaskReplacePlaceholders {
+    fn push_with_origin(context: &mut ParsingContext<'_, char>, ch: char, origin: CharOrigin) {
+        if context.wrap.is_none() {
+            // lines aren't wrapped, so there is neither an indentation nor a break point
+            Self::append(context, ch, origin);
+            return;
+        }
+        if ch != '\n' {
+            Self::indent_line(context);
+        }
+        Self::append(context, ch, origin);
+        Self::wrap_line(context, ch);
+    }
+
+    /// Appends a char to the output without wrapping lines.
+    fn append(context: &mut ParsingContext<'_, char>, ch: char, origin: CharOrigin) {
+        context.advance_column(ch);
+        context.vout.push(ch);
+        if let Some(origins) = context.origins.as_mut() {
+            origins.push(origin);
+        }
+    }
+
+    /// Indents the line before its first char if lines are wrapped: the first line by `indent1`
+    /// and the continuation lines by `indent2`.
+    fn indent_line(context: &mut ParsingContext<'_, char>) {
+        let column = context.column;
+        let Some(wrap) = context.wrap.as_mut() else {
+            return;
+        };
+        let indent = match (wrap.started, column) {
+            (false, _) => wrap.indent1,
+            (true, 0) => wrap.indent2,
+            _ => return,
+        };
+        wrap.started = true;
+        if !context.reserve_output(indent) {
+            return;
+        }
+        for _i in 0..indent {
+            Self::append(context, ' ', CharOrigin::Generated);
+        }
+    }
+
+    /// Breaks the line at its last space if the char exceeds the wrap width. The space is
+    /// replaced by a line feed, followed by the indentation of the continuation line. Words which
+    /// are longer than the width aren't broken.
+    fn wrap_line(context: &mut ParsingContext<'_, char>, ch: char) {
+        let column = context.column;
+        let position = context.vout.len();
+        let Some(wrap) = context.wrap.as_mut() else {
+            return;
+        };
+        match ch {
+            '\n' => wrap.space = None,
+            ' ' => wrap.space = Some((position, column)),
+            _ if wrap.width > 0 && column > wrap.width => {
+                let Some((position, space_column)) = wrap.space.take() else {
+                    return;
+                };
+                let indent = wrap.indent2;
+                if !context.reserve_output(indent) {
+                    return;
+                }
+                context.vout[position - 1] = '\n';
+                context
+                    .vout
+                    .splice(position..position, std::iter::repeat_n(' ', indent));
+                if let Some(origins) = context.origins.as_mut() {
+                    origins.splice(
+                        position..position,
+                        std::iter::repeat_n(CharOrigin::Generated, indent),
+                    );
+                }
+                Self::shift_positions(context, position, indent);
+                context.column = (indent + column).saturating_sub(space_column);
+            }
+            _ => {}
+        }
+    }
+
+    /// Moves the recorded output positions behind `position` by `count` after chars were
+    /// inserted at `position`.
+    fn shift_positions(context: &mut ParsingContext<'_, char>, position: usize, count: usize) {
+        let shift = |range: &mut Range<usize>| {
+            if range.start >= position {
+                range.start += count;
+                range.end += count;
+            } else if range.end > position {
+                range.end += count;
+            }
+        };
+        for cell in context.cells.iter_mut() {
+            let mut range = cell.0..cell.1;
+            shift(&mut range);
+            *cell = (range.start, range.end);
+        }
+        for truncation in context.truncations.iter_mut().flatten() {
+            shift(&mut truncation.output);
+        }
+        #[cfg(feature = "html")]
+        for span in context.value_spans.iter_mut().flatten() {
+            shift(&mut span.output);
+        }
+        #[cfg(feature = "colors")]
+        for (start, _) in context.style_runs.iter_mut().flatten() {
+            if *start >= position {
+                *start += count;
+            }
+        }
+    }
+
+    /// Pushes a char which has no counterpart in the input, e.g. padding.
+    fn push(context: &mut ParsingContext<'_, char>, ch: char) {
+        Self::push_with_origin(context, ch, CharOrigin::Generated);
+    }
+
+    /// Pushes chars of the placeholder between the mark and the current position.
+    fn extend_placeholder(
+        context: &mut ParsingContext<'_, char>,
+        iter: impl IntoIterator<Item = char>,
+    ) {
+        let start = context.iter.marked_position().unwrap_or(0);
+        let end = context.iter.position();
+        for ch in iter {
+            Self::push_with_origin(context, ch, CharOrigin::Template(start..end));
+        }
+    }
+
+    /// Pushes the chars of a value, starting with the char at `index` of the value.
+    fn extend_value(
+        context: &mut ParsingContext<'_, char>,
+        key: &Option<Rc<str>>,
+        iter: impl IntoIterator<Item = char>,
+        index: usize,
+    ) {
+        let start = context.iter.marked_position().unwrap_or(0);
+        let end = context.iter.position();
+        for (idx, ch) in iter.into_iter().enumerate() {
+            if idx % QUOTA_CHECK_INTERVAL == 0 && !context.reserve_output(0) {
+                return;
+            }
+            let origin = match key {
+                Some(key) => CharOrigin::Value(key.clone(), index + idx),
+                // the default value is part of the template
+                None => CharOrigin::Template(start..end),
+            };
+            Self::push_with_origin(context, ch, origin);
+            if ch == '\n' {
+                Self::push_block_indent(context);
+            }
+        }
+    }
+
+    /// Pushes the prefix of the lines of a value set by `%I(...)`.
+    fn push_block_indent(context: &mut ParsingContext<'_, char>) {
+        let Some(indent) = context.block_indent.clone() else {
+            return;
+        };
+        if !context.reserve_output(indent.chars().count()) {
+            return;
+        }
+        for ch in indent.chars() {
+            Self::push(context, ch);
+        }
+    }
+
+    /// Pushes the chars between the mark and the current position unchanged.
+    fn push_unparsed(context: &mut ParsingContext<'_, char>) {
+        let unparsed = context.iter.get_mark2cur().unwrap();
+        let start = context.iter.marked_position().unwrap_or(0);
+        for (idx, ch) in unparsed.into_iter().enumerate() {
+            let pos = start + idx;
+            Self::push_with_origin(context, ch, CharOrigin::Template(pos..pos + 1));
+        }
+    }
+
+    /// Pushes `count` spaces.
+    fn pad(context: &mut ParsingContext<'_, char>, count: usize) {
+        if !context.reserve_output(count) {
+            return;
+        }
+        for idx in 0..count {
+            if idx % QUOTA_CHECK_INTERVAL == 0 && !context.reserve_output(0) {
+                return;
+            }
+            Self::push(context, ' ');
+        }
+    }
+
+    /// Returns the number of chars which fit into `width` and the width they occupy.
+    fn fit(
+        context: &ParsingContext<'_, char>,
+        chars: impl Iterator<Item = char>,
+        width: usize,
+    ) -> (usize, usize) {
+        let mut count = 0;
+        let mut used = 0;
+        for ch in chars {
+            let ch_width = context.char_width(ch);
+            if used + ch_width > width {
+                break;
+            }
+            count += 1;
+            used += ch_width;
+        }
+        (count, used)
+    }
+
+    /// Pushes the start of the value followed by `…`, occupying `width` columns. Wide chars
+    /// which don't fit completely are replaced by padding.
+    fn truncate_end(
+        context: &mut ParsingContext<'_, char>,
+        key: &Option<Rc<str>>,
+        value: &str,
+        width: usize,
+    ) {
+        // -1 due to …
+        let (count, used) = Self::fit(context, value.chars(), width.saturating_sub(1));
+        Self::extend_value(context, key, value.chars().take(count), 0);
+        Self::push(context, context.ellipsis);
+        Self::pad(context, width.saturating_sub(1) - used);
+    }
+
+    /// Pushes `…` followed by the end of the value, occupying `width` columns. Wide chars which
+    /// don't fit completely are replaced by padding.
+    fn truncate_start(
+        context: &mut ParsingContext<'_, char>,
+        key: &Option<Rc<str>>,
+        value: &str,
+        width: usize,
+    ) {
+        // -1 due to …
+        let (count, used) = Self::fit(context, value.chars().rev(), width.saturating_sub(1));
+        let skip = value.chars().count() - count;
+        Self::push(context, context.ellipsis);
+        Self::extend_value(context, key, value.chars().skip(skip), skip);
+        Self::pad(context, width.saturating_sub(1) - used);
+    }
+
+    /// Pushes the words of the value as a block of lines occupying exactly `width` columns. The
+    /// words are distributed greedily over the lines and the gaps between them widened to fill
+    /// the width. The last line of a multi-line block is left-aligned like in typesetting.
+    /// Words longer than `width` are truncated with `…`. Continuation lines start at the column
+    /// of the value.
+    fn justify(
+        context: &mut ParsingContext<'_, char>,
+        key: &Option<Rc<str>>,
+        value: &str,
+        width: usize,
+    ) {
+        let chars: Vec<char> = value.chars().collect();
+        let mut words = Vec::<Range<usize>>::new();
+        let mut idx = 0;
+        while idx < chars.len() {
+            if chars[idx].is_whitespace() {
+                idx += 1;
+                continue;
+            }
+            let start = idx;
+            while idx < chars.len() && !chars[idx].is_whitespace() {
+                idx += 1;
+            }
+            words.push(start..idx);
+        }
+
+        let mut lines = Vec::<Vec<Range<usize>>>::new();
+        let mut line_width = 0;
+        for word in words {
+            let word_width: usize = chars[word.clone()]
+                .iter()
+                .map(|&ch| context.char_width(ch))
+                .sum();
+            match lines.last_mut() {
+                Some(line) if line_width + 1 + word_width <= width => {
+                    line.push(word);
+                    line_width += 1 + word_width;
+                }
+                _ => {
+                    lines.push(vec![word]);
+                    line_width = word_width;
+                }
+            }
+        }
+
+        let column = context.column;
+        let line_count = lines.len();
+        for (line_idx, line) in lines.into_iter().enumerate() {
+            if line_idx > 0 {
+                Self::push(context, '\n');
+                Self::pad(context, column);
+            }
+            let words_width: usize = line
+                .iter()
+                .flat_map(|word| &chars[word.clone()])
+                .map(|&ch| context.char_width(ch))
+                .sum();
+            if words_width > width {
+                // A word longer than the width is alone on its line and truncated like `trunc`
+                let word = line[0].clone();
+                let (count, used) = Self::fit(
+                    context,
+                    chars[word.clone()].iter().copied(),
+                    width.saturating_sub(1),
+                );
+                let start = word.start;
+                Self::extend_value(context, key, chars[word].iter().copied().take(count), start);
+                Self::push(context, context.ellipsis);
+                Self::pad(context, width.saturating_sub(1) - used);
+                continue;
+            }
+            let gaps = line.len() - 1;
+            let extra = width - words_width;
+            let justified = gaps > 0 && (line_count == 1 || line_idx + 1 < line_count);
+            for (word_idx, word) in line.into_iter().enumerate() {
+                if word_idx > 0 {
+                    let spaces = match justified {
+                        true => extra / gaps + usize::from(word_idx - 1 < extra % gaps),
+                        false => 1,
+                    };
+                    Self::pad(context, spaces);
+                }
+                let start = word.start;
+                Self::extend_value(context, key, chars[word].iter().copied(), start);
+            }
+            if !justified {
+                Self::pad(context, extra.saturating_sub(gaps));
+            }
+        }
+        if line_count == 0 {
+            Self::pad(context, width);
+        }
+    }
+
+    /// Returns the order of the output chars with reversed cells within each line. Literal text
+    /// between the cells stays in place.
+    pub fn mirror_lines(vout: &[char], cells: &[(usize, usize)]) -> Vec<usize> {
+        Self::mirror_layout(vout, cells).0
+    }
+
+    /// Returns the order of the output chars like `mirror_lines` and the output range of each
+    /// cell after reordering. Cells are moved as a whole, so the reordered ranges are
+    /// contiguous; empty cells are moved like the others. Cells spanning several lines stay in
+    /// place.
+    pub fn mirror_layout(
+        vout: &[char],
+        cells: &[(usize, usize)],
+    ) -> (Vec<usize>, Vec<Range<usize>>) {
+        let mut out = Vec::<usize>::with_capacity(vout.len());
+        let mut ranges: Vec<_> = cells.iter().map(|&(start, end)| start..end).collect();
+        let mut line_start = 0;
+        while line_start <= vout.len() {
+            let line_end = vout[line_start..]
+                .iter()
+                .position(|&ch| ch == '\n')
+                .map_or(vout.len(), |idx| line_start + idx);
+
+            let line_cells: Vec<_> = (0..cells.len())
+                .filter(|&idx| cells[idx].0 >= line_start && cells[idx].1 <= line_end)
+                .collect();
+
+            let mut pos = line_start;
+            for (&cell, &mirrored) in line_cells.iter().zip(line_cells.iter().rev()) {
+                out.extend(pos..cells[cell].0);
+                let (start, end) = cells[mirrored];
+                ranges[mirrored] = out.len()..out.len() + end - start;
+                out.extend(start..end);
+                pos = cells[cell].1;
+            }
+            out.extend(pos..line_end);
+
+            if line_end < vout.len() {
+                out.push(line_end);
+            }
+            line_start = line_end + 1;
+        }
+        (out, ranges)
+    }
+
+    /// Closes the BBCode tags which are still open at the end of the output.
+    #[cfg(feature = "colors")]
+    pub fn close_styles(mut context: ParsingContext<'_, char>) -> ParsingContext<'_, char> {
+        let Some(spec) = context.open_style.take() else {
+            return context;
+        };
+        for ch in spec.to_bbcode_close().chars() {
+            context.vout.push(ch);
+            if let Some(origins) = context.origins.as_mut() {
+                origins.push(CharOrigin::Generated);
+            }
+        }
+        context
+    }
+}
+
 impl ParsingTask for ParsingTaskReplacePlaceholders {
     type Item = char;
     type Output = String;
@@ -16,181 +420,283 @@ impl ParsingTask for ParsingTaskReplacePlaceholders {
         inp: &'a str,
         key_value: &'a HashMap<&'a str, String>,
     ) -> ParsingContext<'a, Self::Item> {
-        let vec: Vec<_> = inp.chars().collect();
-        ParsingContext::<'_, Self::Item> {
-            key_value,
-            iter: PeekCharIterator::new(vec),
-            vout: Vec::<char>::new(),
-            format: OutputFormat::None,
-        }
+        ParsingContext::new(inp, key_value, Vec::<char>::new())
     }
 
     fn error(context: &mut ParsingContext<'_, Self::Item>) {
-        context.vout.extend(context.iter.get_mark2cur().unwrap());
+        #[cfg(feature = "tracing")]
+        if !context.measuring {
+            tracing::warn!(
+                template_id = %context.template_id,
+                position = context.iter.marked_position().unwrap_or(0),
+                found = %context.iter.get_mark2cur().unwrap_or_default().into_iter().collect::<String>(),
+                "invalid placeholder"
+            );
+        }
+        Self::push_unparsed(context);
     }
 
     fn process_char(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
-        context.vout.push(ch);
+        // the origin is only built if a source map is requested
+        let origin = match context.origins {
+            Some(_) => {
+                let pos = context.iter.position() - 1;
+                CharOrigin::Template(pos..pos + 1)
+            }
+            None => CharOrigin::Generated,
+        };
+        Self::push_with_origin(context, ch, origin);
     }
 
+    /// Line feeds which are part of a rendered value are kept.
+    fn remove_trailing_line_feeds(context: &mut ParsingContext<'_, Self::Item>) {
+        let protected = context.cells.last().map_or(0, |&(_, end)| end);
+        let mut removed = false;
+        while context.vout.len() > protected && context.vout.last() == Some(&'\n') {
+            context.vout.pop();
+            if let Some(origins) = context.origins.as_mut() {
+                origins.pop();
+            }
+            removed = true;
+        }
+        if removed {
+            context.column = context
+                .vout
+                .iter()
+                .rev()
+                .take_while(|&&ch| ch != '\n')
+                .map(|&ch| context.char_width(ch))
+                .sum();
+        }
+    }
+
+    /// Spaces of char placeholders like `%_` aren't break points of wrapped lines, line feeds like
+    /// `%n` start a new line without break point.
     fn process_char_placeholder(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
-        context.vout.push(ch);
+        let space = context.wrap.and_then(|wrap| wrap.space);
+        Self::extend_placeholder(context, [ch]);
+        if let Some(wrap) = context.wrap.as_mut().filter(|_| ch != '\n') {
+            wrap.space = space;
+        }
     }
 
-    fn process_str_placeholder(context: &mut ParsingContext<'_, Self::Item>, arg: String) {
-        let Some(repl_str) = context.key_value.get(arg.as_str()) else {
-            Self::error(context);
+    fn process_str_placeholder(
+        context: &mut ParsingContext<'_, Self::Item>,
+        placeholder: StrPlaceholder,
+    ) {
+        let Some(repl_str) = context.resolve(&placeholder).or_else(|| {
+            #[cfg(feature = "tracing")]
+            if !context.measuring {
+                tracing::warn!(
+                    template_id = %context.template_id,
+                    position = context.iter.marked_position().unwrap_or(0),
+                    key = %placeholder.key,
+                    "missing key"
+                );
+            }
+            #[cfg(feature = "metrics")]
+            if !context.measuring {
+                render_metrics::record_missing_key();
+            }
+            context.record_missing_key(&placeholder.key);
+            context.missing_key.substitute(&placeholder.key)
+        }) else {
+            Self::push_unparsed(context);
             return;
         };
+        #[cfg(feature = "tables")]
+        match (context.format, context.cell_widths.get(context.cells.len())) {
+            // a spanning cell is aligned over the combined width of its columns
+            (format, Some(&width)) if context.cell_span > 1 => {
+                context.format = format.with_exact_width(width as u32);
+            }
+            (OutputFormat::None, Some(&width)) => {
+                context.format = OutputFormat::LeftAlign(width as u32);
+            }
+            _ => {}
+        }
+        if let Some(&width) = context.exact_cell_widths.get(context.cells.len()) {
+            context.format = context.format.with_exact_width(width as u32);
+        }
+        if context.right_to_left {
+            context.format = context.format.mirrored();
+        }
+        let key = (context.origins.is_some() || context.truncations.is_some())
+            .then(|| context.resolved_key(&placeholder))
+            .flatten()
+            .map(Rc::from);
+        // the cell is recorded in advance, so line breaks within the value keep it up to date
+        context.cells.push((context.vout.len(), context.vout.len()));
+        if !repl_str.is_empty() {
+            Self::push_block_indent(context);
+        }
         let repl = repl_str.chars();
+        let value_len = context.width(&repl_str);
         match context.format {
             OutputFormat::None => {
-                context.vout.extend(repl);
+                Self::extend_value(context, &key, repl, 0);
             }
 
             OutputFormat::LeftAlign(la) => {
-                context.vout.extend(repl.clone());
-                let value_len = repl.into_iter().count();
-                let len_diff = (la as i32) - (value_len as i32);
-                if len_diff > 0 {
-                    for _i in 0..len_diff {
-                        context.vout.push(' ');
-                    }
-                }
+                Self::extend_value(context, &key, repl, 0);
+                Self::pad(context, (la as usize).saturating_sub(value_len));
             }
 
             OutputFormat::LeftAlignTrunc(la) => {
-                let value_len = repl.clone().count();
-                let len_diff = (la as i32) - (value_len as i32);
-
-                match len_diff {
-                    _ if len_diff > 0 => {
-                        context.vout.extend(repl);
-                        for _i in 0..len_diff {
-                            context.vout.push(' ');
-                        }
+                let la = la as usize;
+                match value_len {
+                    _ if value_len < la => {
+                        Self::extend_value(context, &key, repl, 0);
+                        Self::pad(context, la - value_len);
                     }
 
-                    _ if len_diff < 0 => {
-                        // -1 due to …
-                        let let_cmp = (value_len as i32) + len_diff - 1;
-                        for (idx, ch) in repl.into_iter().enumerate() {
-                            if idx >= let_cmp as usize {
-                                break;
-                            }
-                            context.vout.push(ch);
-                        }
-                        context.vout.push('…');
+                    _ if value_len > la => {
+                        Self::truncate_end(context, &key, &repl_str, la);
                     }
                     _ => {
-                        // len_diff ==0
-                        context.vout.extend(repl);
+                        // value_len == la
+                        Self::extend_value(context, &key, repl, 0);
                     }
                 }
             }
 
-            OutputFormat::LeftAlignLTrunc(ra) => {
-                let value_len = repl.clone().count();
-                let len_diff = (ra as i32) - (value_len as i32);
-
-                match len_diff {
-                    _ if len_diff > 0 => {
-                        context.vout.extend(repl);
-                        for _i in 0..len_diff {
-                            context.vout.push(' ');
-                        }
+            OutputFormat::LeftAlignLTrunc(la) => {
+                let la = la as usize;
+                match value_len {
+                    _ if value_len < la => {
+                        Self::extend_value(context, &key, repl, 0);
+                        Self::pad(context, la - value_len);
                     }
 
-                    _ if len_diff < 0 => {
-                        context.vout.push('…');
-                        let mut iter = repl.into_iter();
-                        for _ in 0..-len_diff + 1 {
-                            // +1 due to …
-                            iter.next();
-                        }
-
-                        context.vout.extend(iter);
+                    _ if value_len > la => {
+                        Self::truncate_start(context, &key, &repl_str, la);
                     }
                     _ => {
-                        // len_diff ==0
-                        context.vout.extend(repl);
+                        // value_len == la
+                        Self::extend_value(context, &key, repl, 0);
                     }
                 }
             }
 
             OutputFormat::RightAlign(ra) => {
-                let value_len = repl.clone().count();
-                let len_diff = (ra as i32) - (value_len as i32);
-                if len_diff > 0 {
-                    for _i in 0..len_diff {
-                        context.vout.push(' ');
-                    }
-                }
-                context.vout.extend(repl);
+                Self::pad(context, (ra as usize).saturating_sub(value_len));
+                Self::extend_value(context, &key, repl, 0);
             }
 
             OutputFormat::RightAlignTrunc(ra) => {
-                let value_len = repl.clone().count();
-                let len_diff = (ra as i32) - (value_len as i32);
-
-                match len_diff {
-                    _ if len_diff > 0 => {
-                        for _i in 0..len_diff {
-                            context.vout.push(' ');
-                        }
-                        context.vout.extend(repl);
+                let ra = ra as usize;
+                match value_len {
+                    _ if value_len < ra => {
+                        Self::pad(context, ra - value_len);
+                        Self::extend_value(context, &key, repl, 0);
                     }
 
-                    _ if len_diff < 0 => {
-                        // -1 due to …
-                        let let_cmp = (value_len as i32) + len_diff - 1;
-                        for (idx, ch) in repl.into_iter().enumerate() {
-                            if idx >= let_cmp as usize {
-                                break;
-                            }
-                            context.vout.push(ch);
-                        }
-                        context.vout.push('…');
+                    _ if value_len > ra => {
+                        Self::truncate_end(context, &key, &repl_str, ra);
                     }
                     _ => {
-                        // len_diff ==0
-                        context.vout.extend(repl);
+                        // value_len == ra
+                        Self::extend_value(context, &key, repl, 0);
                     }
                 }
             }
 
+            OutputFormat::Justify(width) => {
+                Self::justify(context, &key, &repl_str, width as usize);
+            }
+
             OutputFormat::RightAlignLTrunc(ra) => {
-                let value_len = repl.clone().count();
-                let len_diff = (ra as i32) - (value_len as i32);
-
-                match len_diff {
-                    _ if len_diff > 0 => {
-                        for _i in 0..len_diff {
-                            context.vout.push(' ');
-                        }
-                        context.vout.extend(repl);
+                let ra = ra as usize;
+                match value_len {
+                    _ if value_len < ra => {
+                        Self::pad(context, ra - value_len);
+                        Self::extend_value(context, &key, repl, 0);
                     }
 
-                    _ if len_diff < 0 => {
-                        context.vout.push('…');
-                        let mut iter = repl.into_iter();
-                        for _ in 0..-len_diff + 1 {
-                            // +1 due to …
-                            iter.next();
-                        }
-
-                        context.vout.extend(iter);
+                    _ if value_len > ra => {
+                        Self::truncate_start(context, &key, &repl_str, ra);
                     }
                     _ => {
-                        // len_diff ==0
-                        context.vout.extend(repl);
+                        // value_len == ra
+                        Self::extend_value(context, &key, repl, 0);
                     }
                 }
             }
         }
+        let end = context.vout.len();
+        let start = context.cells.last_mut().map_or(end, |cell| {
+            cell.1 = end;
+            cell.0
+        });
+        let truncated = match context.format {
+            OutputFormat::LeftAlignTrunc(width)
+            | OutputFormat::LeftAlignLTrunc(width)
+            | OutputFormat::RightAlignTrunc(width)
+            | OutputFormat::RightAlignLTrunc(width) => repl_str.chars().count() > width as usize,
+            _ => false,
+        };
+        #[cfg(feature = "tracing")]
+        if truncated && !context.measuring {
+            tracing::debug!(
+                template_id = %context.template_id,
+                key = %placeholder.key,
+                width = end - start,
+                value_len = repl_str.chars().count(),
+                "truncated value"
+            );
+        }
+        if let Some(truncations) = context.truncations.as_mut().filter(|_| truncated) {
+            truncations.push(Truncation {
+                output: start..end,
+                key: key.map(|key| key.to_string()),
+                value: repl_str,
+            });
+        }
+    }
+
+    #[cfg(feature = "colors")]
+    fn process_color_placeholder(context: &mut ParsingContext<'_, Self::Item>, spec: &ColorSpec) {
+        let start = context.iter.marked_position().unwrap_or(0);
+        let end = context.iter.position();
+        let sequence = match context.color_target {
+            ColorTarget::Ansi => spec.to_ansi(),
+            ColorTarget::Irc => spec.to_irc(),
+            ColorTarget::BbCode => {
+                let close = context
+                    .open_style
+                    .take()
+                    .map(|open| open.to_bbcode_close())
+                    .unwrap_or_default();
+                if !spec.reset {
+                    context.open_style = Some(spec.clone());
+                }
+                close + &spec.to_bbcode_open()
+            }
+        };
+        for ch in sequence.chars() {
+            context.vout.push(ch);
+            if let Some(origins) = context.origins.as_mut() {
+                origins.push(CharOrigin::Template(start..end));
+            }
+        }
     }
 
     fn done(context: ParsingContext<'_, Self::Item>) -> Self::Output {
-        context.vout.into_iter().collect()
+        #[cfg(feature = "colors")]
+        let context = Self::close_styles(context);
+        #[cfg(feature = "metrics")]
+        let (nested, measuring) = (!context.expansions.is_empty(), context.measuring);
+        let out: String = match context.right_to_left {
+            true => Self::mirror_lines(&context.vout, &context.cells)
+                .into_iter()
+                .map(|idx| context.vout[idx])
+                .collect(),
+            false => context.vout.into_iter().collect(),
+        };
+        #[cfg(feature = "metrics")]
+        if !nested && !measuring {
+            render_metrics::record_render(&out);
+        }
+        out
     }
 }
@@ -0,0 +1,131 @@
+#[cfg(feature = "colors")]
+use super::color_spec::ColorSpec;
+use super::parsing_context::ParsingContext;
+use super::parsing_task::ParsingTask;
+use super::segment::Segment;
+use super::str_placeholder::StrPlaceholder;
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Splits the template into segments. All block branches are visited, values aren't resolved.
+pub struct ParsingTaskSegments;
+
+impl ParsingTaskSegments {
+    /// Returns the span of the placeholder between the mark and the current position.
+    fn placeholder_span(context: &ParsingContext<'_, Segment>) -> Range<usize> {
+        context.iter.marked_position().unwrap_or(0)..context.iter.position()
+    }
+
+    /// Returns true if the last segment covers `span`, e.g. for a rule placeholder emitting
+    /// several chars.
+    fn is_last_span(context: &ParsingContext<'_, Segment>, span: &Range<usize>) -> bool {
+        context.vout.last().is_some_and(|last| last.span() == span)
+    }
+}
+
+impl ParsingTask for ParsingTaskSegments {
+    type Item = Segment;
+    type Output = Vec<Segment>;
+    #[cfg(feature = "blocks")]
+    const VISIT_ALL_BRANCHES: bool = true;
+
+    /// Called in case the context should be initialized
+    fn init<'a>(
+        inp: &'a str,
+        key_value: &'a HashMap<&'a str, String>,
+    ) -> ParsingContext<'a, Self::Item> {
+        let vout = Vec::<Self::Item>::new();
+        ParsingContext::new(inp, key_value, vout)
+    }
+
+    fn error(context: &mut ParsingContext<'_, Self::Item>) {
+        let span = Self::placeholder_span(context);
+        if Self::is_last_span(context, &span) {
+            return; // a length placeholder like `%(#key)` whose value is missing
+        }
+        context.vout.push(Segment::Error { span });
+    }
+
+    fn process_char(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
+        let position = context.iter.position();
+        if let Some(Segment::Literal { text, span }) = context.vout.last_mut() {
+            if span.end + 1 == position {
+                text.push(ch);
+                span.end = position;
+                return;
+            }
+        }
+        context.vout.push(Segment::Literal {
+            text: ch.to_string(),
+            span: position - 1..position,
+        });
+    }
+
+    fn process_char_placeholder(context: &mut ParsingContext<'_, Self::Item>, _ch: char) {
+        let span = Self::placeholder_span(context);
+        if Self::is_last_span(context, &span) {
+            return;
+        }
+        let source = context.iter.get_mark2cur().unwrap_or_default();
+        let segment = match source.get(1..3) {
+            // parameterized placeholders like `%-(5)` or `%T(8)` emit chars as well
+            Some([_, '(']) => Segment::Directive { span },
+            _ => Segment::CharPlaceholder {
+                ch: source.get(1).copied().unwrap_or('%'),
+                span,
+            },
+        };
+        context.vout.push(segment);
+    }
+
+    fn process_str_placeholder(
+        context: &mut ParsingContext<'_, Self::Item>,
+        placeholder: StrPlaceholder,
+    ) {
+        let span = Self::placeholder_span(context);
+        let format = context.format.spec();
+        context.vout.push(Segment::KeyPlaceholder {
+            key: placeholder.key,
+            format,
+            span,
+        });
+    }
+
+    #[cfg(feature = "colors")]
+    fn process_color_placeholder(_context: &mut ParsingContext<'_, Self::Item>, _spec: &ColorSpec) {
+    }
+
+    /// Key references of block tags and length placeholders like `%(#key)` are directives.
+    fn process_key_reference(context: &mut ParsingContext<'_, Self::Item>, _key: &str) {
+        let span = Self::placeholder_span(context);
+        if !Self::is_last_span(context, &span) {
+            context.vout.push(Segment::Directive { span });
+        }
+    }
+
+    fn done(context: ParsingContext<'_, Self::Item>) -> Self::Output {
+        // the template text between the segments consists of directives
+        let mut segments = Vec::with_capacity(context.vout.len());
+        let mut position = 0;
+        for segment in context.vout {
+            let span = segment.span().clone();
+            if span.start > position {
+                segments.push(Segment::Directive {
+                    span: position..span.start,
+                });
+            }
+            if span.start >= position {
+                position = span.end;
+                segments.push(segment);
+            }
+        }
+        let end = context.iter.position();
+        if end > position {
+            segments.push(Segment::Directive {
+                span: position..end,
+            });
+        }
+        segments
+    }
+}
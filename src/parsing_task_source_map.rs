@@ -0,0 +1,81 @@
+#[cfg(feature = "colors")]
+use super::color_spec::ColorSpec;
+use super::parsing_context::ParsingContext;
+use super::parsing_task::ParsingTask;
+use super::parsing_task_replace_placeholders::ParsingTaskReplacePlaceholders;
+#[cfg(feature = "metrics")]
+use super::render_metrics;
+use super::source_map::{build_source_map, SourceMapEntry};
+use super::str_placeholder::StrPlaceholder;
+
+use std::collections::HashMap;
+
+/// Replaces placeholders like `ParsingTaskReplacePlaceholders` and additionally records the
+/// origin of each output char.
+pub struct ParsingTaskSourceMap;
+
+impl ParsingTask for ParsingTaskSourceMap {
+    type Item = char;
+    type Output = (String, Vec<SourceMapEntry>);
+
+    /// Called in case the context should be initialized
+    fn init<'a>(
+        inp: &'a str,
+        key_value: &'a HashMap<&'a str, String>,
+    ) -> ParsingContext<'a, Self::Item> {
+        let mut context = ParsingTaskReplacePlaceholders::init(inp, key_value);
+        context.origins = Some(Vec::new());
+        context
+    }
+
+    fn error(context: &mut ParsingContext<'_, Self::Item>) {
+        ParsingTaskReplacePlaceholders::error(context);
+    }
+
+    fn process_char(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
+        ParsingTaskReplacePlaceholders::process_char(context, ch);
+    }
+
+    fn remove_trailing_line_feeds(context: &mut ParsingContext<'_, Self::Item>) {
+        ParsingTaskReplacePlaceholders::remove_trailing_line_feeds(context);
+    }
+
+    fn process_char_placeholder(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
+        ParsingTaskReplacePlaceholders::process_char_placeholder(context, ch);
+    }
+
+    fn process_str_placeholder(
+        context: &mut ParsingContext<'_, Self::Item>,
+        placeholder: StrPlaceholder,
+    ) {
+        ParsingTaskReplacePlaceholders::process_str_placeholder(context, placeholder);
+    }
+
+    #[cfg(feature = "colors")]
+    fn process_color_placeholder(context: &mut ParsingContext<'_, Self::Item>, spec: &ColorSpec) {
+        ParsingTaskReplacePlaceholders::process_color_placeholder(context, spec);
+    }
+
+    fn done(context: ParsingContext<'_, Self::Item>) -> Self::Output {
+        #[cfg(feature = "colors")]
+        let context = ParsingTaskReplacePlaceholders::close_styles(context);
+        let origins = context.origins.unwrap_or_default();
+        let (out, source_map): (String, _) = match context.right_to_left {
+            true => {
+                let order =
+                    ParsingTaskReplacePlaceholders::mirror_lines(&context.vout, &context.cells);
+                (
+                    order.iter().map(|&idx| context.vout[idx]).collect(),
+                    build_source_map(order.iter().map(|&idx| origins[idx].clone())),
+                )
+            }
+            false => (
+                context.vout.into_iter().collect(),
+                build_source_map(origins),
+            ),
+        };
+        #[cfg(feature = "metrics")]
+        render_metrics::record_render(&out);
+        (out, source_map)
+    }
+}
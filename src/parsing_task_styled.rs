@@ -0,0 +1,90 @@
+#[cfg(feature = "colors")]
+use super::color_spec::ColorSpec;
+use super::parsing_context::ParsingContext;
+use super::parsing_task::ParsingTask;
+use super::parsing_task_replace_placeholders::ParsingTaskReplacePlaceholders;
+use super::str_placeholder::StrPlaceholder;
+#[cfg(feature = "colors")]
+use super::tui::apply_color_spec;
+
+use ratatui::style::Style;
+use std::collections::HashMap;
+
+/// Replaces placeholders like `ParsingTaskReplacePlaceholders` and returns each output char with
+/// its terminal style. Color placeholders set the style of the subsequent chars instead of
+/// emitting escape sequences.
+pub struct ParsingTaskStyled;
+
+impl ParsingTask for ParsingTaskStyled {
+    type Item = char;
+    type Output = Vec<(char, Style)>;
+
+    fn init<'a>(
+        inp: &'a str,
+        key_value: &'a HashMap<&'a str, String>,
+    ) -> ParsingContext<'a, Self::Item> {
+        #[allow(unused_mut)]
+        let mut context = ParsingTaskReplacePlaceholders::init(inp, key_value);
+        #[cfg(feature = "colors")]
+        {
+            context.style_runs = Some(Vec::new());
+        }
+        context
+    }
+
+    fn error(context: &mut ParsingContext<'_, Self::Item>) {
+        ParsingTaskReplacePlaceholders::error(context);
+    }
+
+    fn process_char(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
+        ParsingTaskReplacePlaceholders::process_char(context, ch);
+    }
+
+    fn remove_trailing_line_feeds(context: &mut ParsingContext<'_, Self::Item>) {
+        ParsingTaskReplacePlaceholders::remove_trailing_line_feeds(context);
+    }
+
+    fn process_char_placeholder(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
+        ParsingTaskReplacePlaceholders::process_char_placeholder(context, ch);
+    }
+
+    fn process_str_placeholder(
+        context: &mut ParsingContext<'_, Self::Item>,
+        placeholder: StrPlaceholder,
+    ) {
+        ParsingTaskReplacePlaceholders::process_str_placeholder(context, placeholder);
+    }
+
+    #[cfg(feature = "colors")]
+    fn process_color_placeholder(context: &mut ParsingContext<'_, Self::Item>, spec: &ColorSpec) {
+        let position = context.vout.len();
+        if let Some(runs) = context.style_runs.as_mut() {
+            runs.push((position, spec.clone()));
+        }
+    }
+
+    fn done(context: ParsingContext<'_, Self::Item>) -> Self::Output {
+        #[allow(unused_mut)]
+        let mut styles = vec![Style::default(); context.vout.len()];
+        #[cfg(feature = "colors")]
+        {
+            let runs = context.style_runs.as_deref().unwrap_or_default();
+            let mut runs = runs.iter().peekable();
+            let mut style = Style::default();
+            for (idx, slot) in styles.iter_mut().enumerate() {
+                while let Some((_, spec)) = runs.next_if(|(position, _)| *position <= idx) {
+                    style = apply_color_spec(style, spec);
+                }
+                *slot = style;
+            }
+        }
+        let order: Vec<usize> = match context.right_to_left {
+            true => ParsingTaskReplacePlaceholders::mirror_lines(&context.vout, &context.cells),
+            false => (0..context.vout.len()).collect(),
+        };
+        order
+            .into_iter()
+            .map(|idx| (context.vout[idx], styles[idx]))
+            .collect()
+    }
+}
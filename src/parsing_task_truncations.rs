@@ -0,0 +1,85 @@
+#[cfg(feature = "colors")]
+use super::color_spec::ColorSpec;
+use super::parsing_context::ParsingContext;
+use super::parsing_task::ParsingTask;
+use super::parsing_task_replace_placeholders::ParsingTaskReplacePlaceholders;
+#[cfg(feature = "metrics")]
+use super::render_metrics;
+use super::str_placeholder::StrPlaceholder;
+use super::truncation::Truncation;
+
+use std::collections::HashMap;
+
+/// Replaces placeholders like `ParsingTaskReplacePlaceholders` and additionally records the
+/// complete values of all truncated values.
+pub struct ParsingTaskTruncations;
+
+impl ParsingTask for ParsingTaskTruncations {
+    type Item = char;
+    type Output = (String, Vec<Truncation>);
+
+    /// Called in case the context should be initialized
+    fn init<'a>(
+        inp: &'a str,
+        key_value: &'a HashMap<&'a str, String>,
+    ) -> ParsingContext<'a, Self::Item> {
+        let mut context = ParsingTaskReplacePlaceholders::init(inp, key_value);
+        context.truncations = Some(Vec::new());
+        context
+    }
+
+    fn error(context: &mut ParsingContext<'_, Self::Item>) {
+        ParsingTaskReplacePlaceholders::error(context);
+    }
+
+    fn process_char(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
+        ParsingTaskReplacePlaceholders::process_char(context, ch);
+    }
+
+    fn remove_trailing_line_feeds(context: &mut ParsingContext<'_, Self::Item>) {
+        ParsingTaskReplacePlaceholders::remove_trailing_line_feeds(context);
+    }
+
+    fn process_char_placeholder(context: &mut ParsingContext<'_, Self::Item>, ch: char) {
+        ParsingTaskReplacePlaceholders::process_char_placeholder(context, ch);
+    }
+
+    fn process_str_placeholder(
+        context: &mut ParsingContext<'_, Self::Item>,
+        placeholder: StrPlaceholder,
+    ) {
+        ParsingTaskReplacePlaceholders::process_str_placeholder(context, placeholder);
+    }
+
+    #[cfg(feature = "colors")]
+    fn process_color_placeholder(context: &mut ParsingContext<'_, Self::Item>, spec: &ColorSpec) {
+        ParsingTaskReplacePlaceholders::process_color_placeholder(context, spec);
+    }
+
+    fn done(context: ParsingContext<'_, Self::Item>) -> Self::Output {
+        #[cfg(feature = "colors")]
+        let context = ParsingTaskReplacePlaceholders::close_styles(context);
+        let mut truncations = context.truncations.unwrap_or_default();
+        let out: String = if context.right_to_left {
+            let (order, mirrored) =
+                ParsingTaskReplacePlaceholders::mirror_layout(&context.vout, &context.cells);
+            for truncation in truncations.iter_mut() {
+                let output = &truncation.output;
+                if let Some(idx) = context
+                    .cells
+                    .iter()
+                    .position(|&(start, end)| start == output.start && end == output.end)
+                {
+                    truncation.output = mirrored[idx].clone();
+                }
+            }
+            truncations.sort_by_key(|truncation| (truncation.output.start, truncation.output.end));
+            order.iter().map(|&idx| context.vout[idx]).collect()
+        } else {
+            context.vout.into_iter().collect()
+        };
+        #[cfg(feature = "metrics")]
+        render_metrics::record_render(&out);
+        (out, truncations)
+    }
+}
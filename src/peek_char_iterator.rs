@@ -1,3 +1,5 @@
+use std::ops::Range;
+
 /// A char iterator with peek, mark, and backtrack functionalities.
 ///
 /// This iterator operates on a `Vec<char>` and uses indices
@@ -37,11 +39,51 @@ impl PeekCharIterator {
         self.chars.get(self.peeked_index.unwrap()).copied()
     }
 
+    /// Returns the current position in the iterator.
+    pub fn position(&self) -> usize {
+        self.current_index
+    }
+
+    /// Moves the iterator to the given position, e.g. to repeat a section of the input.
+    pub fn set_position(&mut self, position: usize) {
+        self.current_index = position;
+        self.peeked_index = None;
+    }
+
     /// Marks the current position in the iterator.
     pub fn mark(&mut self) {
         self.marked_index = Some(self.current_index);
     }
 
+    /// Marks the start of `range` and moves to its end, as if its characters were just consumed.
+    pub fn select(&mut self, range: Range<usize>) {
+        self.marked_index = Some(range.start);
+        self.set_position(range.end);
+    }
+
+    /// Checks if the remaining characters start with the given pattern without consuming them.
+    pub fn starts_with(&self, pattern: &str) -> bool {
+        let mut remaining = self.chars.iter().skip(self.current_index);
+        pattern.chars().all(|ch| remaining.next() == Some(&ch))
+    }
+
+    /// Returns all characters, regardless of the current position.
+    pub fn chars(&self) -> &[char] {
+        &self.chars
+    }
+
+    /// Returns the marked position, if any.
+    pub fn marked_position(&self) -> Option<usize> {
+        self.marked_index
+    }
+
+    /// Returns the byte range of the input between the mark and the current position.
+    pub fn mark2cur_bytes(&self) -> Range<usize> {
+        let byte_len = |chars: &[char]| chars.iter().map(|ch| ch.len_utf8()).sum::<usize>();
+        let start = byte_len(&self.chars[..self.marked_index.unwrap_or(0)]);
+        start..start + byte_len(&self.chars[self.marked_index.unwrap_or(0)..self.current_index])
+    }
+
     /// Returns a vector of chars between the mark and the current position
     pub fn get_mark2cur(&self) -> Option<Vec<char>> {
         self.marked_index
@@ -58,12 +100,13 @@ impl Iterator for PeekCharIterator {
     /// Otherwise, it fetches the next character from the vector.
     fn next(&mut self) -> Option<Self::Item> {
         if let Some(index) = self.peeked_index.take() {
-            self.current_index = index + 1;
-            return self.chars.get(index).copied();
+            self.current_index = index;
         }
 
         let result = self.chars.get(self.current_index).copied();
-        self.current_index += 1;
+        if result.is_some() {
+            self.current_index += 1;
+        }
         result
     }
 }
@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+
+/// Extension point for third-party placeholder families like `%(qr:...)`.
+///
+/// An extension claims all bracket placeholders starting with its sigil followed by a colon.
+/// The text up to the closing bracket is passed as argument to `parse` and `render`. The
+/// rendered value is treated like the value of a value placeholder, so a preceding format
+/// placeholder applies to it.
+///
+/// # Examples
+/// ```
+/// # use formatify::{Formatify, PlaceholderExtension, PlaceholderFormatter};
+/// # use std::collections::HashMap;
+/// struct Repeat;
+///
+/// impl PlaceholderExtension for Repeat {
+///     fn sigil(&self) -> &str {
+///         "repeat"
+///     }
+///
+///     fn parse(&self, arg: &str) -> bool {
+///         arg.split_once(',').is_some_and(|(count, _)| count.parse::<usize>().is_ok())
+///     }
+///
+///     fn render(&self, arg: &str, _key_value: &HashMap<&str, String>) -> String {
+///         let (count, text) = arg.split_once(',').unwrap();
+///         text.repeat(count.parse().unwrap())
+///     }
+/// }
+///
+/// let mut formatter = Formatify::new();
+/// formatter.register_extension(Repeat);
+/// let out = formatter.replace_placeholders(&HashMap::new(), "%(repeat:3,ab)");
+/// assert_eq!(out, "ababab");
+/// ```
+pub trait PlaceholderExtension: Send + Sync {
+    /// The sigil introducing the placeholder, e.g. `qr` for `%(qr:...)`.
+    fn sigil(&self) -> &str;
+
+    /// Validates the argument of a placeholder while parsing. If `false` is returned, the
+    /// placeholder is treated as invalid and remains unchanged in the output.
+    fn parse(&self, _arg: &str) -> bool {
+        true
+    }
+
+    /// Renders the placeholder with the given argument.
+    fn render(&self, arg: &str, key_value: &HashMap<&str, String>) -> String;
+}
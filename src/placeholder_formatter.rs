@@ -1,6 +1,45 @@
-use std::collections::HashMap;
+use super::audit::{AuditEntry, ValueSource};
+use super::display_width::max_line_width;
+use super::format_value::FormatValue;
+#[cfg(feature = "html")]
+use super::html;
+use super::key_access::{KeyAccess, KeyPolicy};
+use super::key_provider::KeyProvider;
+#[cfg(feature = "markdown")]
+use super::markdown::MarkdownFlavor;
+use super::multi_byte_policy::MultiBytePolicy;
+#[cfg(feature = "sandbox")]
+use super::quota::{QuotaState, Quotas};
+use super::render_error::RenderError;
+use super::segment::{Annotation, PlaceholderInfo, Segment};
+use super::source_map::SourceMapEntry;
+use super::token::{tokenize, Token, TokenKind};
+use super::truncation::Truncation;
+use super::value_tree::ValueTree;
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Shared formatter which can be injected across crate and thread boundaries.
+pub type ArcFormatter = Arc<dyn PlaceholderFormatter + Send + Sync>;
+
+/// Wraps `body` in a markdown code fence which is longer than any backtick run of the body.
+#[cfg(feature = "tables")]
+pub(crate) fn fence_code_block(body: &str) -> String {
+    let mut longest_run = 0;
+    let mut run = 0;
+    for ch in body.chars() {
+        run = if ch == '`' { run + 1 } else { 0 };
+        longest_run = longest_run.max(run);
+    }
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    format!("{fence}\n{body}\n{fence}")
+}
 
 /// Trait used to abstract Formatify from a system.
+///
+/// The trait is object safe, so it can be used as `dyn PlaceholderFormatter`. New methods
+/// take `&self` only and come with a default implementation where possible.
 pub trait PlaceholderFormatter {
     /// Replaces placeholders in the input string with corresponding values from a HashMap.
     ///
@@ -37,6 +76,813 @@ pub trait PlaceholderFormatter {
     /// that needs to be generated or modified based on changing data.
     fn replace_placeholders(&self, key_value: &HashMap<&str, String>, inp: &str) -> String;
 
+    /// Replaces placeholders and guarantees that the output has exactly the declared width.
+    ///
+    /// This method is intended for fixed-length records (e.g. protocol frames or legacy batch
+    /// formats) where an off-by-one is a data corruption. Every value placeholder must be preceded
+    /// by a truncating format (`%<(width,trunc)`, `%>(width,ltrunc)`, ...) and must exist in the
+    /// `key_value` map. The rendered output is measured according to `policy` and compared against
+    /// `width`. Note that the truncation ellipsis `…` is a multi-byte character, so truncated
+    /// values can't satisfy the `Bytes` and `RejectNonAscii` policies.
+    ///
+    /// The default implementation only measures the output; it doesn't check that the values are
+    /// bounded, so it never returns `RenderError::UnboundedField`.
+    ///
+    /// # Arguments
+    /// * `key_value` - A reference to a HashMap where keys correspond to placeholder identifiers in the input string and values are their replacements.
+    /// * `inp` - The input string containing placeholders.
+    /// * `width` - The exact width the output must have.
+    /// * `policy` - Defines how multi-byte characters are counted.
+    ///
+    /// # Returns
+    /// The formatted `String`, or a `RenderError` describing the first violation found.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, MultiBytePolicy, PlaceholderFormatter, RenderError};
+    /// # use std::collections::HashMap;
+    /// let mut key_value : HashMap<&str, String> = HashMap::new();
+    /// key_value.insert("id", "42".into());
+    /// key_value.insert("name", "Alice".into());
+    /// let formatter = Formatify::new();
+    /// let record = formatter.replace_placeholders_fixed_width(
+    ///     &key_value, "%>(4,trunc)%(id)|%<(8,trunc)%(name)", 13, MultiBytePolicy::Bytes);
+    /// assert_eq!(record, Ok("  42|Alice   ".to_string()));
+    ///
+    /// let record = formatter.replace_placeholders_fixed_width(
+    ///     &key_value, "%(id)", 2, MultiBytePolicy::Bytes);
+    /// assert_eq!(record, Err(RenderError::UnboundedField { key: "id".into() }));
+    /// ```
+    fn replace_placeholders_fixed_width(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+        width: usize,
+        policy: MultiBytePolicy,
+    ) -> Result<String, RenderError> {
+        let out = self.replace_placeholders(key_value, inp);
+        let found = policy.measure(&out)?;
+        if found != width {
+            return Err(RenderError::WidthMismatch {
+                expected: width,
+                found,
+            });
+        }
+        Ok(out)
+    }
+
+    /// Replaces placeholders and guarantees that all literal text is preserved byte-for-byte.
+    ///
+    /// This method is intended for templates which generate source code or other output where a
+    /// stray character matters. `replace_placeholders` passes sequences it can't parse (e.g. an
+    /// unknown `%z`, an unterminated `%(key` or a placeholder whose key is missing) through
+    /// unchanged. This method rejects such ambiguous input instead, so the output consists only of
+    /// the literal text of the template and the rendered placeholders. Use `%%` to emit a literal
+    /// percent sign.
+    ///
+    /// The default implementation only rejects placeholders of missing keys, as listed by
+    /// `extract_placeholders`.
+    ///
+    /// # Arguments
+    /// * `key_value` - A reference to a HashMap where keys correspond to placeholder identifiers in the input string and values are their replacements.
+    /// * `inp` - The input string containing placeholders.
+    ///
+    /// # Returns
+    /// The formatted `String`, or a `RenderError` describing the first ambiguous sequence found.
+    /// A placeholder of a key absent from the map fails with `RenderError::MissingKey` carrying
+    /// the key and the byte range of the placeholder.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter, RenderError};
+    /// # use std::collections::HashMap;
+    /// let mut key_value : HashMap<&str, String> = HashMap::new();
+    /// key_value.insert("name", "count".into());
+    /// let formatter = Formatify::new();
+    /// let code = formatter.replace_placeholders_strict(&key_value, "let %(name) = 100%%;");
+    /// assert_eq!(code, Ok("let count = 100%;".to_string()));
+    ///
+    /// let code = formatter.replace_placeholders_strict(&key_value, "printf(\"%d\", %(name));");
+    /// assert_eq!(code, Err(RenderError::MalformedPlaceholder { position: 8, found: "%d".into() }));
+    ///
+    /// let code = formatter.replace_placeholders_strict(&key_value, "let %(name) = %(value);");
+    /// assert_eq!(code, Err(RenderError::MissingKey { key: "value".into(), span: 14..22 }));
+    /// ```
+    fn replace_placeholders_strict(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+    ) -> Result<String, RenderError> {
+        let missing = self
+            .extract_placeholders(inp)
+            .into_iter()
+            .find(|placeholder| !key_value.contains_key(placeholder.key.as_str()));
+        if let Some(placeholder) = missing {
+            return Err(RenderError::MissingKey {
+                key: placeholder.key,
+                span: placeholder.span,
+            });
+        }
+        Ok(self.replace_placeholders(key_value, inp))
+    }
+
+    /// Replaces placeholders within hard quotas, e.g. for templates submitted by end users.
+    ///
+    /// The render is aborted as soon as one of the `quotas` is exceeded, bounding the work spent
+    /// on hostile templates like deeply nested loops or self-expanding values.
+    /// Classes of placeholders can be disabled with `Quotas::deny`, e.g. extensions for
+    /// templates of customers.
+    ///
+    /// The default implementation differs from `Formatify`:
+    /// - It can't abort the render, the output size and the time budget are checked afterwards.
+    /// - The token, loop iteration and expansion depth quotas are ignored.
+    /// - Denied capabilities are ignored, e.g. `%(uuid)` renders although dynamic built-ins are
+    ///   denied.
+    ///
+    /// Implementors which render untrusted templates should override it.
+    ///
+    /// # Arguments
+    /// * `key_value` - A reference to a HashMap where keys correspond to placeholder identifiers in the input string and values are their replacements.
+    /// * `inp` - The input string containing placeholders.
+    /// * `quotas` - The limits of the render.
+    ///
+    /// # Returns
+    /// The formatted `String`, `RenderError::QuotaExceeded` naming the first exceeded quota or
+    /// `RenderError::CapabilityDenied` naming the first denied class of placeholders used.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter, Quota, Quotas, RenderError};
+    /// # use std::collections::HashMap;
+    /// let mut key_value : HashMap<&str, String> = HashMap::new();
+    /// key_value.insert("name", "Alice".into());
+    /// let formatter = Formatify::new();
+    /// let quotas = Quotas::new().max_output_bytes(16);
+    /// assert_eq!(
+    ///     formatter.replace_placeholders_sandboxed(&key_value, "Hi %(name)!", &quotas),
+    ///     Ok("Hi Alice!".to_string())
+    /// );
+    /// assert_eq!(
+    ///     formatter.replace_placeholders_sandboxed(&key_value, "%(name) %(name) %(name)", &quotas),
+    ///     Err(RenderError::QuotaExceeded { quota: Quota::OutputBytes })
+    /// );
+    /// ```
+    #[cfg(feature = "sandbox")]
+    fn replace_placeholders_sandboxed(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+        quotas: &Quotas,
+    ) -> Result<String, RenderError> {
+        let quota = QuotaState::new(quotas.clone());
+        let out = self.replace_placeholders(key_value, inp);
+        // checks the time budget unless the output is too long already
+        quota.check_output(&out);
+        quota.reserve_output(0, 0);
+        match quota.exceeded() {
+            Some(quota) => Err(RenderError::QuotaExceeded { quota }),
+            None => Ok(out),
+        }
+    }
+
+    /// Replaces placeholders with values of a hierarchical value tree.
+    ///
+    /// Placeholders address nested values with dotted paths, e.g. `%(commit.author.email)`.
+    /// Paths to branches and unknown paths are treated like missing keys. See `ValueTree` for an
+    /// example.
+    ///
+    /// # Arguments
+    /// * `tree` - The hierarchical value source, e.g. converted from JSON.
+    /// * `inp` - The input string containing placeholders.
+    ///
+    /// # Returns
+    /// A new `String` with placeholders replaced by their respective values from `tree`.
+    fn replace_placeholders_tree(&self, tree: &ValueTree, inp: &str) -> String {
+        let flat = tree.flatten();
+        let key_value: HashMap<&str, String> = flat
+            .iter()
+            .map(|(path, value)| (path.as_str(), value.clone()))
+            .collect();
+        self.replace_placeholders(&key_value, inp)
+    }
+
+    /// Replaces placeholders with values queried on demand from a provider, e.g. for value
+    /// sources with thousands of keys.
+    ///
+    /// Only the keys listed by `extract_placeholder_keys` are queried, each once, so memory stays
+    /// proportional to the keys referenced by the template. Keys which are only referenced by
+    /// values (see `FormatifyBuilder::recursive_expansion`) or only read by registered char
+    /// placeholders or extensions aren't queried. See `KeyProvider` for an example.
+    ///
+    /// # Arguments
+    /// * `provider` - The value source, e.g. a closure or several `ScopedProvider`s.
+    /// * `inp` - The input string containing placeholders.
+    ///
+    /// # Returns
+    /// A new `String` with placeholders replaced by the values of `provider`.
+    fn replace_placeholders_provider(&self, provider: &dyn KeyProvider, inp: &str) -> String {
+        let keys = self.extract_unique_placeholder_keys(inp);
+        let key_value: HashMap<&str, String> = keys
+            .iter()
+            .filter_map(|key| Some((key.as_str(), provider.get(key)?)))
+            .collect();
+        self.replace_placeholders(&key_value, inp)
+    }
+
+    /// Replaces placeholders with values of a JSON document.
+    ///
+    /// Placeholders address nested values with dotted paths, e.g. `%(commit.author.email)`. See
+    /// `ValueTree::from_json` for how arrays and `null` are handled. Requires the `json` feature.
+    ///
+    /// # Arguments
+    /// * `json` - The JSON document, e.g. an API response.
+    /// * `inp` - The input string containing placeholders.
+    ///
+    /// # Returns
+    /// A new `String` with placeholders replaced by their respective values from `json`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter};
+    /// let json = serde_json::json!({"user": {"name": "Alice", "roles": ["admin", "dev"]}});
+    /// let formatter = Formatify::new();
+    /// let out = formatter.replace_placeholders_json(&json, "%(user.name) (%(user.roles[0]))");
+    /// assert_eq!(out, "Alice (admin)");
+    /// ```
+    #[cfg(feature = "json")]
+    fn replace_placeholders_json(&self, json: &serde_json::Value, inp: &str) -> String {
+        let tree = ValueTree::from_json(json).unwrap_or_else(ValueTree::branch);
+        self.replace_placeholders_tree(&tree, inp)
+    }
+
+    /// Replaces placeholders with typed values.
+    ///
+    /// Lists can be indexed (`%(tags[0])`) and iterated by loop blocks, maps are addressed with
+    /// dotted paths (`%(user.name)`). See `FormatValue` for an example.
+    ///
+    /// # Arguments
+    /// * `values` - A reference to a HashMap where keys correspond to placeholder identifiers in the input string and values are their typed replacements.
+    /// * `inp` - The input string containing placeholders.
+    ///
+    /// # Returns
+    /// A new `String` with placeholders replaced by their respective rendered values.
+    fn replace_placeholders_values(
+        &self,
+        values: &HashMap<&str, FormatValue>,
+        inp: &str,
+    ) -> String {
+        let tree = values
+            .iter()
+            .fold(ValueTree::branch(), |tree, (key, value)| {
+                tree.with(key, value.to_value_tree())
+            });
+        self.replace_placeholders_tree(&tree, inp)
+    }
+
+    /// Renders several templates against the same key-value map with consistent column widths.
+    ///
+    /// All templates are measured in one pass before rendering. Each value placeholder without
+    /// format placeholder is then left-aligned to the widest value found at the same position
+    /// (n-th value placeholder) in any of the templates, so e.g. a header, the rows and a footer
+    /// line up identically. Value placeholders with an explicit format keep it.
+    ///
+    /// Header labels count like any other value, so a column is never narrower than its label
+    /// and an automatically aligned header is never truncated. An explicit truncating format
+    /// like `%<(3,trunc)` overrides this per column, for the header and the rows alike.
+    ///
+    /// A cell like `%<(span=2)%(title)` spans several columns, e.g. for a table title or a
+    /// grouped header. It is aligned over the combined width of the spanned columns including
+    /// the separators between them, as rendered by a template which has them as single cells.
+    /// A wider value widens the last spanned column.
+    ///
+    /// The default implementation renders the templates without aligning them.
+    ///
+    /// # Arguments
+    /// * `key_value` - A reference to a HashMap where keys correspond to placeholder identifiers in the input string and values are their replacements.
+    /// * `templates` - The templates to render, e.g. header, row and footer.
+    ///
+    /// # Returns
+    /// The rendered templates joined with newlines.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter};
+    /// # use std::collections::HashMap;
+    /// let mut key_value : HashMap<&str, String> = HashMap::new();
+    /// key_value.insert("header", "Name".into());
+    /// key_value.insert("name", "Alice".into());
+    /// let formatter = Formatify::new();
+    /// let table = formatter.replace_placeholders_joined(&key_value, &["%(header)|", "%(name)|"]);
+    /// assert_eq!(table, "Name |\nAlice|");
+    /// ```
+    #[cfg(feature = "tables")]
+    fn replace_placeholders_joined(
+        &self,
+        key_value: &HashMap<&str, String>,
+        templates: &[&str],
+    ) -> String {
+        let lines: Vec<String> = templates
+            .iter()
+            .map(|template| self.replace_placeholders(key_value, template))
+            .collect();
+        lines.join("\n")
+    }
+
+    /// Renders several templates with consistent column widths inside a fenced code block.
+    ///
+    /// Works like `replace_placeholders_joined`, but measures all widths in monospace display
+    /// columns: wide East Asian chars and emoji take two columns, combining marks none. The
+    /// result is wrapped in a fenced code block, so tables survive the proportional fonts of
+    /// chat and markdown targets. The fence is longer than any backtick run of the output.
+    ///
+    /// The default implementation fences the output of `replace_placeholders_joined`.
+    ///
+    /// # Arguments
+    /// * `key_value` - A reference to a HashMap where keys correspond to placeholder identifiers in the input string and values are their replacements.
+    /// * `templates` - The templates to render, e.g. header, row and footer.
+    ///
+    /// # Returns
+    /// The rendered templates joined with newlines inside a fenced code block.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter};
+    /// # use std::collections::HashMap;
+    /// let mut key_value : HashMap<&str, String> = HashMap::new();
+    /// key_value.insert("header", "Name".into());
+    /// key_value.insert("name", "東京".into());
+    /// let formatter = Formatify::new();
+    /// let table = formatter.replace_placeholders_code_block(&key_value, &["%(header)|", "%(name)|"]);
+    /// assert_eq!(table, "```\nName|\n東京|\n```");
+    /// ```
+    #[cfg(feature = "tables")]
+    fn replace_placeholders_code_block(
+        &self,
+        key_value: &HashMap<&str, String>,
+        templates: &[&str],
+    ) -> String {
+        fence_code_block(&self.replace_placeholders_joined(key_value, templates))
+    }
+
+    /// Renders the first of several templates whose output fits into `width` columns, e.g. to
+    /// degrade a CLI status line gracefully from a full over a compact to a minimal variant as
+    /// the terminal gets narrower.
+    ///
+    /// The templates are tried in order. The width of an output is the width of its widest line
+    /// in monospace display columns, ANSI escape sequences don't count. If no output fits, the
+    /// output of the last template is returned.
+    ///
+    /// # Arguments
+    /// * `key_value` - A reference to a HashMap where keys correspond to placeholder identifiers in the input string and values are their replacements.
+    /// * `templates` - The templates in order of preference, e.g. full, compact and minimal.
+    /// * `width` - The available width, e.g. of the terminal.
+    ///
+    /// # Returns
+    /// The output of the first fitting template. An empty `String` if there are no templates.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter};
+    /// # use std::collections::HashMap;
+    /// let mut key_value : HashMap<&str, String> = HashMap::new();
+    /// key_value.insert("branch", "main".into());
+    /// key_value.insert("ahead", "2".into());
+    /// key_value.insert("path", "~/projects/formatify".into());
+    /// let templates = ["%(path) on %(branch), %(ahead) ahead", "%(branch) +%(ahead)", "%(branch)"];
+    /// let formatter = Formatify::new();
+    /// assert_eq!(formatter.replace_placeholders_fitting(&key_value, &templates, 80), "~/projects/formatify on main, 2 ahead");
+    /// assert_eq!(formatter.replace_placeholders_fitting(&key_value, &templates, 10), "main +2");
+    /// assert_eq!(formatter.replace_placeholders_fitting(&key_value, &templates, 3), "main");
+    /// ```
+    fn replace_placeholders_fitting(
+        &self,
+        key_value: &HashMap<&str, String>,
+        templates: &[&str],
+        width: usize,
+    ) -> String {
+        let mut out = String::new();
+        for template in templates {
+            out = self.replace_placeholders(key_value, template);
+            if max_line_width(&out) <= width {
+                break;
+            }
+        }
+        out
+    }
+
+    /// Replaces placeholders with field widths negotiated by the caller.
+    ///
+    /// Before any output is produced, `negotiate` receives the natural widths of all value
+    /// placeholders (as returned by `measure_lengths`, without the total) and returns the widths
+    /// to use, e.g. with a shrunk flexible column. Each value is then padded or truncated to
+    /// exactly the returned width, keeping the alignment of its format. Placeholders without a
+    /// returned width keep their natural width.
+    ///
+    /// The default implementation doesn't call `negotiate` and renders like `replace_placeholders`.
+    ///
+    /// # Arguments
+    /// * `key_value` - A reference to a HashMap where keys correspond to placeholder identifiers in the input string and values are their replacements.
+    /// * `inp` - The input string containing placeholders.
+    /// * `negotiate` - Maps the natural widths to the widths used for rendering.
+    ///
+    /// # Returns
+    /// The formatted `String`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter};
+    /// # use std::collections::HashMap;
+    /// let mut key_value : HashMap<&str, String> = HashMap::new();
+    /// key_value.insert("id", "42".into());
+    /// key_value.insert("title", "A rather long title".into());
+    /// let formatter = Formatify::new();
+    /// // Fit the line into 16 chars by shrinking the title column
+    /// let out = formatter.replace_placeholders_negotiated(&key_value, "%>(4)%(id) %(title)", &|widths| {
+    ///     vec![widths[0], 16 - widths[0] - 1]
+    /// });
+    /// assert_eq!(out, "  42 A rather l…");
+    /// ```
+    fn replace_placeholders_negotiated(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+        negotiate: &dyn Fn(&[usize]) -> Vec<usize>,
+    ) -> String {
+        let _ = negotiate;
+        self.replace_placeholders(key_value, inp)
+    }
+
+    /// Replaces placeholders while an access policy decides about each value.
+    ///
+    /// `policy` receives each key and its value when it is looked up and allows, denies or masks
+    /// it. A denied key is treated as missing, so fallback keys, default values and else branches
+    /// apply. A masked value replaces the value everywhere, including conditions and modifiers.
+    /// Extensions and registered single-char placeholders see the filtered key-value map. This
+    /// allows one template to serve several permission levels.
+    ///
+    /// The default implementation applies the policy to all values of the map before rendering.
+    ///
+    /// # Arguments
+    /// * `key_value` - A reference to a HashMap where keys correspond to placeholder identifiers in the input string and values are their replacements.
+    /// * `inp` - The input string containing placeholders.
+    /// * `policy` - Decides about the access to the value of a key.
+    ///
+    /// # Returns
+    /// The formatted `String`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, KeyAccess, PlaceholderFormatter};
+    /// # use std::collections::HashMap;
+    /// let mut key_value : HashMap<&str, String> = HashMap::new();
+    /// key_value.insert("name", "Alice".into());
+    /// key_value.insert("salary", "85000".into());
+    /// let formatter = Formatify::new();
+    /// let out = formatter.replace_placeholders_with_policy(&key_value, "%(name): %(salary)", &|key, _| {
+    ///     match key {
+    ///         "salary" => KeyAccess::Mask("***".into()),
+    ///         _ => KeyAccess::Allow,
+    ///     }
+    /// });
+    /// assert_eq!(out, "Alice: ***");
+    /// ```
+    fn replace_placeholders_with_policy(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+        policy: &KeyPolicy,
+    ) -> String {
+        let allowed: HashMap<&str, String> = key_value
+            .iter()
+            .filter_map(|(key, value)| match policy(key, value) {
+                KeyAccess::Allow => Some((*key, value.clone())),
+                KeyAccess::Deny => None,
+                KeyAccess::Mask(mask) => Some((*key, mask)),
+            })
+            .collect();
+        self.replace_placeholders(&allowed, inp)
+    }
+
+    /// Replaces placeholders and returns a source map of the output.
+    ///
+    /// The source map maps each char range of the output back to the part of the input which
+    /// produced it: either a char range of the template (literal text, single-char placeholders,
+    /// unchanged placeholders and default values) or a char range of the value of a key. This
+    /// allows interactive tools to jump from a position in the output to the template or datum
+    /// which produced it. Padding and the truncation ellipsis aren't covered by any entry.
+    ///
+    /// The default implementation returns an empty source map.
+    ///
+    /// # Arguments
+    /// * `key_value` - A reference to a HashMap where keys correspond to placeholder identifiers in the input string and values are their replacements.
+    /// * `inp` - The input string containing placeholders.
+    ///
+    /// # Returns
+    /// The formatted `String`, identical to the output of `replace_placeholders`, and the source
+    /// map entries ordered by output position.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter, SourceMapEntry, SourceOrigin};
+    /// # use std::collections::HashMap;
+    /// let mut key_value : HashMap<&str, String> = HashMap::new();
+    /// key_value.insert("name", "Alice".into());
+    /// let formatter = Formatify::new();
+    /// let (out, source_map) = formatter.replace_placeholders_with_source_map(&key_value, "Hi %(name)!");
+    /// assert_eq!(out, "Hi Alice!");
+    /// assert_eq!(source_map[1], SourceMapEntry {
+    ///     output: 3..8,
+    ///     origin: SourceOrigin::Value { key: "name".into(), range: 0..5 },
+    /// });
+    /// assert_eq!(source_map[2], SourceMapEntry {
+    ///     output: 8..9,
+    ///     origin: SourceOrigin::Template(10..11),
+    /// });
+    /// ```
+    fn replace_placeholders_with_source_map(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+    ) -> (String, Vec<SourceMapEntry>) {
+        (self.replace_placeholders(key_value, inp), Vec::new())
+    }
+
+    /// Replaces placeholders and records the complete values of all truncated values.
+    ///
+    /// Each entry covers the output range of a value which was truncated by a `trunc` or `ltrunc`
+    /// format, including the ellipsis, and holds the value before truncation. TUIs or HTML targets
+    /// can use it to show the complete value on hover or expansion.
+    ///
+    /// The default implementation doesn't record truncations.
+    ///
+    /// # Arguments
+    /// * `key_value` - A reference to a HashMap where keys correspond to placeholder identifiers in the input string and values are their replacements.
+    /// * `inp` - The input string containing placeholders.
+    ///
+    /// # Returns
+    /// The formatted `String`, identical to the output of `replace_placeholders`, and the
+    /// truncations ordered by output position.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter, Truncation};
+    /// # use std::collections::HashMap;
+    /// let mut key_value : HashMap<&str, String> = HashMap::new();
+    /// key_value.insert("title", "A rather long title".into());
+    /// let formatter = Formatify::new();
+    /// let (out, truncations) = formatter.replace_placeholders_with_truncations(&key_value, "[%<(8,trunc)%(title)]");
+    /// assert_eq!(out, "[A rathe…]");
+    /// assert_eq!(truncations, vec![Truncation {
+    ///     output: 1..9,
+    ///     key: Some("title".into()),
+    ///     value: "A rather long title".into(),
+    /// }]);
+    /// ```
+    fn replace_placeholders_with_truncations(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+    ) -> (String, Vec<Truncation>) {
+        (self.replace_placeholders(key_value, inp), Vec::new())
+    }
+
+    /// Replaces placeholders and records each resolved value with its source, e.g. as audit
+    /// artifact of customer-facing documents.
+    ///
+    /// Each entry names the key which provided the value and where it came from: the key-value
+    /// map, the ambient values, a built-in, a loop variable, a resolver like an extension or the
+    /// default value. It also states whether the value was transformed by a format or modifiers
+    /// and whether it was truncated. Placeholders with missing values aren't listed.
+    ///
+    /// The default implementation lists the values of the map referenced by the template.
+    ///
+    /// # Arguments
+    /// * `key_value` - A reference to a HashMap where keys correspond to placeholder identifiers in the input string and values are their replacements.
+    /// * `inp` - The input string containing placeholders.
+    ///
+    /// # Returns
+    /// The formatted `String`, identical to the output of `replace_placeholders`, and the entries
+    /// in order of appearance in the template.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{AuditEntry, Formatify, PlaceholderFormatter, ValueSource};
+    /// # use std::collections::HashMap;
+    /// let mut key_value : HashMap<&str, String> = HashMap::new();
+    /// key_value.insert("name", "Alice".into());
+    /// let formatter = Formatify::new();
+    /// let (out, audit) =
+    ///     formatter.replace_placeholders_with_audit(&key_value, "%<(3,trunc)%(name) %(title:-n/a)");
+    /// assert_eq!(out, "Al… n/a");
+    /// assert_eq!(audit, vec![
+    ///     AuditEntry {
+    ///         key: "name".into(),
+    ///         source: ValueSource::KeyValue,
+    ///         value: "Alice".into(),
+    ///         transformed: false,
+    ///         truncated: true,
+    ///     },
+    ///     AuditEntry {
+    ///         key: "title".into(),
+    ///         source: ValueSource::Default,
+    ///         value: "n/a".into(),
+    ///         transformed: false,
+    ///         truncated: false,
+    ///     },
+    /// ]);
+    /// ```
+    fn replace_placeholders_with_audit(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+    ) -> (String, Vec<AuditEntry>) {
+        let audit = self
+            .extract_placeholder_keys(inp)
+            .into_iter()
+            .filter_map(|key| {
+                let value = key_value.get(key.as_str())?.clone();
+                Some(AuditEntry {
+                    key,
+                    source: ValueSource::KeyValue,
+                    value,
+                    transformed: false,
+                    truncated: false,
+                })
+            })
+            .collect();
+        (self.replace_placeholders(key_value, inp), audit)
+    }
+
+    /// Replaces placeholders on a best-effort basis and lists the keys which were referenced but
+    /// not found, e.g. to log data-quality problems of batch jobs in the same pass.
+    ///
+    /// A key is listed if a rendered value placeholder can't be resolved, neither by its fallback
+    /// keys nor by its default value. Placeholders in inactive blocks aren't counted.
+    ///
+    /// The default implementation lists all keys absent from the map, as counted by
+    /// `count_placeholder_keys`, regardless of fallback keys, default values and blocks.
+    ///
+    /// # Arguments
+    /// * `key_value` - A reference to a HashMap where keys correspond to placeholder identifiers in the input string and values are their replacements.
+    /// * `inp` - The input string containing placeholders.
+    ///
+    /// # Returns
+    /// The formatted `String`, identical to the output of `replace_placeholders`, and the missing
+    /// keys with the number of their placeholders, in order of their first appearance.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter};
+    /// # use std::collections::HashMap;
+    /// let mut key_value : HashMap<&str, String> = HashMap::new();
+    /// key_value.insert("name", "Alice".into());
+    /// let formatter = Formatify::new();
+    /// let (out, missing) =
+    ///     formatter.replace_placeholders_checked(&key_value, "%(name) %(city) %(zip) %(city:-?)%(city)");
+    /// assert_eq!(out, "Alice %(city) %(zip) ?%(city)");
+    /// assert_eq!(missing, vec![("city".to_string(), 2), ("zip".to_string(), 1)]);
+    /// ```
+    fn replace_placeholders_checked(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+    ) -> (String, Vec<(String, usize)>) {
+        let mut missing = self.count_placeholder_keys(inp);
+        missing.retain(|(key, _)| !key_value.contains_key(key.as_str()));
+        (self.replace_placeholders(key_value, inp), missing)
+    }
+
+    /// Replaces placeholders and renders the output as HTML with semantic spans.
+    ///
+    /// Literal text and values are HTML-escaped. Each substituted value, including its padding,
+    /// is wrapped in `<span class="fmt-value" data-key="key">`, so front-ends can attach behavior
+    /// to specific fields. With the `colors` feature, color placeholders don't emit escape
+    /// sequences; instead the style classes of the last color placeholder, e.g. `fmt-bold
+    /// fmt-fg-red` for `%C(bold red)`, are added to the spans of the subsequent values.
+    /// `data-key` is omitted if the default value is rendered.
+    ///
+    /// The default implementation escapes the output without wrapping values in spans.
+    ///
+    /// # Arguments
+    /// * `key_value` - A reference to a HashMap where keys correspond to placeholder identifiers in the input string and values are their replacements.
+    /// * `inp` - The input string containing placeholders.
+    ///
+    /// # Returns
+    /// The formatted HTML fragment.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter};
+    /// # use std::collections::HashMap;
+    /// let mut key_value : HashMap<&str, String> = HashMap::new();
+    /// key_value.insert("name", "<Alice>".into());
+    /// let formatter = Formatify::new();
+    /// let out = formatter.replace_placeholders_html(&key_value, "Hi %(name) & co");
+    /// assert_eq!(out, "Hi <span class=\"fmt-value\" data-key=\"name\">&lt;Alice&gt;</span> &amp; co");
+    /// ```
+    #[cfg(feature = "html")]
+    fn replace_placeholders_html(&self, key_value: &HashMap<&str, String>, inp: &str) -> String {
+        html::escape(&self.replace_placeholders(key_value, inp))
+    }
+
+    /// Replaces placeholders and renders the output as Slack mrkdwn or Discord markdown.
+    ///
+    /// Literal text of the template is passed through, so the template may use markdown itself.
+    /// Values are escaped for the chosen flavor. Aligned values, i.e. values with a format like
+    /// `%<(10)`, are wrapped in code spans so that their padding survives proportional fonts.
+    /// With the `colors` feature, values following a color placeholder other than `%C(reset)`
+    /// are set in bold.
+    ///
+    /// The default implementation escapes the values before rendering, so escape characters count
+    /// towards the width of aligned values.
+    ///
+    /// # Arguments
+    /// * `key_value` - A reference to a HashMap where keys correspond to placeholder identifiers in the input string and values are their replacements.
+    /// * `inp` - The input string containing placeholders.
+    /// * `flavor` - The markdown dialect to produce.
+    ///
+    /// # Returns
+    /// The formatted markdown `String`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, MarkdownFlavor, PlaceholderFormatter};
+    /// # use std::collections::HashMap;
+    /// let mut key_value : HashMap<&str, String> = HashMap::new();
+    /// key_value.insert("job", "build_all".into());
+    /// key_value.insert("status", "ok".into());
+    /// let formatter = Formatify::new();
+    /// let out = formatter.replace_placeholders_markdown(&key_value, "%(job): %<(6)%(status)|", MarkdownFlavor::Discord);
+    /// assert_eq!(out, "build\\_all: `ok    `|");
+    /// ```
+    #[cfg(feature = "markdown")]
+    fn replace_placeholders_markdown(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+        flavor: MarkdownFlavor,
+    ) -> String {
+        let escaped: HashMap<&str, String> = key_value
+            .iter()
+            .map(|(key, value)| (*key, flavor.escape(value)))
+            .collect();
+        self.replace_placeholders(&escaped, inp)
+    }
+
+    /// Renders one record as a vertical block with one `name: value` line per placeholder.
+    ///
+    /// The block is generated from the value placeholders used in the template `inp`, so the same
+    /// template serves a tabular and a vertical (record-per-block) view, similar to `\G` in MySQL
+    /// clients. Format placeholders of the template are ignored, keys occurring several times are
+    /// listed once and names are right-aligned to the longest name. Keys without a corresponding
+    /// value are rendered as unchanged placeholder, like in `replace_placeholders`.
+    ///
+    /// # Arguments
+    /// * `key_value` - A reference to a HashMap where keys correspond to placeholder identifiers in the input string and values are their replacements.
+    /// * `inp` - The template whose placeholders define the fields of the block.
+    ///
+    /// # Returns
+    /// A `String` with one line per field, separated by newlines.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter};
+    /// # use std::collections::HashMap;
+    /// let mut key_value : HashMap<&str, String> = HashMap::new();
+    /// key_value.insert("id", "42".into());
+    /// key_value.insert("name", "Alice".into());
+    /// let formatter = Formatify::new();
+    /// let block = formatter.replace_placeholders_vertical(&key_value, "%>(4)%(id) | %<(10)%(name)");
+    /// assert_eq!(block, "  id: 42\nname: Alice");
+    /// ```
+    fn replace_placeholders_vertical(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+    ) -> String {
+        let mut keys = self.extract_placeholder_keys(inp);
+        let mut seen = HashSet::new();
+        keys.retain(|key| seen.insert(key.clone()));
+
+        let name_width = keys
+            .iter()
+            .map(|key| key.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        let lines: Vec<String> = keys
+            .iter()
+            .map(|key| {
+                let value = match key_value.get(key.as_str()) {
+                    Some(value) => value.clone(),
+                    None => format!("%({})", key),
+                };
+                format!("{:>width$}: {}", key, value, width = name_width)
+            })
+            .collect();
+        lines.join("\n")
+    }
+
     /// Measures the length of the entire string and the lengths of valid placeholders within it.
     ///
     /// This method processes the input string `inp`, which is analyzed as if it were to be formatted.
@@ -47,6 +893,14 @@ pub trait PlaceholderFormatter {
     ///
     /// For detailed information on supported placeholders, see [Supported Placeholder Types](#supported-placeholder-types).
     ///
+    /// The default implementation measures the output and the values of the keys listed by
+    /// `extract_placeholder_keys` which are part of the map. It differs from `Formatify`:
+    /// - Values are measured before they are formatted, e.g. `%<(3,trunc)%(name)` measures the
+    ///   whole value.
+    /// - Keys of inactive block branches are measured as well.
+    ///
+    /// Implementors whose lengths are used to lay out output should override it.
+    ///
     /// # Arguments
     /// * `key_value` - A reference to a HashMap containing key-value pairs. The keys represent placeholders in the input string, and the values are their potential replacements.
     /// * `inp` - The input string with placeholders to be measured.
@@ -66,7 +920,52 @@ pub trait PlaceholderFormatter {
     /// let lengths = formatter.measure_lengths(&key_value, "Hello, %(name)! This is a test.");
     /// assert_eq!(lengths, vec![29, 5]); // Total length with "Alice" as the placeholder, length of "Alice"
     /// ```
-    fn measure_lengths(&self, key_value: &HashMap<&str, String>, inp: &str) -> Vec<usize>;
+    fn measure_lengths(&self, key_value: &HashMap<&str, String>, inp: &str) -> Vec<usize> {
+        let out = self.replace_placeholders(key_value, inp);
+        let values = self
+            .extract_placeholder_keys(inp)
+            .into_iter()
+            .filter_map(|key| {
+                let value = key_value.get(key.as_str())?;
+                Some(value.chars().count())
+            });
+        [out.chars().count()].into_iter().chain(values).collect()
+    }
+
+    /// Renders the input string and measures it in one go.
+    ///
+    /// This is equivalent to calling `replace_placeholders` and `measure_lengths`, but `Formatify`
+    /// performs the lookups and the format parsing only once. Use it when rendering rows which are
+    /// measured as well, e.g. to lay out a table.
+    ///
+    /// # Arguments
+    /// * `key_value` - A reference to a HashMap where keys correspond to placeholder identifiers in the input string and values are their replacements.
+    /// * `inp` - The input string containing placeholders.
+    ///
+    /// # Returns
+    /// The formatted `String` and the lengths as returned by `measure_lengths`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter};
+    /// # use std::collections::HashMap;
+    /// let mut key_value : HashMap<&str, String> = HashMap::new();
+    /// key_value.insert("name", "Alice".into());
+    /// let formatter = Formatify::new();
+    /// let (out, lengths) = formatter.measure_and_render(&key_value, "Hi %<(7)%(name)!");
+    /// assert_eq!(out, "Hi Alice  !");
+    /// assert_eq!(lengths, vec![11, 7]);
+    /// ```
+    fn measure_and_render(
+        &self,
+        key_value: &HashMap<&str, String>,
+        inp: &str,
+    ) -> (String, Vec<usize>) {
+        (
+            self.replace_placeholders(key_value, inp),
+            self.measure_lengths(key_value, inp),
+        )
+    }
 
     /// Extracts and lists all placeholder keys from a given string.
     ///
@@ -94,4 +993,484 @@ pub trait PlaceholderFormatter {
     /// assert_eq!(placeholder_keys, vec!["name", "day"]);
     /// ```
     fn extract_placeholder_keys(&self, inp: &str) -> Vec<String>;
+
+    /// Lists the keys of `extract_placeholder_keys` once each, in order of their first
+    /// appearance.
+    ///
+    /// # Arguments
+    /// * `inp` - The input string to be analyzed for placeholder keys.
+    ///
+    /// # Returns
+    /// A `Vec<String>` containing each placeholder key of the input string once.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter};
+    /// let formatter = Formatify::new();
+    /// let keys = formatter.extract_unique_placeholder_keys("%(name) <%(email)> %(name)");
+    /// assert_eq!(keys, vec!["name", "email"]);
+    /// ```
+    fn extract_unique_placeholder_keys(&self, inp: &str) -> Vec<String> {
+        self.count_placeholder_keys(inp)
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect()
+    }
+
+    /// Counts how often each key of `extract_placeholder_keys` is referenced, e.g. to find the
+    /// values which are worth computing once.
+    ///
+    /// # Arguments
+    /// * `inp` - The input string to be analyzed for placeholder keys.
+    ///
+    /// # Returns
+    /// Each placeholder key with the number of its references, in order of their first
+    /// appearance.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter};
+    /// let formatter = Formatify::new();
+    /// let counts = formatter.count_placeholder_keys("%(name) <%(email)> %(name)");
+    /// assert_eq!(counts, vec![("name".to_string(), 2), ("email".to_string(), 1)]);
+    /// ```
+    fn count_placeholder_keys(&self, inp: &str) -> Vec<(String, usize)> {
+        let mut counts: Vec<(String, usize)> = Vec::new();
+        for key in self.extract_placeholder_keys(inp) {
+            match counts.iter_mut().find(|(counted, _)| *counted == key) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((key, 1)),
+            }
+        }
+        counts
+    }
+
+    /// Lists the value placeholders of a template with the format which applies to them and
+    /// their byte range, e.g. to generate documentation of the fields of a template.
+    ///
+    /// The format is the spec of the preceding format placeholder or the default spec of the key
+    /// (see `Formatify::default_spec`), like the format of `Segment::KeyPlaceholder`. Like
+    /// `extract_placeholder_keys`, the placeholders of all block branches are listed.
+    ///
+    /// # Arguments
+    /// * `inp` - The input string to be analyzed for placeholders.
+    ///
+    /// # Returns
+    /// The value placeholders in order of appearance.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter, PlaceholderInfo};
+    /// let formatter = Formatify::new();
+    /// let placeholders = formatter.extract_placeholders("Né: %<(8,trunc)%(name) %(age)");
+    /// assert_eq!(placeholders, vec![
+    ///     PlaceholderInfo { key: "name".into(), format: Some("<8,trunc".into()), span: 16..23 },
+    ///     PlaceholderInfo { key: "age".into(), format: None, span: 24..30 },
+    /// ]);
+    /// ```
+    fn extract_placeholders(&self, inp: &str) -> Vec<PlaceholderInfo> {
+        self.annotate(inp)
+            .into_iter()
+            .filter_map(|annotation| match annotation.segment {
+                Segment::KeyPlaceholder { key, format, .. } => Some(PlaceholderInfo {
+                    key,
+                    format,
+                    span: annotation.bytes,
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Lists the keys of the key-value map which aren't referenced by the template, e.g. to find
+    /// stale entries when maintaining large templates and the code extracting their data.
+    ///
+    /// A key counts as referenced if `extract_placeholder_keys` lists it, so keys of all block
+    /// branches count. Keys which are only read by registered char placeholders or extensions
+    /// are reported as unused.
+    ///
+    /// # Arguments
+    /// * `key_value` - A reference to a HashMap where keys correspond to placeholder identifiers in the input string and values are their replacements.
+    /// * `inp` - The input string to be analyzed for placeholder keys.
+    ///
+    /// # Returns
+    /// The unused keys in sorted order.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter};
+    /// # use std::collections::HashMap;
+    /// let mut key_value : HashMap<&str, String> = HashMap::new();
+    /// key_value.insert("name", "Alice".into());
+    /// key_value.insert("email", "alice@example.com".into());
+    /// key_value.insert("age", "31".into());
+    /// let formatter = Formatify::new();
+    /// assert_eq!(formatter.unused_keys(&key_value, "Hello, %(nick|name)!"), vec!["age", "email"]);
+    /// ```
+    fn unused_keys<'k>(&self, key_value: &HashMap<&'k str, String>, inp: &str) -> Vec<&'k str> {
+        let used: HashSet<String> = self.extract_placeholder_keys(inp).into_iter().collect();
+        let mut unused: Vec<&str> = key_value
+            .keys()
+            .copied()
+            .filter(|key| !used.contains(*key))
+            .collect();
+        unused.sort_unstable();
+        unused
+    }
+
+    /// Parses a template into segments without rendering it.
+    ///
+    /// The segments cover the template in order: literal text, value placeholders with their
+    /// format, single-char placeholders, directives like block tags and invalid placeholders.
+    /// All block branches are included. This allows tools to analyze, transform or validate
+    /// templates without re-implementing the parser.
+    ///
+    /// The default implementation groups the tokens of `tokenize` without validating the placeholders.
+    /// It differs from `Formatify`:
+    /// - Only unterminated placeholders are errors, e.g. `%<(x)` is a directive.
+    /// - Formats aren't resolved, the `format` of value placeholders is always `None`.
+    ///
+    /// # Arguments
+    /// * `inp` - The template to parse.
+    ///
+    /// # Returns
+    /// The segments of the template with their char ranges.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter, Segment};
+    /// let formatter = Formatify::new();
+    /// let segments = formatter.parse("Hi %<(6)%(name)%n");
+    /// assert_eq!(
+    ///     segments,
+    ///     vec![
+    ///         Segment::Literal { text: "Hi ".into(), span: 0..3 },
+    ///         Segment::Directive { span: 3..8 },
+    ///         Segment::KeyPlaceholder { key: "name".into(), format: Some("<6".into()), span: 8..15 },
+    ///         Segment::CharPlaceholder { ch: 'n', span: 15..17 },
+    ///     ]
+    /// );
+    /// ```
+    fn parse(&self, inp: &str) -> Vec<Segment> {
+        let tokens: Vec<Token> = tokenize(inp).collect();
+        let char_pos = |byte: usize| inp[..byte].chars().count();
+        let mut segments: Vec<Segment> = Vec::new();
+        let mut idx = 0;
+        while idx < tokens.len() {
+            let start = &tokens[idx];
+            if start.kind == TokenKind::Literal {
+                segments.push(Segment::Literal {
+                    text: start.text.into(),
+                    span: char_pos(start.span.start)..char_pos(start.span.end),
+                });
+                idx += 1;
+                continue;
+            }
+
+            // a placeholder ends at its closing bracket or, without brackets, at the next
+            // literal text or placeholder
+            let mut depth = 0;
+            let mut end = idx + 1;
+            while let Some(token) = tokens.get(end) {
+                match token.kind {
+                    TokenKind::Open => depth += 1,
+                    TokenKind::Close if depth == 1 => {
+                        depth = 0;
+                        end += 1;
+                        break;
+                    }
+                    TokenKind::Close => depth -= 1,
+                    TokenKind::Literal | TokenKind::Percent if depth == 0 => break,
+                    _ => {}
+                }
+                end += 1;
+            }
+            let placeholder = &tokens[idx..end];
+            idx = end;
+
+            let last = placeholder.last().unwrap_or(start);
+            let span = char_pos(start.span.start)..char_pos(last.span.end);
+            let segment = match placeholder {
+                _ if depth > 0 => Segment::Error { span },
+                [_, token] if token.kind == TokenKind::Sigil => Segment::CharPlaceholder {
+                    ch: token.text.chars().next().unwrap_or('%'),
+                    span,
+                },
+                [_, open, key, ..]
+                    if open.kind == TokenKind::Open
+                        && key.kind == TokenKind::Key
+                        && placeholder
+                            .iter()
+                            .all(|token| token.kind != TokenKind::Sigil) =>
+                {
+                    Segment::KeyPlaceholder {
+                        key: key.text.into(),
+                        format: None,
+                        span,
+                    }
+                }
+                _ => match segments.last_mut() {
+                    Some(Segment::Directive { span: last }) if last.end == span.start => {
+                        last.end = span.end;
+                        continue;
+                    }
+                    _ => Segment::Directive { span },
+                },
+            };
+            segments.push(segment);
+        }
+        segments
+    }
+
+    /// Lists the placeholders and invalid placeholders of a template with byte ranges.
+    ///
+    /// Works like `parse` without the literal segments, but each segment is annotated with its
+    /// byte range in `inp`, so e.g. an editor extension can underline invalid placeholders or
+    /// complete keys at the cursor.
+    ///
+    /// # Arguments
+    /// * `inp` - The template to annotate.
+    ///
+    /// # Returns
+    /// The placeholders, directives and invalid placeholders in order.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, PlaceholderFormatter, Segment};
+    /// let formatter = Formatify::new();
+    /// let annotations = formatter.annotate("Grüße %(name) %<(x)");
+    /// assert_eq!(annotations[0].bytes, 8..15);
+    /// assert!(matches!(annotations[1].segment, Segment::Error { .. }));
+    /// assert_eq!(annotations[1].bytes, 16..19);
+    /// ```
+    fn annotate(&self, inp: &str) -> Vec<Annotation> {
+        let offsets: Vec<usize> = inp
+            .char_indices()
+            .map(|(offset, _)| offset)
+            .chain([inp.len()])
+            .collect();
+        self.parse(inp)
+            .into_iter()
+            .filter(|segment| !matches!(segment, Segment::Literal { .. }))
+            .map(|segment| {
+                let span = segment.span();
+                let bytes = offsets[span.start]..offsets[span.end];
+                Annotation { segment, bytes }
+            })
+            .collect()
+    }
+}
+
+/// Forwards all methods of `PlaceholderFormatter` to the wrapped formatter.
+macro_rules! forward_placeholder_formatter {
+    ($($wrapper:ty),+) => {$(
+        impl<T: PlaceholderFormatter + ?Sized> PlaceholderFormatter for $wrapper {
+            fn replace_placeholders(&self, key_value: &HashMap<&str, String>, inp: &str) -> String {
+                (**self).replace_placeholders(key_value, inp)
+            }
+
+            fn replace_placeholders_fixed_width(
+                &self,
+                key_value: &HashMap<&str, String>,
+                inp: &str,
+                width: usize,
+                policy: MultiBytePolicy,
+            ) -> Result<String, RenderError> {
+                (**self).replace_placeholders_fixed_width(key_value, inp, width, policy)
+            }
+
+            fn replace_placeholders_strict(
+                &self,
+                key_value: &HashMap<&str, String>,
+                inp: &str,
+            ) -> Result<String, RenderError> {
+                (**self).replace_placeholders_strict(key_value, inp)
+            }
+
+            #[cfg(feature = "sandbox")]
+            fn replace_placeholders_sandboxed(
+                &self,
+                key_value: &HashMap<&str, String>,
+                inp: &str,
+                quotas: &Quotas,
+            ) -> Result<String, RenderError> {
+                (**self).replace_placeholders_sandboxed(key_value, inp, quotas)
+            }
+
+            fn replace_placeholders_tree(&self, tree: &ValueTree, inp: &str) -> String {
+                (**self).replace_placeholders_tree(tree, inp)
+            }
+
+            fn replace_placeholders_provider(
+                &self,
+                provider: &dyn KeyProvider,
+                inp: &str,
+            ) -> String {
+                (**self).replace_placeholders_provider(provider, inp)
+            }
+
+            #[cfg(feature = "json")]
+            fn replace_placeholders_json(&self, json: &serde_json::Value, inp: &str) -> String {
+                (**self).replace_placeholders_json(json, inp)
+            }
+
+            fn replace_placeholders_values(
+                &self,
+                values: &HashMap<&str, FormatValue>,
+                inp: &str,
+            ) -> String {
+                (**self).replace_placeholders_values(values, inp)
+            }
+
+            #[cfg(feature = "tables")]
+            fn replace_placeholders_joined(
+                &self,
+                key_value: &HashMap<&str, String>,
+                templates: &[&str],
+            ) -> String {
+                (**self).replace_placeholders_joined(key_value, templates)
+            }
+
+            #[cfg(feature = "tables")]
+            fn replace_placeholders_code_block(
+                &self,
+                key_value: &HashMap<&str, String>,
+                templates: &[&str],
+            ) -> String {
+                (**self).replace_placeholders_code_block(key_value, templates)
+            }
+
+            fn replace_placeholders_fitting(
+                &self,
+                key_value: &HashMap<&str, String>,
+                templates: &[&str],
+                width: usize,
+            ) -> String {
+                (**self).replace_placeholders_fitting(key_value, templates, width)
+            }
+
+            fn replace_placeholders_negotiated(
+                &self,
+                key_value: &HashMap<&str, String>,
+                inp: &str,
+                negotiate: &dyn Fn(&[usize]) -> Vec<usize>,
+            ) -> String {
+                (**self).replace_placeholders_negotiated(key_value, inp, negotiate)
+            }
+
+            fn replace_placeholders_with_policy(
+                &self,
+                key_value: &HashMap<&str, String>,
+                inp: &str,
+                policy: &KeyPolicy,
+            ) -> String {
+                (**self).replace_placeholders_with_policy(key_value, inp, policy)
+            }
+
+            fn replace_placeholders_with_source_map(
+                &self,
+                key_value: &HashMap<&str, String>,
+                inp: &str,
+            ) -> (String, Vec<SourceMapEntry>) {
+                (**self).replace_placeholders_with_source_map(key_value, inp)
+            }
+
+            fn replace_placeholders_with_truncations(
+                &self,
+                key_value: &HashMap<&str, String>,
+                inp: &str,
+            ) -> (String, Vec<Truncation>) {
+                (**self).replace_placeholders_with_truncations(key_value, inp)
+            }
+
+            fn replace_placeholders_with_audit(
+                &self,
+                key_value: &HashMap<&str, String>,
+                inp: &str,
+            ) -> (String, Vec<AuditEntry>) {
+                (**self).replace_placeholders_with_audit(key_value, inp)
+            }
+
+            fn replace_placeholders_checked(
+                &self,
+                key_value: &HashMap<&str, String>,
+                inp: &str,
+            ) -> (String, Vec<(String, usize)>) {
+                (**self).replace_placeholders_checked(key_value, inp)
+            }
+
+            #[cfg(feature = "html")]
+            fn replace_placeholders_html(
+                &self,
+                key_value: &HashMap<&str, String>,
+                inp: &str,
+            ) -> String {
+                (**self).replace_placeholders_html(key_value, inp)
+            }
+
+            #[cfg(feature = "markdown")]
+            fn replace_placeholders_markdown(
+                &self,
+                key_value: &HashMap<&str, String>,
+                inp: &str,
+                flavor: MarkdownFlavor,
+            ) -> String {
+                (**self).replace_placeholders_markdown(key_value, inp, flavor)
+            }
+
+            fn replace_placeholders_vertical(
+                &self,
+                key_value: &HashMap<&str, String>,
+                inp: &str,
+            ) -> String {
+                (**self).replace_placeholders_vertical(key_value, inp)
+            }
+
+            fn measure_lengths(&self, key_value: &HashMap<&str, String>, inp: &str) -> Vec<usize> {
+                (**self).measure_lengths(key_value, inp)
+            }
+
+            fn measure_and_render(
+                &self,
+                key_value: &HashMap<&str, String>,
+                inp: &str,
+            ) -> (String, Vec<usize>) {
+                (**self).measure_and_render(key_value, inp)
+            }
+
+            fn extract_placeholder_keys(&self, inp: &str) -> Vec<String> {
+                (**self).extract_placeholder_keys(inp)
+            }
+
+            fn extract_unique_placeholder_keys(&self, inp: &str) -> Vec<String> {
+                (**self).extract_unique_placeholder_keys(inp)
+            }
+
+            fn count_placeholder_keys(&self, inp: &str) -> Vec<(String, usize)> {
+                (**self).count_placeholder_keys(inp)
+            }
+
+            fn extract_placeholders(&self, inp: &str) -> Vec<PlaceholderInfo> {
+                (**self).extract_placeholders(inp)
+            }
+
+            fn unused_keys<'k>(
+                &self,
+                key_value: &HashMap<&'k str, String>,
+                inp: &str,
+            ) -> Vec<&'k str> {
+                (**self).unused_keys(key_value, inp)
+            }
+
+            fn parse(&self, inp: &str) -> Vec<Segment> {
+                (**self).parse(inp)
+            }
+
+            fn annotate(&self, inp: &str) -> Vec<Annotation> {
+                (**self).annotate(inp)
+            }
+        }
+    )+};
 }
+
+forward_placeholder_formatter!(&T, Box<T>, Arc<T>);
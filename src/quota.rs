@@ -0,0 +1,293 @@
+#[cfg(feature = "sandbox")]
+use std::cell::Cell;
+use std::fmt;
+#[cfg(feature = "sandbox")]
+use std::time::{Duration, Instant};
+
+/// Number of output items generated between two checks of the quotas by loops which generate
+/// output, e.g. padding or repeated values.
+pub const QUOTA_CHECK_INTERVAL: usize = 1024;
+
+/// Hard limits for rendering untrusted templates with `replace_placeholders_sandboxed`.
+///
+/// All limits are disabled by default.
+///
+/// # Examples
+/// ```
+/// # use formatify::Quotas;
+/// # use std::time::Duration;
+/// let quotas = Quotas::new()
+///     .max_tokens(10_000)
+///     .max_expansion_depth(8)
+///     .max_output_bytes(64 * 1024)
+///     .time_budget(Duration::from_millis(50));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg(feature = "sandbox")]
+pub struct Quotas {
+    max_tokens: Option<usize>,
+    #[cfg(feature = "blocks")]
+    max_loop_iterations: Option<usize>,
+    max_expansion_depth: Option<usize>,
+    max_output_bytes: Option<usize>,
+    time_budget: Option<Duration>,
+    denied: Vec<Capability>,
+}
+
+#[cfg(feature = "sandbox")]
+impl Quotas {
+    /// Creates quotas without limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits the number of processed tokens: literal chars and placeholders, including the
+    /// ones of repeated loop bodies, inactive branches and expanded values.
+    pub fn max_tokens(mut self, max: usize) -> Self {
+        self.max_tokens = Some(max);
+        self
+    }
+
+    /// Limits the total number of loop iterations (requires the `blocks` feature).
+    #[cfg(feature = "blocks")]
+    pub fn max_loop_iterations(mut self, max: usize) -> Self {
+        self.max_loop_iterations = Some(max);
+        self
+    }
+
+    /// Limits the depth of recursively expanded values. Unlike the depth configured with
+    /// `FormatifyBuilder::recursive_expansion`, values which would be expanded deeper fail the
+    /// render instead of being inserted unchanged.
+    pub fn max_expansion_depth(mut self, max: usize) -> Self {
+        self.max_expansion_depth = Some(max);
+        self
+    }
+
+    /// Limits the size of the output in bytes.
+    pub fn max_output_bytes(mut self, max: usize) -> Self {
+        self.max_output_bytes = Some(max);
+        self
+    }
+
+    /// Limits the wall-clock time of the render.
+    pub fn time_budget(mut self, budget: Duration) -> Self {
+        self.time_budget = Some(budget);
+        self
+    }
+
+    /// Disables a class of placeholders. A template using it fails to render.
+    pub fn deny(mut self, capability: Capability) -> Self {
+        if !self.denied.contains(&capability) {
+            self.denied.push(capability);
+        }
+        self
+    }
+}
+
+/// Class of placeholders which can be disabled for sandboxed renders with `Quotas::deny`. Without
+/// the `sandbox` feature all capabilities are allowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Capability {
+    /// Extension placeholders like `%(qr:...)` and registered single-char placeholders.
+    Extensions,
+    /// Values computed at render time: `%(now)`, `%(uuid)`, `%(rand)` and `|reldate`.
+    DynamicBuiltins,
+    /// Ambient values set with `Formatify::set_ambient`.
+    Ambient,
+    /// Recursive expansion of placeholders contained in values.
+    Expansion,
+    /// Loop blocks like `%(for:item in items)`.
+    #[cfg(feature = "blocks")]
+    Loops,
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Capability::Extensions => "extensions",
+            Capability::DynamicBuiltins => "dynamic built-ins",
+            Capability::Ambient => "ambient values",
+            Capability::Expansion => "recursive expansion",
+            #[cfg(feature = "blocks")]
+            Capability::Loops => "loops",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Quota exceeded by a sandboxed render.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+#[cfg(feature = "sandbox")]
+pub enum Quota {
+    /// See `Quotas::max_tokens`.
+    Tokens,
+    /// See `Quotas::max_loop_iterations`.
+    #[cfg(feature = "blocks")]
+    LoopIterations,
+    /// See `Quotas::max_expansion_depth`.
+    ExpansionDepth,
+    /// See `Quotas::max_output_bytes`.
+    OutputBytes,
+    /// See `Quotas::time_budget`.
+    Time,
+}
+
+#[cfg(feature = "sandbox")]
+impl fmt::Display for Quota {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Quota::Tokens => "tokens",
+            #[cfg(feature = "blocks")]
+            Quota::LoopIterations => "loop iterations",
+            Quota::ExpansionDepth => "expansion depth",
+            Quota::OutputBytes => "output bytes",
+            Quota::Time => "time budget",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Consumption of the quotas and use of the denied capabilities of a sandboxed render, shared by the contexts of expanded values.
+#[cfg(feature = "sandbox")]
+pub struct QuotaState {
+    quotas: Quotas,
+    start: Instant,
+    tokens: Cell<usize>,
+    #[cfg(feature = "blocks")]
+    loop_iterations: Cell<usize>,
+    exceeded: Cell<Option<Quota>>,
+    denied: Cell<Option<Capability>>,
+}
+
+#[cfg(feature = "sandbox")]
+impl QuotaState {
+    pub fn new(quotas: Quotas) -> Self {
+        Self {
+            quotas,
+            start: Instant::now(),
+            tokens: Cell::new(0),
+            #[cfg(feature = "blocks")]
+            loop_iterations: Cell::new(0),
+            exceeded: Cell::new(None),
+            denied: Cell::new(None),
+        }
+    }
+
+    /// Returns the first denied capability used by the template.
+    pub fn denied(&self) -> Option<Capability> {
+        self.denied.get()
+    }
+
+    /// Returns true if the capability is allowed, otherwise records its use.
+    pub fn allows(&self, capability: Capability) -> bool {
+        if !self.quotas.denied.contains(&capability) {
+            return true;
+        }
+        if self.denied.get().is_none() {
+            self.denied.set(Some(capability));
+        }
+        false
+    }
+
+    /// Returns the first exceeded quota.
+    pub fn exceeded(&self) -> Option<Quota> {
+        self.exceeded.get()
+    }
+
+    /// Records the exceeded quota unless another one was exceeded before. Returns false.
+    pub fn exceed(&self, quota: Quota) -> bool {
+        if self.exceeded.get().is_none() {
+            self.exceeded.set(Some(quota));
+        }
+        false
+    }
+
+    /// Consumes a token, `output_len` is the current output length in chars. Returns false if
+    /// a quota is exceeded and the render must stop.
+    pub fn consume_token(&self, output_len: usize) -> bool {
+        if self.exceeded.get().is_some() || self.denied.get().is_some() {
+            return false;
+        }
+        self.tokens.set(self.tokens.get() + 1);
+        if self
+            .quotas
+            .max_tokens
+            .is_some_and(|max| self.tokens.get() > max)
+        {
+            return self.exceed(Quota::Tokens);
+        }
+        self.reserve_output(output_len, 0)
+    }
+
+    /// Checks the output length before `count` further chars are generated, `output_len` is the
+    /// current output length in chars. Returns false if a quota is exceeded and the chars must
+    /// not be generated.
+    pub fn reserve_output(&self, output_len: usize, count: usize) -> bool {
+        if self.exceeded.get().is_some() {
+            return false;
+        }
+        // each char has at least one byte, the exact size is checked at the end
+        if self
+            .quotas
+            .max_output_bytes
+            .is_some_and(|max| output_len.saturating_add(count) > max)
+        {
+            return self.exceed(Quota::OutputBytes);
+        }
+        if self
+            .quotas
+            .time_budget
+            .is_some_and(|budget| self.start.elapsed() > budget)
+        {
+            return self.exceed(Quota::Time);
+        }
+        true
+    }
+
+    /// Returns the number of bytes which may still be generated after `output_len` chars.
+    pub fn remaining_output(&self, output_len: usize) -> usize {
+        self.quotas
+            .max_output_bytes
+            .map_or(usize::MAX, |max| max.saturating_sub(output_len))
+    }
+
+    /// Consumes a loop iteration. Returns false if the quota is exceeded.
+    #[cfg(feature = "blocks")]
+    pub fn consume_loop_iteration(&self) -> bool {
+        self.loop_iterations.set(self.loop_iterations.get() + 1);
+        if self
+            .quotas
+            .max_loop_iterations
+            .is_some_and(|max| self.loop_iterations.get() > max)
+        {
+            return self.exceed(Quota::LoopIterations);
+        }
+        true
+    }
+
+    /// Checks the depth of an expanded value. Returns false if the quota is exceeded.
+    pub fn enter_expansion(&self, depth: usize) -> bool {
+        if self
+            .quotas
+            .max_expansion_depth
+            .is_some_and(|max| depth > max)
+        {
+            return self.exceed(Quota::ExpansionDepth);
+        }
+        true
+    }
+
+    /// Checks the size of the final output. Returns false if the quota is exceeded.
+    pub fn check_output(&self, output: &str) -> bool {
+        if self
+            .quotas
+            .max_output_bytes
+            .is_some_and(|max| output.len() > max)
+        {
+            return self.exceed(Quota::OutputBytes);
+        }
+        true
+    }
+}
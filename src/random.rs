@@ -0,0 +1,39 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Source of random numbers of the built-ins `%(uuid)` and `%(rand)`.
+pub type RandomFn = Arc<dyn Fn() -> u64 + Send + Sync>;
+
+/// Returns a SplitMix64 generator which yields the same sequence for the same seed.
+pub fn seeded(seed: u64) -> RandomFn {
+    let state = AtomicU64::new(seed);
+    Arc::new(move || {
+        let mut z = state
+            .fetch_add(0x9e37_79b9_7f4a_7c15, Ordering::Relaxed)
+            .wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    })
+}
+
+/// Returns a generator seeded from the per-process random keys of the standard library.
+pub fn system_random() -> RandomFn {
+    seeded(RandomState::new().build_hasher().finish())
+}
+
+/// Renders a random (version 4) UUID, e.g. `6f1c2a0e-3b7d-4f5a-9c2e-1d8b7a6e5f40`.
+pub fn uuid(random: &RandomFn) -> String {
+    let high = (random() & !0xf000) | 0x4000;
+    let low = (random() & !(0b11 << 62)) | (0b10 << 62);
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        high >> 32,
+        (high >> 16) & 0xffff,
+        high & 0xffff,
+        low >> 48,
+        low & 0xffff_ffff_ffff
+    )
+}
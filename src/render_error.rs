@@ -0,0 +1,93 @@
+#[cfg(feature = "sandbox")]
+use super::quota::{Capability, Quota};
+
+use std::fmt;
+use std::ops::Range;
+
+/// Errors returned by the checked rendering methods of `Formatify`.
+///
+/// New variants may be added as the template syntax evolves, so matches outside this crate
+/// need a wildcard arm. Positions are char positions in the template or output, spans are byte
+/// ranges of the template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RenderError {
+    /// The rendered output does not have the declared width.
+    WidthMismatch { expected: usize, found: usize },
+
+    /// A placeholder is not guaranteed to render with a fixed width. Only placeholders
+    /// preceded by a truncating format (`trunc` or `ltrunc`) have a fixed width.
+    UnboundedField { key: String },
+
+    /// A placeholder references a key which is not part of the key-value map. `span` is the
+    /// byte range of the whole placeholder in the template, like `TemplateError::span`.
+    MissingKey { key: String, span: Range<usize> },
+
+    /// The output contains a non-ASCII character at the given char position.
+    NonAsciiOutput { position: usize },
+
+    /// The input contains a sequence at the given char position which starts like a
+    /// placeholder but can't be parsed as one. `found` contains the unparsed sequence.
+    MalformedPlaceholder { position: usize, found: String },
+
+    /// A block (e.g. `%(if:key)`) is not closed until the end of the input.
+    UnclosedBlock { name: String },
+
+    /// A custom single-char placeholder can't be registered because `ch` is reserved for a
+    /// built-in placeholder like `%n`.
+    ReservedChar { ch: char },
+
+    /// A sandboxed render exceeded one of its quotas and was aborted.
+    #[cfg(feature = "sandbox")]
+    QuotaExceeded { quota: Quota },
+
+    /// A sandboxed render used a class of placeholders which is denied by its quotas.
+    #[cfg(feature = "sandbox")]
+    CapabilityDenied { capability: Capability },
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::WidthMismatch { expected, found } => {
+                write!(f, "expected output width {}, found {}", expected, found)
+            }
+            RenderError::UnboundedField { key } => {
+                write!(f, "placeholder '{}' has no fixed width", key)
+            }
+            RenderError::MissingKey { key, span } => {
+                write!(
+                    f,
+                    "key '{}' not found at bytes {}..{}",
+                    key, span.start, span.end
+                )
+            }
+            RenderError::NonAsciiOutput { position } => {
+                write!(f, "non-ASCII character in output at position {}", position)
+            }
+            RenderError::MalformedPlaceholder { position, found } => {
+                write!(
+                    f,
+                    "malformed placeholder '{}' at position {}",
+                    found, position
+                )
+            }
+            RenderError::UnclosedBlock { name } => write!(f, "block '{}' is not closed", name),
+            RenderError::ReservedChar { ch } => {
+                write!(
+                    f,
+                    "'%{}' is a built-in placeholder and can't be registered",
+                    ch
+                )
+            }
+            #[cfg(feature = "sandbox")]
+            RenderError::QuotaExceeded { quota } => write!(f, "quota exceeded: {}", quota),
+            #[cfg(feature = "sandbox")]
+            RenderError::CapabilityDenied { capability } => {
+                write!(f, "capability denied: {}", capability)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
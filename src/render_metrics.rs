@@ -0,0 +1,10 @@
+/// Records a rendered template and the size of its output in bytes.
+pub fn record_render(output: &str) {
+    metrics::counter!("formatify_renders_total").increment(1);
+    metrics::histogram!("formatify_output_bytes").record(output.len() as f64);
+}
+
+/// Records a value placeholder whose key is missing.
+pub fn record_missing_key() {
+    metrics::counter!("formatify_missing_keys_total").increment(1);
+}
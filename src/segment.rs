@@ -0,0 +1,58 @@
+use std::ops::Range;
+
+/// Part of a parsed template, see `PlaceholderFormatter::parse`. Spans are char ranges of the
+/// template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    /// Literal text which is copied to the output.
+    Literal { text: String, span: Range<usize> },
+    /// A value placeholder like `%(key)`. `format` is the spec of the preceding format
+    /// placeholder or the default spec of the key, e.g. `<8,trunc`, see `Formatify::default_spec`.
+    KeyPlaceholder {
+        key: String,
+        format: Option<String>,
+        span: Range<usize>,
+    },
+    /// A single-char placeholder like `%n`, `%x1b` or a registered one, named by the char
+    /// following `%`.
+    CharPlaceholder { ch: char, span: Range<usize> },
+    /// Placeholders which don't render a value themselves, e.g. format, block, color or rule
+    /// placeholders. Consecutive directives form one segment.
+    Directive { span: Range<usize> },
+    /// An invalid placeholder, which is rendered unchanged.
+    Error { span: Range<usize> },
+}
+
+impl Segment {
+    /// Returns the char range of the template covered by the segment.
+    pub fn span(&self) -> &Range<usize> {
+        match self {
+            Segment::Literal { span, .. }
+            | Segment::KeyPlaceholder { span, .. }
+            | Segment::CharPlaceholder { span, .. }
+            | Segment::Directive { span }
+            | Segment::Error { span } => span,
+        }
+    }
+}
+
+/// A placeholder of a template with its byte range, see `PlaceholderFormatter::annotate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    /// The placeholder, with its char range.
+    pub segment: Segment,
+    /// Byte range of the placeholder in the template.
+    pub bytes: Range<usize>,
+}
+
+/// A value placeholder of a template, see `PlaceholderFormatter::extract_placeholders`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlaceholderInfo {
+    /// Key of the placeholder.
+    pub key: String,
+    /// Spec of the format which applies to the placeholder, e.g. `<8,trunc`. `None` if the value
+    /// is rendered unformatted.
+    pub format: Option<String>,
+    /// Byte range of the placeholder in the template.
+    pub span: Range<usize>,
+}
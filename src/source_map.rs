@@ -0,0 +1,81 @@
+use std::ops::Range;
+use std::rc::Rc;
+
+/// Part of the input which produced a range of the output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceOrigin {
+    /// Char range of the template, e.g. literal text, a single-char placeholder or the default
+    /// value of a value placeholder.
+    Template(Range<usize>),
+    /// Char range of the value of `key`.
+    Value { key: String, range: Range<usize> },
+}
+
+/// Maps a char range of the rendered output back to its origin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceMapEntry {
+    /// Char range of the rendered output.
+    pub output: Range<usize>,
+    /// Part of the input the output range was produced from.
+    pub origin: SourceOrigin,
+}
+
+/// Origin of a single output char. Used to build the source map.
+#[derive(Debug, Clone)]
+pub enum CharOrigin {
+    Template(Range<usize>),
+    Value(Rc<str>, usize),
+    /// Chars without counterpart in the input, e.g. padding or the truncation ellipsis.
+    Generated,
+}
+
+/// Merges the origins of consecutive output chars into source map entries. Generated chars
+/// aren't covered by any entry.
+pub fn build_source_map(origins: impl IntoIterator<Item = CharOrigin>) -> Vec<SourceMapEntry> {
+    let mut entries = Vec::<SourceMapEntry>::new();
+    for (idx, origin) in origins.into_iter().enumerate() {
+        let origin = match origin {
+            CharOrigin::Template(range) => SourceOrigin::Template(range),
+            CharOrigin::Value(key, index) => SourceOrigin::Value {
+                key: key.to_string(),
+                range: index..index + 1,
+            },
+            CharOrigin::Generated => continue,
+        };
+
+        if let Some(last) = entries.last_mut().filter(|last| last.output.end == idx) {
+            let merged = match (&mut last.origin, &origin) {
+                (SourceOrigin::Template(last_range), SourceOrigin::Template(range)) => {
+                    if last_range == range {
+                        true
+                    } else if last_range.end == range.start {
+                        last_range.end = range.end;
+                        true
+                    } else {
+                        false
+                    }
+                }
+                (
+                    SourceOrigin::Value {
+                        key: last_key,
+                        range: last_range,
+                    },
+                    SourceOrigin::Value { key, range },
+                ) if last_key == key && last_range.end == range.start => {
+                    last_range.end = range.end;
+                    true
+                }
+                _ => false,
+            };
+            if merged {
+                last.output.end = idx + 1;
+                continue;
+            }
+        }
+        entries.push(SourceMapEntry {
+            output: idx..idx + 1,
+            origin,
+        });
+    }
+    entries
+}
@@ -0,0 +1,120 @@
+use super::list_value::split_list;
+use super::message_format::MessageFormat;
+#[cfg(feature = "modifiers")]
+use super::modifier::Modifier;
+use super::number_format::{NumberFormat, NumberSymbols};
+
+/// Parsed representation of a value placeholder like `%(key)`, `%(key[0])`, `%(key1|key2)`,
+/// `%(key|upper)`, `%(key,num=.2)`, `%(key,plural,one{# file}other{# files})` or `%(key:-default)`.
+#[derive(Debug, Clone)]
+pub struct StrPlaceholder {
+    /// The key used to look up the value.
+    pub key: String,
+    /// Index of the list item of `key` to render. Negative indices count from the end.
+    pub index: Option<isize>,
+    /// Keys used in order if `key` is not part of the key-value map.
+    pub fallbacks: Vec<String>,
+    /// Transformations applied in order to the looked-up value.
+    #[cfg(feature = "modifiers")]
+    pub modifiers: Vec<Modifier>,
+    /// Numeric format applied to the looked-up value before the modifiers.
+    pub number: Option<NumberFormat>,
+    /// Plural or select format applied to the looked-up value after the numeric format.
+    pub message: Option<MessageFormat>,
+    /// Literal used if the key is missing or its value is empty.
+    pub default: Option<String>,
+}
+
+impl StrPlaceholder {
+    /// Creates a placeholder for `key` without default.
+    pub fn new(key: String) -> Self {
+        Self {
+            key,
+            index: None,
+            fallbacks: Vec::new(),
+            #[cfg(feature = "modifiers")]
+            modifiers: Vec::new(),
+            number: None,
+            message: None,
+            default: None,
+        }
+    }
+
+    /// Looks up the value of the first key providing one, using `lookup` to get the values of
+    /// the keys. Returns the key and its value.
+    pub fn find_value(&self, lookup: impl Fn(&str) -> Option<String>) -> Option<(&String, String)> {
+        let value = lookup(&self.key).and_then(|value| self.select(value));
+        if let Some(value) = value {
+            return Some((&self.key, value));
+        }
+        self.fallbacks
+            .iter()
+            .find_map(|key| lookup(key).map(|value| (key, value)))
+    }
+
+    /// Selects the indexed list item of the value. `None` if the index is out of range.
+    fn select(&self, value: String) -> Option<String> {
+        let Some(index) = self.index else {
+            return Some(value);
+        };
+        let mut items = split_list(&value);
+        let idx = match index {
+            0.. => index as usize,
+            _ => items.len().checked_sub(index.unsigned_abs())?,
+        };
+        (idx < items.len()).then(|| items.swap_remove(idx))
+    }
+
+    /// Applies the numeric format using `symbols`, the plural or select format and all
+    /// modifiers to the looked-up value.
+    pub fn transform(&self, value: String, symbols: &NumberSymbols) -> String {
+        self.transform_within(value, symbols, usize::MAX)
+            .unwrap_or_default()
+    }
+
+    /// Like `transform`, but returns `None` instead of repeating the value to more than
+    /// `max_len` bytes, e.g. with `repeat:1000000000`.
+    pub fn transform_within(
+        &self,
+        value: String,
+        symbols: &NumberSymbols,
+        max_len: usize,
+    ) -> Option<String> {
+        let value = match (&self.number, &self.message) {
+            (Some(number), Some(message)) => message.apply(&value, &number.apply(&value, symbols)),
+            (Some(number), None) => number.apply(&value, symbols),
+            (None, Some(message)) => message.apply(&value, &value),
+            (None, None) => value,
+        };
+        self.apply_modifiers_within(value, max_len)
+    }
+
+    /// Applies all modifiers to the value. `None` if a repeated value exceeds `max_len` bytes.
+    #[cfg(feature = "modifiers")]
+    fn apply_modifiers_within(&self, value: String, max_len: usize) -> Option<String> {
+        self.modifiers
+            .iter()
+            .try_fold(value, |value, modifier| match modifier {
+                Modifier::Repeat(count)
+                    if value
+                        .len()
+                        .checked_mul(*count)
+                        .is_none_or(|len| len > max_len) =>
+                {
+                    None
+                }
+                _ => Some(modifier.apply(&value)),
+            })
+    }
+
+    /// Applies all modifiers to the value.
+    #[cfg(not(feature = "modifiers"))]
+    fn apply_modifiers_within(&self, value: String, _max_len: usize) -> Option<String> {
+        Some(value)
+    }
+
+    /// Returns the key and all fallback keys in lookup order.
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        std::iter::once(&self.key).chain(self.fallbacks.iter())
+    }
+}
@@ -0,0 +1,234 @@
+#[cfg(feature = "colors")]
+use super::color_spec::ColorSpec;
+use super::key_provider::KeyProvider;
+use super::output_format::OutputFormat;
+use super::parsing_context::ParsingContext;
+use super::parsing_task::ParsingTask;
+use super::parsing_task_compile::ParsingTaskCompile;
+use super::parsing_task_extract_placeholder_keys::ParsingTaskExtractPlaceholderKeys;
+use super::parsing_task_measure_lengths::ParsingTaskMeasureLengths;
+use super::parsing_task_replace_placeholders::ParsingTaskReplacePlaceholders;
+use super::parsing_task_source_map::ParsingTaskSourceMap;
+use super::source_map::SourceOrigin;
+use super::str_placeholder::StrPlaceholder;
+use super::Formatify;
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+/// Parsed value placeholders by the position of their `%` in the template, with the position
+/// following them.
+pub type CompiledPlaceholders = HashMap<usize, (StrPlaceholder, usize)>;
+
+/// Step of a compiled template. The ranges are the char positions of the source in the
+/// template.
+pub enum Instruction {
+    /// Literal text, copied as is.
+    Literal(Range<usize>),
+    /// Char placeholder like `%n`.
+    Char(char, Range<usize>),
+    /// Value placeholder rendered with the format in effect.
+    Value(StrPlaceholder, OutputFormat, Range<usize>),
+    /// Color placeholder like `%C(red)`.
+    #[cfg(feature = "colors")]
+    Color(ColorSpec, Range<usize>),
+}
+
+impl Instruction {
+    /// Returns the char range of the source of the instruction in the template.
+    pub fn span(&self) -> Range<usize> {
+        match self {
+            Instruction::Literal(span)
+            | Instruction::Char(_, span)
+            | Instruction::Value(_, _, span) => span.clone(),
+            #[cfg(feature = "colors")]
+            Instruction::Color(_, span) => span.clone(),
+        }
+    }
+}
+
+/// A template which is parsed once and rendered many times, e.g. for thousands of records.
+///
+/// Parsing the placeholders, including their modifiers and formats, is the main cost of a
+/// render. A `Template` compiles them in advance, so each render only resolves the values. A
+/// flat template, i.e. one without blocks, alignment anchors, wrapping, tab stops and other
+/// placeholders which depend on the state of the render, is compiled into a list of literal
+/// texts and placeholders which is rendered without parsing the template again. Other
+/// templates parse their value placeholders in advance only. The output is identical to the
+/// corresponding methods of [`Formatify`].
+///
+/// # Examples
+/// ```
+/// # use formatify::{Formatify, Template};
+/// # use std::collections::HashMap;
+/// let formatter = Formatify::new();
+/// let template = Template::parse(&formatter, "%<(6)%(name)|%>(4)%(age)");
+/// assert_eq!(template.keys(), ["name", "age"]);
+///
+/// for (name, age) in [("Alice", "31"), ("Bob", "4")] {
+///     let mut key_value: HashMap<&str, String> = HashMap::new();
+///     key_value.insert("name", name.into());
+///     key_value.insert("age", age.into());
+///     println!("{}", template.render(&key_value));
+/// }
+/// ```
+pub struct Template<'f> {
+    formatter: &'f Formatify,
+    source: String,
+    placeholders: CompiledPlaceholders,
+    instructions: Option<Vec<Instruction>>,
+    keys: Vec<String>,
+}
+
+impl<'f> Template<'f> {
+    /// Parses `source` with the configuration of `formatter`, e.g. its modifiers and presets.
+    pub fn parse(formatter: &'f Formatify, source: &str) -> Self {
+        let key_value = HashMap::new();
+        let (placeholders, instructions) =
+            formatter.parse_generic::<ParsingTaskCompile>(&key_value, source);
+        let keys = formatter.parse_generic::<ParsingTaskExtractPlaceholderKeys>(&key_value, source);
+        Self {
+            formatter,
+            source: source.to_string(),
+            placeholders,
+            instructions,
+            keys,
+        }
+    }
+
+    /// Returns the template source.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the keys of the template like `extract_placeholder_keys`.
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Renders the template like `replace_placeholders`.
+    pub fn render(&self, key_value: &HashMap<&str, String>) -> String {
+        let context = self
+            .formatter
+            .init_context::<ParsingTaskReplacePlaceholders>(key_value, &self.source);
+        self.run::<ParsingTaskReplacePlaceholders>(context)
+    }
+
+    /// Renders the template with values queried from `provider` like
+    /// `replace_placeholders_provider`. Each key of the template is queried once.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, Template};
+    /// let formatter = Formatify::new();
+    /// let template = Template::parse(&formatter, "%(user)@%(host)");
+    /// let env = |key: &str| (key == "user").then(|| "alice".to_string());
+    /// assert_eq!(template.render_with(&env), "alice@%(host)");
+    /// ```
+    pub fn render_with(&self, provider: &dyn KeyProvider) -> String {
+        let mut queried = HashSet::new();
+        let key_value: HashMap<&str, String> = self
+            .keys
+            .iter()
+            .filter(|key| queried.insert(key.as_str()))
+            .filter_map(|key| Some((key.as_str(), provider.get(key)?)))
+            .collect();
+        self.render(&key_value)
+    }
+
+    /// Renders the template with each value padded or truncated to exactly the width at its
+    /// position, like `replace_placeholders_negotiated` with fixed widths. Values without a
+    /// width keep their natural width.
+    pub fn render_fixed(&self, key_value: &HashMap<&str, String>, widths: &[usize]) -> String {
+        let mut context = self
+            .formatter
+            .init_context::<ParsingTaskReplacePlaceholders>(key_value, &self.source);
+        context.exact_cell_widths = widths.to_vec();
+        self.run::<ParsingTaskReplacePlaceholders>(context)
+    }
+
+    /// Renders the template for each record and joins the outputs with NUL chars, so they can be
+    /// consumed by `xargs -0` style pipelines even if values contain line feeds. NUL chars of
+    /// values are escaped as `\0` and backslashes as `\\`, so each item can be unescaped to its
+    /// original text. NUL chars of the template, e.g. of `%0`, are kept and split a record into
+    /// several items.
+    ///
+    /// # Examples
+    /// ```
+    /// # use formatify::{Formatify, Template};
+    /// # use std::collections::HashMap;
+    /// let formatter = Formatify::new();
+    /// let template = Template::parse(&formatter, "%(name): %(msg)");
+    ///
+    /// let records: Vec<HashMap<&str, String>> = [("Alice", "line 1\nline 2"), ("Bob", "a\0b")]
+    ///     .into_iter()
+    ///     .map(|(name, msg)| HashMap::from([("name", name.into()), ("msg", msg.into())]))
+    ///     .collect();
+    /// let out = template.render_nul_delimited(&records);
+    /// assert_eq!(out, "Alice: line 1\nline 2\0Bob: a\\0b");
+    ///
+    /// let template = Template::parse(&formatter, "%(name)%0%(msg)");
+    /// let out = template.render_nul_delimited(&records[1..]);
+    /// assert_eq!(out, "Bob\0a\\0b");
+    /// ```
+    pub fn render_nul_delimited<'r, 'k: 'r, I>(&self, records: I) -> String
+    where
+        I: IntoIterator<Item = &'r HashMap<&'k str, String>>,
+    {
+        let mut out = String::new();
+        for (idx, key_value) in records.into_iter().enumerate() {
+            if idx > 0 {
+                out.push('\0');
+            }
+            let record = self.render(key_value);
+            if record.contains('\0') {
+                self.push_escaped_values(&mut out, key_value);
+            } else {
+                out.push_str(&record.replace('\\', "\\\\"));
+            }
+        }
+        out
+    }
+
+    /// Renders a record which contains NUL chars and appends it to `out` with backslashes and
+    /// the NUL chars of values escaped. The source map tells values and the template apart.
+    fn push_escaped_values(&self, out: &mut String, key_value: &HashMap<&str, String>) {
+        let context = self
+            .formatter
+            .init_context::<ParsingTaskSourceMap>(key_value, &self.source);
+        let (record, source_map) = self.run::<ParsingTaskSourceMap>(context);
+        let mut of_value = vec![false; record.chars().count()];
+        for entry in source_map {
+            if matches!(entry.origin, SourceOrigin::Value { .. }) {
+                of_value[entry.output].fill(true);
+            }
+        }
+        for (ch, of_value) in record.chars().zip(of_value) {
+            match ch {
+                '\\' => out.push_str("\\\\"),
+                '\0' if of_value => out.push_str("\\0"),
+                _ => out.push(ch),
+            }
+        }
+    }
+
+    /// Measures the template like `measure_lengths`.
+    pub fn measure(&self, key_value: &HashMap<&str, String>) -> Vec<usize> {
+        let context = self
+            .formatter
+            .init_context::<ParsingTaskMeasureLengths>(key_value, &self.source);
+        self.run::<ParsingTaskMeasureLengths>(context)
+    }
+
+    /// Renders the template with task `T`: runs the instructions of a flat template, otherwise
+    /// parses it with the value placeholders parsed in advance.
+    fn run<'a, T: ParsingTask>(&'a self, mut context: ParsingContext<'a, T::Item>) -> T::Output {
+        match &self.instructions {
+            Some(instructions) => self.formatter.run_instructions::<T>(context, instructions),
+            None => {
+                context.compiled = Some(&self.placeholders);
+                self.formatter.parse_context::<T>(context)
+            }
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use std::fmt;
+use std::ops::Range;
+
+/// Kind of a problem found by `Formatify::validate`.
+///
+/// New variants may be added as the template syntax evolves, so matches outside this crate
+/// need a wildcard arm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TemplateErrorKind {
+    /// The width of a format placeholder like `%<(x)` isn't a number.
+    BadWidth,
+    /// A format placeholder has an argument it doesn't know, e.g. `%<(8,foo)`.
+    UnknownArgument,
+    /// A placeholder isn't closed until the end of the template, e.g. `%(name`.
+    Unterminated,
+    /// A block (e.g. `%(if:key)`) isn't closed until the end of the template.
+    UnclosedBlock,
+    /// Any other sequence which starts like a placeholder but can't be parsed as one.
+    Malformed,
+}
+
+/// A problem of a template found by `Formatify::validate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateError {
+    /// Kind of the problem.
+    pub kind: TemplateErrorKind,
+    /// Byte range of the offending sequence in the template. Empty at the end of the template
+    /// for an unclosed block.
+    pub span: Range<usize>,
+    /// Human-readable description of the problem.
+    pub message: String,
+}
+
+impl TemplateError {
+    /// Classifies the unparsable sequence `found` at the byte range `span` of `inp`.
+    pub(crate) fn malformed(inp: &str, span: Range<usize>) -> Self {
+        let found = &inp[span.clone()];
+        let (kind, message) = match Self::format_args(found) {
+            _ if span.end == inp.len() && Self::is_open(found) => (
+                TemplateErrorKind::Unterminated,
+                format!("unterminated placeholder '{}'", found),
+            ),
+            Some(args) if args.contains(',') => (
+                TemplateErrorKind::UnknownArgument,
+                format!("unknown argument in '{}'", found),
+            ),
+            Some(_) => (
+                TemplateErrorKind::BadWidth,
+                format!("invalid width in '{}'", found),
+            ),
+            None => (
+                TemplateErrorKind::Malformed,
+                format!("malformed placeholder '{}'", found),
+            ),
+        };
+        TemplateError {
+            kind,
+            span,
+            message,
+        }
+    }
+
+    /// Returns true if the placeholder `found` still expects further chars, e.g. `%` or `%(name`.
+    fn is_open(found: &str) -> bool {
+        found == "%" || (found.contains('(') && !found.ends_with(')'))
+    }
+
+    /// Returns the arguments of a format placeholder like `%<(8,trunc)`, `None` for other
+    /// placeholders.
+    fn format_args(found: &str) -> Option<&str> {
+        let rest = found.strip_prefix('%')?;
+        let rest = ["<|", ">|", "<", ">", "-", "T", "w"]
+            .iter()
+            .find_map(|sigil| rest.strip_prefix(sigil))?;
+        rest.strip_prefix('(')
+    }
+
+    /// Error of a block named `name` which isn't closed until the end of `inp`.
+    pub(crate) fn unclosed_block(inp: &str, name: &str) -> Self {
+        TemplateError {
+            kind: TemplateErrorKind::UnclosedBlock,
+            span: inp.len()..inp.len(),
+            message: format!("block '{}' is not closed", name),
+        }
+    }
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at bytes {}..{}",
+            self.message, self.span.start, self.span.end
+        )
+    }
+}
+
+impl std::error::Error for TemplateError {}
@@ -0,0 +1,8 @@
+/// Returns a stable identity of a template for diagnostics: the 64-bit FNV-1a hash of the
+/// template as 16 hex digits. Identical templates have the same identity across processes.
+pub fn template_id(inp: &str) -> String {
+    let hash = inp.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    format!("{hash:016x}")
+}
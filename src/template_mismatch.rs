@@ -0,0 +1,29 @@
+use super::output_edit::OutputEdit;
+
+use std::fmt;
+
+/// First sample for which two templates render different outputs, see
+/// `Formatify::equivalent`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateMismatch {
+    /// Index of the sample map.
+    pub sample: usize,
+    /// Output of the first template.
+    pub left: String,
+    /// Output of the second template.
+    pub right: String,
+    /// Edits which turn the output of the first template into the output of the second one.
+    pub diff: Vec<OutputEdit>,
+}
+
+impl fmt::Display for TemplateMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "outputs differ for sample {}: {:?} != {:?}",
+            self.sample, self.left, self.right
+        )
+    }
+}
+
+impl std::error::Error for TemplateMismatch {}
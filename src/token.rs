@@ -0,0 +1,339 @@
+use std::ops::Range;
+
+/// Kind of a raw token of a template, see [`tokenize`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Literal text outside of placeholders.
+    Literal,
+    /// The `%` starting a placeholder.
+    Percent,
+    /// Selects the kind of a placeholder, e.g. `n`, `<`, `>|`, `C` or `+` following `%`, a
+    /// block prefix like `?`, `/`, `#` or `if:` following `(`, or a prefix like `preset:` or
+    /// `span=` of a format argument.
+    Sigil,
+    /// Opening bracket of the arguments of a placeholder.
+    Open,
+    /// Closing bracket of the arguments of a placeholder.
+    Close,
+    /// The first name of a value placeholder or block tag.
+    Key,
+    /// A width of a format placeholder, digits or `*`.
+    Width,
+    /// Separates the arguments of a placeholder: `,`, `|` or `:-`.
+    Separator,
+    /// Any other argument, e.g. a fallback key, a modifier, a default value, `trunc`, a fill
+    /// char or a color specification.
+    Arg,
+}
+
+/// A raw token of a template with its byte range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token<'a> {
+    /// Kind of the token.
+    pub kind: TokenKind,
+    /// Text of the token.
+    pub text: &'a str,
+    /// Byte range of the token in the template.
+    pub span: Range<usize>,
+}
+
+/// Splits a template into raw tokens with byte offsets, e.g. for linters and syntax highlighting.
+///
+/// Tokenizing follows the lexical structure of the placeholders without validating them, so
+/// any input is accepted. Whitespace within brackets isn't part of any token, all other bytes
+/// are covered by exactly one token. Nested placeholders like `%-(%(width_of:title))` are
+/// tokenized as well. Use `PlaceholderFormatter::parse` for validated segments.
+///
+/// # Examples
+/// ```
+/// # use formatify::{tokenize, TokenKind};
+/// let tokens: Vec<_> = tokenize("Hi %<(8,trunc)%(name)")
+///     .map(|token| (token.kind, token.text))
+///     .collect();
+/// assert_eq!(
+///     tokens,
+///     vec![
+///         (TokenKind::Literal, "Hi "),
+///         (TokenKind::Percent, "%"),
+///         (TokenKind::Sigil, "<"),
+///         (TokenKind::Open, "("),
+///         (TokenKind::Width, "8"),
+///         (TokenKind::Separator, ","),
+///         (TokenKind::Arg, "trunc"),
+///         (TokenKind::Close, ")"),
+///         (TokenKind::Percent, "%"),
+///         (TokenKind::Open, "("),
+///         (TokenKind::Key, "name"),
+///         (TokenKind::Close, ")"),
+///     ]
+/// );
+/// ```
+pub fn tokenize(inp: &str) -> impl Iterator<Item = Token<'_>> {
+    let mut tokenizer = Tokenizer {
+        inp,
+        tokens: Vec::new(),
+    };
+    tokenizer.tokenize(0, inp.len());
+    tokenizer.tokens.into_iter()
+}
+
+/// Block prefixes following the opening bracket of a value placeholder.
+const BLOCK_SIGILS: [&str; 9] = [
+    "?",
+    "/",
+    "#",
+    "if:",
+    "for:",
+    "switch:",
+    "case:",
+    ":else",
+    "width_of:",
+];
+
+struct Tokenizer<'a> {
+    inp: &'a str,
+    tokens: Vec<Token<'a>>,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn push(&mut self, kind: TokenKind, span: Range<usize>) {
+        if span.is_empty() {
+            return;
+        }
+        self.tokens.push(Token {
+            kind,
+            text: &self.inp[span.clone()],
+            span,
+        });
+    }
+
+    /// Pushes the trimmed range as token, if it isn't empty.
+    fn push_trimmed(&mut self, kind: TokenKind, span: Range<usize>) {
+        let raw = &self.inp[span.clone()];
+        let start = span.start + (raw.len() - raw.trim_start().len());
+        self.push(kind, start..start + raw.trim().len());
+    }
+
+    /// Returns the char at byte position `pos`.
+    fn char_at(&self, pos: usize) -> Option<char> {
+        self.inp.get(pos..)?.chars().next()
+    }
+
+    /// Tokenizes the template between the byte positions `start` and `end`.
+    fn tokenize(&mut self, start: usize, end: usize) {
+        let mut pos = start;
+        while pos < end {
+            let literal_end = self.inp[pos..end].find('%').map_or(end, |idx| pos + idx);
+            self.push(TokenKind::Literal, pos..literal_end);
+            if literal_end == end {
+                break;
+            }
+            pos = self.placeholder(literal_end, end);
+        }
+    }
+
+    /// Tokenizes the placeholder starting with `%` at `pos`. Returns the position following it.
+    fn placeholder(&mut self, pos: usize, end: usize) -> usize {
+        self.push(TokenKind::Percent, pos..pos + 1);
+        let pos = pos + 1;
+        let Some(ch) = self.char_at(pos).filter(|_| pos < end) else {
+            return pos;
+        };
+        let after = pos + ch.len_utf8();
+        let follows_bracket = self.char_at(after) == Some('(');
+        match ch {
+            '(' => self.group(pos, end, false),
+            '+' | ' ' if follows_bracket => {
+                self.push(TokenKind::Sigil, pos..after);
+                self.group(after, end, false)
+            }
+            '-' if follows_bracket => {
+                self.push(TokenKind::Sigil, pos..after);
+                // `%-(key)` removes line feeds, `%-(5)` is a horizontal rule
+                let is_key = self
+                    .char_at(after + 1)
+                    .is_some_and(|ch| ch.is_alphabetic() || ch == '_');
+                self.group(after, end, !is_key)
+            }
+            '<' | '>' => {
+                let sigil_end = match self.char_at(after) {
+                    Some('|') => after + 1,
+                    _ => after,
+                };
+                self.push(TokenKind::Sigil, pos..sigil_end);
+                match self.char_at(sigil_end) {
+                    Some('(') => self.group(sigil_end, end, true),
+                    _ => sigil_end,
+                }
+            }
+            'x' => {
+                self.push(TokenKind::Sigil, pos..after);
+                let hex_end = self.inp[after..end]
+                    .char_indices()
+                    .take(2)
+                    .take_while(|(_, ch)| ch.is_ascii_hexdigit())
+                    .last()
+                    .map_or(after, |(idx, _)| after + idx + 1);
+                self.push(TokenKind::Arg, after..hex_end);
+                hex_end
+            }
+            'C' if !follows_bracket => {
+                // shorthands like `%Cred`
+                self.push(TokenKind::Sigil, pos..after);
+                let name_end = self.inp[after..end]
+                    .find(|ch: char| !ch.is_ascii_lowercase())
+                    .map_or(end, |idx| after + idx);
+                self.push(TokenKind::Arg, after..name_end);
+                name_end
+            }
+            _ => {
+                self.push(TokenKind::Sigil, pos..after);
+                match follows_bracket {
+                    true => self.group(after, end, true),
+                    false => after,
+                }
+            }
+        }
+    }
+
+    /// Tokenizes the bracketed arguments starting with `(` at `pos`, the arguments of a format
+    /// like placeholder if `format`, otherwise of a value placeholder or block tag. Returns the
+    /// position following the closing bracket.
+    fn group(&mut self, pos: usize, end: usize, format: bool) -> usize {
+        self.push(TokenKind::Open, pos..pos + 1);
+        let start = pos + 1;
+        let close = self.closing_bracket(start, end);
+        let args_end = close.unwrap_or(end);
+        match format {
+            true => self.format_args(start, args_end),
+            false => self.value_args(start, args_end),
+        }
+        match close {
+            Some(close) => {
+                self.push(TokenKind::Close, close..close + 1);
+                close + 1
+            }
+            None => end,
+        }
+    }
+
+    /// Returns the position of the bracket closing the group starting at `start`. Brackets
+    /// within quotes and nested brackets are skipped.
+    fn closing_bracket(&self, start: usize, end: usize) -> Option<usize> {
+        let mut depth = 0;
+        let mut quote = None;
+        for (idx, ch) in self.inp[start..end].char_indices() {
+            match (quote, ch) {
+                (Some(open), _) if ch == open => quote = None,
+                (Some(_), _) => {}
+                (None, '\'' | '"') => quote = Some(ch),
+                (None, '(' | '{') => depth += 1,
+                (None, ')') if depth == 0 => return Some(start + idx),
+                (None, ')' | '}') => depth -= 1,
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Returns the positions of the top-level occurrences of `separators` between `start` and
+    /// `end` with their length.
+    fn split(&self, start: usize, end: usize, separators: &[&str]) -> Vec<(usize, usize)> {
+        let mut positions = Vec::new();
+        let mut depth = 0;
+        let mut quote = None;
+        let mut skip_until = start;
+        for (idx, ch) in self.inp[start..end].char_indices() {
+            let pos = start + idx;
+            if pos < skip_until {
+                continue;
+            }
+            match (quote, ch) {
+                (Some(open), _) if ch == open => quote = None,
+                (Some(_), _) => {}
+                (None, '\'' | '"') => quote = Some(ch),
+                (None, '(' | '{') => depth += 1,
+                (None, ')' | '}') => depth -= 1,
+                (None, _) if depth == 0 => {
+                    if let Some(separator) = separators
+                        .iter()
+                        .find(|separator| self.inp[pos..end].starts_with(**separator))
+                    {
+                        positions.push((pos, separator.len()));
+                        skip_until = pos + separator.len();
+                    }
+                }
+                _ => {}
+            }
+        }
+        positions
+    }
+
+    /// Tokenizes the arguments of a format like placeholder: widths, prefixed arguments like
+    /// `preset:name`, nested placeholders and other arguments separated by `,`.
+    fn format_args(&mut self, start: usize, end: usize) {
+        let mut arg_start = start;
+        for (pos, len) in self.split(start, end, &[","]).into_iter().chain([(end, 0)]) {
+            self.format_arg(arg_start, pos);
+            self.push(TokenKind::Separator, pos..pos + len);
+            arg_start = pos + len;
+        }
+    }
+
+    fn format_arg(&mut self, start: usize, end: usize) {
+        let raw = &self.inp[start..end];
+        let text = raw.trim();
+        let start = start + (raw.len() - raw.trim_start().len());
+        let end = start + text.len();
+        if text.starts_with('%') {
+            self.tokenize(start, end);
+        } else if !text.is_empty() && (text == "*" || text.chars().all(|ch| ch.is_ascii_digit())) {
+            self.push(TokenKind::Width, start..end);
+        } else if let Some(idx) = ["preset:", "span="]
+            .iter()
+            .find_map(|prefix| text.starts_with(prefix).then_some(prefix.len()))
+        {
+            self.push(TokenKind::Sigil, start..start + idx);
+            self.push_trimmed(TokenKind::Arg, start + idx..end);
+        } else {
+            self.push(TokenKind::Arg, start..end);
+        }
+    }
+
+    /// Tokenizes the arguments of a value placeholder or block tag: an optional block prefix,
+    /// the key and further arguments separated by `,`, `|` or `:-`. The default value following
+    /// `:-` is a single argument.
+    fn value_args(&mut self, start: usize, end: usize) {
+        let text = &self.inp[start..end];
+        let mut start = start;
+        if let Some(sigil) = BLOCK_SIGILS.iter().find(|sigil| text.starts_with(**sigil)) {
+            self.push(TokenKind::Sigil, start..start + sigil.len());
+            start += sigil.len();
+            if matches!(*sigil, "?" | "if:") {
+                // conditions may contain `|` and `,`
+                self.push_trimmed(TokenKind::Arg, start..end);
+                return;
+            }
+        }
+
+        let mut kind = TokenKind::Key;
+        let mut arg_start = start;
+        for (pos, len) in self
+            .split(start, end, &[":-", ",", "|"])
+            .into_iter()
+            .chain([(end, 0)])
+        {
+            if arg_start > pos {
+                continue; // part of the default value
+            }
+            self.push_trimmed(kind, arg_start..pos);
+            self.push(TokenKind::Separator, pos..pos + len);
+            kind = TokenKind::Arg;
+            arg_start = pos + len;
+            if &self.inp[pos..pos + len] == ":-" {
+                self.push(TokenKind::Arg, arg_start..end);
+                arg_start = end;
+            }
+        }
+    }
+}
@@ -0,0 +1,14 @@
+use std::ops::Range;
+
+/// A value which was truncated to fit its field, e.g. by `%<(10,trunc)`.
+///
+/// Allows targets like TUIs or HTML to show the complete value on hover or expansion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Truncation {
+    /// Char range of the truncated value in the rendered output, including the ellipsis.
+    pub output: Range<usize>,
+    /// Key which provided the value. `None` if the default value was truncated.
+    pub key: Option<String>,
+    /// The complete value before truncation.
+    pub value: String,
+}
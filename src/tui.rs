@@ -0,0 +1,204 @@
+#[cfg(feature = "colors")]
+use super::color_spec::{Attribute, Color, ColorSpec};
+use super::display_width::char_width;
+use super::Formatify;
+
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::Style;
+use std::collections::HashMap;
+
+/// Region of a ratatui `Buffer` which displays a rendered template and repaints only the cells
+/// which changed since the previous render.
+///
+/// Color placeholders like `%C(bold red)` style the subsequent chars. The fill placeholders
+/// `%<(*)` and `%>(*)` resolve against the width of the region. Lines and chars outside of the
+/// region are clipped; the remaining cells are cleared with the base style.
+///
+/// # Examples
+/// ```
+/// # use formatify::{Formatify, TemplateRegion};
+/// # use ratatui::{buffer::Buffer, layout::Rect};
+/// # use std::collections::HashMap;
+/// let formatter = Formatify::new();
+/// let area = Rect::new(0, 0, 12, 1);
+/// let mut buf = Buffer::empty(area);
+/// let mut region = TemplateRegion::new("cpu %>(3)%(cpu)%", area);
+///
+/// let mut key_value : HashMap<&str, String> = HashMap::new();
+/// key_value.insert("cpu", "12".into());
+/// assert_eq!(region.render(&formatter, &key_value, &mut buf), 12);
+///
+/// key_value.insert("cpu", "13".into());
+/// assert_eq!(region.render(&formatter, &key_value, &mut buf), 1);
+/// assert_eq!(buf, Buffer::with_lines(["cpu  13%    "]));
+/// ```
+#[derive(Debug, Clone)]
+pub struct TemplateRegion {
+    template: String,
+    area: Rect,
+    style: Style,
+    /// Symbols and styles of the cells written by the previous render, row by row. `None` if
+    /// the next render must repaint all cells.
+    painted: Option<Vec<(String, Style)>>,
+}
+
+impl TemplateRegion {
+    /// Creates a region which displays `template` in `area`.
+    pub fn new(template: &str, area: Rect) -> Self {
+        Self {
+            template: template.to_string(),
+            area,
+            style: Style::default(),
+            painted: None,
+        }
+    }
+
+    /// Sets the base style of the region. Color placeholders are applied on top of it.
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Returns the area of the region.
+    pub fn area(&self) -> Rect {
+        self.area
+    }
+
+    /// Moves or resizes the region. The next render repaints all cells.
+    pub fn set_area(&mut self, area: Rect) {
+        self.area = area;
+        self.invalidate();
+    }
+
+    /// Forces the next render to repaint all cells, e.g. after the buffer was cleared.
+    pub fn invalidate(&mut self) {
+        self.painted = None;
+    }
+
+    /// Renders the template with the values of `key_value` into `buf` and returns the number of
+    /// cells which were written. Only cells which differ from the previous render are written.
+    pub fn render(
+        &mut self,
+        formatter: &Formatify,
+        key_value: &HashMap<&str, String>,
+        buf: &mut Buffer,
+    ) -> usize {
+        let styled = formatter.render_styled(key_value, &self.template, self.area.width.into());
+        let cells = self.layout(&styled);
+
+        let mut written = 0;
+        for (idx, (symbol, style)) in cells.iter().enumerate() {
+            let unchanged = self
+                .painted
+                .as_ref()
+                .is_some_and(|painted| painted[idx] == (symbol.clone(), *style));
+            if unchanged {
+                continue;
+            }
+            let width = usize::from(self.area.width);
+            let x = self.area.x + (idx % width) as u16;
+            let y = self.area.y + (idx / width) as u16;
+            if let Some(cell) = buf.cell_mut((x, y)) {
+                cell.reset();
+                cell.set_symbol(symbol).set_style(*style);
+                written += 1;
+            }
+        }
+        self.painted = Some(cells);
+        written
+    }
+
+    /// Distributes the styled output onto the cells of the region, row by row. Wide chars occupy
+    /// two cells, zero-width chars are appended to the symbol of the previous cell.
+    fn layout(&self, styled: &[(char, Style)]) -> Vec<(String, Style)> {
+        let width = usize::from(self.area.width);
+        let height = usize::from(self.area.height);
+        let blank = (" ".to_string(), self.style);
+        let mut cells = vec![blank.clone(); width * height];
+
+        for (row, line) in styled.split(|(ch, _)| *ch == '\n').take(height).enumerate() {
+            let mut column = 0;
+            for &(ch, style) in line {
+                let idx = row * width + column;
+                match char_width(ch) {
+                    0 if column > 0 => cells[idx - 1].0.push(ch),
+                    0 => {}
+                    ch_width if column + ch_width <= width => {
+                        cells[idx] = (ch.to_string(), self.style.patch(style));
+                        if ch_width == 2 {
+                            cells[idx + 1] = (" ".to_string(), self.style.patch(style));
+                        }
+                        column += ch_width;
+                    }
+                    _ => break,
+                }
+            }
+        }
+        cells
+    }
+}
+
+/// Applies a color specification to the current style. Attributes and colors accumulate like
+/// ANSI escape sequences; `reset` restores the default style.
+#[cfg(feature = "colors")]
+pub fn apply_color_spec(style: Style, spec: &ColorSpec) -> Style {
+    use ratatui::style::Modifier as TextModifier;
+
+    if spec.reset {
+        return Style::default();
+    }
+    let mut style = style;
+    for attr in spec.attrs.iter() {
+        style = style.add_modifier(match attr {
+            Attribute::Bold => TextModifier::BOLD,
+            Attribute::Dim => TextModifier::DIM,
+            Attribute::Italic => TextModifier::ITALIC,
+            Attribute::Underline => TextModifier::UNDERLINED,
+            Attribute::Blink => TextModifier::SLOW_BLINK,
+            Attribute::Reverse => TextModifier::REVERSED,
+            Attribute::Strike => TextModifier::CROSSED_OUT,
+        });
+    }
+    if let Some(fg) = spec.fg {
+        style = style.fg(terminal_color(fg));
+    }
+    if let Some(bg) = spec.bg {
+        style = style.bg(terminal_color(bg));
+    }
+    style
+}
+
+/// Returns the ratatui color of a color of a color placeholder.
+#[cfg(feature = "colors")]
+fn terminal_color(color: Color) -> ratatui::style::Color {
+    use ratatui::style::Color as TerminalColor;
+
+    const BASIC: [TerminalColor; 8] = [
+        TerminalColor::Black,
+        TerminalColor::Red,
+        TerminalColor::Green,
+        TerminalColor::Yellow,
+        TerminalColor::Blue,
+        TerminalColor::Magenta,
+        TerminalColor::Cyan,
+        TerminalColor::Gray,
+    ];
+    const BRIGHT: [TerminalColor; 8] = [
+        TerminalColor::DarkGray,
+        TerminalColor::LightRed,
+        TerminalColor::LightGreen,
+        TerminalColor::LightYellow,
+        TerminalColor::LightBlue,
+        TerminalColor::LightMagenta,
+        TerminalColor::LightCyan,
+        TerminalColor::White,
+    ];
+    match color {
+        Color::Default => TerminalColor::Reset,
+        Color::Basic(idx) => BASIC[usize::from(idx & 7)],
+        Color::Bright(idx) => BRIGHT[usize::from(idx & 7)],
+        Color::Palette(idx) => TerminalColor::Indexed(idx),
+        Color::Rgb(r, g, b) => TerminalColor::Rgb(r, g, b),
+    }
+}
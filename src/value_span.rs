@@ -0,0 +1,25 @@
+use std::ops::Range;
+
+/// Semantic information of a rendered value placeholder. Used by the markup targets.
+#[derive(Debug, Clone)]
+pub struct ValueSpan {
+    /// Char range of the value in the rendered output, including padding.
+    pub output: Range<usize>,
+    /// Key which provided the value. `None` if the default value is used.
+    pub key: Option<String>,
+    /// Style classes of the active color placeholder.
+    pub classes: Vec<String>,
+    /// The value was padded or truncated by a format. Used by the markdown target.
+    #[cfg(feature = "markdown")]
+    pub aligned: bool,
+}
+
+/// Moves the spans to their output ranges `mirrored` after reordering the output, see
+/// `ParsingTaskReplacePlaceholders::mirror_layout`. There is a span for each cell.
+pub fn mirror_spans(spans: &mut [ValueSpan], mirrored: &[Range<usize>]) {
+    for (span, range) in spans.iter_mut().zip(mirrored) {
+        span.output = range.clone();
+    }
+    // empty spans precede a span starting at the same position
+    spans.sort_by_key(|span| (span.output.start, span.output.end));
+}
@@ -0,0 +1,129 @@
+#[cfg(feature = "json")]
+use super::list_value::join_list;
+
+use std::collections::{BTreeMap, HashMap};
+
+/// Hierarchical value source, e.g. converted from JSON, for dotted-path placeholders like
+/// `%(commit.author.email)`.
+///
+/// # Examples
+/// ```
+/// # use formatify::{Formatify, PlaceholderFormatter, ValueTree};
+/// let author = ValueTree::branch()
+///     .with("name", "Alice")
+///     .with("email", "alice@example.com");
+/// let tree = ValueTree::branch().with("commit", ValueTree::branch().with("author", author));
+///
+/// let formatter = Formatify::new();
+/// let out = formatter.replace_placeholders_tree(&tree, "%(commit.author.name) <%(commit.author.email)>");
+/// assert_eq!(out, "Alice <alice@example.com>");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueTree {
+    /// A value which can be rendered.
+    Leaf(String),
+    /// Named child values.
+    Branch(BTreeMap<String, ValueTree>),
+}
+
+impl ValueTree {
+    /// Creates an empty branch.
+    pub fn branch() -> Self {
+        ValueTree::Branch(BTreeMap::new())
+    }
+
+    /// Adds a child value. Has no effect on leafs.
+    pub fn with(mut self, name: &str, value: impl Into<ValueTree>) -> Self {
+        if let ValueTree::Branch(children) = &mut self {
+            children.insert(name.to_string(), value.into());
+        }
+        self
+    }
+
+    /// Returns the value at the dotted path, e.g. `author.name`.
+    pub fn get(&self, path: &str) -> Option<&ValueTree> {
+        path.split('.').try_fold(self, |node, name| match node {
+            ValueTree::Branch(children) => children.get(name),
+            ValueTree::Leaf(_) => None,
+        })
+    }
+
+    /// Returns all leafs keyed by their dotted path.
+    pub fn flatten(&self) -> HashMap<String, String> {
+        let mut out = HashMap::new();
+        self.flatten_into("", &mut out);
+        out
+    }
+
+    fn flatten_into(&self, prefix: &str, out: &mut HashMap<String, String>) {
+        match self {
+            ValueTree::Leaf(value) => {
+                out.insert(prefix.to_string(), value.clone());
+            }
+            ValueTree::Branch(children) => {
+                for (name, child) in children {
+                    let path = match prefix {
+                        "" => name.clone(),
+                        _ => format!("{}.{}", prefix, name),
+                    };
+                    child.flatten_into(&path, out);
+                }
+            }
+        }
+    }
+}
+
+impl From<String> for ValueTree {
+    fn from(value: String) -> Self {
+        ValueTree::Leaf(value)
+    }
+}
+
+impl From<&str> for ValueTree {
+    fn from(value: &str) -> Self {
+        ValueTree::Leaf(value.to_string())
+    }
+}
+
+#[cfg(feature = "json")]
+impl ValueTree {
+    /// Converts a JSON document into a value tree.
+    ///
+    /// Objects become branches and scalars become leafs; `null` values are omitted. Arrays of
+    /// scalars become list values which can be indexed (`%(tags[0])`) and iterated by loop blocks.
+    /// Other arrays become branches with the item indices as names, e.g. `%(items.0.name)`.
+    pub fn from_json(value: &serde_json::Value) -> Option<Self> {
+        use serde_json::Value;
+        match value {
+            Value::Null => None,
+            Value::Bool(value) => Some(ValueTree::Leaf(value.to_string())),
+            Value::Number(value) => Some(ValueTree::Leaf(value.to_string())),
+            Value::String(value) => Some(ValueTree::Leaf(value.clone())),
+            Value::Array(items) => {
+                let leafs: Option<Vec<String>> = items
+                    .iter()
+                    .map(|item| match Self::from_json(item) {
+                        Some(ValueTree::Leaf(leaf)) => Some(leaf),
+                        _ => None,
+                    })
+                    .collect();
+                if let Some(leafs) = leafs {
+                    return Some(ValueTree::Leaf(join_list(leafs)));
+                }
+                let children = items
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(idx, item)| Some((idx.to_string(), Self::from_json(item)?)))
+                    .collect();
+                Some(ValueTree::Branch(children))
+            }
+            Value::Object(members) => {
+                let children = members
+                    .iter()
+                    .filter_map(|(name, member)| Some((name.clone(), Self::from_json(member)?)))
+                    .collect();
+                Some(ValueTree::Branch(children))
+            }
+        }
+    }
+}
@@ -0,0 +1,10 @@
+/// Git-style whitespace prefix of a value placeholder, e.g. `%+(key)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WhitespacePrefix {
+    /// `%+(key)`: inserts a line feed before a non-empty value.
+    LineFeed,
+    /// `% (key)`: inserts a space before a non-empty value.
+    Space,
+    /// `%-(key)`: removes the line feeds directly preceding an empty value.
+    RemoveLineFeeds,
+}